@@ -16,6 +16,7 @@ use std::fmt::{Debug, Formatter};
 pub mod event_types;
 pub mod io;
 pub mod spaces;
+pub mod translation;
 
 mod header;
 mod helpers;
@@ -26,6 +26,7 @@ impl NoteExpressionType {
             CLAP_NOTE_EXPRESSION_PAN => Some(Pan),
             CLAP_NOTE_EXPRESSION_TUNING => Some(Tuning),
             CLAP_NOTE_EXPRESSION_VIBRATO => Some(Vibrato),
+            CLAP_NOTE_EXPRESSION_EXPRESSION => Some(Expression),
             CLAP_NOTE_EXPRESSION_BRIGHTNESS => Some(Brightness),
             CLAP_NOTE_EXPRESSION_PRESSURE => Some(Pressure),
             _ => None,
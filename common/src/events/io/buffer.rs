@@ -143,14 +143,17 @@ impl EventBuffer {
 
     /// Sorts the events contained in this buffer, based on their time.
     ///
+    /// Events with the same time are kept in the order they were added to the buffer in.
+    ///
     /// It is necessary to sort the events before passing them to a plugin.
     pub fn sort(&mut self) {
-        // this needs to be an unstable sort, as the std stable sort might allocate
+        // this needs to be an unstable sort, as the std stable sort might allocate.
+        // Header indexes grow with each added event, so they are used to break ties.
         self.indexes.sort_unstable_by_key(|i| {
             // SAFETY: Registered indexes always have actual event headers written by append_header_data
             // PANIC: We used registered indexes, this should never panic
             let event = unsafe { self.headers[*i as usize].assume_init_ref() };
-            event.0.time
+            (event.0.time, *i)
         })
     }
 
@@ -182,6 +185,21 @@ impl EventBuffer {
         self.indexes.push(index as u32);
     }
 
+    /// Pushes the given event into the buffer, replacing its time with the given `time`.
+    ///
+    /// The event is always added at the end of the buffer. This is useful to move events from
+    /// one timeline to another, e.g. when splitting a long sequence of events into process blocks.
+    pub fn push_with_time<E: AsRef<UnknownEvent> + ?Sized>(&mut self, event: &E, time: u32) {
+        let index = self.append_header_data(event.as_ref());
+
+        // SAFETY: the event header was just written by append_header_data
+        // PANIC: append_header_data always returns the index of the header it wrote
+        let header = unsafe { self.headers[index].assume_init_mut() };
+        header.0.time = time;
+
+        self.indexes.push(index as u32);
+    }
+
     /// Produces an [`InputEvents`] that wraps this buffer as an [`InputEventBuffer`] implementation.
     ///
     /// This helper method is strictly equivalent to using [`InputEvents::from_buffer`].
@@ -339,4 +357,35 @@ mod test {
         assert_eq!(Some(&event_2), buffer.get(2).unwrap().as_event());
         assert_eq!(Some(&event_3), buffer.get(3).unwrap().as_event());
     }
+
+    #[test]
+    fn sort_keeps_simultaneous_events_in_order() {
+        let events: Vec<_> = (0..64u8)
+            .map(|i| MidiEvent::new(3 - (i as u32 % 4), 0, [i; 3]))
+            .collect();
+
+        let mut buffer = EventBuffer::new();
+        buffer.push_all(&events);
+        buffer.sort();
+
+        let sorted: Vec<_> = buffer
+            .iter()
+            .map(|e| e.as_event::<MidiEvent>().unwrap())
+            .collect();
+
+        assert!(sorted.is_sorted_by_key(|e| (e.time(), e.data()[0])));
+    }
+
+    #[test]
+    fn push_with_time_replaces_time() {
+        let event = MidiEvent::new(3, 0, [1, 2, 3]);
+
+        let mut buffer = EventBuffer::new();
+        buffer.push_with_time(&event, 42);
+
+        let pushed: &MidiEvent = buffer.get(0).unwrap().as_event().unwrap();
+        assert_eq!(pushed.time(), 42);
+        assert_eq!(pushed.data(), [1, 2, 3]);
+        assert_eq!(event.time(), 3);
+    }
 }
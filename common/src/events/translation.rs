@@ -0,0 +1,750 @@
+//! Translation between the different note dialects a plugin's note port may support.
+//!
+//! Plugins and hosts negotiate which note dialects they use: CLAP note events ([`NoteOnEvent`],
+//! [`NoteOffEvent`] and [`NoteExpressionEvent`]), raw MIDI 1.0 ([`MidiEvent`]), or raw MIDI 2.0
+//! Universal MIDI Packets ([`Midi2Event`]). A [`NoteTranslator`] converts note events from any of
+//! these dialects to any other, so that either side can use the dialect the other one prefers.
+//!
+//! All translations are done without allocating, and can be performed on the audio thread.
+//!
+//! # Addressing
+//!
+//! The port, channel and key of the events are preserved. Since MIDI has no concept of note IDs,
+//! translated CLAP events target all note IDs, and note IDs are dropped when translating to MIDI.
+//!
+//! # Expressions
+//!
+//! The following messages are translated to and from note expressions:
+//!
+//! | Expression     | MIDI 1.0                 | MIDI 2.0                            |
+//! |----------------|--------------------------|-------------------------------------|
+//! | `Pressure`     | Polyphonic key pressure  | Polyphonic key pressure             |
+//! | `Tuning`       | -                        | Per-note pitch bend                 |
+//! | `Vibrato`      | -                        | Registered per-note controller #1   |
+//! | `Pan`          | -                        | Registered per-note controller #10  |
+//! | `Expression`   | -                        | Registered per-note controller #11  |
+//! | `Brightness`   | -                        | Registered per-note controller #74  |
+//!
+//! When [MPE](Mpe) is enabled, messages sent on member channels apply to the single note playing
+//! on that channel: channel pressure, pitch bend and control change #74 are then translated to
+//! the `Pressure`, `Tuning` and `Brightness` expressions of all notes on that channel.
+//!
+//! # Example
+//!
+//! ```
+//! use clack_common::events::Event;
+//! use clack_common::events::event_types::{MidiEvent, NoteOnEvent};
+//! use clack_common::events::translation::{ClapNoteEvent, NoteTranslator};
+//!
+//! let translator = NoteTranslator::new();
+//!
+//! let midi = MidiEvent::new(10, 0, [0x90, 60, 127]);
+//! let Some(ClapNoteEvent::NoteOn(note_on)) = translator.to_clap(midi.as_unknown()) else {
+//!     panic!("Expected a Note On event");
+//! };
+//!
+//! assert_eq!(note_on.header().time(), 10);
+//! assert_eq!(note_on.velocity(), 1.0);
+//!
+//! let back = translator.to_midi(note_on.as_unknown()).unwrap();
+//! assert_eq!(back.data(), [0x90, 60, 127]);
+//! ```
+
+use crate::events::event_types::{
+    Midi2Event, MidiEvent, NoteExpressionEvent, NoteExpressionType, NoteOffEvent, NoteOnEvent,
+};
+use crate::events::io::{OutputEvents, TryPushError};
+use crate::events::{Event, EventFlags, Match, Pckn, UnknownEvent};
+
+/// The pitch bend range of MIDI 2.0 per-note pitch bend messages, in semitones, unless
+/// configured otherwise.
+const DEFAULT_PER_NOTE_PITCH_BEND_RANGE: f64 = 48.0;
+
+/// The MIDI Polyphonic Expression (MPE) configuration of a [`NoteTranslator`].
+///
+/// When MPE is enabled, every channel except the master channel is a member channel, on which
+/// channel-wide messages only apply to the single note playing on that channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mpe {
+    /// The master channel of the MPE zone: 0 for the lower zone, or 15 for the upper zone.
+    pub master_channel: u8,
+    /// The pitch bend range of the member channels, in semitones.
+    pub pitch_bend_range: f64,
+}
+
+impl Mpe {
+    /// The MPE lower zone, with its master channel being the first channel, and a pitch bend
+    /// range of 48 semitones.
+    pub const LOWER_ZONE: Self = Self {
+        master_channel: 0,
+        pitch_bend_range: 48.0,
+    };
+
+    /// The MPE upper zone, with its master channel being the last channel, and a pitch bend
+    /// range of 48 semitones.
+    pub const UPPER_ZONE: Self = Self {
+        master_channel: 15,
+        pitch_bend_range: 48.0,
+    };
+}
+
+/// A note dialect, as negotiated between the host and the plugin.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NoteFormat {
+    /// CLAP note events.
+    Clap,
+    /// Raw MIDI 1.0 events.
+    Midi,
+    /// Raw MIDI 2.0 events.
+    Midi2,
+}
+
+/// A CLAP note event, as produced by a [`NoteTranslator`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClapNoteEvent {
+    /// A note being pressed.
+    NoteOn(NoteOnEvent),
+    /// A note being released.
+    NoteOff(NoteOffEvent),
+    /// An expression applied to one or more notes.
+    NoteExpression(NoteExpressionEvent),
+}
+
+impl ClapNoteEvent {
+    /// Returns the note event contained in the given event, if it is one.
+    pub fn from_unknown(event: &UnknownEvent) -> Option<Self> {
+        if let Some(event) = event.as_event::<NoteOnEvent>() {
+            Some(Self::NoteOn(*event))
+        } else if let Some(event) = event.as_event::<NoteOffEvent>() {
+            Some(Self::NoteOff(*event))
+        } else {
+            event
+                .as_event::<NoteExpressionEvent>()
+                .map(|e| Self::NoteExpression(*e))
+        }
+    }
+
+    /// Returns this event as an [`UnknownEvent`].
+    #[inline]
+    pub fn as_unknown(&self) -> &UnknownEvent {
+        match self {
+            ClapNoteEvent::NoteOn(event) => event.as_unknown(),
+            ClapNoteEvent::NoteOff(event) => event.as_unknown(),
+            ClapNoteEvent::NoteExpression(event) => event.as_unknown(),
+        }
+    }
+}
+
+impl AsRef<UnknownEvent> for ClapNoteEvent {
+    #[inline]
+    fn as_ref(&self) -> &UnknownEvent {
+        self.as_unknown()
+    }
+}
+
+/// A channel voice message, with its values normalized.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Message {
+    NoteOn { key: u8, velocity: f64 },
+    NoteOff { key: u8, velocity: f64 },
+    PolyPressure { key: u8, value: f64 },
+    PerNotePitchBend { key: u8, value: f64 },
+    PerNoteController { key: u8, index: u8, value: f64 },
+    ChannelPressure(f64),
+    PitchBend(f64),
+    ControlChange { index: u8, value: f64 },
+}
+
+/// The registered per-note controllers (and MPE control changes) matching each note expression.
+const CONTROLLERS: [(u8, NoteExpressionType); 4] = [
+    (1, NoteExpressionType::Vibrato),
+    (10, NoteExpressionType::Pan),
+    (11, NoteExpressionType::Expression),
+    (74, NoteExpressionType::Brightness),
+];
+
+const BRIGHTNESS_CONTROLLER: u8 = 74;
+
+/// Translates note events between CLAP, MIDI 1.0 and MIDI 2.0.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteTranslator {
+    mpe: Option<Mpe>,
+    per_note_pitch_bend_range: f64,
+}
+
+impl Default for NoteTranslator {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoteTranslator {
+    /// Creates a new translator, with MPE disabled.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            mpe: None,
+            per_note_pitch_bend_range: DEFAULT_PER_NOTE_PITCH_BEND_RANGE,
+        }
+    }
+
+    /// Enables MIDI Polyphonic Expression, using the given configuration.
+    #[inline]
+    pub const fn with_mpe(mut self, mpe: Mpe) -> Self {
+        self.mpe = Some(mpe);
+        self
+    }
+
+    /// Sets the range of MIDI 2.0 per-note pitch bend messages, in semitones.
+    ///
+    /// This is 48 semitones by default.
+    #[inline]
+    pub const fn with_per_note_pitch_bend_range(mut self, semitones: f64) -> Self {
+        self.per_note_pitch_bend_range = semitones;
+        self
+    }
+
+    /// Returns the MPE configuration of this translator, if MPE is enabled.
+    #[inline]
+    pub const fn mpe(&self) -> Option<Mpe> {
+        self.mpe
+    }
+
+    /// Translates the given note event to a CLAP note event.
+    ///
+    /// CLAP note events are returned as is. This returns `None` if the event is not a note
+    /// event, or if it has no CLAP equivalent.
+    pub fn to_clap(&self, event: &UnknownEvent) -> Option<ClapNoteEvent> {
+        if let Some(note) = ClapNoteEvent::from_unknown(event) {
+            return Some(note);
+        }
+
+        let (port, channel, message) = decode(event)?;
+        let header = event.header();
+        let note = self.message_to_clap(header.time(), port, channel, message)?;
+
+        Some(with_flags(note, header.flags()))
+    }
+
+    /// Translates the given note event to a MIDI 1.0 event.
+    ///
+    /// MIDI 1.0 events are returned as is. This returns `None` if the event is not a note event,
+    /// or if it has no MIDI 1.0 equivalent.
+    pub fn to_midi(&self, event: &UnknownEvent) -> Option<MidiEvent> {
+        if let Some(event) = event.as_event::<MidiEvent>() {
+            return Some(*event);
+        }
+
+        let (port, channel, message) = self.to_message(event, false)?;
+        let header = event.header();
+
+        Some(
+            MidiEvent::new(header.time(), port, encode_midi(channel, message)?)
+                .with_flags(header.flags()),
+        )
+    }
+
+    /// Translates the given note event to a MIDI 2.0 event.
+    ///
+    /// MIDI 2.0 events are returned as is. This returns `None` if the event is not a note event,
+    /// or if it has no MIDI 2.0 equivalent.
+    pub fn to_midi2(&self, event: &UnknownEvent) -> Option<Midi2Event> {
+        if let Some(event) = event.as_event::<Midi2Event>() {
+            return Some(*event);
+        }
+
+        let (port, channel, message) = self.to_message(event, true)?;
+        let header = event.header();
+
+        Some(
+            Midi2Event::new(header.time(), port, encode_midi2(channel, message))
+                .with_flags(header.flags()),
+        )
+    }
+
+    /// Translates the given event to the given format, and pushes it to `output`.
+    ///
+    /// Events that cannot be translated, such as parameter changes or MIDI control changes, are
+    /// pushed unchanged.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the output event queue is full.
+    pub fn translate(
+        &self,
+        event: &UnknownEvent,
+        format: NoteFormat,
+        output: &mut OutputEvents,
+    ) -> Result<(), TryPushError> {
+        match format {
+            NoteFormat::Clap => match self.to_clap(event) {
+                Some(translated) => output.try_push(translated),
+                None => output.try_push(event),
+            },
+            NoteFormat::Midi => match self.to_midi(event) {
+                Some(translated) => output.try_push(translated),
+                None => output.try_push(event),
+            },
+            NoteFormat::Midi2 => match self.to_midi2(event) {
+                Some(translated) => output.try_push(translated),
+                None => output.try_push(event),
+            },
+        }
+    }
+
+    fn is_member_channel(&self, channel: u8) -> bool {
+        self.mpe.is_some_and(|mpe| mpe.master_channel != channel)
+    }
+
+    fn message_to_clap(
+        &self,
+        time: u32,
+        port: u16,
+        channel: u8,
+        message: Message,
+    ) -> Option<ClapNoteEvent> {
+        let note = |key: u8| Pckn::new(port, channel as u16, key as u16, Match::All);
+        let channel_notes = Pckn::new(port, channel as u16, Match::All, Match::All);
+        let expression = |pckn, expression_type, value| {
+            Some(ClapNoteEvent::NoteExpression(NoteExpressionEvent::new(
+                time,
+                pckn,
+                expression_type,
+                value,
+            )))
+        };
+
+        match message {
+            Message::NoteOn { key, velocity } => Some(ClapNoteEvent::NoteOn(NoteOnEvent::new(
+                time,
+                note(key),
+                velocity,
+            ))),
+            Message::NoteOff { key, velocity } => Some(ClapNoteEvent::NoteOff(NoteOffEvent::new(
+                time,
+                note(key),
+                velocity,
+            ))),
+            Message::PolyPressure { key, value } => {
+                expression(note(key), NoteExpressionType::Pressure, value)
+            }
+            Message::PerNotePitchBend { key, value } => expression(
+                note(key),
+                NoteExpressionType::Tuning,
+                value * self.per_note_pitch_bend_range,
+            ),
+            Message::PerNoteController { key, index, value } => {
+                let (_, expression_type) = CONTROLLERS.iter().find(|(i, _)| *i == index)?;
+                expression(note(key), *expression_type, value)
+            }
+            _ if !self.is_member_channel(channel) => None,
+            Message::ChannelPressure(value) => {
+                expression(channel_notes, NoteExpressionType::Pressure, value)
+            }
+            Message::PitchBend(value) => {
+                let range = self.mpe.map_or(0.0, |mpe| mpe.pitch_bend_range);
+                expression(channel_notes, NoteExpressionType::Tuning, value * range)
+            }
+            Message::ControlChange {
+                index: BRIGHTNESS_CONTROLLER,
+                value,
+            } => expression(channel_notes, NoteExpressionType::Brightness, value),
+            Message::ControlChange { .. } => None,
+        }
+    }
+
+    /// Converts the given MIDI or CLAP note event to a channel voice message.
+    fn to_message(&self, event: &UnknownEvent, per_note: bool) -> Option<(u16, u8, Message)> {
+        if event.as_event::<MidiEvent>().is_some() || event.as_event::<Midi2Event>().is_some() {
+            // Messages may need to be adapted, e.g. MPE messages, so we go through CLAP events.
+            let note = self.to_clap(event)?;
+            return self.clap_to_message(&note, per_note);
+        }
+
+        self.clap_to_message(&ClapNoteEvent::from_unknown(event)?, per_note)
+    }
+
+    fn clap_to_message(&self, note: &ClapNoteEvent, per_note: bool) -> Option<(u16, u8, Message)> {
+        let pckn = match note {
+            ClapNoteEvent::NoteOn(event) => event.pckn(),
+            ClapNoteEvent::NoteOff(event) => event.pckn(),
+            ClapNoteEvent::NoteExpression(event) => event.pckn(),
+        };
+
+        let port = pckn.port_index.into_specific().unwrap_or(0);
+        let channel = pckn.channel.into_specific().filter(|c| *c < 16)? as u8;
+        let key = pckn
+            .key
+            .into_specific()
+            .filter(|k| *k < 128)
+            .map(|k| k as u8);
+
+        let message = match note {
+            ClapNoteEvent::NoteOn(event) => Message::NoteOn {
+                key: key?,
+                velocity: event.velocity(),
+            },
+            ClapNoteEvent::NoteOff(event) => Message::NoteOff {
+                key: key?,
+                velocity: event.velocity(),
+            },
+            ClapNoteEvent::NoteExpression(event) => {
+                let expression_type = event.expression_type()?;
+                let value = event.value();
+
+                if self.is_member_channel(channel) {
+                    match expression_type {
+                        NoteExpressionType::Pressure => Message::ChannelPressure(value),
+                        NoteExpressionType::Tuning => {
+                            let range = self.mpe.map_or(1.0, |mpe| mpe.pitch_bend_range);
+                            Message::PitchBend(value / range)
+                        }
+                        NoteExpressionType::Brightness => Message::ControlChange {
+                            index: BRIGHTNESS_CONTROLLER,
+                            value,
+                        },
+                        _ => return None,
+                    }
+                } else {
+                    let key = key?;
+
+                    match expression_type {
+                        NoteExpressionType::Pressure => Message::PolyPressure { key, value },
+                        _ if !per_note => return None,
+                        NoteExpressionType::Tuning => Message::PerNotePitchBend {
+                            key,
+                            value: value / self.per_note_pitch_bend_range,
+                        },
+                        _ => {
+                            let (index, _) =
+                                CONTROLLERS.iter().find(|(_, t)| *t == expression_type)?;
+                            Message::PerNoteController {
+                                key,
+                                index: *index,
+                                value,
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        Some((port, channel, message))
+    }
+}
+
+fn with_flags(note: ClapNoteEvent, flags: EventFlags) -> ClapNoteEvent {
+    match note {
+        ClapNoteEvent::NoteOn(event) => ClapNoteEvent::NoteOn(event.with_flags(flags)),
+        ClapNoteEvent::NoteOff(event) => ClapNoteEvent::NoteOff(event.with_flags(flags)),
+        ClapNoteEvent::NoteExpression(event) => {
+            ClapNoteEvent::NoteExpression(event.with_flags(flags))
+        }
+    }
+}
+
+/// Decodes the channel voice message of a MIDI 1.0 or MIDI 2.0 event.
+fn decode(event: &UnknownEvent) -> Option<(u16, u8, Message)> {
+    if let Some(event) = event.as_event::<MidiEvent>() {
+        let (channel, message) = decode_midi(event.data())?;
+        Some((event.port_index(), channel, message))
+    } else {
+        let event = event.as_event::<Midi2Event>()?;
+        let (channel, message) = decode_midi2(event.data())?;
+        Some((event.port_index(), channel, message))
+    }
+}
+
+fn decode_midi([status, first, second]: [u8; 3]) -> Option<(u8, Message)> {
+    let channel = status & 0x0F;
+    let key = first & 0x7F;
+
+    let message = match status & 0xF0 {
+        0x80 => Message::NoteOff {
+            key,
+            velocity: from_7bit(second),
+        },
+        // A Note On with a velocity of zero is a Note Off in MIDI 1.0.
+        0x90 if second == 0 => Message::NoteOff { key, velocity: 0.0 },
+        0x90 => Message::NoteOn {
+            key,
+            velocity: from_7bit(second),
+        },
+        0xA0 => Message::PolyPressure {
+            key,
+            value: from_7bit(second),
+        },
+        0xB0 => Message::ControlChange {
+            index: first & 0x7F,
+            value: from_7bit(second),
+        },
+        0xD0 => Message::ChannelPressure(from_7bit(first)),
+        0xE0 => {
+            let raw = ((second as u16 & 0x7F) << 7) | (first as u16 & 0x7F);
+            Message::PitchBend((raw as f64 - 8192.0) / 8192.0)
+        }
+        _ => return None,
+    };
+
+    Some((channel, message))
+}
+
+fn decode_midi2([header, data, ..]: [u32; 4]) -> Option<(u8, Message)> {
+    match header >> 28 {
+        // MIDI 1.0 channel voice message, wrapped in a Universal MIDI Packet.
+        0x2 => decode_midi([(header >> 16) as u8, (header >> 8) as u8, header as u8]),
+        // MIDI 2.0 channel voice message.
+        0x4 => {
+            let status = (header >> 20) as u8 & 0x0F;
+            let channel = (header >> 16) as u8 & 0x0F;
+            let key = (header >> 8) as u8 & 0x7F;
+            let index = header as u8;
+
+            let message = match status {
+                0x0 => Message::PerNoteController {
+                    key,
+                    index,
+                    value: from_32bit(data),
+                },
+                0x6 => Message::PerNotePitchBend {
+                    key,
+                    value: bend_from_32bit(data),
+                },
+                0x8 => Message::NoteOff {
+                    key,
+                    velocity: from_16bit((data >> 16) as u16),
+                },
+                // Unlike MIDI 1.0, a Note On with a velocity of zero is a valid Note On.
+                0x9 => Message::NoteOn {
+                    key,
+                    velocity: from_16bit((data >> 16) as u16),
+                },
+                0xA => Message::PolyPressure {
+                    key,
+                    value: from_32bit(data),
+                },
+                0xB => Message::ControlChange {
+                    index: key,
+                    value: from_32bit(data),
+                },
+                0xD => Message::ChannelPressure(from_32bit(data)),
+                0xE => Message::PitchBend(bend_from_32bit(data)),
+                _ => return None,
+            };
+
+            Some((channel, message))
+        }
+        _ => None,
+    }
+}
+
+fn encode_midi(channel: u8, message: Message) -> Option<[u8; 3]> {
+    let data = match message {
+        // Note On velocities are at least 1, as a velocity of 0 would mean a Note Off.
+        Message::NoteOn { key, velocity } => [0x90, key, to_7bit(velocity).max(1)],
+        Message::NoteOff { key, velocity } => [0x80, key, to_7bit(velocity)],
+        Message::PolyPressure { key, value } => [0xA0, key, to_7bit(value)],
+        Message::ControlChange { index, value } => [0xB0, index, to_7bit(value)],
+        Message::ChannelPressure(value) => [0xD0, to_7bit(value), 0],
+        Message::PitchBend(value) => {
+            let raw = (value * 8192.0 + 8192.0).round().clamp(0.0, 16383.0) as u16;
+            [0xE0, (raw & 0x7F) as u8, (raw >> 7) as u8]
+        }
+        Message::PerNotePitchBend { .. } | Message::PerNoteController { .. } => return None,
+    };
+
+    Some([data[0] | channel, data[1], data[2]])
+}
+
+fn encode_midi2(channel: u8, message: Message) -> [u32; 4] {
+    let (status, index, data) = match message {
+        Message::PerNoteController { key, index, value } => {
+            (0x0, (key as u32) << 8 | index as u32, to_32bit(value))
+        }
+        Message::PerNotePitchBend { key, value } => (0x6, (key as u32) << 8, bend_to_32bit(value)),
+        Message::NoteOff { key, velocity } => {
+            (0x8, (key as u32) << 8, (to_16bit(velocity) as u32) << 16)
+        }
+        Message::NoteOn { key, velocity } => {
+            (0x9, (key as u32) << 8, (to_16bit(velocity) as u32) << 16)
+        }
+        Message::PolyPressure { key, value } => (0xA, (key as u32) << 8, to_32bit(value)),
+        Message::ControlChange { index, value } => (0xB, (index as u32) << 8, to_32bit(value)),
+        Message::ChannelPressure(value) => (0xD, 0, to_32bit(value)),
+        Message::PitchBend(value) => (0xE, 0, bend_to_32bit(value)),
+    };
+
+    let header = (0x4 << 28) | (status << 20) | ((channel as u32 & 0x0F) << 16) | index;
+    [header, data, 0, 0]
+}
+
+#[inline]
+fn from_7bit(value: u8) -> f64 {
+    (value & 0x7F) as f64 / 127.0
+}
+
+#[inline]
+fn to_7bit(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 127.0).round() as u8
+}
+
+#[inline]
+fn from_16bit(value: u16) -> f64 {
+    value as f64 / u16::MAX as f64
+}
+
+#[inline]
+fn to_16bit(value: f64) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+}
+
+#[inline]
+fn from_32bit(value: u32) -> f64 {
+    value as f64 / u32::MAX as f64
+}
+
+#[inline]
+fn to_32bit(value: f64) -> u32 {
+    (value.clamp(0.0, 1.0) * u32::MAX as f64).round() as u32
+}
+
+const BEND_CENTER: f64 = 0x8000_0000u32 as f64;
+
+#[inline]
+fn bend_from_32bit(value: u32) -> f64 {
+    (value as f64 - BEND_CENTER) / BEND_CENTER
+}
+
+#[inline]
+fn bend_to_32bit(value: f64) -> u32 {
+    (value * BEND_CENTER + BEND_CENTER)
+        .round()
+        .clamp(0.0, u32::MAX as f64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::io::EventBuffer;
+
+    #[test]
+    fn translates_midi_notes() {
+        let translator = NoteTranslator::new();
+
+        let note_on = MidiEvent::new(5, 2, [0x93, 64, 127]).with_flags(EventFlags::IS_LIVE);
+        let Some(ClapNoteEvent::NoteOn(clap)) = translator.to_clap(note_on.as_unknown()) else {
+            panic!("Expected a Note On event");
+        };
+
+        assert_eq!(clap.header().time(), 5);
+        assert_eq!(clap.flags(), EventFlags::IS_LIVE);
+        assert_eq!(clap.pckn(), Pckn::new(2u16, 3u16, 64u16, Match::All));
+        assert_eq!(clap.velocity(), 1.0);
+        assert_eq!(translator.to_midi(clap.as_unknown()), Some(note_on));
+
+        // A Note On with a velocity of zero is a Note Off.
+        let note_off = MidiEvent::new(0, 0, [0x90, 64, 0]);
+        assert!(matches!(
+            translator.to_clap(note_off.as_unknown()),
+            Some(ClapNoteEvent::NoteOff(_))
+        ));
+
+        // Control changes are not note events without MPE.
+        let control_change = MidiEvent::new(0, 0, [0xB0, 74, 0]);
+        assert_eq!(translator.to_clap(control_change.as_unknown()), None);
+    }
+
+    #[test]
+    fn translates_midi2_notes() {
+        let translator = NoteTranslator::new();
+
+        let note_on = NoteOnEvent::new(0, Pckn::new(0u16, 1u16, 60u16, 42u32), 1.0);
+        let midi2 = translator.to_midi2(note_on.as_unknown()).unwrap();
+        assert_eq!(midi2.data(), [0x4091_3C00, 0xFFFF_0000, 0, 0]);
+
+        // Note IDs are lost in MIDI.
+        let Some(ClapNoteEvent::NoteOn(back)) = translator.to_clap(midi2.as_unknown()) else {
+            panic!("Expected a Note On event");
+        };
+        assert_eq!(back.pckn(), Pckn::new(0u16, 1u16, 60u16, Match::All));
+
+        // MIDI 2.0 to MIDI 1.0.
+        let midi = translator.to_midi(midi2.as_unknown()).unwrap();
+        assert_eq!(midi.data(), [0x91, 60, 127]);
+
+        let tuning = NoteExpressionEvent::new(
+            0,
+            Pckn::new(0u16, 0u16, 60u16, Match::All),
+            NoteExpressionType::Tuning,
+            -24.0,
+        );
+        let midi2 = translator.to_midi2(tuning.as_unknown()).unwrap();
+        assert_eq!(midi2.data(), [0x4060_3C00, 0x4000_0000, 0, 0]);
+        assert_eq!(
+            translator.to_clap(midi2.as_unknown()),
+            Some(ClapNoteEvent::NoteExpression(tuning))
+        );
+
+        // There is no per-note pitch bend in MIDI 1.0.
+        assert_eq!(translator.to_midi(tuning.as_unknown()), None);
+    }
+
+    #[test]
+    fn translates_mpe_expressions() {
+        let translator = NoteTranslator::new().with_mpe(Mpe::LOWER_ZONE);
+        let channel_notes = Pckn::new(0u16, 2u16, Match::All, Match::All);
+
+        let pitch_bend = MidiEvent::new(0, 0, [0xE2, 0, 0x60]);
+        let expected = NoteExpressionEvent::new(0, channel_notes, NoteExpressionType::Tuning, 24.0);
+        assert_eq!(
+            translator.to_clap(pitch_bend.as_unknown()),
+            Some(ClapNoteEvent::NoteExpression(expected))
+        );
+        assert_eq!(translator.to_midi(expected.as_unknown()), Some(pitch_bend));
+
+        let brightness = MidiEvent::new(0, 0, [0xB2, 74, 127]);
+        let expected =
+            NoteExpressionEvent::new(0, channel_notes, NoteExpressionType::Brightness, 1.0);
+        assert_eq!(
+            translator.to_clap(brightness.as_unknown()),
+            Some(ClapNoteEvent::NoteExpression(expected))
+        );
+
+        // Messages on the master channel apply to the whole zone.
+        let master_pressure = MidiEvent::new(0, 0, [0xD0, 127, 0]);
+        assert_eq!(translator.to_clap(master_pressure.as_unknown()), None);
+
+        let member_pressure = MidiEvent::new(0, 0, [0xD2, 127, 0]);
+        let midi2 = translator.to_midi2(member_pressure.as_unknown()).unwrap();
+        assert_eq!(midi2.data(), [0x40D2_0000, u32::MAX, 0, 0]);
+    }
+
+    #[test]
+    fn passes_through_untranslatable_events() {
+        let translator = NoteTranslator::new();
+        let mut output = EventBuffer::new();
+
+        let note_on = MidiEvent::new(0, 0, [0x90, 60, 100]);
+        let control_change = MidiEvent::new(1, 0, [0xB0, 7, 100]);
+
+        for event in [note_on, control_change] {
+            translator
+                .translate(
+                    event.as_unknown(),
+                    NoteFormat::Clap,
+                    &mut output.as_output(),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(output.len(), 2);
+        assert!(output.get(0).unwrap().as_event::<NoteOnEvent>().is_some());
+        assert_eq!(
+            output.get(1).unwrap().as_event::<MidiEvent>(),
+            Some(&control_change)
+        );
+    }
+}
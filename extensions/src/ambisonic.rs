@@ -0,0 +1,356 @@
+//! Allows plugins and hosts to exchange the channel ordering and normalization of ambisonic audio
+//! ports.
+//!
+//! This extension only applies to audio ports whose [`AudioPortType`] is
+//! [`AudioPortType::AMBISONIC`]. The host can check which [`AmbisonicConfig`]s a plugin supports
+//! using [`is_config_supported`](PluginAmbisonic::is_config_supported), and retrieve the
+//! configuration of a given port using [`get_config`](PluginAmbisonic::get_config).
+//!
+//! Plugins can notify the host that the ambisonic configuration of their ports has changed using
+//! [`HostAmbisonic::changed`]. This can only happen while the plugin is deactivated.
+
+#![deny(missing_docs)]
+
+use crate::audio_ports::AudioPortType;
+use clack_common::extensions::*;
+use clap_sys::ext::ambisonic::*;
+use std::ffi::CStr;
+
+/// Plugin-side of the Ambisonic extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginAmbisonic(RawExtension<PluginExtensionSide, clap_plugin_ambisonic>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginAmbisonic {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_AMBISONIC, CLAP_EXT_AMBISONIC_COMPAT];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Ambisonic extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostAmbisonic(RawExtension<HostExtensionSide, clap_host_ambisonic>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostAmbisonic {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_AMBISONIC, CLAP_EXT_AMBISONIC_COMPAT];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+impl AudioPortType<'_> {
+    /// An ambisonic audio port.
+    ///
+    /// The channel ordering and normalization of ports of this type can be queried using the
+    /// [`PluginAmbisonic`] extension.
+    pub const AMBISONIC: AudioPortType<'static> = AudioPortType(CLAP_PORT_AMBISONIC);
+}
+
+/// The order in which the ambisonic components are laid out in the channels of a port.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[repr(u32)]
+pub enum AmbisonicOrdering {
+    /// FuMa (Furse-Malham) channel ordering.
+    FuMa = CLAP_AMBISONIC_ORDERING_FUMA,
+    /// ACN (Ambisonic Channel Number) channel ordering.
+    Acn = CLAP_AMBISONIC_ORDERING_ACN,
+}
+
+impl AmbisonicOrdering {
+    /// Returns the ordering as the raw C-FFI-compatible integer type.
+    #[inline]
+    pub const fn to_raw(self) -> clap_ambisonic_ordering {
+        self as _
+    }
+
+    /// Reads the ordering from the raw C-FFI-compatible integer type.
+    ///
+    /// This returns [`None`] if the given integer's value doesn't match any known ordering.
+    #[inline]
+    pub const fn from_raw(raw: clap_ambisonic_ordering) -> Option<Self> {
+        match raw {
+            CLAP_AMBISONIC_ORDERING_FUMA => Some(Self::FuMa),
+            CLAP_AMBISONIC_ORDERING_ACN => Some(Self::Acn),
+            _ => None,
+        }
+    }
+}
+
+/// The normalization applied to the ambisonic components of a port.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[repr(u32)]
+pub enum AmbisonicNormalization {
+    /// MaxN normalization.
+    MaxN = CLAP_AMBISONIC_NORMALIZATION_MAXN,
+    /// SN3D (Schmidt semi-normalized, 3D) normalization.
+    Sn3d = CLAP_AMBISONIC_NORMALIZATION_SN3D,
+    /// N3D (full 3D) normalization.
+    N3d = CLAP_AMBISONIC_NORMALIZATION_N3D,
+    /// SN2D (Schmidt semi-normalized, 2D) normalization.
+    Sn2d = CLAP_AMBISONIC_NORMALIZATION_SN2D,
+    /// N2D (full 2D) normalization.
+    N2d = CLAP_AMBISONIC_NORMALIZATION_N2D,
+}
+
+impl AmbisonicNormalization {
+    /// Returns the normalization as the raw C-FFI-compatible integer type.
+    #[inline]
+    pub const fn to_raw(self) -> clap_ambisonic_normalization {
+        self as _
+    }
+
+    /// Reads the normalization from the raw C-FFI-compatible integer type.
+    ///
+    /// This returns [`None`] if the given integer's value doesn't match any known normalization.
+    #[inline]
+    pub const fn from_raw(raw: clap_ambisonic_normalization) -> Option<Self> {
+        match raw {
+            CLAP_AMBISONIC_NORMALIZATION_MAXN => Some(Self::MaxN),
+            CLAP_AMBISONIC_NORMALIZATION_SN3D => Some(Self::Sn3d),
+            CLAP_AMBISONIC_NORMALIZATION_N3D => Some(Self::N3d),
+            CLAP_AMBISONIC_NORMALIZATION_SN2D => Some(Self::Sn2d),
+            CLAP_AMBISONIC_NORMALIZATION_N2D => Some(Self::N2d),
+            _ => None,
+        }
+    }
+}
+
+/// The ambisonic configuration of an audio port.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct AmbisonicConfig {
+    /// The channel ordering of the port.
+    pub ordering: AmbisonicOrdering,
+    /// The normalization of the port.
+    pub normalization: AmbisonicNormalization,
+}
+
+impl AmbisonicConfig {
+    /// Creates a new ambisonic configuration from the given ordering and normalization.
+    #[inline]
+    pub const fn new(ordering: AmbisonicOrdering, normalization: AmbisonicNormalization) -> Self {
+        Self {
+            ordering,
+            normalization,
+        }
+    }
+
+    /// Reads an ambisonic configuration from its raw, C-FFI compatible representation.
+    ///
+    /// This returns [`None`] if either the ordering or the normalization are unknown.
+    #[inline]
+    pub const fn from_raw(raw: &clap_ambisonic_config) -> Option<Self> {
+        let Some(ordering) = AmbisonicOrdering::from_raw(raw.ordering) else {
+            return None;
+        };
+
+        let Some(normalization) = AmbisonicNormalization::from_raw(raw.normalization) else {
+            return None;
+        };
+
+        Some(Self::new(ordering, normalization))
+    }
+
+    /// Returns the raw, C-FFI compatible representation of this configuration.
+    #[inline]
+    pub const fn to_raw(&self) -> clap_ambisonic_config {
+        clap_ambisonic_config {
+            ordering: self.ordering.to_raw(),
+            normalization: self.normalization.to_raw(),
+        }
+    }
+}
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_host::extensions::prelude::*;
+    use std::mem::MaybeUninit;
+
+    impl PluginAmbisonic {
+        /// Returns `true` if the given [`AmbisonicConfig`] is supported by the plugin.
+        pub fn is_config_supported(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            config: AmbisonicConfig,
+        ) -> bool {
+            let Some(is_config_supported) = plugin.use_extension(&self.0).is_config_supported
+            else {
+                return false;
+            };
+
+            let config = config.to_raw();
+
+            // SAFETY: This type ensures the function pointer is valid.
+            unsafe { is_config_supported(plugin.as_raw(), &config) }
+        }
+
+        /// Retrieves the [`AmbisonicConfig`] of the given audio port.
+        ///
+        /// If the plugin failed to provide a configuration, or if it provided an unknown ordering
+        /// or normalization, this returns [`None`].
+        pub fn get_config(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            is_input: bool,
+            port_index: u32,
+        ) -> Option<AmbisonicConfig> {
+            let get_config = plugin.use_extension(&self.0).get_config?;
+            let mut config = MaybeUninit::zeroed();
+
+            // SAFETY: This type ensures the function pointer is valid.
+            let success =
+                unsafe { get_config(plugin.as_raw(), is_input, port_index, config.as_mut_ptr()) };
+
+            if !success {
+                return None;
+            }
+
+            // SAFETY: the buffer was zero-initialized, which is a valid bit pattern for all fields.
+            AmbisonicConfig::from_raw(unsafe { config.assume_init_ref() })
+        }
+    }
+
+    /// Implementation of the Host-side of the Ambisonic extension.
+    pub trait HostAmbisonicImpl {
+        /// Informs the host that the ambisonic configuration of the plugin's ports has changed.
+        ///
+        /// The plugin can only call this while it is deactivated.
+        fn changed(&mut self);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostAmbisonic
+    where
+        H: for<'a> HostHandlers<MainThread<'a>: HostAmbisonicImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_ambisonic {
+                changed: Some(changed::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn changed<H>(host: *const clap_host)
+    where
+        H: for<'a> HostHandlers<MainThread<'a>: HostAmbisonicImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().changed();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_plugin::extensions::prelude::*;
+
+    impl HostAmbisonic {
+        /// Informs the host that the ambisonic configuration of the plugin's ports has changed.
+        ///
+        /// This can only be called while the plugin is deactivated.
+        #[inline]
+        pub fn changed(&self, host: &mut HostMainThreadHandle) {
+            if let Some(changed) = host.use_extension(&self.0).changed {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { changed(host.as_raw()) }
+            }
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Ambisonic extension.
+    pub trait PluginAmbisonicImpl {
+        /// Returns `true` if the given [`AmbisonicConfig`] is supported by the plugin.
+        fn is_config_supported(&mut self, config: AmbisonicConfig) -> bool;
+
+        /// Returns the [`AmbisonicConfig`] of the given audio port, or [`None`] if the port
+        /// doesn't exist or isn't an ambisonic port.
+        fn get_config(&mut self, is_input: bool, port_index: u32) -> Option<AmbisonicConfig>;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginAmbisonic
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginAmbisonicImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_ambisonic {
+                is_config_supported: Some(is_config_supported::<P>),
+                get_config: Some(get_config::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn is_config_supported<P>(
+        plugin: *const clap_plugin,
+        config: *const clap_ambisonic_config,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginAmbisonicImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |plugin| {
+            if config.is_null() {
+                return Err(PluginWrapperError::NulPtr("clap_ambisonic_config"));
+            }
+
+            // Unknown orderings or normalizations can never be supported.
+            let Some(config) = AmbisonicConfig::from_raw(&*config) else {
+                return Ok(false);
+            };
+
+            Ok(plugin.main_thread().as_mut().is_config_supported(config))
+        })
+        .unwrap_or(false)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_config<P>(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        info: *mut clap_ambisonic_config,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginAmbisonicImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |plugin| {
+            if info.is_null() {
+                return Err(PluginWrapperError::NulPtr("clap_ambisonic_config"));
+            }
+
+            match plugin
+                .main_thread()
+                .as_mut()
+                .get_config(is_input, port_index)
+            {
+                None => Ok(false),
+                Some(config) => {
+                    info.write(config.to_raw());
+                    Ok(true)
+                }
+            }
+        })
+        .unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
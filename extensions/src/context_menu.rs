@@ -0,0 +1,615 @@
+#![deny(missing_docs)]
+
+//! Allows both plugins and hosts to contribute entries to each other's context menus.
+//!
+//! A plugin may add entries to the context menu the host shows for one of its parameters, and the
+//! host may add entries to a context menu drawn by the plugin's GUI. Menus are populated through a
+//! [`ContextMenuBuilder`], and the chosen entry is then reported back through `perform`.
+//!
+//! Menus can also be recorded into an owned [`ContextMenu`] tree, so that they can be rendered by
+//! the side that populated them.
+
+use crate::utils::{cstr_from_nullable_ptr, handle_panic};
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clack_common::utils::ClapId;
+use clap_sys::ext::context_menu::*;
+use std::error::Error;
+use std::ffi::{CStr, CString, c_void};
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+
+/// Plugin-side of the Context Menu extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginContextMenu(RawExtension<PluginExtensionSide, clap_plugin_context_menu>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginContextMenu {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_CONTEXT_MENU, CLAP_EXT_CONTEXT_MENU_COMPAT];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Context Menu extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostContextMenu(RawExtension<HostExtensionSide, clap_host_context_menu>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostContextMenu {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_CONTEXT_MENU, CLAP_EXT_CONTEXT_MENU_COMPAT];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// The element a context menu is related to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ContextMenuTarget {
+    /// The menu is not related to any specific element, e.g. the plugin's GUI background.
+    Global,
+    /// The menu is related to the parameter with the given ID.
+    Param(ClapId),
+}
+
+impl ContextMenuTarget {
+    /// Gets a context menu target from its raw, C-FFI compatible representation.
+    ///
+    /// Returns [`None`] if the target kind is unknown, or if a parameter ID is invalid.
+    #[inline]
+    pub fn from_raw(raw: &clap_context_menu_target) -> Option<Self> {
+        match raw.kind {
+            CLAP_CONTEXT_MENU_TARGET_KIND_GLOBAL => Some(Self::Global),
+            CLAP_CONTEXT_MENU_TARGET_KIND_PARAM => Some(Self::Param(ClapId::from_raw(raw.id)?)),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw, C-FFI compatible representation of this context menu target.
+    #[inline]
+    pub fn to_raw(&self) -> clap_context_menu_target {
+        match self {
+            Self::Global => clap_context_menu_target {
+                kind: CLAP_CONTEXT_MENU_TARGET_KIND_GLOBAL,
+                id: ClapId::optional_to_raw(None),
+            },
+            Self::Param(id) => clap_context_menu_target {
+                kind: CLAP_CONTEXT_MENU_TARGET_KIND_PARAM,
+                id: id.get(),
+            },
+        }
+    }
+}
+
+/// The kinds of item a context menu can contain.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ContextMenuItemKind {
+    /// A simple entry. See [`ContextMenuItem::Entry`].
+    Entry = CLAP_CONTEXT_MENU_ITEM_ENTRY,
+    /// An entry with a checkmark. See [`ContextMenuItem::CheckEntry`].
+    CheckEntry = CLAP_CONTEXT_MENU_ITEM_CHECK_ENTRY,
+    /// A separator line. See [`ContextMenuItem::Separator`].
+    Separator = CLAP_CONTEXT_MENU_ITEM_SEPARATOR,
+    /// The start of a submenu. See [`ContextMenuItem::BeginSubmenu`].
+    BeginSubmenu = CLAP_CONTEXT_MENU_ITEM_BEGIN_SUBMENU,
+    /// The end of a submenu. See [`ContextMenuItem::EndSubmenu`].
+    EndSubmenu = CLAP_CONTEXT_MENU_ITEM_END_SUBMENU,
+    /// A title. See [`ContextMenuItem::Title`].
+    Title = CLAP_CONTEXT_MENU_ITEM_TITLE,
+}
+
+impl ContextMenuItemKind {
+    /// Gets a context menu item kind from its raw, C-FFI compatible representation.
+    ///
+    /// Returns [`None`] if the given kind is unknown.
+    #[inline]
+    pub const fn from_raw(raw: clap_context_menu_item_kind) -> Option<Self> {
+        match raw {
+            CLAP_CONTEXT_MENU_ITEM_ENTRY => Some(Self::Entry),
+            CLAP_CONTEXT_MENU_ITEM_CHECK_ENTRY => Some(Self::CheckEntry),
+            CLAP_CONTEXT_MENU_ITEM_SEPARATOR => Some(Self::Separator),
+            CLAP_CONTEXT_MENU_ITEM_BEGIN_SUBMENU => Some(Self::BeginSubmenu),
+            CLAP_CONTEXT_MENU_ITEM_END_SUBMENU => Some(Self::EndSubmenu),
+            CLAP_CONTEXT_MENU_ITEM_TITLE => Some(Self::Title),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw, C-FFI compatible representation of this context menu item kind.
+    #[inline]
+    pub const fn to_raw(self) -> clap_context_menu_item_kind {
+        self as _
+    }
+}
+
+/// An item to be added to a context menu.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ContextMenuItem<'a> {
+    /// A simple entry, which triggers the given action when selected.
+    Entry {
+        /// The text displayed for this entry.
+        label: &'a CStr,
+        /// Whether this entry can be selected by the user.
+        is_enabled: bool,
+        /// The ID of the action passed to `perform` when this entry is selected.
+        action_id: ClapId,
+    },
+    /// An entry with a checkmark, which triggers the given action when selected.
+    CheckEntry {
+        /// The text displayed for this entry.
+        label: &'a CStr,
+        /// Whether this entry can be selected by the user.
+        is_enabled: bool,
+        /// Whether the checkmark is displayed.
+        is_checked: bool,
+        /// The ID of the action passed to `perform` when this entry is selected.
+        action_id: ClapId,
+    },
+    /// A separator line.
+    Separator,
+    /// Starts a submenu. All following items belong to it, until the matching
+    /// [`EndSubmenu`](ContextMenuItem::EndSubmenu) item.
+    BeginSubmenu {
+        /// The text displayed for this submenu.
+        label: &'a CStr,
+        /// Whether this submenu can be opened by the user.
+        is_enabled: bool,
+    },
+    /// Ends the submenu that was last started.
+    EndSubmenu,
+    /// A title, which can be used to label a group of items. It cannot be selected.
+    Title {
+        /// The text displayed for this title.
+        title: &'a CStr,
+        /// Whether this title is displayed as enabled.
+        is_enabled: bool,
+    },
+}
+
+impl<'a> ContextMenuItem<'a> {
+    /// Returns the kind of this item.
+    #[inline]
+    pub const fn kind(&self) -> ContextMenuItemKind {
+        match self {
+            Self::Entry { .. } => ContextMenuItemKind::Entry,
+            Self::CheckEntry { .. } => ContextMenuItemKind::CheckEntry,
+            Self::Separator => ContextMenuItemKind::Separator,
+            Self::BeginSubmenu { .. } => ContextMenuItemKind::BeginSubmenu,
+            Self::EndSubmenu => ContextMenuItemKind::EndSubmenu,
+            Self::Title { .. } => ContextMenuItemKind::Title,
+        }
+    }
+
+    /// Reads an item from its raw kind and data pointer.
+    ///
+    /// Returns [`None`] if the kind is unknown, or if the data is missing or invalid.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure `data` is either null or points to a valid item structure matching
+    /// the given `kind`, and that all of its string pointers are valid for the lifetime `'a`.
+    pub unsafe fn from_raw(kind: clap_context_menu_item_kind, data: *const c_void) -> Option<Self> {
+        let kind = ContextMenuItemKind::from_raw(kind)?;
+
+        // SAFETY: The caller guarantees data and the strings it points to are valid.
+        unsafe {
+            Some(match kind {
+                ContextMenuItemKind::Separator => Self::Separator,
+                ContextMenuItemKind::EndSubmenu => Self::EndSubmenu,
+                ContextMenuItemKind::Entry => {
+                    let data = data.cast::<clap_context_menu_entry>().as_ref()?;
+                    Self::Entry {
+                        label: cstr_from_nullable_ptr(data.label)?,
+                        is_enabled: data.is_enabled,
+                        action_id: ClapId::from_raw(data.action_id)?,
+                    }
+                }
+                ContextMenuItemKind::CheckEntry => {
+                    let data = data.cast::<clap_context_menu_check_entry>().as_ref()?;
+                    Self::CheckEntry {
+                        label: cstr_from_nullable_ptr(data.label)?,
+                        is_enabled: data.is_enabled,
+                        is_checked: data.is_checked,
+                        action_id: ClapId::from_raw(data.action_id)?,
+                    }
+                }
+                ContextMenuItemKind::BeginSubmenu => {
+                    let data = data.cast::<clap_context_menu_submenu>().as_ref()?;
+                    Self::BeginSubmenu {
+                        label: cstr_from_nullable_ptr(data.label)?,
+                        is_enabled: data.is_enabled,
+                    }
+                }
+                ContextMenuItemKind::Title => {
+                    let data = data.cast::<clap_context_menu_item_title>().as_ref()?;
+                    Self::Title {
+                        title: cstr_from_nullable_ptr(data.title)?,
+                        is_enabled: data.is_enabled,
+                    }
+                }
+            })
+        }
+    }
+}
+
+/// A lightweight borrowed handle to a context menu builder, provided by either the host or the
+/// plugin.
+///
+/// Items are added to the menu in order using [`add_item`](Self::add_item).
+#[repr(C)]
+pub struct ContextMenuBuilder<'a> {
+    inner: clap_context_menu_builder,
+    // Raw pointer is here to make sure this is !Send !Sync
+    lifetime: PhantomData<(&'a clap_context_menu_builder, *const ())>,
+}
+
+impl ContextMenuBuilder<'_> {
+    /// # Safety
+    ///
+    /// Pointer must be valid for 'a, as well as its contents
+    #[allow(dead_code)] // Unused if neither clack-host nor clack-plugin are enabled
+    pub(crate) unsafe fn from_raw<'a>(raw: *const clap_context_menu_builder) -> &'a Self {
+        // SAFETY: This is safe to transmute as it's repr(C) and has the same memory representation
+        // Other safety invariants are upheld by the caller
+        unsafe { &*(raw as *const Self) }
+    }
+
+    /// Adds an item to the menu.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextMenuError::AddItem`] if the menu rejected the item, e.g. because it does
+    /// not support its kind.
+    pub fn add_item(&self, item: ContextMenuItem) -> Result<&Self, ContextMenuError> {
+        let add_item = self.inner.add_item.ok_or(ContextMenuError::AddItem)?;
+        let kind = item.kind().to_raw();
+
+        // SAFETY: This type guarantees inner is valid. Item data and string pointers are valid as
+        // they come from references, and outlive the call.
+        let success = unsafe {
+            match item {
+                ContextMenuItem::Separator | ContextMenuItem::EndSubmenu => {
+                    add_item(&self.inner, kind, core::ptr::null())
+                }
+                ContextMenuItem::Entry {
+                    label,
+                    is_enabled,
+                    action_id,
+                } => {
+                    let data = clap_context_menu_entry {
+                        label: label.as_ptr(),
+                        is_enabled,
+                        action_id: action_id.get(),
+                    };
+                    add_item(&self.inner, kind, (&raw const data).cast())
+                }
+                ContextMenuItem::CheckEntry {
+                    label,
+                    is_enabled,
+                    is_checked,
+                    action_id,
+                } => {
+                    let data = clap_context_menu_check_entry {
+                        label: label.as_ptr(),
+                        is_enabled,
+                        is_checked,
+                        action_id: action_id.get(),
+                    };
+                    add_item(&self.inner, kind, (&raw const data).cast())
+                }
+                ContextMenuItem::BeginSubmenu { label, is_enabled } => {
+                    let data = clap_context_menu_submenu {
+                        label: label.as_ptr(),
+                        is_enabled,
+                    };
+                    add_item(&self.inner, kind, (&raw const data).cast())
+                }
+                ContextMenuItem::Title { title, is_enabled } => {
+                    let data = clap_context_menu_item_title {
+                        title: title.as_ptr(),
+                        is_enabled,
+                    };
+                    add_item(&self.inner, kind, (&raw const data).cast())
+                }
+            }
+        };
+
+        if success {
+            Ok(self)
+        } else {
+            Err(ContextMenuError::AddItem)
+        }
+    }
+
+    /// Returns `true` if the menu supports items of the given kind.
+    #[inline]
+    pub fn supports(&self, kind: ContextMenuItemKind) -> bool {
+        match self.inner.supports {
+            // SAFETY: This type guarantees inner is valid.
+            Some(supports) => unsafe { supports(&self.inner, kind.to_raw()) },
+            None => false,
+        }
+    }
+}
+
+/// An implementation of a context menu builder.
+///
+/// This can be passed to the other side's `populate` method to record the items it provides.
+/// See [`ContextMenu`] for an implementation that records the menu into an owned tree.
+pub trait ContextMenuBuilderImpl {
+    /// Adds an item to the menu.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the item cannot be added, e.g. because its kind isn't supported or
+    /// because it ends a submenu that was never started.
+    fn add_item(&mut self, item: ContextMenuItem) -> Result<(), ContextMenuError>;
+
+    /// Returns `true` if this builder supports items of the given kind.
+    fn supports(&self, kind: ContextMenuItemKind) -> bool;
+}
+
+#[allow(dead_code)] // Unused if neither clack-host nor clack-plugin are enabled
+pub(crate) fn raw_builder<B: ContextMenuBuilderImpl>(builder: &mut B) -> clap_context_menu_builder {
+    clap_context_menu_builder {
+        ctx: (builder as *mut B).cast(),
+        add_item: Some(add_item::<B>),
+        supports: Some(supports::<B>),
+    }
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn add_item<B: ContextMenuBuilderImpl>(
+    builder: *const clap_context_menu_builder,
+    item_kind: clap_context_menu_item_kind,
+    item_data: *const c_void,
+) -> bool {
+    // SAFETY: The builder pointer is guaranteed to be valid by the caller.
+    unsafe {
+        handle_builder::<B, _>(builder, |builder| {
+            // SAFETY: Item data is guaranteed to be valid by the CLAP spec.
+            let item = ContextMenuItem::from_raw(item_kind, item_data)?;
+            Some(builder.add_item(item).is_ok())
+        })
+    }
+    .unwrap_or(false)
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn supports<B: ContextMenuBuilderImpl>(
+    builder: *const clap_context_menu_builder,
+    item_kind: clap_context_menu_item_kind,
+) -> bool {
+    // SAFETY: The builder pointer is guaranteed to be valid by the caller.
+    unsafe {
+        handle_builder::<B, _>(builder, |builder| {
+            Some(builder.supports(ContextMenuItemKind::from_raw(item_kind)?))
+        })
+    }
+    .unwrap_or(false)
+}
+
+/// # Safety
+///
+/// `builder` must be valid and come from [`raw_builder`].
+unsafe fn handle_builder<B: ContextMenuBuilderImpl, T>(
+    builder: *const clap_context_menu_builder,
+    handler: impl FnOnce(&mut B) -> Option<T>,
+) -> Option<T> {
+    // SAFETY: CLAP spec guarantees this is valid for reads
+    let builder = unsafe { builder.as_ref() }?;
+
+    // SAFETY: We created that pointer ourselves from an exclusive &mut reference.
+    // Builders are only used on the main thread, during a single populate call.
+    let builder = unsafe { builder.ctx.cast::<B>().as_mut() }?;
+
+    handle_panic(AssertUnwindSafe(|| handler(builder))).ok()?
+}
+
+/// An owned item of a [`ContextMenu`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ContextMenuNode {
+    /// A simple entry. See [`ContextMenuItem::Entry`].
+    Entry {
+        /// The text displayed for this entry.
+        label: CString,
+        /// Whether this entry can be selected by the user.
+        is_enabled: bool,
+        /// The ID of the action to perform when this entry is selected.
+        action_id: ClapId,
+    },
+    /// An entry with a checkmark. See [`ContextMenuItem::CheckEntry`].
+    CheckEntry {
+        /// The text displayed for this entry.
+        label: CString,
+        /// Whether this entry can be selected by the user.
+        is_enabled: bool,
+        /// Whether the checkmark is displayed.
+        is_checked: bool,
+        /// The ID of the action to perform when this entry is selected.
+        action_id: ClapId,
+    },
+    /// A separator line.
+    Separator,
+    /// A submenu, containing its own items.
+    Submenu {
+        /// The text displayed for this submenu.
+        label: CString,
+        /// Whether this submenu can be opened by the user.
+        is_enabled: bool,
+        /// The items contained in this submenu.
+        items: Vec<ContextMenuNode>,
+    },
+    /// A title. See [`ContextMenuItem::Title`].
+    Title {
+        /// The text displayed for this title.
+        title: CString,
+        /// Whether this title is displayed as enabled.
+        is_enabled: bool,
+    },
+}
+
+/// A context menu builder that records all items it receives into an owned tree.
+///
+/// This is meant to be passed to the other side's `populate` method, after which the resulting
+/// [`items`](Self::items) can be rendered by a GUI.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ContextMenu {
+    items: Vec<ContextMenuNode>,
+    open_submenus: Vec<(CString, bool, Vec<ContextMenuNode>)>,
+}
+
+impl ContextMenu {
+    /// Creates a new, empty context menu.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            open_submenus: Vec::new(),
+        }
+    }
+
+    /// Returns the top-level items of this menu.
+    ///
+    /// Submenus that have been started but not ended yet are not included.
+    #[inline]
+    pub fn items(&self) -> &[ContextMenuNode] {
+        &self.items
+    }
+
+    /// Returns `true` if this menu has no top-level items.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns `true` if all submenus that were started have also been ended.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.open_submenus.is_empty()
+    }
+
+    /// Removes all items from this menu, so that it can be populated again.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.open_submenus.clear();
+    }
+
+    fn current_items(&mut self) -> &mut Vec<ContextMenuNode> {
+        match self.open_submenus.last_mut() {
+            Some((_, _, items)) => items,
+            None => &mut self.items,
+        }
+    }
+}
+
+impl ContextMenuBuilderImpl for ContextMenu {
+    fn add_item(&mut self, item: ContextMenuItem) -> Result<(), ContextMenuError> {
+        let node = match item {
+            ContextMenuItem::Entry {
+                label,
+                is_enabled,
+                action_id,
+            } => ContextMenuNode::Entry {
+                label: label.to_owned(),
+                is_enabled,
+                action_id,
+            },
+            ContextMenuItem::CheckEntry {
+                label,
+                is_enabled,
+                is_checked,
+                action_id,
+            } => ContextMenuNode::CheckEntry {
+                label: label.to_owned(),
+                is_enabled,
+                is_checked,
+                action_id,
+            },
+            ContextMenuItem::Separator => ContextMenuNode::Separator,
+            ContextMenuItem::Title { title, is_enabled } => ContextMenuNode::Title {
+                title: title.to_owned(),
+                is_enabled,
+            },
+            ContextMenuItem::BeginSubmenu { label, is_enabled } => {
+                self.open_submenus
+                    .push((label.to_owned(), is_enabled, Vec::new()));
+                return Ok(());
+            }
+            ContextMenuItem::EndSubmenu => {
+                let (label, is_enabled, items) =
+                    self.open_submenus.pop().ok_or(ContextMenuError::AddItem)?;
+
+                ContextMenuNode::Submenu {
+                    label,
+                    is_enabled,
+                    items,
+                }
+            }
+        };
+
+        self.current_items().push(node);
+        Ok(())
+    }
+
+    #[inline]
+    fn supports(&self, _kind: ContextMenuItemKind) -> bool {
+        true
+    }
+}
+
+/// Errors that can occur while using context menus.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ContextMenuError {
+    /// The menu failed or refused to add an item.
+    AddItem,
+    /// The other side failed or refused to populate the menu.
+    Populate,
+    /// The other side failed or refused to perform an action.
+    Perform,
+    /// The host failed or refused to show a popup menu.
+    Popup,
+}
+
+impl Display for ContextMenuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContextMenuError::AddItem => f.write_str("Failed to add item to context menu"),
+            ContextMenuError::Populate => f.write_str("Failed to populate context menu"),
+            ContextMenuError::Perform => f.write_str("Failed to perform context menu action"),
+            ContextMenuError::Popup => f.write_str("Failed to show context menu popup"),
+        }
+    }
+}
+
+impl Error for ContextMenuError {}
+
+#[cfg(feature = "clack-host")]
+mod host;
+#[cfg(feature = "clack-host")]
+pub use host::*;
+
+#[cfg(feature = "clack-plugin")]
+mod plugin;
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(test)]
+mod test {
+    extern crate static_assertions as sa;
+    use super::*;
+
+    sa::assert_not_impl_any!(ContextMenuBuilder<'static>: Send, Sync);
+}
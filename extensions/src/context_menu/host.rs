@@ -0,0 +1,219 @@
+use super::*;
+use clack_host::extensions::prelude::*;
+
+impl PluginContextMenu {
+    /// Asks the plugin to add its items for the given target to a menu.
+    ///
+    /// The menu is populated through the given `builder`, which can e.g. be a [`ContextMenu`] to
+    /// record the items into an owned tree.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextMenuError::Populate`] if the plugin failed to populate the menu.
+    pub fn populate(
+        &self,
+        plugin: &mut PluginMainThreadHandle,
+        target: ContextMenuTarget,
+        builder: &mut impl ContextMenuBuilderImpl,
+    ) -> Result<(), ContextMenuError> {
+        let populate = plugin
+            .use_extension(&self.0)
+            .populate
+            .ok_or(ContextMenuError::Populate)?;
+
+        let target = target.to_raw();
+        let builder = raw_builder(builder);
+
+        // SAFETY: This type ensures the function pointer is valid. The target and builder are
+        // valid for the duration of the call.
+        match unsafe { populate(plugin.as_raw(), &target, &builder) } {
+            true => Ok(()),
+            false => Err(ContextMenuError::Populate),
+        }
+    }
+
+    /// Asks the plugin to perform the action with the given ID, after it was selected by the user
+    /// in a menu the plugin populated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextMenuError::Perform`] if the plugin failed to perform the action.
+    pub fn perform(
+        &self,
+        plugin: &mut PluginMainThreadHandle,
+        target: ContextMenuTarget,
+        action_id: ClapId,
+    ) -> Result<(), ContextMenuError> {
+        let perform = plugin
+            .use_extension(&self.0)
+            .perform
+            .ok_or(ContextMenuError::Perform)?;
+
+        let target = target.to_raw();
+
+        // SAFETY: This type ensures the function pointer is valid.
+        match unsafe { perform(plugin.as_raw(), &target, action_id.get()) } {
+            true => Ok(()),
+            false => Err(ContextMenuError::Perform),
+        }
+    }
+}
+
+/// Implementation of the Host-side of the Context Menu extension.
+pub trait HostContextMenuImpl {
+    /// Adds the host's items for the given target to a menu provided by the plugin.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the menu could not be populated.
+    fn populate(
+        &mut self,
+        target: ContextMenuTarget,
+        builder: &ContextMenuBuilder,
+    ) -> Result<(), HostError>;
+
+    /// Performs the action with the given ID, after it was selected by the user in a menu the host
+    /// populated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the action could not be performed.
+    fn perform(&mut self, target: ContextMenuTarget, action_id: ClapId) -> Result<(), HostError>;
+
+    /// Returns `true` if the host can display a popup menu for the plugin.
+    ///
+    /// This may depend on the current state of the plugin's GUI, e.g. whether it is floating or
+    /// embedded.
+    fn can_popup(&mut self) -> bool;
+
+    /// Shows the context menu for the given target as a popup, at the given screen coordinates.
+    ///
+    /// The menu is populated by the host, which also queries the plugin's items using
+    /// [`PluginContextMenu::populate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the popup could not be shown.
+    fn popup(
+        &mut self,
+        target: ContextMenuTarget,
+        screen_index: i32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), HostError>;
+}
+
+// SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+unsafe impl<H> ExtensionImplementation<H> for HostContextMenu
+where
+    for<'a> H: HostHandlers<MainThread<'a>: HostContextMenuImpl>,
+{
+    #[doc(hidden)]
+    const IMPLEMENTATION: RawExtensionImplementation =
+        RawExtensionImplementation::new(&clap_host_context_menu {
+            populate: Some(populate::<H>),
+            perform: Some(perform::<H>),
+            can_popup: Some(can_popup::<H>),
+            popup: Some(popup::<H>),
+        });
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn populate<H>(
+    host: *const clap_host,
+    target: *const clap_context_menu_target,
+    builder: *const clap_context_menu_builder,
+) -> bool
+where
+    for<'a> H: HostHandlers<MainThread<'a>: HostContextMenuImpl>,
+{
+    HostWrapper::<H>::handle(host, |host| {
+        let target = target
+            .as_ref()
+            .and_then(ContextMenuTarget::from_raw)
+            .ok_or(HostWrapperError::InvalidParameter(
+                "Invalid context menu target",
+            ))?;
+
+        if builder.is_null() {
+            return Err(HostWrapperError::InvalidParameter(
+                "Null context menu builder",
+            ));
+        }
+
+        let builder = ContextMenuBuilder::from_raw(builder);
+
+        Ok(host
+            .main_thread()
+            .as_mut()
+            .populate(target, builder)
+            .is_ok())
+    })
+    .unwrap_or(false)
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn perform<H>(
+    host: *const clap_host,
+    target: *const clap_context_menu_target,
+    action_id: u32,
+) -> bool
+where
+    for<'a> H: HostHandlers<MainThread<'a>: HostContextMenuImpl>,
+{
+    HostWrapper::<H>::handle(host, |host| {
+        let target = target
+            .as_ref()
+            .and_then(ContextMenuTarget::from_raw)
+            .ok_or(HostWrapperError::InvalidParameter(
+                "Invalid context menu target",
+            ))?;
+
+        let action_id = ClapId::from_raw(action_id)
+            .ok_or(HostWrapperError::InvalidParameter("Invalid action ID"))?;
+
+        Ok(host
+            .main_thread()
+            .as_mut()
+            .perform(target, action_id)
+            .is_ok())
+    })
+    .unwrap_or(false)
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn can_popup<H>(host: *const clap_host) -> bool
+where
+    for<'a> H: HostHandlers<MainThread<'a>: HostContextMenuImpl>,
+{
+    HostWrapper::<H>::handle(host, |host| Ok(host.main_thread().as_mut().can_popup()))
+        .unwrap_or(false)
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn popup<H>(
+    host: *const clap_host,
+    target: *const clap_context_menu_target,
+    screen_index: i32,
+    x: i32,
+    y: i32,
+) -> bool
+where
+    for<'a> H: HostHandlers<MainThread<'a>: HostContextMenuImpl>,
+{
+    HostWrapper::<H>::handle(host, |host| {
+        let target = target
+            .as_ref()
+            .and_then(ContextMenuTarget::from_raw)
+            .ok_or(HostWrapperError::InvalidParameter(
+                "Invalid context menu target",
+            ))?;
+
+        Ok(host
+            .main_thread()
+            .as_mut()
+            .popup(target, screen_index, x, y)
+            .is_ok())
+    })
+    .unwrap_or(false)
+}
@@ -0,0 +1,192 @@
+use super::*;
+use clack_plugin::extensions::prelude::*;
+
+impl HostContextMenu {
+    /// Asks the host to add its items for the given target to a menu.
+    ///
+    /// The menu is populated through the given `builder`, which can e.g. be a [`ContextMenu`] to
+    /// record the items into an owned tree the plugin's GUI can then render.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextMenuError::Populate`] if the host failed to populate the menu.
+    pub fn populate(
+        &self,
+        host: &mut HostMainThreadHandle,
+        target: ContextMenuTarget,
+        builder: &mut impl ContextMenuBuilderImpl,
+    ) -> Result<(), ContextMenuError> {
+        let populate = host
+            .use_extension(&self.0)
+            .populate
+            .ok_or(ContextMenuError::Populate)?;
+
+        let target = target.to_raw();
+        let builder = raw_builder(builder);
+
+        // SAFETY: This type ensures the function pointer is valid. The target and builder are
+        // valid for the duration of the call.
+        match unsafe { populate(host.as_raw(), &target, &builder) } {
+            true => Ok(()),
+            false => Err(ContextMenuError::Populate),
+        }
+    }
+
+    /// Asks the host to perform the action with the given ID, after it was selected by the user
+    /// in a menu the host populated.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextMenuError::Perform`] if the host failed to perform the action.
+    pub fn perform(
+        &self,
+        host: &mut HostMainThreadHandle,
+        target: ContextMenuTarget,
+        action_id: ClapId,
+    ) -> Result<(), ContextMenuError> {
+        let perform = host
+            .use_extension(&self.0)
+            .perform
+            .ok_or(ContextMenuError::Perform)?;
+
+        let target = target.to_raw();
+
+        // SAFETY: This type ensures the function pointer is valid.
+        match unsafe { perform(host.as_raw(), &target, action_id.get()) } {
+            true => Ok(()),
+            false => Err(ContextMenuError::Perform),
+        }
+    }
+
+    /// Returns `true` if the host can display a popup menu for the plugin.
+    ///
+    /// This may depend on the current state of the plugin's GUI, e.g. whether it is floating or
+    /// embedded.
+    #[inline]
+    pub fn can_popup(&self, host: &mut HostMainThreadHandle) -> bool {
+        match host.use_extension(&self.0).can_popup {
+            // SAFETY: This type ensures the function pointer is valid.
+            Some(can_popup) => unsafe { can_popup(host.as_raw()) },
+            None => false,
+        }
+    }
+
+    /// Asks the host to show the context menu for the given target as a popup, at the given
+    /// screen coordinates.
+    ///
+    /// The host populates the menu itself, and also queries the plugin's items using
+    /// [`PluginContextMenuImpl::populate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ContextMenuError::Popup`] if the host failed to show the popup.
+    pub fn popup(
+        &self,
+        host: &mut HostMainThreadHandle,
+        target: ContextMenuTarget,
+        screen_index: i32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), ContextMenuError> {
+        let popup = host
+            .use_extension(&self.0)
+            .popup
+            .ok_or(ContextMenuError::Popup)?;
+
+        let target = target.to_raw();
+
+        // SAFETY: This type ensures the function pointer is valid.
+        match unsafe { popup(host.as_raw(), &target, screen_index, x, y) } {
+            true => Ok(()),
+            false => Err(ContextMenuError::Popup),
+        }
+    }
+}
+
+/// Implementation of the Plugin-side of the Context Menu extension.
+pub trait PluginContextMenuImpl {
+    /// Adds the plugin's items for the given target to a menu provided by the host.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the menu could not be populated.
+    fn populate(
+        &mut self,
+        target: ContextMenuTarget,
+        builder: &ContextMenuBuilder,
+    ) -> Result<(), PluginError>;
+
+    /// Performs the action with the given ID, after it was selected by the user in a menu the
+    /// plugin populated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the action could not be performed.
+    fn perform(&mut self, target: ContextMenuTarget, action_id: ClapId) -> Result<(), PluginError>;
+}
+
+// SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+unsafe impl<P> ExtensionImplementation<P> for PluginContextMenu
+where
+    for<'a> P: Plugin<MainThread<'a>: PluginContextMenuImpl>,
+{
+    #[doc(hidden)]
+    const IMPLEMENTATION: RawExtensionImplementation =
+        RawExtensionImplementation::new(&clap_plugin_context_menu {
+            populate: Some(populate::<P>),
+            perform: Some(perform::<P>),
+        });
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn populate<P>(
+    plugin: *const clap_plugin,
+    target: *const clap_context_menu_target,
+    builder: *const clap_context_menu_builder,
+) -> bool
+where
+    for<'a> P: Plugin<MainThread<'a>: PluginContextMenuImpl>,
+{
+    PluginWrapper::<P>::handle(plugin, |p| {
+        let target = target
+            .as_ref()
+            .ok_or(PluginWrapperError::NulPtr("clap_context_menu_target"))?;
+        let target = ContextMenuTarget::from_raw(target).ok_or(
+            PluginWrapperError::InvalidParameter("Invalid context menu target"),
+        )?;
+
+        if builder.is_null() {
+            return Err(PluginWrapperError::NulPtr("clap_context_menu_builder"));
+        }
+
+        let builder = ContextMenuBuilder::from_raw(builder);
+
+        Ok(p.main_thread().as_mut().populate(target, builder).is_ok())
+    })
+    .unwrap_or(false)
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn perform<P>(
+    plugin: *const clap_plugin,
+    target: *const clap_context_menu_target,
+    action_id: u32,
+) -> bool
+where
+    for<'a> P: Plugin<MainThread<'a>: PluginContextMenuImpl>,
+{
+    PluginWrapper::<P>::handle(plugin, |p| {
+        let target = target
+            .as_ref()
+            .ok_or(PluginWrapperError::NulPtr("clap_context_menu_target"))?;
+        let target = ContextMenuTarget::from_raw(target).ok_or(
+            PluginWrapperError::InvalidParameter("Invalid context menu target"),
+        )?;
+
+        let action_id = ClapId::from_raw(action_id)
+            .ok_or(PluginWrapperError::InvalidParameter("Invalid action ID"))?;
+
+        Ok(p.main_thread().as_mut().perform(target, action_id).is_ok())
+    })
+    .unwrap_or(false)
+}
@@ -0,0 +1,31 @@
+//! Draft CLAP extensions.
+//!
+//! These extensions are still being designed in the CLAP specification, and are not part of its
+//! stable API yet. Their ABI, and therefore these wrappers, may change or be removed between
+//! releases.
+//!
+//! Each draft extension is enabled by its own `draft-*` feature, and all of them can be enabled
+//! at once with the `all-draft-extensions` feature.
+
+#[cfg(feature = "draft-gain-adjustment-metering")]
+pub mod gain_adjustment_metering;
+#[cfg(feature = "draft-mini-curve-display")]
+pub mod mini_curve_display;
+#[cfg(feature = "draft-plugin-invalidation")]
+pub mod plugin_invalidation;
+#[cfg(feature = "draft-plugin-state-converter")]
+pub mod plugin_state_converter;
+#[cfg(feature = "draft-project-location")]
+pub mod project_location;
+#[cfg(feature = "draft-resource-directory")]
+pub mod resource_directory;
+#[cfg(feature = "draft-transport-control")]
+pub mod transport_control;
+#[cfg(feature = "draft-triggers")]
+pub mod triggers;
+#[cfg(feature = "draft-tuning")]
+pub mod tuning;
+#[cfg(feature = "draft-undo")]
+pub mod undo;
+#[cfg(feature = "draft-webview")]
+pub mod webview;
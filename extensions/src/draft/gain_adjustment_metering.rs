@@ -0,0 +1,111 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to report the gain adjustment they are currently applying to their signal, e.g.
+//! the gain reduction of a compressor or a limiter.
+//!
+//! The reported value is intended for informational display only, such as a meter in the host's
+//! mixer. It is expressed in decibels: `0.0` means no adjustment, negative values mean the signal
+//! is being attenuated, and positive values mean it is being amplified.
+
+use clack_common::extensions::{Extension, PluginExtensionSide, RawExtension};
+use clap_sys::plugin::clap_plugin;
+use std::ffi::CStr;
+
+/// The identifier of the Gain Adjustment Metering extension.
+pub const CLAP_EXT_GAIN_ADJUSTMENT_METERING: &CStr = c"clap.gain-adjustment-metering/0";
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_plugin_gain_adjustment_metering {
+    get_adjustment: Option<unsafe extern "C" fn(plugin: *const clap_plugin) -> f64>,
+}
+
+/// Plugin-side of the Gain Adjustment Metering extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginGainAdjustmentMetering(
+    RawExtension<PluginExtensionSide, clap_plugin_gain_adjustment_metering>,
+);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginGainAdjustmentMetering {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_GAIN_ADJUSTMENT_METERING];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_host::extensions::prelude::*;
+
+    impl PluginGainAdjustmentMetering {
+        /// Returns the gain adjustment the plugin applied to the last sample it processed, in
+        /// decibels.
+        ///
+        /// This returns `0.0` if the plugin doesn't implement this function, or if it returned a
+        /// non-finite value.
+        #[inline]
+        pub fn get_adjustment(&self, plugin: &mut PluginAudioProcessorHandle) -> f64 {
+            match plugin.use_extension(&self.0).get_adjustment {
+                Some(get_adjustment) => {
+                    // SAFETY: This type ensures the function pointer is valid.
+                    let adjustment = unsafe { get_adjustment(plugin.as_raw()) };
+                    if adjustment.is_finite() {
+                        adjustment
+                    } else {
+                        0.0
+                    }
+                }
+                None => 0.0,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_plugin::extensions::prelude::*;
+
+    /// Implementation of the Plugin-side of the Gain Adjustment Metering extension.
+    pub trait PluginGainAdjustmentMeteringImpl {
+        /// Returns the gain adjustment applied to the last sample of the most recently processed
+        /// block, in decibels.
+        ///
+        /// This should return `0.0` if the plugin isn't adjusting its signal's gain.
+        fn get_adjustment(&mut self) -> f64;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginGainAdjustmentMetering
+    where
+        for<'a> P: Plugin<AudioProcessor<'a>: PluginGainAdjustmentMeteringImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_gain_adjustment_metering {
+                get_adjustment: Some(get_adjustment::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_adjustment<P>(plugin: *const clap_plugin) -> f64
+    where
+        for<'a> P: Plugin<AudioProcessor<'a>: PluginGainAdjustmentMeteringImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |plugin| {
+            Ok(plugin.audio_processor()?.as_mut().get_adjustment())
+        })
+        .unwrap_or(0.0)
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
@@ -0,0 +1,605 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to provide a small curve preview of their processing, such as an EQ's frequency
+//! response or a compressor's transfer curve, which the host can display e.g. in its mixer.
+//!
+//! The host provides a buffer of `u16` values, which the plugin renders its curve into: `0` is
+//! the bottom of the display and [`u16::MAX`] is its top, the first value being the leftmost one.
+//!
+//! The host only requests renders while the curve is being observed, as notified through
+//! `set_observed`. A static curve tells the host when it changed, while a dynamic curve is
+//! periodically re-rendered by the host.
+
+use bitflags::bitflags;
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use std::ffi::{CStr, c_char};
+
+/// The identifier of the Mini Curve Display extension.
+pub const CLAP_EXT_MINI_CURVE_DISPLAY: &CStr = c"clap.mini-curve-display/1";
+
+const CLAP_MINI_CURVE_DISPLAY_CURVE_CHANGED: u32 = 1 << 0;
+const CLAP_MINI_CURVE_DISPLAY_AXIS_NAME_CHANGED: u32 = 1 << 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_mini_display_curve_hints {
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_plugin_mini_curve_display {
+    render: Option<
+        unsafe extern "C" fn(plugin: *const clap_plugin, data: *mut u16, data_size: u32) -> bool,
+    >,
+    set_observed: Option<unsafe extern "C" fn(plugin: *const clap_plugin, is_observed: bool)>,
+    get_axis_name: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            x_name: *mut c_char,
+            y_name: *mut c_char,
+            name_capacity: u32,
+        ) -> bool,
+    >,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_host_mini_curve_display {
+    get_hints: Option<
+        unsafe extern "C" fn(
+            host: *const clap_host,
+            hints: *mut clap_mini_display_curve_hints,
+        ) -> bool,
+    >,
+    set_dynamic: Option<unsafe extern "C" fn(host: *const clap_host, is_dynamic: bool)>,
+    changed: Option<unsafe extern "C" fn(host: *const clap_host, flags: u32)>,
+}
+
+/// Plugin-side of the Mini Curve Display extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginMiniCurveDisplay(
+    RawExtension<PluginExtensionSide, clap_plugin_mini_curve_display>,
+);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginMiniCurveDisplay {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_MINI_CURVE_DISPLAY];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Mini Curve Display extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostMiniCurveDisplay(RawExtension<HostExtensionSide, clap_host_mini_curve_display>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostMiniCurveDisplay {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_MINI_CURVE_DISPLAY];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+bitflags! {
+    /// Flags describing what changed in a plugin's curve.
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct MiniCurveDisplayChangeFlags: u32 {
+        /// The curve's contents changed.
+        ///
+        /// This can only be notified while the curve is observed and static.
+        const CURVE_CHANGED = CLAP_MINI_CURVE_DISPLAY_CURVE_CHANGED;
+        /// The curve's axis names changed.
+        ///
+        /// This can only be notified while the curve is observed.
+        const AXIS_NAME_CHANGED = CLAP_MINI_CURVE_DISPLAY_AXIS_NAME_CHANGED;
+    }
+}
+
+/// Hints given by the host about the range the curve will be displayed in.
+///
+/// This allows the plugin to match the scale of the host's display, e.g. to use the same decibel
+/// range for all of the curves displayed in a mixer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MiniCurveDisplayHints {
+    /// The value of the leftmost point of the display.
+    pub x_min: f64,
+    /// The value of the rightmost point of the display.
+    pub x_max: f64,
+    /// The value of the bottom of the display.
+    pub y_min: f64,
+    /// The value of the top of the display.
+    pub y_max: f64,
+}
+
+impl MiniCurveDisplayHints {
+    #[cfg(feature = "clack-plugin")]
+    #[inline]
+    fn from_raw(raw: &clap_mini_display_curve_hints) -> Self {
+        Self {
+            x_min: raw.x_min,
+            x_max: raw.x_max,
+            y_min: raw.y_min,
+            y_max: raw.y_max,
+        }
+    }
+
+    #[cfg(feature = "clack-host")]
+    #[inline]
+    fn to_raw(self) -> clap_mini_display_curve_hints {
+        clap_mini_display_curve_hints {
+            x_min: self.x_min,
+            x_max: self.x_max,
+            y_min: self.y_min,
+            y_max: self.y_max,
+        }
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_plugin::extensions::prelude::*;
+    use std::mem::MaybeUninit;
+
+    impl HostMiniCurveDisplay {
+        /// Returns the host's hints about the range the curve will be displayed in, if any.
+        pub fn get_hints(&self, host: &mut HostMainThreadHandle) -> Option<MiniCurveDisplayHints> {
+            let get_hints = host.use_extension(&self.0).get_hints?;
+            let mut hints = MaybeUninit::zeroed();
+
+            // SAFETY: This type ensures the function pointer is valid.
+            let success = unsafe { get_hints(host.as_raw(), hints.as_mut_ptr()) };
+
+            if success {
+                // SAFETY: the buffer was zero-initialized, and only contains plain floats.
+                Some(MiniCurveDisplayHints::from_raw(unsafe {
+                    hints.assume_init_ref()
+                }))
+            } else {
+                None
+            }
+        }
+
+        /// Marks the curve as either dynamic or static.
+        ///
+        /// A static curve notifies the host when it changed, using [`changed`](Self::changed),
+        /// while a dynamic curve is constantly changing and is periodically re-rendered by the
+        /// host. Curves are initially considered static.
+        #[inline]
+        pub fn set_dynamic(&self, host: &mut HostMainThreadHandle, is_dynamic: bool) {
+            if let Some(set_dynamic) = host.use_extension(&self.0).set_dynamic {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { set_dynamic(host.as_raw(), is_dynamic) }
+            }
+        }
+
+        /// Informs the host that the curve, or its axis names, changed.
+        #[inline]
+        pub fn changed(&self, host: &mut HostMainThreadHandle, flags: MiniCurveDisplayChangeFlags) {
+            if let Some(changed) = host.use_extension(&self.0).changed {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { changed(host.as_raw(), flags.bits()) }
+            }
+        }
+    }
+
+    /// A writer for the values of a curve, into the buffer provided by the host.
+    ///
+    /// Each value is a point of the curve, from left to right. `0` is the bottom of the display,
+    /// and [`u16::MAX`] is its top.
+    pub struct MiniCurveWriter<'a> {
+        values: &'a mut [u16],
+    }
+
+    impl<'a> MiniCurveWriter<'a> {
+        #[inline]
+        fn new(values: &'a mut [u16]) -> Self {
+            Self { values }
+        }
+
+        /// Returns the number of points in the curve.
+        #[inline]
+        pub fn len(&self) -> usize {
+            self.values.len()
+        }
+
+        /// Returns `true` if the host provided an empty buffer.
+        #[inline]
+        pub fn is_empty(&self) -> bool {
+            self.values.is_empty()
+        }
+
+        /// Returns the curve's values as a mutable slice.
+        #[inline]
+        pub fn values_mut(&mut self) -> &mut [u16] {
+            self.values
+        }
+
+        /// Sets the point at the given index to the given normalized value.
+        ///
+        /// `0.0` is the bottom of the display, and `1.0` is its top. Values out of this range are
+        /// clamped, and NaN is considered as `0.0`. Indices out of bounds are ignored.
+        #[inline]
+        pub fn set(&mut self, index: usize, value: f64) {
+            if let Some(point) = self.values.get_mut(index) {
+                *point = normalized_to_raw(value);
+            }
+        }
+
+        /// Sets all of the curve's points using the given function.
+        ///
+        /// The function receives the normalized horizontal position of each point, from `0.0`
+        /// (leftmost) to `1.0` (rightmost), and returns its normalized value, from `0.0` (bottom)
+        /// to `1.0` (top).
+        pub fn fill_with(&mut self, mut f: impl FnMut(f64) -> f64) {
+            let last = self.values.len().saturating_sub(1).max(1) as f64;
+
+            for (index, point) in self.values.iter_mut().enumerate() {
+                *point = normalized_to_raw(f(index as f64 / last));
+            }
+        }
+    }
+
+    #[inline]
+    fn normalized_to_raw(value: f64) -> u16 {
+        // Float to int casts saturate, and turn NaN into 0.
+        (value.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+    }
+
+    /// A writer for an axis name, into the buffer provided by the host.
+    ///
+    /// Unlike parameter display texts, axis names are never truncated: writing a name which doesn't
+    /// fit in the host's buffer returns an error.
+    pub struct MiniCurveAxisNameWriter<'a> {
+        cursor_position: usize,
+        buffer: &'a mut [u8],
+    }
+
+    impl<'a> MiniCurveAxisNameWriter<'a> {
+        #[inline]
+        fn new(buffer: &'a mut [u8]) -> Self {
+            if let Some(first) = buffer.first_mut() {
+                *first = 0;
+            }
+
+            Self {
+                cursor_position: 0,
+                buffer,
+            }
+        }
+
+        /// Returns the maximum length of the name, in bytes.
+        #[inline]
+        #[allow(clippy::len_without_is_empty)] // Len should never be 0, unless host is misbehaving
+        pub fn len(&self) -> usize {
+            self.buffer.len().saturating_sub(1)
+        }
+
+        /// Returns the remaining number of bytes that can be written.
+        #[inline]
+        pub fn remaining_len(&self) -> usize {
+            self.buffer.len().saturating_sub(self.cursor_position + 1)
+        }
+    }
+
+    impl core::fmt::Write for MiniCurveAxisNameWriter<'_> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let s = s.as_bytes();
+            if s.len() > self.remaining_len() {
+                return Err(core::fmt::Error);
+            }
+
+            self.buffer[self.cursor_position..self.cursor_position + s.len()].copy_from_slice(s);
+            self.cursor_position += s.len();
+            self.buffer[self.cursor_position] = 0;
+
+            Ok(())
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Mini Curve Display extension.
+    pub trait PluginMiniCurveDisplayImpl {
+        /// Renders the curve into the given writer.
+        ///
+        /// Returns `true` if the curve was successfully rendered.
+        fn render(&mut self, curve: &mut MiniCurveWriter) -> bool;
+
+        /// Informs the plugin whether the curve is currently observed by the host.
+        ///
+        /// The host only calls [`render`](Self::render) while the curve is observed. When it
+        /// becomes observed, the curve and its axis names are implicitly considered as changed.
+        fn set_observed(&mut self, is_observed: bool);
+
+        /// Writes the names of the curve's horizontal and vertical axes into the given writers.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if a name doesn't fit in the host's buffer, or if the curve has no
+        /// axis names.
+        fn get_axis_name(
+            &mut self,
+            x_name: &mut MiniCurveAxisNameWriter,
+            y_name: &mut MiniCurveAxisNameWriter,
+        ) -> core::fmt::Result;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginMiniCurveDisplay
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginMiniCurveDisplayImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_mini_curve_display {
+                render: Some(render::<P>),
+                set_observed: Some(set_observed::<P>),
+                get_axis_name: Some(get_axis_name::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn render<P>(
+        plugin: *const clap_plugin,
+        data: *mut u16,
+        data_size: u32,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginMiniCurveDisplayImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let values = if data_size == 0 {
+                &mut []
+            } else if data.is_null() {
+                return Err(PluginWrapperError::NulPtr("Mini curve display data"));
+            } else {
+                core::slice::from_raw_parts_mut(data, data_size as usize)
+            };
+
+            Ok(p.main_thread()
+                .as_mut()
+                .render(&mut MiniCurveWriter::new(values)))
+        })
+        .unwrap_or(false)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_observed<P>(plugin: *const clap_plugin, is_observed: bool)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginMiniCurveDisplayImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread().as_mut().set_observed(is_observed);
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_axis_name<P>(
+        plugin: *const clap_plugin,
+        x_name: *mut c_char,
+        y_name: *mut c_char,
+        name_capacity: u32,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginMiniCurveDisplayImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            if x_name.is_null() {
+                return Err(PluginWrapperError::NulPtr("Mini curve display X axis name"));
+            }
+            if y_name.is_null() {
+                return Err(PluginWrapperError::NulPtr("Mini curve display Y axis name"));
+            }
+            if name_capacity == 0 {
+                return Err(PluginWrapperError::InvalidParameter(
+                    "Mini curve display axis name capacity is zero",
+                ));
+            }
+
+            let x_name =
+                core::slice::from_raw_parts_mut(x_name.cast::<u8>(), name_capacity as usize);
+            let y_name =
+                core::slice::from_raw_parts_mut(y_name.cast::<u8>(), name_capacity as usize);
+
+            Ok(p.main_thread()
+                .as_mut()
+                .get_axis_name(
+                    &mut MiniCurveAxisNameWriter::new(x_name),
+                    &mut MiniCurveAxisNameWriter::new(y_name),
+                )
+                .is_ok())
+        })
+        .unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_host::extensions::prelude::*;
+
+    /// The names of the axes of a plugin's curve.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct MiniCurveAxisNames {
+        /// The name of the horizontal axis.
+        pub x_name: String,
+        /// The name of the vertical axis.
+        pub y_name: String,
+    }
+
+    impl PluginMiniCurveDisplay {
+        /// Asks the plugin to render its curve into the given buffer.
+        ///
+        /// Each value is a point of the curve, from left to right. `0` is the bottom of the
+        /// display, and [`u16::MAX`] is its top.
+        ///
+        /// Returns `true` if the plugin successfully rendered its curve.
+        pub fn render(&self, plugin: &mut PluginMainThreadHandle, values: &mut [u16]) -> bool {
+            let Some(render) = plugin.use_extension(&self.0).render else {
+                return false;
+            };
+
+            let len = u32::try_from(values.len()).unwrap_or(u32::MAX);
+
+            // SAFETY: This type ensures the function pointer is valid.
+            // The buffer is valid for writes of len values.
+            unsafe { render(plugin.as_raw(), values.as_mut_ptr(), len) }
+        }
+
+        /// Informs the plugin whether its curve is currently being displayed.
+        #[inline]
+        pub fn set_observed(&self, plugin: &mut PluginMainThreadHandle, is_observed: bool) {
+            if let Some(set_observed) = plugin.use_extension(&self.0).set_observed {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { set_observed(plugin.as_raw(), is_observed) }
+            }
+        }
+
+        /// Retrieves the names of the curve's axes.
+        ///
+        /// Names longer than 255 bytes can't be retrieved. Invalid UTF-8 is replaced with
+        /// [`char::REPLACEMENT_CHARACTER`].
+        ///
+        /// Returns [`None`] if the plugin failed to provide the axis names.
+        pub fn get_axis_name(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+        ) -> Option<MiniCurveAxisNames> {
+            const NAME_CAPACITY: usize = 256;
+
+            let get_axis_name = plugin.use_extension(&self.0).get_axis_name?;
+            let mut x_name = [0u8; NAME_CAPACITY];
+            let mut y_name = [0u8; NAME_CAPACITY];
+
+            // SAFETY: This type ensures the function pointer is valid.
+            // Both buffers are valid for writes of NAME_CAPACITY bytes.
+            let success = unsafe {
+                get_axis_name(
+                    plugin.as_raw(),
+                    x_name.as_mut_ptr().cast(),
+                    y_name.as_mut_ptr().cast(),
+                    NAME_CAPACITY as u32,
+                )
+            };
+
+            if !success {
+                return None;
+            }
+
+            Some(MiniCurveAxisNames {
+                x_name: string_from_buffer(&x_name),
+                y_name: string_from_buffer(&y_name),
+            })
+        }
+    }
+
+    fn string_from_buffer(buffer: &[u8]) -> String {
+        let len = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+        String::from_utf8_lossy(&buffer[..len]).into_owned()
+    }
+
+    /// Implementation of the Host-side of the Mini Curve Display extension.
+    pub trait HostMiniCurveDisplayImpl {
+        /// Returns hints about the range the plugin's curve will be displayed in, if any.
+        fn get_hints(&mut self) -> Option<MiniCurveDisplayHints>;
+
+        /// Called when the plugin marks its curve as either dynamic or static.
+        ///
+        /// A dynamic curve is constantly changing, and should be periodically re-rendered.
+        fn set_dynamic(&mut self, is_dynamic: bool);
+
+        /// Called when the plugin's curve, or its axis names, changed.
+        fn changed(&mut self, flags: MiniCurveDisplayChangeFlags);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostMiniCurveDisplay
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostMiniCurveDisplayImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_mini_curve_display {
+                get_hints: Some(get_hints::<H>),
+                set_dynamic: Some(set_dynamic::<H>),
+                changed: Some(changed::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_hints<H>(
+        host: *const clap_host,
+        hints: *mut clap_mini_display_curve_hints,
+    ) -> bool
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostMiniCurveDisplayImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            if hints.is_null() {
+                return Err(HostWrapperError::InvalidParameter(
+                    "Mini curve display hints pointer is null",
+                ));
+            }
+
+            match host.main_thread().as_mut().get_hints() {
+                Some(value) => {
+                    hints.write(value.to_raw());
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+        .unwrap_or(false)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_dynamic<H>(host: *const clap_host, is_dynamic: bool)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostMiniCurveDisplayImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().set_dynamic(is_dynamic);
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn changed<H>(host: *const clap_host, flags: u32)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostMiniCurveDisplayImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread()
+                .as_mut()
+                .changed(MiniCurveDisplayChangeFlags::from_bits_truncate(flags));
+            Ok(())
+        });
+    }
+}
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
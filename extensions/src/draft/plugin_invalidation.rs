@@ -0,0 +1,359 @@
+#![deny(missing_docs)]
+
+//! Allows plugin bundles to tell the host which files and directories, when modified, should
+//! cause the host to re-scan the plugins they expose.
+//!
+//! This is mostly useful for plugins that are themselves loaders for other content, such as
+//! plugin shells or plugins generated from user scripts: a change to these files may add, remove
+//! or modify the plugins exposed by the bundle, without the bundle itself changing.
+//!
+//! Hosts watch the [`InvalidationSource`]s listed by the [`PluginInvalidationFactory`], and call
+//! [`refresh`](PluginInvalidationFactory::refresh) when one of them changed. If refreshing is not
+//! possible, the host has to reload the whole bundle instead.
+
+use crate::utils::{OwnedCString, path_from_bytes, path_to_bytes};
+use clack_common::factory::{Factory, RawFactoryPointer};
+use clap_sys::factory::draft::plugin_invalidation::*;
+use std::ffi::{CStr, CString};
+use std::path::Path;
+
+/// A [`Factory`] which allows hosts to know which files may invalidate the list of plugins
+/// exposed by a bundle.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub struct PluginInvalidationFactory<'a>(RawFactoryPointer<'a, clap_plugin_invalidation_factory>);
+
+// SAFETY: clap_plugin_invalidation_factory is the CLAP type tied to CLAP_PLUGIN_INVALIDATION_FACTORY_ID
+unsafe impl<'a> Factory<'a> for PluginInvalidationFactory<'a> {
+    const IDENTIFIERS: &'static [&'static CStr] = &[CLAP_PLUGIN_INVALIDATION_FACTORY_ID];
+    type Raw = clap_plugin_invalidation_factory;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawFactoryPointer<'a, Self::Raw>) -> Self {
+        Self(raw)
+    }
+}
+
+impl<'a> PluginInvalidationFactory<'a> {
+    /// Returns this factory as a raw pointer to its C-FFI compatible raw CLAP structure
+    #[inline]
+    pub const fn raw(&self) -> RawFactoryPointer<'a, clap_plugin_invalidation_factory> {
+        self.0
+    }
+}
+
+/// A set of files which, when modified, invalidate the list of plugins exposed by a bundle.
+///
+/// A source is made of a `directory`, and a `filename_glob` pattern matching the files to watch
+/// inside that directory (e.g. `"*.wav"`). The directory may also be scanned recursively.
+///
+/// Note that the read accessors of this type are exposed as the CLAP-native [`CStr`], as they are
+/// not required by the CLAP spec to be UTF-8 compliant.
+///
+/// # Example
+///
+/// ```
+/// use clack_extensions::draft::plugin_invalidation::InvalidationSource;
+/// use std::path::Path;
+///
+/// let source = InvalidationSource::new(Path::new("/opt/my-synth/scripts"), "*.lua")
+///     .with_recursive_scan(true);
+/// ```
+#[repr(C)]
+#[derive(Clone)]
+pub struct InvalidationSource {
+    directory: OwnedCString,
+    filename_glob: OwnedCString,
+    recursive_scan: bool,
+}
+
+impl InvalidationSource {
+    /// Creates a new, non-recursive invalidation source, watching all the files matching the
+    /// given `filename_glob` pattern in the given `directory`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if either the given directory or pattern are empty, or if they
+    /// contain NULL-byte characters, which are invalid.
+    ///
+    /// On non-Unix platforms, this function will also panic if the given directory is not valid
+    /// UTF-8.
+    pub fn new(directory: &Path, filename_glob: &str) -> Self {
+        let directory = path_to_bytes(directory).expect("Invalid invalidation source directory");
+        if directory.is_empty() {
+            panic!("Invalidation source directory must not be blank!");
+        }
+
+        if filename_glob.is_empty() {
+            panic!("Invalidation source filename glob must not be blank!");
+        }
+
+        let directory = CString::new(directory).expect("Invalid invalidation source directory");
+        let filename_glob =
+            CString::new(filename_glob).expect("Invalid invalidation source filename glob");
+
+        Self {
+            directory: OwnedCString::new(Some(directory)),
+            filename_glob: OwnedCString::new(Some(filename_glob)),
+            recursive_scan: false,
+        }
+    }
+
+    /// Sets whether the directory should be scanned recursively.
+    #[inline]
+    pub fn with_recursive_scan(mut self, recursive_scan: bool) -> Self {
+        self.recursive_scan = recursive_scan;
+        self
+    }
+
+    /// Creates an [`InvalidationSource`] reference from a pointer to a raw, C-FFI compatible CLAP
+    /// invalidation source structure.
+    ///
+    /// # Safety
+    ///
+    /// All fields must either be null, or point to a valid for reads, null-terminated C string. All
+    /// must also be valid for reads for the lifetime of the resulting [`InvalidationSource`] reference.
+    pub const unsafe fn from_raw(raw: &clap_plugin_invalidation_source) -> &Self {
+        // SAFETY: Same as ProviderDescriptor::from_raw: we MUST be CERTAIN that neither `set` or
+        // `Drop` can be called on any of the transmuted fields, which returning a shared reference
+        // guarantees.
+        unsafe { &*(raw as *const clap_plugin_invalidation_source as *const Self) }
+    }
+
+    /// Returns the invalidation source as a reference to the C-FFI compatible CLAP struct.
+    #[inline]
+    pub fn as_raw(&self) -> &clap_plugin_invalidation_source {
+        // SAFETY: This type is ABI-compatible with clap_plugin_invalidation_source
+        unsafe { &*(self as *const Self as *const clap_plugin_invalidation_source) }
+    }
+
+    /// The directory containing the files to watch, as a raw C string.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn directory(&self) -> Option<&CStr> {
+        self.directory.get()
+    }
+
+    /// The directory containing the files to watch.
+    ///
+    /// This method will return [`None`] if this is either missing, a blank string, or is not a
+    /// valid path on this platform.
+    #[inline]
+    pub fn directory_path(&self) -> Option<&Path> {
+        path_from_bytes(self.directory()?.to_bytes())
+    }
+
+    /// The glob pattern matching the names of the files to watch, e.g. `"*.wav"`.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn filename_glob(&self) -> Option<&CStr> {
+        self.filename_glob.get()
+    }
+
+    /// Whether the directory should be scanned recursively.
+    #[inline]
+    pub fn recursive_scan(&self) -> bool {
+        self.recursive_scan
+    }
+}
+
+const _: () = {
+    assert!(align_of::<clap_plugin_invalidation_source>() == align_of::<InvalidationSource>());
+    assert!(size_of::<clap_plugin_invalidation_source>() == size_of::<InvalidationSource>());
+};
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_plugin::factory::*;
+
+    /// A wrapper around a given [`PluginInvalidationFactoryImpl`] implementation.
+    ///
+    /// This wrapper is required in order to expose a C FFI-compatible factory to the host, and is
+    /// what needs to be exposed by an [`Entry`](clack_plugin::entry::Entry).
+    pub struct PluginInvalidationFactoryWrapper<F> {
+        inner: FactoryWrapper<clap_plugin_invalidation_factory, F>,
+    }
+
+    impl<F: PluginInvalidationFactoryImpl> PluginInvalidationFactoryWrapper<F> {
+        const RAW: clap_plugin_invalidation_factory = clap_plugin_invalidation_factory {
+            count: Some(count::<F>),
+            get: Some(get::<F>),
+            refresh: Some(refresh::<F>),
+        };
+
+        /// Wraps a given [`PluginInvalidationFactoryImpl`] implementation.
+        pub fn new(inner: F) -> Self {
+            Self {
+                inner: FactoryWrapper::new(Self::RAW, inner),
+            }
+        }
+    }
+
+    // SAFETY: The returned raw implementation matches the spec for clap_plugin_invalidation_factory
+    unsafe impl<'a, F: PluginInvalidationFactoryImpl + 'a> FactoryImplementation<'a>
+        for PluginInvalidationFactoryWrapper<F>
+    {
+        type Factory = PluginInvalidationFactory<'a>;
+
+        type Wrapped = F;
+
+        #[inline]
+        fn wrapper(&self) -> &FactoryWrapper<<Self::Factory as Factory<'a>>::Raw, Self::Wrapped> {
+            &self.inner
+        }
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn count<F: PluginInvalidationFactoryImpl>(
+        factory: *const clap_plugin_invalidation_factory,
+    ) -> u32 {
+        FactoryWrapper::<_, F>::handle(factory, |factory| Ok(factory.source_count())).unwrap_or(0)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get<F: PluginInvalidationFactoryImpl>(
+        factory: *const clap_plugin_invalidation_factory,
+        index: u32,
+    ) -> *const clap_plugin_invalidation_source {
+        FactoryWrapper::<_, F>::handle(factory, |factory| match factory.source(index) {
+            Some(source) => Ok(source.as_raw() as *const _),
+            None => Ok(core::ptr::null()),
+        })
+        .unwrap_or(core::ptr::null())
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn refresh<F: PluginInvalidationFactoryImpl>(
+        factory: *const clap_plugin_invalidation_factory,
+    ) -> bool {
+        FactoryWrapper::<_, F>::handle(factory, |factory| Ok(factory.refresh())).unwrap_or(false)
+    }
+
+    /// A [`PluginInvalidationFactory`] implementation.
+    pub trait PluginInvalidationFactoryImpl: Send + Sync {
+        /// Returns the number of invalidation sources exposed by this factory.
+        fn source_count(&self) -> u32;
+
+        /// Returns the [`InvalidationSource`] that is assigned the given index.
+        ///
+        /// If the given index is out of bounds, this returns [`None`].
+        fn source(&self, index: u32) -> Option<&InvalidationSource>;
+
+        /// Called by the host when one of the invalidation sources changed.
+        ///
+        /// The plugin bundle should then update the list of plugins it exposes through its plugin
+        /// factory. If this is not possible, this returns `false`, and the host will then reload
+        /// the whole bundle instead.
+        fn refresh(&self) -> bool;
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use std::iter::FusedIterator;
+
+    impl<'a> PluginInvalidationFactory<'a> {
+        /// Returns the number of invalidation sources exposed by this factory.
+        pub fn source_count(&self) -> u32 {
+            let Some(count) = self.0.get().count else {
+                return 0;
+            };
+
+            // SAFETY: This type enforces the contained pointer is still valid.
+            unsafe { count(self.0.as_ptr()) }
+        }
+
+        /// Returns the [`InvalidationSource`] that is assigned the given index.
+        ///
+        /// If the given index is out of bounds, this returns [`None`].
+        pub fn get_source(&self, index: u32) -> Option<&'a InvalidationSource> {
+            let get = self.0.get().get?;
+
+            // SAFETY: This type enforces the contained pointer is still valid.
+            let source = unsafe { get(self.0.as_ptr(), index) };
+
+            // SAFETY: The CLAP spec guarantees that if non-NULL, the source pointer is properly
+            // aligned and valid, and that its contents are valid C strings for as long as the
+            // factory lives, which this type tracks as the 'a lifetime.
+            unsafe { Some(InvalidationSource::from_raw(source.as_ref()?)) }
+        }
+
+        /// Returns an iterator over all the [`InvalidationSource`]s exposed by this factory.
+        #[inline]
+        pub fn sources(&self) -> InvalidationSourcesIter<'a> {
+            InvalidationSourcesIter {
+                factory: *self,
+                range: 0..self.source_count(),
+            }
+        }
+
+        /// Asks the plugin bundle to update its list of plugins, after one of the invalidation
+        /// sources changed.
+        ///
+        /// If this returns `false`, the bundle could not refresh its list of plugins, and the host
+        /// must reload the whole bundle instead.
+        pub fn refresh(&self) -> bool {
+            let Some(refresh) = self.0.get().refresh else {
+                return false;
+            };
+
+            // SAFETY: This type enforces the contained pointer is still valid.
+            unsafe { refresh(self.0.as_ptr()) }
+        }
+    }
+
+    /// An [`Iterator`] over all the [`InvalidationSource`]s exposed by a plugin invalidation
+    /// factory.
+    ///
+    /// See the [`PluginInvalidationFactory::sources`] method that produces this iterator.
+    pub struct InvalidationSourcesIter<'a> {
+        factory: PluginInvalidationFactory<'a>,
+        range: core::ops::Range<u32>,
+    }
+
+    impl<'a> Iterator for InvalidationSourcesIter<'a> {
+        type Item = &'a InvalidationSource;
+
+        #[inline]
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                let next = self.range.next()?;
+
+                if let Some(source) = self.factory.get_source(next) {
+                    return Some(source);
+                }
+            }
+        }
+
+        #[inline]
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            // Sources the plugin fails to return are skipped
+            (0, Some(self.range.len()))
+        }
+    }
+
+    impl FusedIterator for InvalidationSourcesIter<'_> {}
+
+    impl DoubleEndedIterator for InvalidationSourcesIter<'_> {
+        #[inline]
+        fn next_back(&mut self) -> Option<Self::Item> {
+            loop {
+                let next = self.range.next_back()?;
+
+                if let Some(source) = self.factory.get_source(next) {
+                    return Some(source);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
@@ -0,0 +1,283 @@
+#![deny(missing_docs)]
+
+//! Allows plugin bundles to convert the state of a plugin into the state of another plugin.
+//!
+//! This is mainly used when a plugin is superseded by a newer one with a different ID, or when
+//! migrating from a plugin using another plugin API (e.g. VST3) to its CLAP counterpart. In both
+//! cases, the host can use a state converter to load a project saved with the old plugin into the
+//! new one.
+//!
+//! Each converter is described by a [`StateConverterDescriptor`], which gives the
+//! [`UniversalPluginId`]s of both the source and destination plugins. On top of the state itself,
+//! converters can also convert parameter values, e.g. to translate automation data.
+
+use crate::utils::OwnedCString;
+use clack_common::factory::{Factory, RawFactoryPointer};
+use clack_common::utils::UniversalPluginId;
+use clap_sys::factory::draft::plugin_state_converter::*;
+use clap_sys::version::{CLAP_VERSION, clap_version};
+use std::ffi::{CStr, CString};
+
+#[cfg(feature = "clack-host")]
+mod host;
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
+
+#[cfg(feature = "clack-plugin")]
+mod plugin;
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+/// A [`Factory`] which allows hosts to list and create plugin state converters.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub struct PluginStateConverterFactory<'a>(
+    RawFactoryPointer<'a, clap_plugin_state_converter_factory>,
+);
+
+// SAFETY: clap_plugin_state_converter_factory is the CLAP type tied to CLAP_PLUGIN_STATE_CONVERTER_FACTORY_ID
+unsafe impl<'a> Factory<'a> for PluginStateConverterFactory<'a> {
+    const IDENTIFIERS: &'static [&'static CStr] = &[CLAP_PLUGIN_STATE_CONVERTER_FACTORY_ID];
+    type Raw = clap_plugin_state_converter_factory;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawFactoryPointer<'a, Self::Raw>) -> Self {
+        Self(raw)
+    }
+}
+
+impl<'a> PluginStateConverterFactory<'a> {
+    /// Returns this factory as a raw pointer to its C-FFI compatible raw CLAP structure
+    #[inline]
+    pub const fn raw(&self) -> RawFactoryPointer<'a, clap_plugin_state_converter_factory> {
+        self.0
+    }
+}
+
+/// Provides metadata about a given state converter, such as its ID, name, and the plugins it
+/// converts from and to.
+///
+/// The [`id`](StateConverterDescriptor::id) and [`name`](StateConverterDescriptor::name) fields,
+/// as well as both plugin IDs, are required by the CLAP specification. All the other fields are
+/// completely optional.
+///
+/// Note that all the read accessors of this type are exposed as the CLAP-native [`CStr`], as they
+/// are not required by the CLAP spec to be UTF-8 compliant.
+///
+/// The write accessors on this type take [string](str) references for convenience reasons, but they
+/// will still internally convert them into null-terminated C strings, and panic if that conversion fails.
+///
+/// # Example
+///
+/// ```
+/// use clack_common::utils::UniversalPluginId;
+/// use clack_extensions::draft::plugin_state_converter::StateConverterDescriptor;
+///
+/// fn get_descriptor() -> StateConverterDescriptor {
+///     StateConverterDescriptor::new(
+///         "org.rust-audio.clack.gain-v1-to-v2",
+///         "Gain v1 to v2",
+///         UniversalPluginId::clap(c"org.rust-audio.clack.gain"),
+///         UniversalPluginId::clap(c"org.rust-audio.clack.gain-v2"),
+///     )
+///     .with_vendor("Clack")
+/// }
+/// ```
+#[repr(C)]
+#[derive(Clone)]
+pub struct StateConverterDescriptor {
+    clap_version: clap_version,
+    src_plugin_abi: OwnedCString,
+    src_plugin_id: OwnedCString,
+    dst_plugin_abi: OwnedCString,
+    dst_plugin_id: OwnedCString,
+    id: OwnedCString,
+    name: OwnedCString,
+    vendor: OwnedCString,
+    version: OwnedCString,
+    description: OwnedCString,
+}
+
+impl StateConverterDescriptor {
+    /// Creates a new state converter descriptor, initializing it with the given converter ID and
+    /// name, as well as the IDs of the plugins it converts the state from and to.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if either the given ID or name are empty strings, or if they
+    /// contain NULL-byte characters, which are invalid.
+    pub fn new(
+        id: &str,
+        name: &str,
+        src_plugin_id: UniversalPluginId,
+        dst_plugin_id: UniversalPluginId,
+    ) -> Self {
+        if id.is_empty() {
+            panic!("State converter ID must not be blank!");
+        }
+
+        if name.is_empty() {
+            panic!("State converter name must not be blank!");
+        }
+
+        let mut descriptor = Self {
+            clap_version: CLAP_VERSION,
+            src_plugin_abi: OwnedCString::new(Some(src_plugin_id.abi.to_owned())),
+            src_plugin_id: OwnedCString::new(Some(src_plugin_id.id.to_owned())),
+            dst_plugin_abi: OwnedCString::new(Some(dst_plugin_id.abi.to_owned())),
+            dst_plugin_id: OwnedCString::new(Some(dst_plugin_id.id.to_owned())),
+            id: OwnedCString::empty(),
+            name: OwnedCString::empty(),
+            vendor: OwnedCString::empty(),
+            version: OwnedCString::empty(),
+            description: OwnedCString::empty(),
+        };
+
+        let id = CString::new(id).expect("Invalid State converter ID");
+        descriptor.id.set(Some(id));
+
+        let name = CString::new(name).expect("Invalid State converter name");
+        descriptor.name.set(Some(name));
+
+        descriptor
+    }
+
+    /// Creates a [`StateConverterDescriptor`] reference from a pointer to a raw, C-FFI compatible
+    /// CLAP descriptor structure.
+    ///
+    /// # Safety
+    ///
+    /// All string fields must either be null, or point to a valid for reads, null-terminated C
+    /// string. All must also be valid for reads for the lifetime of the resulting
+    /// [`StateConverterDescriptor`] reference.
+    pub const unsafe fn from_raw(raw: &clap_plugin_state_converter_descriptor) -> &Self {
+        // SAFETY: Same as ProviderDescriptor::from_raw: we MUST be CERTAIN that neither `set` or
+        // `Drop` can be called on any of the transmuted fields, which returning a shared reference
+        // guarantees.
+        unsafe { &*(raw as *const clap_plugin_state_converter_descriptor as *const Self) }
+    }
+
+    /// Returns the state converter descriptor as a reference to the C-FFI compatible CLAP struct.
+    #[inline]
+    pub fn as_raw(&self) -> &clap_plugin_state_converter_descriptor {
+        // SAFETY: This type is ABI-compatible with clap_plugin_state_converter_descriptor
+        unsafe { &*(self as *const Self as *const clap_plugin_state_converter_descriptor) }
+    }
+
+    /// The ID of the plugin this converter reads the state of.
+    ///
+    /// This method will return [`None`] if either the ABI or the ID is missing or blank.
+    #[inline]
+    pub fn src_plugin_id(&self) -> Option<UniversalPluginId<'_>> {
+        Some(UniversalPluginId {
+            abi: self.src_plugin_abi.get()?,
+            id: self.src_plugin_id.get()?,
+        })
+    }
+
+    /// The ID of the plugin this converter produces the state of.
+    ///
+    /// This method will return [`None`] if either the ABI or the ID is missing or blank.
+    #[inline]
+    pub fn dst_plugin_id(&self) -> Option<UniversalPluginId<'_>> {
+        Some(UniversalPluginId {
+            abi: self.dst_plugin_abi.get()?,
+            id: self.dst_plugin_id.get()?,
+        })
+    }
+
+    /// The unique identifier of this converter.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn id(&self) -> Option<&CStr> {
+        self.id.get()
+    }
+
+    /// The display name of this converter.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn name(&self) -> Option<&CStr> {
+        self.name.get()
+    }
+
+    /// The vendor of this converter.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn vendor(&self) -> Option<&CStr> {
+        self.vendor.get()
+    }
+
+    /// Sets the converter's vendor name.
+    ///
+    /// Passing an empty string will mark it as unset, making
+    /// [`vendor`](StateConverterDescriptor::vendor) then return `None`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the given vendor name contains NULL-byte characters, which are
+    /// invalid.
+    pub fn with_vendor(mut self, vendor: &str) -> Self {
+        self.vendor.set_str(vendor);
+        self
+    }
+
+    /// The version string of this converter.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn version(&self) -> Option<&CStr> {
+        self.version.get()
+    }
+
+    /// Sets the converter's version string.
+    ///
+    /// Passing an empty string will mark it as unset, making
+    /// [`version`](StateConverterDescriptor::version) then return `None`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the given version contains NULL-byte characters, which are
+    /// invalid.
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version.set_str(version);
+        self
+    }
+
+    /// A short description of what this converter does.
+    ///
+    /// This method will return [`None`] if this is either missing or a blank (i.e. empty) string.
+    #[inline]
+    pub fn description(&self) -> Option<&CStr> {
+        self.description.get()
+    }
+
+    /// Sets the converter's description.
+    ///
+    /// Passing an empty string will mark it as unset, making
+    /// [`description`](StateConverterDescriptor::description) then return `None`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the given description contains NULL-byte characters, which are
+    /// invalid.
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description.set_str(description);
+        self
+    }
+}
+
+const _: () = {
+    assert!(
+        align_of::<clap_plugin_state_converter_descriptor>()
+            == align_of::<StateConverterDescriptor>()
+    );
+    assert!(
+        size_of::<clap_plugin_state_converter_descriptor>()
+            == size_of::<StateConverterDescriptor>()
+    );
+};
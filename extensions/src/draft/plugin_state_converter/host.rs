@@ -0,0 +1,293 @@
+use super::*;
+use crate::utils::data_from_array_buf;
+use clack_common::stream::{InputStream, OutputStream};
+use clack_common::utils::ClapId;
+use std::error::Error;
+use std::ffi::c_char;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+impl<'a> PluginStateConverterFactory<'a> {
+    /// Returns the number of state converters exposed by this factory.
+    pub fn converter_count(&self) -> u32 {
+        let Some(count) = self.0.get().count else {
+            return 0;
+        };
+
+        // SAFETY: This type enforces the contained pointer is still valid.
+        unsafe { count(self.0.as_ptr()) }
+    }
+
+    /// Returns the [`StateConverterDescriptor`] of the converter that is assigned the given index.
+    ///
+    /// If the given index is out of bounds, or in general does not match any given converters, this
+    /// returns [`None`].
+    pub fn get_descriptor(&self, index: u32) -> Option<&'a StateConverterDescriptor> {
+        let get_descriptor = self.0.get().get_descriptor?;
+
+        // SAFETY: This type enforces the contained pointer is still valid.
+        let descriptor = unsafe { get_descriptor(self.0.as_ptr(), index) };
+
+        // SAFETY: The CLAP spec guarantees that if non-NULL, the descriptor pointer is properly
+        // aligned and valid, and that its contents are either NULL or valid C strings, for as long
+        // as the factory lives, which this type tracks as the 'a lifetime.
+        unsafe { Some(StateConverterDescriptor::from_raw(descriptor.as_ref()?)) }
+    }
+
+    /// Returns an iterator over all the [`StateConverterDescriptor`]s exposed by this factory.
+    #[inline]
+    pub fn descriptors(&self) -> StateConverterDescriptorsIter<'a> {
+        StateConverterDescriptorsIter {
+            factory: *self,
+            range: 0..self.converter_count(),
+        }
+    }
+
+    /// Creates a new instance of the state converter matching the given `converter_id`.
+    ///
+    /// # Errors
+    ///
+    /// This returns [`StateConverterError::CreationFailed`] if the factory does not know the given
+    /// converter ID, or if it failed to create the converter.
+    pub fn create(&self, converter_id: &CStr) -> Result<StateConverter<'a>, StateConverterError> {
+        let create = self
+            .0
+            .get()
+            .create
+            .ok_or(StateConverterError::CreationFailed)?;
+
+        // SAFETY: This type enforces the contained pointer is still valid.
+        let converter = unsafe { create(self.0.as_ptr(), converter_id.as_ptr()) };
+
+        Ok(StateConverter {
+            raw: NonNull::new(converter).ok_or(StateConverterError::CreationFailed)?,
+            _lifetime: PhantomData,
+        })
+    }
+}
+
+/// An [`Iterator`] over all the [`StateConverterDescriptor`]s exposed by a plugin state converter
+/// factory.
+///
+/// See the [`PluginStateConverterFactory::descriptors`] method that produces this iterator.
+pub struct StateConverterDescriptorsIter<'a> {
+    factory: PluginStateConverterFactory<'a>,
+    range: core::ops::Range<u32>,
+}
+
+impl<'a> Iterator for StateConverterDescriptorsIter<'a> {
+    type Item = &'a StateConverterDescriptor;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.range.next()?;
+
+            if let Some(descriptor) = self.factory.get_descriptor(next) {
+                return Some(descriptor);
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Descriptors the plugin fails to return are skipped
+        (0, Some(self.range.len()))
+    }
+}
+
+impl FusedIterator for StateConverterDescriptorsIter<'_> {}
+
+impl DoubleEndedIterator for StateConverterDescriptorsIter<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.range.next_back()?;
+
+            if let Some(descriptor) = self.factory.get_descriptor(next) {
+                return Some(descriptor);
+            }
+        }
+    }
+}
+
+/// An instance of a state converter, created by a [`PluginStateConverterFactory`].
+///
+/// The converter is destroyed when this type is dropped.
+pub struct StateConverter<'a> {
+    raw: NonNull<clap_plugin_state_converter>,
+    _lifetime: PhantomData<&'a clap_plugin_state_converter_factory>,
+}
+
+impl<'a> StateConverter<'a> {
+    /// Returns the [`StateConverterDescriptor`] of this converter, if the plugin provided one.
+    pub fn descriptor(&self) -> Option<&'a StateConverterDescriptor> {
+        // SAFETY: This type ensures the converter pointer is still valid. The CLAP spec guarantees
+        // that if non-NULL, the descriptor is valid for as long as the factory lives.
+        unsafe {
+            Some(StateConverterDescriptor::from_raw(
+                self.get().desc.as_ref()?,
+            ))
+        }
+    }
+
+    /// Converts the state read from the given `reader`, and writes the converted state to the
+    /// given `writer`.
+    ///
+    /// # Errors
+    ///
+    /// This returns [`StateConverterError::ConversionFailed`] if the conversion failed, along
+    /// with the error message given by the plugin, if any.
+    pub fn convert_state(
+        &mut self,
+        reader: &mut impl Read,
+        writer: &mut impl Write,
+    ) -> Result<(), StateConverterError> {
+        let convert_state = self
+            .get()
+            .convert_state
+            .ok_or(StateConverterError::ConversionFailed(String::new()))?;
+
+        let mut input = InputStream::from_reader(reader);
+        let mut output = OutputStream::from_writer(writer);
+        let mut error_buffer: [c_char; 1024] = [0; 1024];
+
+        // SAFETY: This type ensures the function pointer and converter pointer are valid.
+        let success = unsafe {
+            convert_state(
+                self.raw.as_ptr(),
+                input.as_raw_mut(),
+                output.as_raw_mut(),
+                error_buffer.as_mut_ptr(),
+                error_buffer.len(),
+            )
+        };
+
+        if success {
+            Ok(())
+        } else {
+            let message = data_from_array_buf(&error_buffer);
+            Err(StateConverterError::ConversionFailed(
+                String::from_utf8_lossy(message).into_owned(),
+            ))
+        }
+    }
+
+    /// Converts the given normalized value of a parameter of the source plugin to the matching
+    /// parameter ID and normalized value of the destination plugin.
+    ///
+    /// This returns [`None`] if the parameter could not be converted, e.g. if it does not exist in
+    /// the destination plugin.
+    pub fn convert_normalized_value(
+        &mut self,
+        src_param_id: ClapId,
+        src_normalized_value: f64,
+    ) -> Option<(ClapId, f64)> {
+        let convert = self.get().convert_normalized_value?;
+
+        let mut dst_param_id = u32::MAX;
+        let mut dst_normalized_value = 0.0;
+
+        // SAFETY: This type ensures the function pointer and converter pointer are valid.
+        let success = unsafe {
+            convert(
+                self.raw.as_ptr(),
+                src_param_id.get(),
+                src_normalized_value,
+                &mut dst_param_id,
+                &mut dst_normalized_value,
+            )
+        };
+
+        if !success {
+            return None;
+        }
+
+        Some((ClapId::from_raw(dst_param_id)?, dst_normalized_value))
+    }
+
+    /// Converts the given plain value of a parameter of the source plugin to the matching
+    /// parameter ID and plain value of the destination plugin.
+    ///
+    /// This returns [`None`] if the parameter could not be converted, e.g. if it does not exist in
+    /// the destination plugin.
+    pub fn convert_plain_value(
+        &mut self,
+        src_param_id: ClapId,
+        src_plain_value: f64,
+    ) -> Option<(ClapId, f64)> {
+        let convert = self.get().convert_plain_value?;
+
+        let mut dst_param_id = u32::MAX;
+        let mut dst_plain_value = 0.0;
+
+        // SAFETY: This type ensures the function pointer and converter pointer are valid.
+        let success = unsafe {
+            convert(
+                self.raw.as_ptr(),
+                src_param_id.get(),
+                src_plain_value,
+                &mut dst_param_id,
+                &mut dst_plain_value,
+            )
+        };
+
+        if !success {
+            return None;
+        }
+
+        Some((ClapId::from_raw(dst_param_id)?, dst_plain_value))
+    }
+
+    /// Returns the raw, C-FFI compatible pointer to this converter.
+    #[inline]
+    pub fn as_raw(&self) -> *mut clap_plugin_state_converter {
+        self.raw.as_ptr()
+    }
+
+    #[inline]
+    fn get(&self) -> clap_plugin_state_converter {
+        // SAFETY: This type ensures the converter pointer is valid until it is destroyed.
+        unsafe { self.raw.read() }
+    }
+}
+
+impl Drop for StateConverter<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(destroy) = self.get().destroy {
+            // SAFETY: This type ensures the converter pointer is valid, and it is never used again.
+            unsafe { destroy(self.raw.as_ptr()) }
+        }
+    }
+}
+
+/// Errors that can occur while creating or using a [`StateConverter`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StateConverterError {
+    /// The factory failed to create the requested state converter.
+    CreationFailed,
+    /// The state converter failed to convert the state.
+    ///
+    /// This contains the error message given by the plugin, which may be empty.
+    ConversionFailed(String),
+}
+
+impl Display for StateConverterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreationFailed => f.write_str("Failed to create plugin state converter"),
+            Self::ConversionFailed(message) if message.is_empty() => {
+                f.write_str("Failed to convert plugin state")
+            }
+            Self::ConversionFailed(message) => {
+                write!(f, "Failed to convert plugin state: {message}")
+            }
+        }
+    }
+}
+
+impl Error for StateConverterError {}
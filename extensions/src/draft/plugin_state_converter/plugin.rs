@@ -0,0 +1,322 @@
+use super::*;
+use crate::utils::{cstr_from_nullable_ptr, handle_panic};
+use clack_common::stream::{InputStream, OutputStream};
+use clack_common::utils::ClapId;
+use clack_plugin::extensions::prelude::PluginWrapperError;
+use clack_plugin::factory::*;
+use clack_plugin::prelude::PluginError;
+use clap_sys::id::clap_id;
+use clap_sys::stream::{clap_istream, clap_ostream};
+use std::ffi::c_char;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::panic::AssertUnwindSafe;
+
+/// A wrapper around a given [`PluginStateConverterFactoryImpl`] implementation.
+///
+/// This wrapper is required in order to expose a C FFI-compatible factory to the host, and is what
+/// needs to be exposed by an [`Entry`](clack_plugin::entry::Entry).
+pub struct PluginStateConverterFactoryWrapper<F> {
+    inner: FactoryWrapper<clap_plugin_state_converter_factory, F>,
+}
+
+impl<F: PluginStateConverterFactoryImpl> PluginStateConverterFactoryWrapper<F> {
+    const RAW: clap_plugin_state_converter_factory = clap_plugin_state_converter_factory {
+        count: Some(count::<F>),
+        get_descriptor: Some(get_descriptor::<F>),
+        create: Some(create::<F>),
+    };
+
+    /// Wraps a given [`PluginStateConverterFactoryImpl`] implementation.
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner: FactoryWrapper::new(Self::RAW, inner),
+        }
+    }
+}
+
+// SAFETY: The returned raw implementation matches the spec for clap_plugin_state_converter_factory
+unsafe impl<'a, F: PluginStateConverterFactoryImpl + 'a> FactoryImplementation<'a>
+    for PluginStateConverterFactoryWrapper<F>
+{
+    type Factory = PluginStateConverterFactory<'a>;
+
+    type Wrapped = F;
+
+    #[inline]
+    fn wrapper(&self) -> &FactoryWrapper<<Self::Factory as Factory<'a>>::Raw, Self::Wrapped> {
+        &self.inner
+    }
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn count<F: PluginStateConverterFactoryImpl>(
+    factory: *const clap_plugin_state_converter_factory,
+) -> u32 {
+    FactoryWrapper::<_, F>::handle(factory, |factory| Ok(factory.converter_count())).unwrap_or(0)
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn get_descriptor<F: PluginStateConverterFactoryImpl>(
+    factory: *const clap_plugin_state_converter_factory,
+    index: u32,
+) -> *const clap_plugin_state_converter_descriptor {
+    FactoryWrapper::<_, F>::handle(factory, |factory| {
+        match factory.converter_descriptor(index) {
+            Some(descriptor) => Ok(descriptor.as_raw() as *const _),
+            None => Ok(core::ptr::null()),
+        }
+    })
+    .unwrap_or(core::ptr::null())
+}
+
+#[allow(clippy::missing_safety_doc)]
+unsafe extern "C" fn create<F: PluginStateConverterFactoryImpl>(
+    factory: *const clap_plugin_state_converter_factory,
+    converter_id: *const c_char,
+) -> *mut clap_plugin_state_converter {
+    FactoryWrapper::<_, F>::handle(factory, |factory| {
+        let converter_id = cstr_from_nullable_ptr(converter_id)
+            .ok_or(FactoryWrapperError::NulPtr("Invalid converter id string"))?;
+
+        match factory.create_converter(converter_id) {
+            Some(instance) => Ok(instance.into_raw()),
+            None => Ok(core::ptr::null_mut()),
+        }
+    })
+    .unwrap_or(core::ptr::null_mut())
+}
+
+/// A [`PluginStateConverterFactory`] implementation.
+pub trait PluginStateConverterFactoryImpl: Send + Sync {
+    /// Returns the number of state converters exposed by this factory.
+    fn converter_count(&self) -> u32;
+
+    /// Returns the [`StateConverterDescriptor`] of the converter that is assigned the given index.
+    ///
+    /// If the given index is out of bounds, or in general does not match any given converters, this
+    /// returns [`None`].
+    fn converter_descriptor(&self, index: u32) -> Option<&StateConverterDescriptor>;
+
+    /// Creates a new converter instance for the converter matching the given `converter_id`.
+    ///
+    /// If the given `converter_id` matches against one of the converters this factory manages,
+    /// implementors of this trait then use the [`StateConverterInstance::new`] method to
+    /// instantiate the corresponding converter implementation.
+    ///
+    /// If the given `converter_id` does not match any known converters to this factory, this method
+    /// returns [`None`].
+    fn create_converter(&self, converter_id: &CStr) -> Option<StateConverterInstance<'_>>;
+}
+
+/// A state converter implementation.
+pub trait StateConverterImpl {
+    /// Reads the state of the source plugin from the `src` stream, and writes the matching state
+    /// of the destination plugin to the `dst` stream.
+    ///
+    /// If this returns an error, its message is given back to the host.
+    fn convert_state(
+        &mut self,
+        src: &mut InputStream,
+        dst: &mut OutputStream,
+    ) -> Result<(), PluginError>;
+
+    /// Converts the given normalized value of a parameter of the source plugin to the matching
+    /// parameter ID and normalized value of the destination plugin.
+    ///
+    /// This returns [`None`] if the parameter can't be converted.
+    fn convert_normalized_value(
+        &mut self,
+        src_param_id: ClapId,
+        src_normalized_value: f64,
+    ) -> Option<(ClapId, f64)>;
+
+    /// Converts the given plain value of a parameter of the source plugin to the matching
+    /// parameter ID and plain value of the destination plugin.
+    ///
+    /// This returns [`None`] if the parameter can't be converted.
+    fn convert_plain_value(
+        &mut self,
+        src_param_id: ClapId,
+        src_plain_value: f64,
+    ) -> Option<(ClapId, f64)>;
+}
+
+/// A state converter instance that is ready to be used by the host.
+///
+/// This is the type to be returned by [`PluginStateConverterFactoryImpl::create_converter`].
+pub struct StateConverterInstance<'a> {
+    // Owned, allocated by Box::into_raw. Freed by the converter's destroy function.
+    inner: *mut clap_plugin_state_converter,
+    lifetime: PhantomData<&'a clap_plugin_state_converter_descriptor>,
+}
+
+impl<'a> StateConverterInstance<'a> {
+    /// Creates a new [`StateConverterInstance`] from a given
+    /// [converter implementation](StateConverterImpl), and its associated
+    /// [`StateConverterDescriptor`].
+    pub fn new<C: StateConverterImpl + 'a>(
+        descriptor: &'a StateConverterDescriptor,
+        converter: C,
+    ) -> Self {
+        Self {
+            lifetime: PhantomData,
+            inner: Box::into_raw(Box::new(StateConverterData::new_raw(descriptor, converter))),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn into_raw(self) -> *mut clap_plugin_state_converter {
+        ManuallyDrop::new(self).inner
+    }
+}
+
+// In case the instance is dropped by a faulty plugin factory implementation.
+impl Drop for StateConverterInstance<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        // SAFETY: the pointer is valid and owned by this instance until it is destroyed.
+        if let Some(destroy) = unsafe { (*self.inner).destroy } {
+            // SAFETY: the 'destroy' fn is valid as it's provided by us directly.
+            unsafe { destroy(self.inner) }
+        }
+    }
+}
+
+/// The actual data type that is behind the clap_plugin_state_converter.converter_data pointer.
+struct StateConverterData<C> {
+    converter: C,
+}
+
+impl<C: StateConverterImpl> StateConverterData<C> {
+    fn new_raw(descriptor: &StateConverterDescriptor, converter: C) -> clap_plugin_state_converter {
+        clap_plugin_state_converter {
+            desc: descriptor.as_raw(),
+            converter_data: Box::into_raw(Box::new(Self { converter })).cast(),
+            destroy: Some(Self::destroy),
+            convert_state: Some(Self::convert_state),
+            convert_normalized_value: Some(Self::convert_normalized_value),
+            convert_plain_value: Some(Self::convert_plain_value),
+        }
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn convert_state(
+        converter: *mut clap_plugin_state_converter,
+        src: *const clap_istream,
+        dst: *const clap_ostream,
+        error_buffer: *mut c_char,
+        error_buffer_size: usize,
+    ) -> bool {
+        Self::handle(converter, |data| {
+            if src.is_null() || dst.is_null() {
+                return None;
+            }
+
+            let src = InputStream::from_raw_mut(&mut *(src as *mut _));
+            let dst = OutputStream::from_raw_mut(&mut *(dst as *mut _));
+
+            match data.converter.convert_state(src, dst) {
+                Ok(()) => Some(()),
+                Err(e) => {
+                    if !error_buffer.is_null() && error_buffer_size > 0 {
+                        let msg = PluginWrapperError::from(e).format_cstr();
+                        let msg = msg.to_bytes();
+                        let len = msg.len().min(error_buffer_size - 1);
+
+                        // SAFETY: the host guarantees the buffer is valid for error_buffer_size
+                        // bytes, and we leave space for the nul terminator.
+                        core::ptr::copy_nonoverlapping(msg.as_ptr().cast(), error_buffer, len);
+                        error_buffer.add(len).write(0);
+                    }
+
+                    None
+                }
+            }
+        })
+        .is_some()
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn convert_normalized_value(
+        converter: *mut clap_plugin_state_converter,
+        src_param_id: clap_id,
+        src_normalized_value: f64,
+        dst_param_id: *mut clap_id,
+        dst_normalized_value: *mut f64,
+    ) -> bool {
+        Self::handle(converter, |data| {
+            if dst_param_id.is_null() || dst_normalized_value.is_null() {
+                return None;
+            }
+
+            let (id, value) = data
+                .converter
+                .convert_normalized_value(ClapId::from_raw(src_param_id)?, src_normalized_value)?;
+
+            dst_param_id.write(id.get());
+            dst_normalized_value.write(value);
+            Some(())
+        })
+        .is_some()
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn convert_plain_value(
+        converter: *mut clap_plugin_state_converter,
+        src_param_id: clap_id,
+        src_plain_value: f64,
+        dst_param_id: *mut clap_id,
+        dst_plain_value: *mut f64,
+    ) -> bool {
+        Self::handle(converter, |data| {
+            if dst_param_id.is_null() || dst_plain_value.is_null() {
+                return None;
+            }
+
+            let (id, value) = data
+                .converter
+                .convert_plain_value(ClapId::from_raw(src_param_id)?, src_plain_value)?;
+
+            dst_param_id.write(id.get());
+            dst_plain_value.write(value);
+            Some(())
+        })
+        .is_some()
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn destroy(converter: *mut clap_plugin_state_converter) {
+        let Some(raw) = converter.as_mut() else {
+            return;
+        };
+
+        let converter_data =
+            core::mem::replace(&mut raw.converter_data, core::ptr::null_mut()).cast::<Self>();
+
+        if !converter_data.is_null() {
+            let _ = handle_panic(AssertUnwindSafe(|| {
+                let _ = Box::from_raw(converter_data);
+            }));
+        }
+
+        let _ = handle_panic(AssertUnwindSafe(|| {
+            let _ = Box::from_raw(converter);
+        }));
+    }
+
+    /// # Safety
+    ///
+    /// converter must be valid and its data must be a valid instance of C
+    unsafe fn handle<T>(
+        converter: *mut clap_plugin_state_converter,
+        handler: impl FnOnce(&mut Self) -> Option<T>,
+    ) -> Option<T> {
+        if converter.is_null() {
+            return None;
+        }
+        let data = converter.read().converter_data.cast::<Self>();
+
+        handle_panic(AssertUnwindSafe(|| handler(data.as_mut()?))).ok()?
+    }
+}
@@ -0,0 +1,296 @@
+#![deny(missing_docs)]
+
+//! Allows the host to tell plugins where they are located in the project, e.g. on which track and
+//! in which device chain.
+//!
+//! The location is given as a path of [`LocationElement`]s, from the project itself down to the
+//! plugin's device. Each element has a [`LocationKind`], and may have a name and a color.
+
+use crate::utils::{cstr_from_nullable_ptr, cstr_to_nullable_ptr};
+use clack_common::extensions::{Extension, PluginExtensionSide, RawExtension};
+use clack_common::utils::{Color, TRANSPARENT_COLOR};
+use clap_sys::plugin::clap_plugin;
+use std::ffi::{CStr, c_char};
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+/// The identifier of the Project Location extension.
+pub const CLAP_EXT_PROJECT_LOCATION: &CStr = c"clap.project-location/2";
+
+const CLAP_PROJECT_LOCATION_PROJECT: u32 = 1;
+const CLAP_PROJECT_LOCATION_TRACK_GROUP: u32 = 2;
+const CLAP_PROJECT_LOCATION_TRACK: u32 = 3;
+const CLAP_PROJECT_LOCATION_DEVICE: u32 = 4;
+const CLAP_PROJECT_LOCATION_NESTED_DEVICE_CHAIN: u32 = 5;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_project_location_element {
+    id: *const c_char,
+    kind: u32,
+    index_in_group: u32,
+    name: *const c_char,
+    color: Color,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_plugin_project_location {
+    set_location: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            path: *const clap_project_location_element,
+            num_elements: u32,
+        ),
+    >,
+}
+
+/// Plugin-side of the Project Location extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginProjectLocation(RawExtension<PluginExtensionSide, clap_plugin_project_location>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginProjectLocation {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_PROJECT_LOCATION];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// The kind of an element of a plugin's location.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LocationKind {
+    /// The project the plugin is in.
+    Project,
+    /// A group of tracks, e.g. a folder track.
+    TrackGroup,
+    /// A track.
+    Track,
+    /// A device, e.g. the plugin itself, or a device containing nested device chains.
+    Device,
+    /// A device chain nested in another device, e.g. in a rack or a multi-band splitter.
+    NestedDeviceChain,
+}
+
+impl LocationKind {
+    /// Returns the location kind matching the given raw C FFI-compatible value, if it is valid.
+    #[inline]
+    pub const fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            CLAP_PROJECT_LOCATION_PROJECT => Some(Self::Project),
+            CLAP_PROJECT_LOCATION_TRACK_GROUP => Some(Self::TrackGroup),
+            CLAP_PROJECT_LOCATION_TRACK => Some(Self::Track),
+            CLAP_PROJECT_LOCATION_DEVICE => Some(Self::Device),
+            CLAP_PROJECT_LOCATION_NESTED_DEVICE_CHAIN => Some(Self::NestedDeviceChain),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw C FFI-compatible value of this location kind.
+    #[inline]
+    pub const fn to_raw(self) -> u32 {
+        match self {
+            Self::Project => CLAP_PROJECT_LOCATION_PROJECT,
+            Self::TrackGroup => CLAP_PROJECT_LOCATION_TRACK_GROUP,
+            Self::Track => CLAP_PROJECT_LOCATION_TRACK,
+            Self::Device => CLAP_PROJECT_LOCATION_DEVICE,
+            Self::NestedDeviceChain => CLAP_PROJECT_LOCATION_NESTED_DEVICE_CHAIN,
+        }
+    }
+}
+
+/// An element of a plugin's location in the project, e.g. a track.
+///
+/// This type is ABI-compatible with the CLAP location element type, so that a whole location path
+/// can be passed as a slice of elements without any conversion.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LocationElement<'a> {
+    inner: clap_project_location_element,
+    _lifetime: PhantomData<&'a CStr>,
+}
+
+impl<'a> LocationElement<'a> {
+    /// Creates a new location element of the given kind, with no ID, name or color.
+    #[inline]
+    pub const fn new(kind: LocationKind) -> Self {
+        Self {
+            inner: clap_project_location_element {
+                id: core::ptr::null(),
+                kind: kind.to_raw(),
+                index_in_group: 0,
+                name: core::ptr::null(),
+                color: TRANSPARENT_COLOR,
+            },
+            _lifetime: PhantomData,
+        }
+    }
+
+    /// Returns the kind of this element, or [`None`] if the host provided an unknown kind.
+    #[inline]
+    pub const fn kind(&self) -> Option<LocationKind> {
+        LocationKind::from_raw(self.inner.kind)
+    }
+
+    /// Returns the host's internal ID of this element, if any.
+    ///
+    /// This is not meant to be displayed to the user.
+    #[inline]
+    pub fn id(&self) -> Option<&'a CStr> {
+        // SAFETY: this type ensures the pointer is either null or a valid C string for 'a.
+        unsafe { cstr_from_nullable_ptr(self.inner.id) }
+    }
+
+    /// Sets the host's internal ID of this element.
+    #[inline]
+    pub const fn with_id(mut self, id: Option<&'a CStr>) -> Self {
+        self.inner.id = cstr_to_nullable_ptr(id);
+        self
+    }
+
+    /// Returns the index of this element in its parent, e.g. the index of a track in its group.
+    #[inline]
+    pub const fn index_in_group(&self) -> u32 {
+        self.inner.index_in_group
+    }
+
+    /// Sets the index of this element in its parent.
+    #[inline]
+    pub const fn with_index_in_group(mut self, index_in_group: u32) -> Self {
+        self.inner.index_in_group = index_in_group;
+        self
+    }
+
+    /// Returns the user-friendly name of this element, if any.
+    #[inline]
+    pub fn name(&self) -> Option<&'a CStr> {
+        // SAFETY: this type ensures the pointer is either null or a valid C string for 'a.
+        unsafe { cstr_from_nullable_ptr(self.inner.name) }
+    }
+
+    /// Sets the user-friendly name of this element.
+    #[inline]
+    pub const fn with_name(mut self, name: Option<&'a CStr>) -> Self {
+        self.inner.name = cstr_to_nullable_ptr(name);
+        self
+    }
+
+    /// Returns the color of this element, or [`None`] if it has no color.
+    #[inline]
+    pub const fn color(&self) -> Option<Color> {
+        if self.inner.color.alpha == 0 {
+            None
+        } else {
+            Some(self.inner.color)
+        }
+    }
+
+    /// Sets (or unsets) the color of this element.
+    #[inline]
+    pub const fn with_color(mut self, color: Option<Color>) -> Self {
+        self.inner.color = match color {
+            Some(color) => color,
+            None => TRANSPARENT_COLOR,
+        };
+        self
+    }
+}
+
+impl Debug for LocationElement<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocationElement")
+            .field("kind", &self.kind())
+            .field("id", &self.id())
+            .field("index_in_group", &self.index_in_group())
+            .field("name", &self.name())
+            .field("color", &self.color())
+            .finish()
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_plugin::extensions::prelude::*;
+
+    /// Implementation of the Plugin-side of the Project Location extension.
+    pub trait PluginProjectLocationImpl {
+        /// Called when the plugin's location in the project changed.
+        ///
+        /// The path goes from the outermost element (usually the project) to the plugin's own
+        /// device, which is the last element.
+        fn set_location(&mut self, path: &[LocationElement]);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginProjectLocation
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginProjectLocationImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_project_location {
+                set_location: Some(set_location::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_location<P>(
+        plugin: *const clap_plugin,
+        path: *const clap_project_location_element,
+        num_elements: u32,
+    ) where
+        for<'a> P: Plugin<MainThread<'a>: PluginProjectLocationImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let path = if num_elements == 0 {
+                &[]
+            } else if path.is_null() {
+                return Err(PluginWrapperError::NulPtr("Project location path"));
+            } else {
+                // SAFETY: LocationElement is repr(C) and ABI-compatible with the raw element type.
+                core::slice::from_raw_parts(path.cast::<LocationElement>(), num_elements as usize)
+            };
+
+            p.main_thread().as_mut().set_location(path);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_host::extensions::prelude::*;
+
+    impl PluginProjectLocation {
+        /// Informs the plugin of its location in the project.
+        ///
+        /// The path goes from the outermost element (usually the project) to the plugin's own
+        /// device, which should be the last element.
+        #[inline]
+        pub fn set_location(&self, plugin: &mut PluginMainThreadHandle, path: &[LocationElement]) {
+            if let Some(set_location) = plugin.use_extension(&self.0).set_location {
+                // SAFETY: This type ensures the function pointer is valid.
+                // LocationElement is repr(C) and ABI-compatible with the raw element type.
+                unsafe {
+                    set_location(
+                        plugin.as_raw(),
+                        path.as_ptr().cast(),
+                        u32::try_from(path.len()).unwrap_or(u32::MAX),
+                    )
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to store the resources they depend on (samples, impulse responses…) in
+//! directories managed by the host, so they can be collected along with the project.
+//!
+//! The host provides up to two resource directories to each plugin instance: a shared one, which
+//! may be used by multiple plugin instances (e.g. a project-wide sample folder), and an exclusive
+//! one, which only this plugin instance uses.
+//!
+//! When the host saves or exports a project, it may ask the plugin to collect the files it uses
+//! into the shared directory, and then query the list of those files.
+
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clap_sys::ext::draft::resource_directory::*;
+use std::error::Error;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+
+/// Plugin-side of the Resource Directory extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginResourceDirectory(
+    RawExtension<PluginExtensionSide, clap_plugin_resource_directory>,
+);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginResourceDirectory {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_RESOURCE_DIRECTORY];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Resource Directory extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostResourceDirectory(RawExtension<HostExtensionSide, clap_host_resource_directory>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostResourceDirectory {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_RESOURCE_DIRECTORY];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// An error indicating a path can't be passed through the Resource Directory extension.
+///
+/// This happens if the path contains a nul byte, or if it isn't valid UTF-8 on non-Unix platforms.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct InvalidResourcePathError;
+
+impl Display for InvalidResourcePathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Invalid resource directory path")
+    }
+}
+
+impl Error for InvalidResourcePathError {}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use crate::utils::{cstr_from_nullable_ptr, path_from_bytes, path_to_bytes};
+    use clack_plugin::extensions::prelude::*;
+    use std::ffi::c_char;
+    use std::path::Path;
+
+    impl HostResourceDirectory {
+        /// Requests the host to set up a resource directory, either shared or exclusive.
+        ///
+        /// If the host accepts, it will provide the directory through the plugin's
+        /// `set_directory` callback. Returns `true` if the host will perform the request.
+        #[inline]
+        pub fn request_directory(&self, host: &mut HostMainThreadHandle, is_shared: bool) -> bool {
+            match host.use_extension(&self.0).request_directory {
+                None => false,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(request_directory) => unsafe { request_directory(host.as_raw(), is_shared) },
+            }
+        }
+
+        /// Tells the host the resource directory, either shared or exclusive, is not needed anymore.
+        ///
+        /// If the exclusive directory is released, the host may delete its contents.
+        #[inline]
+        pub fn release_directory(&self, host: &mut HostMainThreadHandle, is_shared: bool) {
+            if let Some(release_directory) = host.use_extension(&self.0).release_directory {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { release_directory(host.as_raw(), is_shared) }
+            }
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Resource Directory extension.
+    pub trait PluginResourceDirectoryImpl {
+        /// Sets the directory the plugin can store its resources in, either shared or exclusive.
+        ///
+        /// The directory remains valid until it is overridden, or the plugin is destroyed. If
+        /// `path` is [`None`], the directory is cleared.
+        fn set_directory(&mut self, path: Option<&Path>, is_shared: bool);
+
+        /// Asks the plugin to copy the resources it uses into the shared resource directory.
+        ///
+        /// Resources that belong to the plugin's factory content only need to be collected if
+        /// `all` is `true`.
+        fn collect(&mut self, all: bool);
+
+        /// Returns the number of files the plugin uses in the shared resource directory.
+        fn get_files_count(&mut self) -> u32;
+
+        /// Returns the path of the file at the given index, or [`None`] if the index is out of
+        /// bounds.
+        fn get_file_path(&mut self, index: u32) -> Option<&Path>;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginResourceDirectory
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginResourceDirectoryImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_resource_directory {
+                set_directory: Some(set_directory::<P>),
+                collect: Some(collect::<P>),
+                get_files_count: Some(get_files_count::<P>),
+                get_file_path: Some(get_file_path::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_directory<P>(
+        plugin: *const clap_plugin,
+        path: *const c_char,
+        is_shared: bool,
+    ) where
+        for<'a> P: Plugin<MainThread<'a>: PluginResourceDirectoryImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let path = cstr_from_nullable_ptr(path)
+                .map(CStr::to_bytes)
+                .filter(|p| !p.is_empty());
+
+            let path = match path {
+                None => None,
+                Some(path) => Some(path_from_bytes(path).ok_or(
+                    PluginWrapperError::InvalidParameter("Invalid resource directory path"),
+                )?),
+            };
+
+            p.main_thread().as_mut().set_directory(path, is_shared);
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn collect<P>(plugin: *const clap_plugin, all: bool)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginResourceDirectoryImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread().as_mut().collect(all);
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_files_count<P>(plugin: *const clap_plugin) -> u32
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginResourceDirectoryImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| Ok(p.main_thread().as_mut().get_files_count()))
+            .unwrap_or(0)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_file_path<P>(
+        plugin: *const clap_plugin,
+        index: u32,
+        path: *mut c_char,
+        path_size: u32,
+    ) -> i32
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginResourceDirectoryImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let mut main_thread = p.main_thread();
+            let Some(file_path) = main_thread.as_mut().get_file_path(index) else {
+                return Ok(-1);
+            };
+
+            let bytes = path_to_bytes(file_path).ok_or(PluginWrapperError::InvalidParameter(
+                "File path can't be represented",
+            ))?;
+
+            if !path.is_null() && path_size > 0 {
+                let written = bytes.len().min(path_size as usize - 1);
+                core::ptr::copy_nonoverlapping(bytes.as_ptr(), path.cast(), written);
+                path.add(written).write(0);
+            }
+
+            Ok(i32::try_from(bytes.len()).unwrap_or(-1))
+        })
+        .unwrap_or(-1)
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use crate::utils::{cstr_to_nullable_ptr, path_from_bytes, path_to_bytes};
+    use clack_host::extensions::prelude::*;
+    use std::ffi::CString;
+    use std::path::{Path, PathBuf};
+
+    impl PluginResourceDirectory {
+        /// Sets the directory the plugin can store its resources in, either shared or exclusive.
+        ///
+        /// If `path` is [`None`], the directory is cleared.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`InvalidResourcePathError`] if the path can't be passed to the plugin.
+        pub fn set_directory(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            path: Option<&Path>,
+            is_shared: bool,
+        ) -> Result<(), InvalidResourcePathError> {
+            let path = match path {
+                None => None,
+                Some(path) => {
+                    let bytes = path_to_bytes(path).ok_or(InvalidResourcePathError)?;
+                    Some(CString::new(bytes).map_err(|_| InvalidResourcePathError)?)
+                }
+            };
+
+            if let Some(set_directory) = plugin.use_extension(&self.0).set_directory {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe {
+                    set_directory(
+                        plugin.as_raw(),
+                        cstr_to_nullable_ptr(path.as_deref()),
+                        is_shared,
+                    )
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Asks the plugin to copy the resources it uses into the shared resource directory.
+        ///
+        /// Resources that belong to the plugin's factory content are only collected if `all`
+        /// is `true`.
+        #[inline]
+        pub fn collect(&self, plugin: &mut PluginMainThreadHandle, all: bool) {
+            if let Some(collect) = plugin.use_extension(&self.0).collect {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { collect(plugin.as_raw(), all) }
+            }
+        }
+
+        /// Returns the number of files the plugin uses in the shared resource directory.
+        #[inline]
+        pub fn get_files_count(&self, plugin: &mut PluginMainThreadHandle) -> u32 {
+            match plugin.use_extension(&self.0).get_files_count {
+                None => 0,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(get_files_count) => unsafe { get_files_count(plugin.as_raw()) },
+            }
+        }
+
+        /// Returns the path of the file at the given index, or [`None`] if the index is out of
+        /// bounds, the plugin failed to provide a valid path, or the path's reported length is
+        /// inconsistent between calls.
+        pub fn get_file_path(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            index: u32,
+        ) -> Option<PathBuf> {
+            let get_file_path = plugin.use_extension(&self.0).get_file_path?;
+            let mut buffer = vec![0u8; 1024];
+
+            // If the path doesn't fit, retry once with a buffer of the size reported by the plugin.
+            for _ in 0..2 {
+                // SAFETY: This type ensures the function pointer is valid.
+                // The buffer is valid for writes of its whole length.
+                let len = unsafe {
+                    get_file_path(
+                        plugin.as_raw(),
+                        index,
+                        buffer.as_mut_ptr().cast(),
+                        buffer.len() as u32,
+                    )
+                };
+
+                let len = usize::try_from(len).ok()?;
+
+                if len < buffer.len() {
+                    return path_from_bytes(&buffer[..len]).map(Path::to_path_buf);
+                }
+
+                buffer.resize(len + 1, 0);
+            }
+
+            None
+        }
+
+        /// Returns the paths of all the files the plugin uses in the shared resource directory.
+        ///
+        /// Files the plugin failed to provide a valid path for are skipped.
+        pub fn get_file_paths(&self, plugin: &mut PluginMainThreadHandle) -> Vec<PathBuf> {
+            (0..self.get_files_count(plugin))
+                .filter_map(|index| self.get_file_path(plugin, index))
+                .collect()
+        }
+    }
+
+    /// Implementation of the Host-side of the Resource Directory extension.
+    pub trait HostResourceDirectoryImpl {
+        /// Called when the plugin requests a resource directory, either shared or exclusive.
+        ///
+        /// Returns `true` if the host will provide the directory, using
+        /// [`PluginResourceDirectory::set_directory`].
+        fn request_directory(&mut self, is_shared: bool) -> bool;
+
+        /// Called when the plugin doesn't need the resource directory anymore.
+        ///
+        /// If the exclusive directory is released, the host may delete its contents.
+        fn release_directory(&mut self, is_shared: bool);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostResourceDirectory
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostResourceDirectoryImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_resource_directory {
+                request_directory: Some(request_directory::<H>),
+                release_directory: Some(release_directory::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn request_directory<H>(host: *const clap_host, is_shared: bool) -> bool
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostResourceDirectoryImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            Ok(host.main_thread().as_mut().request_directory(is_shared))
+        })
+        .unwrap_or(false)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn release_directory<H>(host: *const clap_host, is_shared: bool)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostResourceDirectoryImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().release_directory(is_shared);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
@@ -0,0 +1,604 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to expose triggers, which the host can fire using [`TriggerEvent`]s.
+//!
+//! Triggers are similar to parameters, except they do not hold a value: they represent a
+//! momentary action, such as firing a sample or resetting an envelope. Like parameters, they may
+//! be automated by the host, globally or per-note.
+//!
+//! Trigger events do not belong to the core CLAP event space: they are part of their own
+//! [`TriggerEventSpace`], which ID must be retrieved through the `event_registry` extension.
+
+use bitflags::bitflags;
+use clack_common::events::spaces::{EventSpace, EventSpaceId};
+use clack_common::events::{Event, EventFlags, EventHeader, Match, Pckn, UnknownEvent};
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clack_common::utils::{ClapId, Cookie};
+use clap_sys::ext::draft::triggers::*;
+use std::ffi::CStr;
+use std::fmt::{Debug, Formatter};
+
+/// Plugin-side of the Triggers extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginTriggers(RawExtension<PluginExtensionSide, clap_plugin_triggers>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginTriggers {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_TRIGGERS];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Triggers extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostTriggers(RawExtension<HostExtensionSide, clap_host_triggers>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostTriggers {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_TRIGGERS];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+bitflags! {
+    /// Flags providing more information about a trigger.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+    pub struct TriggerInfoFlags: u32 {
+        /// The trigger can be fired for a specific note ID.
+        const IS_AUTOMATABLE_PER_NOTE_ID = CLAP_TRIGGER_IS_AUTOMATABLE_PER_NOTE_ID;
+        /// The trigger can be fired for a specific key.
+        const IS_AUTOMATABLE_PER_KEY = CLAP_TRIGGER_IS_AUTOMATABLE_PER_KEY;
+        /// The trigger can be fired for a specific channel.
+        const IS_AUTOMATABLE_PER_CHANNEL = CLAP_TRIGGER_IS_AUTOMATABLE_PER_CHANNEL;
+        /// The trigger can be fired for a specific port.
+        const IS_AUTOMATABLE_PER_PORT = CLAP_TRIGGER_IS_AUTOMATABLE_PER_PORT;
+    }
+}
+
+bitflags! {
+    /// Flags indicating what the host needs to rescan after the plugin's triggers changed.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+    pub struct TriggerRescanFlags: u32 {
+        /// The trigger infos (names, flags, cookies) changed. This can happen at any time.
+        const INFO = CLAP_TRIGGER_RESCAN_INFO;
+        /// The whole set of triggers changed. This can only happen while the plugin is deactivated.
+        const ALL = CLAP_TRIGGER_RESCAN_ALL;
+    }
+}
+
+bitflags! {
+    /// Flags indicating what the host needs to clear about a trigger.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+    pub struct TriggerClearFlags: u32 {
+        /// Clears all references to the trigger.
+        const ALL = CLAP_TRIGGER_CLEAR_ALL;
+        /// Clears all automations of the trigger.
+        const AUTOMATIONS = CLAP_TRIGGER_CLEAR_AUTOMATIONS;
+    }
+}
+
+/// Information about a trigger.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TriggerInfo<'a> {
+    /// A stable identifier for the trigger, which must never change.
+    pub id: ClapId,
+    /// Flags providing more information about the trigger.
+    pub flags: TriggerInfoFlags,
+    /// An opaque pointer that can be used by the plugin to quickly access the trigger's data.
+    ///
+    /// The host passes this cookie back in every [`TriggerEvent`] targeting this trigger. It is
+    /// invalidated by a rescan with [`TriggerRescanFlags::ALL`], or when the plugin is destroyed.
+    pub cookie: Cookie,
+    /// The display name of the trigger, e.g. "Retrig".
+    pub name: &'a [u8],
+    /// The module path of the trigger, e.g. "Modulators/LFO 1".
+    /// The host can use `/` as a separator to show a tree-like structure.
+    pub module: &'a [u8],
+}
+
+impl<'a> TriggerInfo<'a> {
+    /// Gets trigger information from its raw, C-FFI compatible representation.
+    ///
+    /// Returns [`None`] if the trigger ID is invalid.
+    pub fn from_raw(raw: &'a clap_trigger_info) -> Option<Self> {
+        Some(Self {
+            id: ClapId::from_raw(raw.id)?,
+            flags: TriggerInfoFlags::from_bits_truncate(raw.flags),
+            cookie: Cookie::from_raw(raw.cookie),
+            name: crate::utils::data_from_array_buf(&raw.name),
+            module: crate::utils::data_from_array_buf(&raw.module),
+        })
+    }
+}
+
+/// An event sent by the host to fire a trigger.
+///
+/// This event belongs to the [`TriggerEventSpace`].
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TriggerEvent {
+    inner: clap_event_trigger,
+}
+
+// SAFETY: this matches the type ID and event space
+unsafe impl Event for TriggerEvent {
+    const TYPE_ID: u16 = CLAP_EVENT_TRIGGER;
+    type EventSpace<'a> = TriggerEventSpace<'a>;
+}
+
+impl AsRef<UnknownEvent> for TriggerEvent {
+    #[inline]
+    fn as_ref(&self) -> &UnknownEvent {
+        self.as_unknown()
+    }
+}
+
+impl TriggerEvent {
+    /// Creates a new trigger event.
+    ///
+    /// `space_id` is the ID of the [`TriggerEventSpace`], as retrieved from the host's event
+    /// registry.
+    #[inline]
+    pub const fn new(
+        space_id: EventSpaceId<TriggerEventSpace<'static>>,
+        time: u32,
+        trigger_id: ClapId,
+        pckn: Pckn,
+        cookie: Cookie,
+    ) -> Self {
+        Self {
+            inner: clap_event_trigger {
+                header: EventHeader::<Self>::new_for_space(space_id, time, EventFlags::empty())
+                    .into_raw(),
+                trigger_id: trigger_id.get(),
+                cookie: cookie.as_raw(),
+                note_id: pckn.raw_note_id(),
+                port_index: pckn.raw_port_index(),
+                channel: pckn.raw_channel(),
+                key: pckn.raw_key(),
+            },
+        }
+    }
+
+    /// The ID of the trigger to fire.
+    #[inline]
+    pub const fn trigger_id(&self) -> Option<ClapId> {
+        ClapId::from_raw(self.inner.trigger_id)
+    }
+
+    /// Sets the ID of the trigger to fire.
+    #[inline]
+    pub const fn set_trigger_id(&mut self, trigger_id: ClapId) {
+        self.inner.trigger_id = trigger_id.get()
+    }
+
+    /// Sets the ID of the trigger to fire.
+    ///
+    /// This method takes and returns ownership of the event, allowing it to be used in a
+    /// builder-style pattern.
+    #[inline]
+    pub const fn with_trigger_id(mut self, trigger_id: ClapId) -> Self {
+        self.inner.trigger_id = trigger_id.get();
+        self
+    }
+
+    /// The cookie of the trigger to fire, as provided by the plugin in its [`TriggerInfo`].
+    #[inline]
+    pub const fn cookie(&self) -> Cookie {
+        Cookie::from_raw(self.inner.cookie)
+    }
+
+    /// Sets the cookie of the trigger to fire.
+    #[inline]
+    pub const fn set_cookie(&mut self, cookie: Cookie) {
+        self.inner.cookie = cookie.as_raw()
+    }
+
+    /// Sets the cookie of the trigger to fire.
+    ///
+    /// This method takes and returns ownership of the event, allowing it to be used in a
+    /// builder-style pattern.
+    #[inline]
+    pub const fn with_cookie(mut self, cookie: Cookie) -> Self {
+        self.inner.cookie = cookie.as_raw();
+        self
+    }
+
+    /// The [`Pckn`] tuple indicating which note(s) this trigger event targets.
+    #[inline]
+    pub const fn pckn(&self) -> Pckn {
+        Pckn::from_raw(
+            self.inner.port_index,
+            self.inner.channel,
+            self.inner.key,
+            self.inner.note_id,
+        )
+    }
+
+    /// Sets the [`Pckn`] tuple for this event.
+    #[inline]
+    pub const fn set_pckn(&mut self, pckn: Pckn) {
+        self.inner.port_index = pckn.raw_port_index();
+        self.inner.channel = pckn.raw_channel();
+        self.inner.key = pckn.raw_key();
+        self.inner.note_id = pckn.raw_note_id();
+    }
+
+    /// Sets the [`Pckn`] tuple for this event.
+    ///
+    /// This method takes and returns ownership of the event, allowing it to be used in a
+    /// builder-style pattern.
+    #[inline]
+    pub const fn with_pckn(mut self, pckn: Pckn) -> Self {
+        self.set_pckn(pckn);
+        self
+    }
+
+    /// The index of the note port this event targets.
+    ///
+    /// This returns [`Match::All`] if this event targets all possible note ports.
+    #[inline]
+    pub const fn port_index(&self) -> Match<u16> {
+        Match::<u16>::from_raw(self.inner.port_index)
+    }
+
+    /// The note channel this event targets (0-15).
+    ///
+    /// This returns [`Match::All`] if this event targets all possible note channels.
+    #[inline]
+    pub const fn channel(&self) -> Match<u16> {
+        Match::<u16>::from_raw(self.inner.channel)
+    }
+
+    /// The key of the note(s) this event targets (0-127).
+    ///
+    /// This returns [`Match::All`] if this event targets all possible note keys.
+    #[inline]
+    pub const fn key(&self) -> Match<u16> {
+        Match::<u16>::from_raw(self.inner.key)
+    }
+
+    /// The ID of the note this event targets.
+    ///
+    /// This returns [`Match::All`] if this event targets all possible note IDs.
+    #[inline]
+    pub const fn note_id(&self) -> Match<u32> {
+        Match::<u32>::from_raw(self.inner.note_id)
+    }
+
+    /// Returns a shared reference to the underlying raw, C-FFI compatible event struct.
+    #[inline]
+    pub const fn as_raw(&self) -> &clap_event_trigger {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying raw, C-FFI compatible event struct.
+    #[inline]
+    pub const fn as_raw_mut(&mut self) -> &mut clap_event_trigger {
+        &mut self.inner
+    }
+}
+
+impl PartialEq for TriggerEvent {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.header.time == other.inner.header.time
+            && self.inner.trigger_id == other.inner.trigger_id
+            && self.inner.note_id == other.inner.note_id
+            && self.inner.port_index == other.inner.port_index
+            && self.inner.channel == other.inner.channel
+            && self.inner.key == other.inner.key
+    }
+}
+
+impl Debug for TriggerEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TriggerEvent")
+            .field("header", &self.header())
+            .field("trigger_id", &self.inner.trigger_id)
+            .field("port_index", &self.inner.port_index)
+            .field("channel", &self.inner.channel)
+            .field("key", &self.inner.key)
+            .field("note_id", &self.inner.note_id)
+            .finish()
+    }
+}
+
+/// The event space of the Triggers extension.
+///
+/// Its ID can be retrieved from the host using the `event_registry` extension, and then used to
+/// match incoming events using [`UnknownEvent::as_event_space`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TriggerEventSpace<'a> {
+    /// An event firing a trigger.
+    Trigger(&'a TriggerEvent),
+}
+
+// SAFETY: The trigger event space has the Triggers extension identifier for a name.
+unsafe impl<'a> EventSpace<'a> for TriggerEventSpace<'a> {
+    const NAME: &'static CStr = CLAP_EXT_TRIGGERS;
+
+    unsafe fn from_unknown(event: &'a UnknownEvent) -> Option<Self> {
+        match event.header().type_id() {
+            // SAFETY: The caller guarantees the event belongs to this space, and we just checked its type.
+            TriggerEvent::TYPE_ID => Some(Self::Trigger(unsafe { event.as_event_unchecked() })),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn as_unknown(&self) -> &'a UnknownEvent {
+        match self {
+            TriggerEventSpace::Trigger(e) => e.as_unknown(),
+        }
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use crate::utils::write_to_array_buf;
+    use clack_plugin::extensions::prelude::*;
+    use std::mem::MaybeUninit;
+
+    /// A writer for the host-provided trigger information buffer.
+    pub struct TriggerInfoWriter<'a> {
+        buf: &'a mut MaybeUninit<clap_trigger_info>,
+        is_set: bool,
+    }
+
+    impl TriggerInfoWriter<'_> {
+        /// # Safety
+        ///
+        /// The user must ensure the provided pointer is aligned and points to a valid allocation.
+        /// However, it doesn't have to be initialized.
+        unsafe fn new(raw: *mut clap_trigger_info) -> Self {
+            Self {
+                // SAFETY: MaybeUninit<T> and T have same memory representation
+                buf: unsafe { &mut *raw.cast() },
+                is_set: false,
+            }
+        }
+
+        /// Writes all fields from the given [`TriggerInfo`] into the host buffer.
+        ///
+        /// After this call, the host may read the trigger metadata.
+        #[inline]
+        pub fn set(&mut self, info: &TriggerInfo) {
+            let buf = self.buf.as_mut_ptr();
+
+            // SAFETY: all pointers come from `inner`, which is valid for writes and well-aligned
+            unsafe {
+                (&raw mut (*buf).id).write(info.id.get());
+                (&raw mut (*buf).flags).write(info.flags.bits());
+                (&raw mut (*buf).cookie).write(info.cookie.as_raw());
+
+                write_to_array_buf(&raw mut ((*buf).name), info.name);
+                write_to_array_buf(&raw mut ((*buf).module), info.module);
+            }
+            self.is_set = true;
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Triggers extension.
+    pub trait PluginTriggersImpl {
+        /// Returns the number of triggers this plugin exposes.
+        fn count(&mut self) -> u32;
+
+        /// Writes information about the trigger at the given index into the provided `writer`.
+        ///
+        /// If the index is out of bounds, the writer should be left untouched.
+        fn get_info(&mut self, trigger_index: u32, writer: &mut TriggerInfoWriter);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginTriggers
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginTriggersImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_triggers {
+                count: Some(count::<P>),
+                get_info: Some(get_info::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn count<P>(plugin: *const clap_plugin) -> u32
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginTriggersImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| Ok(p.main_thread().as_mut().count())).unwrap_or(0)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_info<P>(
+        plugin: *const clap_plugin,
+        trigger_index: u32,
+        trigger_info: *mut clap_trigger_info,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginTriggersImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            if trigger_info.is_null() {
+                return Err(PluginWrapperError::NulPtr("clap_trigger_info"));
+            }
+
+            let mut writer = TriggerInfoWriter::new(trigger_info);
+            p.main_thread()
+                .as_mut()
+                .get_info(trigger_index, &mut writer);
+            Ok(writer.is_set)
+        })
+        .unwrap_or(false)
+    }
+
+    impl HostTriggers {
+        /// Notifies the host that the plugin's triggers changed, and which information must be
+        /// rescanned.
+        #[inline]
+        pub fn rescan(&self, host: &mut HostMainThreadHandle, flags: TriggerRescanFlags) {
+            if let Some(rescan) = host.use_extension(&self.0).rescan {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { rescan(host.as_raw(), flags.bits()) }
+            }
+        }
+
+        /// Asks the host to clear references to the given trigger, e.g. because it was removed.
+        #[inline]
+        pub fn clear(
+            &self,
+            host: &mut HostMainThreadHandle,
+            trigger_id: ClapId,
+            flags: TriggerClearFlags,
+        ) {
+            if let Some(clear) = host.use_extension(&self.0).clear {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { clear(host.as_raw(), trigger_id.get(), flags.bits()) }
+            }
+        }
+    }
+}
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_host::extensions::prelude::*;
+    use std::mem::MaybeUninit;
+
+    /// A host-provided buffer for the plugin to write trigger information into.
+    pub struct TriggerInfoBuffer {
+        inner: MaybeUninit<clap_trigger_info>,
+    }
+
+    impl Default for TriggerInfoBuffer {
+        #[inline]
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl TriggerInfoBuffer {
+        /// Creates a new, empty trigger information buffer.
+        #[inline]
+        pub const fn new() -> Self {
+            Self {
+                inner: MaybeUninit::zeroed(),
+            }
+        }
+    }
+
+    impl PluginTriggers {
+        /// Returns the number of triggers the plugin exposes.
+        #[inline]
+        pub fn count(&self, plugin: &mut PluginMainThreadHandle) -> u32 {
+            match plugin.use_extension(&self.0).count {
+                None => 0,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(count) => unsafe { count(plugin.as_raw()) },
+            }
+        }
+
+        /// Retrieves information about the trigger at the given index, using the given buffer.
+        ///
+        /// Returns [`None`] if the index is out of bounds, or if the plugin failed to provide
+        /// valid information.
+        pub fn get_info<'b>(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            trigger_index: u32,
+            buffer: &'b mut TriggerInfoBuffer,
+        ) -> Option<TriggerInfo<'b>> {
+            // SAFETY: This type ensures the function pointer is valid.
+            let success = unsafe {
+                plugin.use_extension(&self.0).get_info?(
+                    plugin.as_raw(),
+                    trigger_index,
+                    buffer.inner.as_mut_ptr(),
+                )
+            };
+
+            if success {
+                // SAFETY: we just checked the buffer was successfully written to.
+                TriggerInfo::from_raw(unsafe { buffer.inner.assume_init_ref() })
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Implementation of the Host-side of the Triggers extension.
+    pub trait HostTriggersImpl {
+        /// Called when the plugin's triggers changed, and the given information must be rescanned.
+        fn rescan(&mut self, flags: TriggerRescanFlags);
+
+        /// Called when the plugin asks the host to clear references to the given trigger.
+        fn clear(&mut self, trigger_id: ClapId, flags: TriggerClearFlags);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostTriggers
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostTriggersImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_triggers {
+                rescan: Some(rescan::<H>),
+                clear: Some(clear::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn rescan<H>(host: *const clap_host, flags: clap_trigger_rescan_flags)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostTriggersImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread()
+                .as_mut()
+                .rescan(TriggerRescanFlags::from_bits_truncate(flags));
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn clear<H>(
+        host: *const clap_host,
+        trigger_id: u32,
+        flags: clap_trigger_clear_flags,
+    ) where
+        for<'a> H: HostHandlers<MainThread<'a>: HostTriggersImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            let trigger_id = ClapId::from_raw(trigger_id)
+                .ok_or(HostWrapperError::InvalidParameter("Invalid trigger ID"))?;
+
+            host.main_thread()
+                .as_mut()
+                .clear(trigger_id, TriggerClearFlags::from_bits_truncate(flags));
+            Ok(())
+        });
+    }
+}
+#[cfg(feature = "clack-host")]
+pub use host::*;
@@ -0,0 +1,705 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to use the tunings provided by the host, e.g. for microtonal music.
+//!
+//! The host exposes a list of tunings, which plugins can query on the main thread. On the audio
+//! thread, the host sends [`TuningEvent`]s to select which tuning applies to a given port and
+//! channel, and the plugin then uses [`HostTuning::get_relative`] to retrieve the tuning of each
+//! key it plays.
+//!
+//! Tuning events do not belong to the core CLAP event space: they are part of their own
+//! [`TuningEventSpace`], which ID must be retrieved through the `event_registry` extension.
+//!
+//! The [`scala`] module also provides tuning tables that hosts can load from Scala files, and use
+//! to implement this extension.
+
+use clack_common::events::spaces::{EventSpace, EventSpaceId};
+use clack_common::events::{Event, EventFlags, EventHeader, Match, UnknownEvent};
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clack_common::utils::ClapId;
+use clap_sys::ext::draft::tuning::*;
+use std::ffi::CStr;
+use std::fmt::{Debug, Formatter};
+
+pub mod scala;
+
+/// Plugin-side of the Tuning extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginTuning(RawExtension<PluginExtensionSide, clap_plugin_tuning_t>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginTuning {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_TUNING];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Tuning extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostTuning(RawExtension<HostExtensionSide, clap_host_tuning>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostTuning {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_TUNING];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Information about a tuning provided by the host.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TuningInfo<'a> {
+    /// The identifier of the tuning, used in [`TuningEvent`]s and when querying the host.
+    pub tuning_id: ClapId,
+    /// The display name of the tuning.
+    pub name: &'a [u8],
+    /// Whether the tuning may change over time. If `false`, the tuning of a given key never
+    /// changes, and plugins can cache it.
+    pub is_dynamic: bool,
+}
+
+impl<'a> TuningInfo<'a> {
+    /// Gets tuning information from its raw, C-FFI compatible representation.
+    ///
+    /// Returns [`None`] if the tuning ID is invalid.
+    pub fn from_raw(raw: &'a clap_tuning_info) -> Option<Self> {
+        Some(Self {
+            tuning_id: ClapId::from_raw(raw.tuning_id)?,
+            name: crate::utils::data_from_array_buf(&raw.name),
+            is_dynamic: raw.is_dynamic,
+        })
+    }
+}
+
+// The draft specification doesn't define a type ID for this event, as it is the only one in the
+// Tuning event space.
+const CLAP_EVENT_TUNING: u16 = 0;
+
+/// An event sent by the host to select the tuning used by a given port and channel.
+///
+/// This event belongs to the [`TuningEventSpace`].
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TuningEvent {
+    inner: clap_event_tuning,
+}
+
+// SAFETY: this matches the type ID and event space
+unsafe impl Event for TuningEvent {
+    const TYPE_ID: u16 = CLAP_EVENT_TUNING;
+    type EventSpace<'a> = TuningEventSpace<'a>;
+}
+
+impl AsRef<UnknownEvent> for TuningEvent {
+    #[inline]
+    fn as_ref(&self) -> &UnknownEvent {
+        self.as_unknown()
+    }
+}
+
+impl TuningEvent {
+    /// Creates a new tuning event.
+    ///
+    /// `space_id` is the ID of the [`TuningEventSpace`], as retrieved from the host's event
+    /// registry. If `tuning_id` is [`None`], no tuning applies to the targeted port and channel
+    /// anymore, i.e. equal temperament must be used.
+    #[inline]
+    pub const fn new(
+        space_id: EventSpaceId<TuningEventSpace<'static>>,
+        time: u32,
+        port_index: Match<u16>,
+        channel: Match<u16>,
+        tuning_id: Option<ClapId>,
+    ) -> Self {
+        Self {
+            inner: clap_event_tuning {
+                header: EventHeader::<Self>::new_for_space(space_id, time, EventFlags::empty())
+                    .into_raw(),
+                port_index: port_index.to_raw(),
+                channel: channel.to_raw(),
+                tunning_id: ClapId::optional_to_raw(tuning_id),
+            },
+        }
+    }
+
+    /// The index of the note port this event targets.
+    ///
+    /// This returns [`Match::All`] if this event targets all note ports.
+    #[inline]
+    pub const fn port_index(&self) -> Match<u16> {
+        Match::<u16>::from_raw(self.inner.port_index)
+    }
+
+    /// Sets the index of the note port this event targets.
+    #[inline]
+    pub const fn set_port_index(&mut self, port_index: Match<u16>) {
+        self.inner.port_index = port_index.to_raw()
+    }
+
+    /// Sets the index of the note port this event targets.
+    ///
+    /// This method takes and returns ownership of the event, allowing it to be used in a
+    /// builder-style pattern.
+    #[inline]
+    pub const fn with_port_index(mut self, port_index: Match<u16>) -> Self {
+        self.inner.port_index = port_index.to_raw();
+        self
+    }
+
+    /// The note channel this event targets (0-15).
+    ///
+    /// This returns [`Match::All`] if this event targets all note channels.
+    #[inline]
+    pub const fn channel(&self) -> Match<u16> {
+        Match::<u16>::from_raw(self.inner.channel)
+    }
+
+    /// Sets the note channel this event targets.
+    #[inline]
+    pub const fn set_channel(&mut self, channel: Match<u16>) {
+        self.inner.channel = channel.to_raw()
+    }
+
+    /// Sets the note channel this event targets.
+    ///
+    /// This method takes and returns ownership of the event, allowing it to be used in a
+    /// builder-style pattern.
+    #[inline]
+    pub const fn with_channel(mut self, channel: Match<u16>) -> Self {
+        self.inner.channel = channel.to_raw();
+        self
+    }
+
+    /// The ID of the tuning to use, or [`None`] if equal temperament must be used.
+    #[inline]
+    pub const fn tuning_id(&self) -> Option<ClapId> {
+        ClapId::from_raw(self.inner.tunning_id)
+    }
+
+    /// Sets the ID of the tuning to use.
+    #[inline]
+    pub const fn set_tuning_id(&mut self, tuning_id: Option<ClapId>) {
+        self.inner.tunning_id = ClapId::optional_to_raw(tuning_id)
+    }
+
+    /// Sets the ID of the tuning to use.
+    ///
+    /// This method takes and returns ownership of the event, allowing it to be used in a
+    /// builder-style pattern.
+    #[inline]
+    pub const fn with_tuning_id(mut self, tuning_id: Option<ClapId>) -> Self {
+        self.inner.tunning_id = ClapId::optional_to_raw(tuning_id);
+        self
+    }
+
+    /// Returns a shared reference to the underlying raw, C-FFI compatible event struct.
+    #[inline]
+    pub const fn as_raw(&self) -> &clap_event_tuning {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying raw, C-FFI compatible event struct.
+    #[inline]
+    pub const fn as_raw_mut(&mut self) -> &mut clap_event_tuning {
+        &mut self.inner
+    }
+}
+
+impl PartialEq for TuningEvent {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.header.time == other.inner.header.time
+            && self.inner.port_index == other.inner.port_index
+            && self.inner.channel == other.inner.channel
+            && self.inner.tunning_id == other.inner.tunning_id
+    }
+}
+
+impl Debug for TuningEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TuningEvent")
+            .field("header", &self.header())
+            .field("port_index", &self.inner.port_index)
+            .field("channel", &self.inner.channel)
+            .field("tuning_id", &self.inner.tunning_id)
+            .finish()
+    }
+}
+
+/// The event space of the Tuning extension.
+///
+/// Its ID can be retrieved from the host using the `event_registry` extension, and then used to
+/// match incoming events using [`UnknownEvent::as_event_space`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TuningEventSpace<'a> {
+    /// An event selecting the tuning of a port and channel.
+    Tuning(&'a TuningEvent),
+}
+
+// SAFETY: The tuning event space has the Tuning extension identifier for a name.
+unsafe impl<'a> EventSpace<'a> for TuningEventSpace<'a> {
+    const NAME: &'static CStr = CLAP_EXT_TUNING;
+
+    unsafe fn from_unknown(event: &'a UnknownEvent) -> Option<Self> {
+        match event.header().type_id() {
+            // SAFETY: The caller guarantees the event belongs to this space, and we just checked its type.
+            TuningEvent::TYPE_ID => Some(Self::Tuning(unsafe { event.as_event_unchecked() })),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn as_unknown(&self) -> &'a UnknownEvent {
+        match self {
+            TuningEventSpace::Tuning(e) => e.as_unknown(),
+        }
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_plugin::extensions::prelude::*;
+    use std::mem::MaybeUninit;
+
+    /// A plugin-provided buffer for the host to write tuning information into.
+    pub struct TuningInfoBuffer {
+        inner: MaybeUninit<clap_tuning_info>,
+    }
+
+    impl Default for TuningInfoBuffer {
+        #[inline]
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl TuningInfoBuffer {
+        /// Creates a new, empty tuning information buffer.
+        #[inline]
+        pub const fn new() -> Self {
+            Self {
+                inner: MaybeUninit::zeroed(),
+            }
+        }
+    }
+
+    impl HostTuning {
+        /// Returns the tuning of the given key, in semitones relative to equal temperament with
+        /// A4 = 440Hz.
+        ///
+        /// If `tuning_id` is [`None`] or unknown to the host, it returns a sensible value, usually
+        /// `0.0`. The tuning may change within a process block: `sample_offset` is the position
+        /// in the current block the tuning is requested for.
+        ///
+        /// This is a cheap call, which plugins may make at a rate suitable for low-frequency
+        /// modulations.
+        #[inline]
+        pub fn get_relative(
+            &self,
+            host: &mut HostAudioProcessorHandle,
+            tuning_id: Option<ClapId>,
+            channel: u16,
+            key: u16,
+            sample_offset: u32,
+        ) -> f64 {
+            match host.use_extension(&self.0).get_relative {
+                None => 0.0,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(get_relative) => unsafe {
+                    get_relative(
+                        host.as_raw(),
+                        ClapId::optional_to_raw(tuning_id),
+                        channel as i32,
+                        key as i32,
+                        sample_offset,
+                    )
+                },
+            }
+        }
+
+        /// Returns whether the given key should be played at all with the given tuning.
+        ///
+        /// Some tunings leave keys unmapped, in which case the plugin should not play them.
+        #[inline]
+        pub fn should_play(
+            &self,
+            host: &mut HostAudioProcessorHandle,
+            tuning_id: Option<ClapId>,
+            channel: u16,
+            key: u16,
+        ) -> bool {
+            match host.use_extension(&self.0).should_play {
+                None => true,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(should_play) => unsafe {
+                    should_play(
+                        host.as_raw(),
+                        ClapId::optional_to_raw(tuning_id),
+                        channel as i32,
+                        key as i32,
+                    )
+                },
+            }
+        }
+
+        /// Returns the number of tunings the host provides.
+        #[inline]
+        pub fn get_tuning_count(&self, host: &mut HostMainThreadHandle) -> u32 {
+            match host.use_extension(&self.0).get_tuning_count {
+                None => 0,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(get_tuning_count) => unsafe { get_tuning_count(host.as_raw()) },
+            }
+        }
+
+        /// Retrieves information about the tuning at the given index, using the given buffer.
+        ///
+        /// Returns [`None`] if the index is out of bounds, or if the host failed to provide
+        /// valid information.
+        pub fn get_info<'b>(
+            &self,
+            host: &mut HostMainThreadHandle,
+            tuning_index: u32,
+            buffer: &'b mut TuningInfoBuffer,
+        ) -> Option<TuningInfo<'b>> {
+            // SAFETY: This type ensures the function pointer is valid.
+            let success = unsafe {
+                host.use_extension(&self.0).get_info?(
+                    host.as_raw(),
+                    tuning_index,
+                    buffer.inner.as_mut_ptr(),
+                )
+            };
+
+            if success {
+                // SAFETY: we just checked the buffer was successfully written to.
+                TuningInfo::from_raw(unsafe { buffer.inner.assume_init_ref() })
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Keeps track of the tuning selected for each note port and channel, as set by the host
+    /// through [`TuningEvent`]s.
+    ///
+    /// This helper is meant to live in the plugin's audio processor. It never allocates after it
+    /// has been created.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct ChannelTunings {
+        default: Option<ClapId>,
+        ports: Vec<[Option<ClapId>; 16]>,
+    }
+
+    impl ChannelTunings {
+        /// Creates a new tracker for the given number of note ports, with no tuning selected.
+        pub fn new(port_count: u16) -> Self {
+            Self {
+                default: None,
+                ports: vec![[None; 16]; port_count as usize],
+            }
+        }
+
+        /// Updates the selected tunings with the given event.
+        pub fn apply(&mut self, event: &TuningEvent) {
+            let tuning_id = event.tuning_id();
+            let channel = event.channel();
+
+            if event.port_index().is_all() && channel.is_all() {
+                self.default = tuning_id;
+            }
+
+            for (port_index, channels) in self.ports.iter_mut().enumerate() {
+                if !event.port_index().matches(port_index as u16) {
+                    continue;
+                }
+
+                for (channel_index, selected) in channels.iter_mut().enumerate() {
+                    if channel.matches(channel_index as u16) {
+                        *selected = tuning_id;
+                    }
+                }
+            }
+        }
+
+        /// Clears all selected tunings.
+        pub fn reset(&mut self) {
+            self.default = None;
+            self.ports.fill([None; 16]);
+        }
+
+        /// Returns the tuning selected for the given port and channel, if any.
+        pub fn tuning_id(&self, port_index: u16, channel: u16) -> Option<ClapId> {
+            self.ports
+                .get(port_index as usize)
+                .and_then(|channels| channels.get(channel as usize))
+                .copied()
+                .unwrap_or(self.default)
+        }
+
+        /// Returns the tuning of the given key in semitones, using the tuning selected for the
+        /// given port and channel.
+        ///
+        /// See [`HostTuning::get_relative`].
+        #[inline]
+        pub fn get_relative(
+            &self,
+            host_tuning: &HostTuning,
+            host: &mut HostAudioProcessorHandle,
+            port_index: u16,
+            channel: u16,
+            key: u16,
+            sample_offset: u32,
+        ) -> f64 {
+            let tuning_id = self.tuning_id(port_index, channel);
+            host_tuning.get_relative(host, tuning_id, channel, key, sample_offset)
+        }
+
+        /// Returns whether the given key should be played, using the tuning selected for the
+        /// given port and channel.
+        ///
+        /// See [`HostTuning::should_play`].
+        #[inline]
+        pub fn should_play(
+            &self,
+            host_tuning: &HostTuning,
+            host: &mut HostAudioProcessorHandle,
+            port_index: u16,
+            channel: u16,
+            key: u16,
+        ) -> bool {
+            let tuning_id = self.tuning_id(port_index, channel);
+            host_tuning.should_play(host, tuning_id, channel, key)
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Tuning extension.
+    pub trait PluginTuningImpl {
+        /// Called when the host's tunings changed, either their list or their information.
+        fn changed(&mut self);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginTuning
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginTuningImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_tuning_t {
+                changed: Some(changed::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn changed<P>(plugin: *const clap_plugin)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginTuningImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread().as_mut().changed();
+            Ok(())
+        });
+    }
+}
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use crate::utils::write_to_array_buf;
+    use clack_host::extensions::prelude::*;
+    use std::mem::MaybeUninit;
+
+    /// A writer for the plugin-provided tuning information buffer.
+    pub struct TuningInfoWriter<'a> {
+        buf: &'a mut MaybeUninit<clap_tuning_info>,
+        is_set: bool,
+    }
+
+    impl TuningInfoWriter<'_> {
+        /// # Safety
+        ///
+        /// The user must ensure the provided pointer is aligned and points to a valid allocation.
+        /// However, it doesn't have to be initialized.
+        unsafe fn new(raw: *mut clap_tuning_info) -> Self {
+            Self {
+                // SAFETY: MaybeUninit<T> and T have same memory representation
+                buf: unsafe { &mut *raw.cast() },
+                is_set: false,
+            }
+        }
+
+        /// Writes all fields from the given [`TuningInfo`] into the plugin buffer.
+        ///
+        /// After this call, the plugin may read the tuning information.
+        #[inline]
+        pub fn set(&mut self, info: &TuningInfo) {
+            let buf = self.buf.as_mut_ptr();
+
+            // SAFETY: all pointers come from `inner`, which is valid for writes and well-aligned
+            unsafe {
+                (&raw mut (*buf).tuning_id).write(info.tuning_id.get());
+                (&raw mut (*buf).is_dynamic).write(info.is_dynamic);
+
+                write_to_array_buf(&raw mut ((*buf).name), info.name);
+            }
+            self.is_set = true;
+        }
+    }
+
+    impl PluginTuning {
+        /// Notifies the plugin that the host's tunings changed.
+        #[inline]
+        pub fn changed(&self, plugin: &mut PluginMainThreadHandle) {
+            if let Some(changed) = plugin.use_extension(&self.0).changed {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { changed(plugin.as_raw()) }
+            }
+        }
+    }
+
+    /// Implementation of the Host-side of the Tuning extension that is called from the audio
+    /// thread.
+    pub trait HostTuningImplShared {
+        /// Returns the tuning of the given key, in semitones relative to equal temperament with
+        /// A4 = 440Hz.
+        ///
+        /// If `tuning_id` is [`None`] or unknown, this must still return a sensible value,
+        /// usually `0.0`.
+        fn get_relative(
+            &self,
+            tuning_id: Option<ClapId>,
+            channel: u16,
+            key: u16,
+            sample_offset: u32,
+        ) -> f64;
+
+        /// Returns whether the given key should be played at all with the given tuning.
+        fn should_play(&self, tuning_id: Option<ClapId>, channel: u16, key: u16) -> bool;
+    }
+
+    /// Implementation of the Host-side of the Tuning extension that is called from the main
+    /// thread.
+    pub trait HostTuningImplMainThread {
+        /// Returns the number of tunings the host provides.
+        fn get_tuning_count(&mut self) -> u32;
+
+        /// Writes information about the tuning at the given index into the provided `writer`.
+        ///
+        /// If the index is out of bounds, the writer should be left untouched.
+        fn get_info(&mut self, tuning_index: u32, writer: &mut TuningInfoWriter);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostTuning
+    where
+        for<'a> H: HostHandlers<Shared<'a>: HostTuningImplShared>,
+        for<'a> H: HostHandlers<MainThread<'a>: HostTuningImplMainThread>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_tuning {
+                get_relative: Some(get_relative::<H>),
+                should_play: Some(should_play::<H>),
+                get_tuning_count: Some(get_tuning_count::<H>),
+                get_info: Some(get_info::<H>),
+            });
+    }
+
+    fn to_note_index(value: i32, name: &'static str) -> Result<u16, HostWrapperError> {
+        u16::try_from(value).map_err(|_| HostWrapperError::InvalidParameter(name))
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_relative<H>(
+        host: *const clap_host,
+        tuning_id: u32,
+        channel: i32,
+        key: i32,
+        sample_offset: u32,
+    ) -> f64
+    where
+        for<'a> H: HostHandlers<Shared<'a>: HostTuningImplShared>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            let channel = to_note_index(channel, "Invalid channel")?;
+            let key = to_note_index(key, "Invalid key")?;
+
+            Ok(host
+                .shared()
+                .get_relative(ClapId::from_raw(tuning_id), channel, key, sample_offset))
+        })
+        .unwrap_or(0.0)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn should_play<H>(
+        host: *const clap_host,
+        tuning_id: u32,
+        channel: i32,
+        key: i32,
+    ) -> bool
+    where
+        for<'a> H: HostHandlers<Shared<'a>: HostTuningImplShared>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            let channel = to_note_index(channel, "Invalid channel")?;
+            let key = to_note_index(key, "Invalid key")?;
+
+            Ok(host
+                .shared()
+                .should_play(ClapId::from_raw(tuning_id), channel, key))
+        })
+        .unwrap_or(true)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_tuning_count<H>(host: *const clap_host) -> u32
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostTuningImplMainThread>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            Ok(host.main_thread().as_mut().get_tuning_count())
+        })
+        .unwrap_or(0)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_info<H>(
+        host: *const clap_host,
+        tuning_index: u32,
+        info: *mut clap_tuning_info,
+    ) -> bool
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostTuningImplMainThread>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            if info.is_null() {
+                return Err(HostWrapperError::InvalidParameter("Null clap_tuning_info"));
+            }
+
+            let mut writer = TuningInfoWriter::new(info);
+            host.main_thread()
+                .as_mut()
+                .get_info(tuning_index, &mut writer);
+            Ok(writer.is_set)
+        })
+        .unwrap_or(false)
+    }
+}
+#[cfg(feature = "clack-host")]
+pub use host::*;
@@ -0,0 +1,560 @@
+//! Tuning tables loaded from [Scala] files, which hosts can use to implement the Tuning extension.
+//!
+//! A tuning is described by a scale (`.scl`) file, listing the pitches of each degree of the scale,
+//! and an optional keyboard mapping (`.kbm`) file, describing which scale degree each key plays
+//! and which key is used as the frequency reference.
+//!
+//! [`TuningTable`] computes the resulting tuning of all 128 keys ahead of time, so that the host
+//! side of the extension can be implemented without any computation on the audio thread. Multiple
+//! tables can be stored in [`TuningTables`], which also takes care of assigning them IDs.
+//!
+//! [Scala]: https://www.huygens-fokker.org/scala/scl_format.html
+
+use super::TuningInfo;
+use clack_common::utils::ClapId;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Errors that can occur while loading Scala files.
+#[derive(Debug)]
+pub enum ScalaError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file ended before the given value could be read.
+    UnexpectedEnd {
+        /// A description of the missing value.
+        expected: &'static str,
+    },
+    /// A line of the file does not contain the expected value.
+    InvalidLine {
+        /// The line number, starting at 1.
+        line: usize,
+        /// A description of the expected value.
+        expected: &'static str,
+    },
+    /// The scale does not contain any note.
+    EmptyScale,
+    /// The reference key of the keyboard mapping is not mapped to any scale degree.
+    UnmappedReferenceKey,
+}
+
+impl Display for ScalaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScalaError::Io(e) => write!(f, "Failed to read Scala file: {e}"),
+            ScalaError::UnexpectedEnd { expected } => {
+                write!(f, "Unexpected end of Scala file, expected {expected}")
+            }
+            ScalaError::InvalidLine { line, expected } => {
+                write!(f, "Invalid Scala file at line {line}, expected {expected}")
+            }
+            ScalaError::EmptyScale => f.write_str("Scala scale does not contain any note"),
+            ScalaError::UnmappedReferenceKey => {
+                f.write_str("The reference key of the keyboard mapping is unmapped")
+            }
+        }
+    }
+}
+
+impl Error for ScalaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScalaError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ScalaError {
+    #[inline]
+    fn from(e: std::io::Error) -> Self {
+        ScalaError::Io(e)
+    }
+}
+
+/// Iterates over the meaningful lines of a Scala file, skipping comments.
+struct ScalaLines<'a> {
+    inner: std::iter::Enumerate<std::str::Lines<'a>>,
+}
+
+impl<'a> ScalaLines<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            inner: source.lines().enumerate(),
+        }
+    }
+
+    fn next_line(&mut self) -> Option<(usize, &'a str)> {
+        self.inner
+            .by_ref()
+            .find(|(_, line)| !line.starts_with('!'))
+            .map(|(index, line)| (index + 1, line.trim()))
+    }
+
+    fn next_token(&mut self, expected: &'static str) -> Result<(usize, &'a str), ScalaError> {
+        let (line, text) = self
+            .next_line()
+            .ok_or(ScalaError::UnexpectedEnd { expected })?;
+
+        let token = text
+            .split_whitespace()
+            .next()
+            .ok_or(ScalaError::InvalidLine { line, expected })?;
+
+        Ok((line, token))
+    }
+
+    fn next_value<T: FromStr>(&mut self, expected: &'static str) -> Result<T, ScalaError> {
+        let (line, token) = self.next_token(expected)?;
+        token
+            .parse()
+            .map_err(|_| ScalaError::InvalidLine { line, expected })
+    }
+
+    fn next_key(&mut self, expected: &'static str) -> Result<u8, ScalaError> {
+        let (line, token) = self.next_token(expected)?;
+        match token.parse() {
+            Ok(key) if key < 128 => Ok(key),
+            _ => Err(ScalaError::InvalidLine { line, expected }),
+        }
+    }
+}
+
+fn parse_pitch(token: &str) -> Option<f64> {
+    // Values containing a period are in cents, others are ratios
+    if token.contains('.') {
+        return token.parse().ok().filter(|c: &f64| c.is_finite());
+    }
+
+    let (numerator, denominator) = token.split_once('/').unwrap_or((token, "1"));
+    let numerator: u64 = numerator.parse().ok()?;
+    let denominator: u64 = denominator.parse().ok()?;
+
+    if numerator == 0 || denominator == 0 {
+        return None;
+    }
+
+    Some(1200.0 * (numerator as f64 / denominator as f64).log2())
+}
+
+/// A scale, as described by a Scala `.scl` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scale {
+    description: String,
+    // The pitch of each degree but the first (which is always 1/1), in cents.
+    // The last one is the period of the scale, usually an octave.
+    pitches: Vec<f64>,
+}
+
+impl Scale {
+    /// Parses the contents of a Scala `.scl` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScalaError`] if the file is malformed or contains no note.
+    pub fn parse(source: &str) -> Result<Self, ScalaError> {
+        let mut lines = ScalaLines::new(source);
+
+        let (_, description) = lines.next_line().ok_or(ScalaError::UnexpectedEnd {
+            expected: "a description",
+        })?;
+        let note_count: u32 = lines.next_value("a note count")?;
+
+        if note_count == 0 {
+            return Err(ScalaError::EmptyScale);
+        }
+
+        let pitches = (0..note_count)
+            .map(|_| {
+                let (line, token) = lines.next_token("a pitch")?;
+                parse_pitch(token).ok_or(ScalaError::InvalidLine {
+                    line,
+                    expected: "a pitch",
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            description: description.to_owned(),
+            pitches,
+        })
+    }
+
+    /// Reads and parses a Scala `.scl` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScalaError`] if the file could not be read, or if it is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScalaError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// The description of the scale.
+    #[inline]
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The number of notes in the scale, including the period but excluding the implicit 1/1.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pitches.len()
+    }
+
+    /// Returns `true` if the scale contains no note.
+    ///
+    /// Parsed scales always contain at least one note.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pitches.is_empty()
+    }
+
+    /// The interval at which the scale repeats, in cents. This is usually an octave (1200 cents).
+    #[inline]
+    pub fn period(&self) -> f64 {
+        self.pitches.last().copied().unwrap_or(0.0)
+    }
+
+    /// Returns the pitch of the given scale degree in cents, relative to the first degree.
+    ///
+    /// Degrees outside the scale are repeated according to its [`period`](Self::period).
+    pub fn degree_cents(&self, degree: i64) -> f64 {
+        if self.pitches.is_empty() {
+            return 0.0;
+        }
+
+        let len = self.pitches.len() as i64;
+        let periods = degree.div_euclid(len);
+
+        let pitch = match degree.rem_euclid(len) {
+            0 => 0.0,
+            step => self.pitches[step as usize - 1],
+        };
+
+        periods as f64 * self.period() + pitch
+    }
+}
+
+/// A keyboard mapping, as described by a Scala `.kbm` file.
+///
+/// The [`Default`] mapping maps each key linearly to the scale, with the first degree on key 60
+/// and key 69 tuned to 440Hz.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardMapping {
+    /// The first key to be retuned. Keys below it are not played.
+    pub first_key: u8,
+    /// The last key to be retuned. Keys above it are not played.
+    pub last_key: u8,
+    /// The key on which the first entry of the mapping is played.
+    pub middle_key: u8,
+    /// The key for which the reference frequency is given.
+    pub reference_key: u8,
+    /// The frequency of the reference key, in Hz.
+    pub reference_frequency: f64,
+    /// The scale degree to use as the formal octave, i.e. the interval between two repetitions
+    /// of the mapping. If `0`, the period of the scale is used.
+    pub octave_degree: u32,
+    /// The scale degree each key plays, starting from the middle key and repeating every
+    /// `mapping.len()` keys. [`None`] entries are not played.
+    ///
+    /// If empty, keys are mapped linearly to successive scale degrees.
+    pub mapping: Vec<Option<u32>>,
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        Self {
+            first_key: 0,
+            last_key: 127,
+            middle_key: 60,
+            reference_key: 69,
+            reference_frequency: 440.0,
+            octave_degree: 0,
+            mapping: Vec::new(),
+        }
+    }
+}
+
+/// The maximum number of entries in a keyboard mapping, i.e. the number of MIDI keys.
+const MAX_MAPPING_SIZE: u32 = 128;
+
+impl KeyboardMapping {
+    /// Parses the contents of a Scala `.kbm` file.
+    ///
+    /// Mapping entries missing at the end of the file are considered unmapped. As the mapping is
+    /// applied to MIDI keys, its size cannot exceed 128 entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScalaError`] if the file is malformed.
+    pub fn parse(source: &str) -> Result<Self, ScalaError> {
+        let mut lines = ScalaLines::new(source);
+
+        let (line, token) = lines.next_token("a mapping size")?;
+        let size = match token.parse::<u32>() {
+            Ok(size) if size <= MAX_MAPPING_SIZE => size,
+            _ => {
+                return Err(ScalaError::InvalidLine {
+                    line,
+                    expected: "a mapping size of at most 128",
+                });
+            }
+        };
+
+        let first_key = lines.next_key("a first key")?;
+        let last_key = lines.next_key("a last key")?;
+        let middle_key = lines.next_key("a middle key")?;
+        let reference_key = lines.next_key("a reference key")?;
+
+        let (line, token) = lines.next_token("a reference frequency")?;
+        let reference_frequency = match token.parse::<f64>() {
+            Ok(frequency) if frequency.is_finite() && frequency > 0.0 => frequency,
+            _ => {
+                return Err(ScalaError::InvalidLine {
+                    line,
+                    expected: "a positive reference frequency",
+                });
+            }
+        };
+
+        let octave_degree = lines.next_value("a formal octave degree")?;
+
+        let mapping = (0..size)
+            .map(|_| {
+                let Some((line, text)) = lines.next_line() else {
+                    return Ok(None);
+                };
+
+                match text.split_whitespace().next() {
+                    Some("x") => Ok(None),
+                    Some(token) => token
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| ScalaError::InvalidLine {
+                            line,
+                            expected: "a scale degree",
+                        }),
+                    None => Err(ScalaError::InvalidLine {
+                        line,
+                        expected: "a scale degree",
+                    }),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            first_key,
+            last_key,
+            middle_key,
+            reference_key,
+            reference_frequency,
+            octave_degree,
+            mapping,
+        })
+    }
+
+    /// Reads and parses a Scala `.kbm` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScalaError`] if the file could not be read, or if it is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScalaError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns the pitch of the given key in cents relative to the first scale degree, or
+    /// [`None`] if the key is unmapped. This ignores the retuned key range.
+    fn key_cents(&self, scale: &Scale, key: u8) -> Option<f64> {
+        let offset = key as i64 - self.middle_key as i64;
+
+        if self.mapping.is_empty() {
+            return Some(scale.degree_cents(offset));
+        }
+
+        let size = self.mapping.len() as i64;
+        let degree = self.mapping[offset.rem_euclid(size) as usize]?;
+
+        let octave_cents = match self.octave_degree {
+            0 => scale.period(),
+            octave_degree => scale.degree_cents(octave_degree as i64),
+        };
+
+        Some(offset.div_euclid(size) as f64 * octave_cents + scale.degree_cents(degree as i64))
+    }
+}
+
+/// The tuning of all 128 keys, computed from a [`Scale`] and a [`KeyboardMapping`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuningTable {
+    name: String,
+    relative: [f64; 128],
+    playable: [bool; 128],
+}
+
+impl TuningTable {
+    /// Computes the tuning table of the given scale and keyboard mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ScalaError::UnmappedReferenceKey`] if the mapping's reference key is unmapped,
+    /// as the tuning can't be computed without it.
+    pub fn new(
+        name: impl Into<String>,
+        scale: &Scale,
+        mapping: &KeyboardMapping,
+    ) -> Result<Self, ScalaError> {
+        let reference_cents = mapping
+            .key_cents(scale, mapping.reference_key)
+            .ok_or(ScalaError::UnmappedReferenceKey)?;
+        // The reference pitch, in fractional MIDI keys
+        let reference_pitch = 69.0 + 12.0 * (mapping.reference_frequency / 440.0).log2();
+
+        let mut relative = [0.0; 128];
+        let mut playable = [false; 128];
+
+        for key in mapping.first_key..=mapping.last_key.min(127) {
+            let Some(cents) = mapping.key_cents(scale, key) else {
+                continue;
+            };
+
+            let pitch = reference_pitch + (cents - reference_cents) / 100.0;
+            relative[key as usize] = pitch - key as f64;
+            playable[key as usize] = true;
+        }
+
+        Ok(Self {
+            name: name.into(),
+            relative,
+            playable,
+        })
+    }
+
+    /// Loads a tuning table from a Scala `.scl` file and an optional `.kbm` file.
+    ///
+    /// If no keyboard mapping is given, the [`Default`] one is used. The table is named after
+    /// the scale's description.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScalaError`] if a file could not be read or is malformed, or if the tuning
+    /// can't be computed.
+    pub fn load(
+        scale_path: impl AsRef<Path>,
+        mapping_path: Option<&Path>,
+    ) -> Result<Self, ScalaError> {
+        let scale = Scale::load(scale_path)?;
+        let mapping = match mapping_path {
+            Some(path) => KeyboardMapping::load(path)?,
+            None => KeyboardMapping::default(),
+        };
+
+        Self::new(scale.description(), &scale, &mapping)
+    }
+
+    /// The display name of this tuning.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the tuning of the given key, in semitones relative to equal temperament with
+    /// A4 = 440Hz.
+    ///
+    /// Keys that are out of range or not played return `0.0`.
+    #[inline]
+    pub fn relative(&self, key: u16) -> f64 {
+        self.relative.get(key as usize).copied().unwrap_or(0.0)
+    }
+
+    /// Returns whether the given key is mapped by this tuning, and should be played.
+    #[inline]
+    pub fn should_play(&self, key: u16) -> bool {
+        self.playable.get(key as usize).copied().unwrap_or(false)
+    }
+
+    /// Returns the frequency of the given key in Hz.
+    #[inline]
+    pub fn frequency(&self, key: u16) -> f64 {
+        let pitch = key as f64 + self.relative(key);
+        440.0 * ((pitch - 69.0) / 12.0).exp2()
+    }
+}
+
+/// A list of [`TuningTable`]s, each identified by the ID the host exposes it with.
+///
+/// This provides everything needed to implement the host side of the Tuning extension.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TuningTables {
+    tables: Vec<TuningTable>,
+}
+
+impl TuningTables {
+    /// Creates an empty list of tuning tables.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { tables: Vec::new() }
+    }
+
+    /// Adds a tuning table to this list, and returns the ID it was assigned.
+    pub fn push(&mut self, table: TuningTable) -> ClapId {
+        let id = ClapId::new(self.tables.len() as u32);
+        self.tables.push(table);
+        id
+    }
+
+    /// Returns the tuning table with the given ID, if any.
+    #[inline]
+    pub fn get(&self, tuning_id: ClapId) -> Option<&TuningTable> {
+        self.tables.get(tuning_id.get() as usize)
+    }
+
+    /// Returns the number of tuning tables in this list.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Returns `true` if this list contains no tuning table.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Returns information about the tuning table at the given index, if any.
+    ///
+    /// Tuning tables are never dynamic.
+    pub fn info(&self, tuning_index: u32) -> Option<TuningInfo<'_>> {
+        let table = self.tables.get(tuning_index as usize)?;
+
+        Some(TuningInfo {
+            tuning_id: ClapId::new(tuning_index),
+            name: table.name.as_bytes(),
+            is_dynamic: false,
+        })
+    }
+
+    /// Returns the tuning of the given key with the given tuning, in semitones relative to equal
+    /// temperament with A4 = 440Hz.
+    ///
+    /// Returns `0.0` if no tuning is given, or if it is unknown.
+    #[inline]
+    pub fn get_relative(&self, tuning_id: Option<ClapId>, key: u16) -> f64 {
+        match tuning_id.and_then(|id| self.get(id)) {
+            Some(table) => table.relative(key),
+            None => 0.0,
+        }
+    }
+
+    /// Returns whether the given key should be played with the given tuning.
+    ///
+    /// Returns `true` if no tuning is given, or if it is unknown.
+    #[inline]
+    pub fn should_play(&self, tuning_id: Option<ClapId>, key: u16) -> bool {
+        match tuning_id.and_then(|id| self.get(id)) {
+            Some(table) => table.should_play(key),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,810 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to integrate their changes into the host's undo history.
+//!
+//! This is made of three extensions:
+//!
+//! * [`HostUndo`], used by the plugin to notify the host of changes it made, and to ask the host
+//!   to undo or redo them;
+//! * [`PluginUndoDelta`], used by the host to apply the deltas provided by the plugin when undoing
+//!   or redoing changes;
+//! * [`PluginUndoContext`], used by the host to keep the plugin informed of the current state of
+//!   the undo history, e.g. to update undo and redo buttons in the plugin's GUI.
+//!
+//! Plugins that do not provide deltas can still notify the host of their changes, in which case
+//! the host will fall back to saving and restoring the plugin's state.
+
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clack_common::utils::ClapId;
+use clap_sys::ext::draft::undo::*;
+use std::error::Error;
+use std::ffi::{CStr, CString};
+use std::fmt::{Display, Formatter};
+
+/// Host-side of the Undo extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostUndo(RawExtension<HostExtensionSide, clap_host_undo>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostUndo {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_UNDO];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Plugin-side of the Undo Delta extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginUndoDelta(RawExtension<PluginExtensionSide, clap_plugin_undo_delta>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginUndoDelta {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_UNDO_DELTA];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Plugin-side of the Undo Context extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginUndoContext(RawExtension<PluginExtensionSide, clap_plugin_undo_context>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginUndoContext {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_UNDO_CONTEXT];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Describes the deltas a plugin provides along with its changes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UndoDeltaProperties {
+    /// Whether the plugin provides a delta when notifying the host of a change.
+    pub has_delta: bool,
+    /// Whether the deltas can be saved with the project, and then used to undo or redo changes
+    /// made in a previous session.
+    pub are_deltas_persistent: bool,
+    /// The format version of the deltas provided by the plugin.
+    ///
+    /// This is [`None`] if the deltas are not persistent.
+    pub format_version: Option<ClapId>,
+}
+
+impl UndoDeltaProperties {
+    /// Properties for a plugin that does not provide any delta.
+    pub const NO_DELTA: Self = Self {
+        has_delta: false,
+        are_deltas_persistent: false,
+        format_version: None,
+    };
+
+    /// Gets undo delta properties from their raw, C-FFI compatible representation.
+    #[inline]
+    pub const fn from_raw(raw: &clap_undo_delta_properties) -> Self {
+        Self {
+            has_delta: raw.has_delta,
+            are_deltas_persistent: raw.are_deltas_persistent,
+            format_version: ClapId::from_raw(raw.format_version),
+        }
+    }
+
+    /// Returns the raw, C-FFI compatible representation of these undo delta properties.
+    #[inline]
+    pub const fn to_raw(&self) -> clap_undo_delta_properties {
+        clap_undo_delta_properties {
+            has_delta: self.has_delta,
+            are_deltas_persistent: self.are_deltas_persistent,
+            format_version: ClapId::optional_to_raw(self.format_version),
+        }
+    }
+}
+
+impl Default for UndoDeltaProperties {
+    #[inline]
+    fn default() -> Self {
+        Self::NO_DELTA
+    }
+}
+
+/// The direction in which an undo delta is applied.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum UndoDirection {
+    /// The change the delta describes is being undone.
+    Undo,
+    /// The change the delta describes is being redone.
+    Redo,
+}
+
+/// A change a plugin notified the host about, including a copy of its delta.
+///
+/// This is what hosts receive in [`HostUndoImpl::change_made`], and can be stored as-is in the
+/// host's undo history.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UndoChange {
+    /// The user-friendly name of the change, e.g. "Set cutoff to 440Hz".
+    pub name: CString,
+    /// The delta describing the change, if the plugin provided one.
+    pub delta: Option<Box<[u8]>>,
+    /// Whether the delta can be used to undo or redo this change.
+    ///
+    /// If this is `false`, the host must rely on saving and restoring the plugin's state instead.
+    pub delta_can_undo: bool,
+}
+
+/// Errors that can occur while applying an undo delta.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct UndoDeltaError {
+    direction: UndoDirection,
+}
+
+impl UndoDeltaError {
+    /// Returns the direction in which the delta failed to be applied.
+    #[inline]
+    pub fn direction(&self) -> UndoDirection {
+        self.direction
+    }
+}
+
+impl Display for UndoDeltaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.direction {
+            UndoDirection::Undo => f.write_str("Plugin failed to apply undo delta"),
+            UndoDirection::Redo => f.write_str("Plugin failed to apply redo delta"),
+        }
+    }
+}
+
+impl Error for UndoDeltaError {}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use crate::utils::cstr_from_nullable_ptr;
+    use clack_plugin::extensions::prelude::*;
+    use std::ffi::{c_char, c_void};
+
+    impl HostUndo {
+        /// Notifies the host that the plugin started a long-running change, e.g. while the user is
+        /// dragging a knob in the plugin's GUI.
+        ///
+        /// The change must be completed by calling either [`change_made`](Self::change_made) or
+        /// [`cancel_change`](Self::cancel_change).
+        #[inline]
+        pub fn begin_change(&self, host: &mut HostMainThreadHandle) {
+            if let Some(begin_change) = host.use_extension(&self.0).begin_change {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { begin_change(host.as_raw()) }
+            }
+        }
+
+        /// Notifies the host that the long-running change that was started was cancelled, e.g.
+        /// because the user pressed escape while dragging a knob.
+        #[inline]
+        pub fn cancel_change(&self, host: &mut HostMainThreadHandle) {
+            if let Some(cancel_change) = host.use_extension(&self.0).cancel_change {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { cancel_change(host.as_raw()) }
+            }
+        }
+
+        /// Notifies the host that a change was made, so that it can be added to its undo history.
+        ///
+        /// `name` is a user-friendly description of the change. If the plugin provides deltas
+        /// (see [`UndoDeltaProperties`]), `delta` is the data the host will later pass back to
+        /// [`PluginUndoDeltaImpl::apply_delta`] to undo or redo this change. The host copies the
+        /// delta, so it does not need to outlive this call.
+        ///
+        /// `delta_can_undo` indicates whether the delta can actually be used to undo or redo this
+        /// change. If it is `false`, the host will rely on saving and restoring the plugin's state
+        /// instead.
+        #[inline]
+        pub fn change_made(
+            &self,
+            host: &mut HostMainThreadHandle,
+            name: &CStr,
+            delta: Option<&[u8]>,
+            delta_can_undo: bool,
+        ) {
+            let Some(change_made) = host.use_extension(&self.0).change_made else {
+                return;
+            };
+
+            let (delta_ptr, delta_size) = match delta {
+                Some(delta) => (delta.as_ptr().cast::<c_void>(), delta.len()),
+                None => (core::ptr::null(), 0),
+            };
+
+            // SAFETY: This type ensures the function pointer is valid. The name and delta pointers
+            // come from references, and are valid for the duration of the call.
+            unsafe {
+                change_made(
+                    host.as_raw(),
+                    name.as_ptr(),
+                    delta_ptr,
+                    delta_size,
+                    delta_can_undo,
+                )
+            }
+        }
+
+        /// Asks the host to undo the last change in its history.
+        ///
+        /// This is typically called when the user presses an undo button in the plugin's GUI. The
+        /// host may or may not perform the request.
+        #[inline]
+        pub fn request_undo(&self, host: &mut HostMainThreadHandle) {
+            if let Some(request_undo) = host.use_extension(&self.0).request_undo {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { request_undo(host.as_raw()) }
+            }
+        }
+
+        /// Asks the host to redo the last change that was undone.
+        ///
+        /// This is typically called when the user presses a redo button in the plugin's GUI. The
+        /// host may or may not perform the request.
+        #[inline]
+        pub fn request_redo(&self, host: &mut HostMainThreadHandle) {
+            if let Some(request_redo) = host.use_extension(&self.0).request_redo {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { request_redo(host.as_raw()) }
+            }
+        }
+
+        /// Subscribes to or unsubscribes from undo context updates.
+        ///
+        /// While subscribed, the host will keep the plugin informed of the state of its undo
+        /// history using the [`PluginUndoContext`] extension. Plugins are unsubscribed by default.
+        #[inline]
+        pub fn set_wants_context_updates(
+            &self,
+            host: &mut HostMainThreadHandle,
+            is_subscribed: bool,
+        ) {
+            if let Some(set_wants_context_updates) =
+                host.use_extension(&self.0).set_wants_context_updates
+            {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { set_wants_context_updates(host.as_raw(), is_subscribed) }
+            }
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Undo Delta extension.
+    pub trait PluginUndoDeltaImpl {
+        /// Returns the properties of the deltas this plugin provides with its changes.
+        fn get_delta_properties(&mut self) -> UndoDeltaProperties;
+
+        /// Returns `true` if this plugin can apply deltas of the given format version.
+        ///
+        /// This is used by the host to check whether persisted deltas from a previous session
+        /// can still be used.
+        fn can_use_delta_format_version(&mut self, format_version: ClapId) -> bool;
+
+        /// Applies the given delta, in order to either undo or redo the change it describes.
+        ///
+        /// The delta is one previously provided by this plugin through [`HostUndo::change_made`],
+        /// in the given `format_version`.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the delta could not be applied.
+        fn apply_delta(
+            &mut self,
+            direction: UndoDirection,
+            format_version: ClapId,
+            delta: &[u8],
+        ) -> Result<(), PluginError>;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginUndoDelta
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoDeltaImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_undo_delta {
+                get_delta_properties: Some(get_delta_properties::<P>),
+                can_use_delta_format_version: Some(can_use_delta_format_version::<P>),
+                undo: Some(undo::<P>),
+                redo: Some(redo::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_delta_properties<P>(
+        plugin: *const clap_plugin,
+        properties: *mut clap_undo_delta_properties,
+    ) where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoDeltaImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            if properties.is_null() {
+                return Err(PluginWrapperError::NulPtr("clap_undo_delta_properties"));
+            }
+
+            let result = p.main_thread().as_mut().get_delta_properties();
+            properties.write(result.to_raw());
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn can_use_delta_format_version<P>(
+        plugin: *const clap_plugin,
+        format_version: u32,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoDeltaImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let Some(format_version) = ClapId::from_raw(format_version) else {
+                return Ok(false);
+            };
+
+            Ok(p.main_thread()
+                .as_mut()
+                .can_use_delta_format_version(format_version))
+        })
+        .unwrap_or(false)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn undo<P>(
+        plugin: *const clap_plugin,
+        format_version: u32,
+        delta: *const c_void,
+        delta_size: usize,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoDeltaImpl>,
+    {
+        apply_delta::<P>(
+            plugin,
+            UndoDirection::Undo,
+            format_version,
+            delta,
+            delta_size,
+        )
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn redo<P>(
+        plugin: *const clap_plugin,
+        format_version: u32,
+        delta: *const c_void,
+        delta_size: usize,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoDeltaImpl>,
+    {
+        apply_delta::<P>(
+            plugin,
+            UndoDirection::Redo,
+            format_version,
+            delta,
+            delta_size,
+        )
+    }
+
+    /// # Safety
+    ///
+    /// `plugin` must be a valid plugin pointer, and `delta` must be valid for reads of
+    /// `delta_size` bytes.
+    unsafe fn apply_delta<P>(
+        plugin: *const clap_plugin,
+        direction: UndoDirection,
+        format_version: u32,
+        delta: *const c_void,
+        delta_size: usize,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoDeltaImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let format_version = ClapId::from_raw(format_version).ok_or(
+                PluginWrapperError::InvalidParameter("Invalid undo delta format version"),
+            )?;
+
+            let delta = if delta_size == 0 {
+                &[]
+            } else if delta.is_null() {
+                return Err(PluginWrapperError::NulPtr("Undo delta"));
+            } else {
+                core::slice::from_raw_parts(delta.cast::<u8>(), delta_size)
+            };
+
+            Ok(p.main_thread()
+                .as_mut()
+                .apply_delta(direction, format_version, delta)
+                .is_ok())
+        })
+        .unwrap_or(false)
+    }
+
+    /// Implementation of the Plugin-side of the Undo Context extension.
+    ///
+    /// These methods are only called by the host after the plugin subscribed to context updates
+    /// using [`HostUndo::set_wants_context_updates`].
+    pub trait PluginUndoContextImpl {
+        /// Indicates whether the host can currently undo a change.
+        fn set_can_undo(&mut self, can_undo: bool);
+
+        /// Indicates whether the host can currently redo a change.
+        fn set_can_redo(&mut self, can_redo: bool);
+
+        /// Sets the user-friendly name of the change that would be undone next, if any.
+        fn set_undo_name(&mut self, name: Option<&CStr>);
+
+        /// Sets the user-friendly name of the change that would be redone next, if any.
+        fn set_redo_name(&mut self, name: Option<&CStr>);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginUndoContext
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoContextImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_undo_context {
+                set_can_undo: Some(set_can_undo::<P>),
+                set_can_redo: Some(set_can_redo::<P>),
+                set_undo_name: Some(set_undo_name::<P>),
+                set_redo_name: Some(set_redo_name::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_can_undo<P>(plugin: *const clap_plugin, can_undo: bool)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoContextImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread().as_mut().set_can_undo(can_undo);
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_can_redo<P>(plugin: *const clap_plugin, can_redo: bool)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoContextImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread().as_mut().set_can_redo(can_redo);
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_undo_name<P>(plugin: *const clap_plugin, name: *const c_char)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoContextImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread()
+                .as_mut()
+                .set_undo_name(cstr_from_nullable_ptr(name));
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_redo_name<P>(plugin: *const clap_plugin, name: *const c_char)
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginUndoContextImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            p.main_thread()
+                .as_mut()
+                .set_redo_name(cstr_from_nullable_ptr(name));
+            Ok(())
+        });
+    }
+}
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use crate::utils::{cstr_from_nullable_ptr, cstr_to_nullable_ptr};
+    use clack_host::extensions::prelude::*;
+    use clap_sys::plugin::clap_plugin;
+    use std::ffi::{c_char, c_void};
+
+    /// Implementation of the Host-side of the Undo extension.
+    pub trait HostUndoImpl {
+        /// Called when the plugin started a long-running change, e.g. while the user is dragging
+        /// a knob in the plugin's GUI.
+        fn begin_change(&mut self);
+
+        /// Called when the long-running change the plugin started was cancelled.
+        fn cancel_change(&mut self);
+
+        /// Called when the plugin made a change, which should be added to the host's undo history.
+        ///
+        /// The given [`UndoChange`] owns a copy of the change's name and delta.
+        fn change_made(&mut self, change: UndoChange);
+
+        /// Called when the plugin asks the host to undo the last change in its history.
+        fn request_undo(&mut self);
+
+        /// Called when the plugin asks the host to redo the last change that was undone.
+        fn request_redo(&mut self);
+
+        /// Called when the plugin subscribes to or unsubscribes from undo context updates.
+        ///
+        /// While subscribed, the host should keep the plugin informed of the state of its undo
+        /// history using the [`PluginUndoContext`] extension.
+        fn set_wants_context_updates(&mut self, is_subscribed: bool);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostUndo
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_undo {
+                begin_change: Some(begin_change::<H>),
+                cancel_change: Some(cancel_change::<H>),
+                change_made: Some(change_made::<H>),
+                request_undo: Some(request_undo::<H>),
+                request_redo: Some(request_redo::<H>),
+                set_wants_context_updates: Some(set_wants_context_updates::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn begin_change<H>(host: *const clap_host)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().begin_change();
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn cancel_change<H>(host: *const clap_host)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().cancel_change();
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn change_made<H>(
+        host: *const clap_host,
+        name: *const c_char,
+        delta: *const c_void,
+        delta_size: usize,
+        delta_can_undo: bool,
+    ) where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            let name = cstr_from_nullable_ptr(name)
+                .ok_or(HostWrapperError::InvalidParameter("Null undo change name"))?;
+
+            let delta = if delta_size == 0 {
+                None
+            } else if delta.is_null() {
+                return Err(HostWrapperError::InvalidParameter("Null undo delta"));
+            } else {
+                Some(core::slice::from_raw_parts(delta.cast::<u8>(), delta_size).into())
+            };
+
+            host.main_thread().as_mut().change_made(UndoChange {
+                name: name.to_owned(),
+                delta,
+                delta_can_undo,
+            });
+
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn request_undo<H>(host: *const clap_host)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().request_undo();
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn request_redo<H>(host: *const clap_host)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().request_redo();
+            Ok(())
+        });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn set_wants_context_updates<H>(host: *const clap_host, is_subscribed: bool)
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostUndoImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread()
+                .as_mut()
+                .set_wants_context_updates(is_subscribed);
+            Ok(())
+        });
+    }
+
+    impl PluginUndoDelta {
+        /// Returns the properties of the deltas the plugin provides with its changes.
+        ///
+        /// If the plugin does not implement this, it is assumed not to provide any delta.
+        #[inline]
+        pub fn get_delta_properties(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+        ) -> UndoDeltaProperties {
+            let Some(get_delta_properties) = plugin.use_extension(&self.0).get_delta_properties
+            else {
+                return UndoDeltaProperties::NO_DELTA;
+            };
+
+            let mut properties = UndoDeltaProperties::NO_DELTA.to_raw();
+
+            // SAFETY: This type ensures the function pointer is valid.
+            unsafe { get_delta_properties(plugin.as_raw(), &mut properties) };
+
+            UndoDeltaProperties::from_raw(&properties)
+        }
+
+        /// Returns `true` if the plugin can apply deltas of the given format version.
+        #[inline]
+        pub fn can_use_delta_format_version(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            format_version: ClapId,
+        ) -> bool {
+            match plugin.use_extension(&self.0).can_use_delta_format_version {
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(can_use) => unsafe { can_use(plugin.as_raw(), format_version.get()) },
+                None => false,
+            }
+        }
+
+        /// Asks the plugin to undo the change described by the given delta.
+        ///
+        /// # Errors
+        ///
+        /// Returns an [`UndoDeltaError`] if the plugin failed to apply the delta.
+        #[inline]
+        pub fn undo(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            format_version: ClapId,
+            delta: &[u8],
+        ) -> Result<(), UndoDeltaError> {
+            let undo = plugin.use_extension(&self.0).undo;
+            // SAFETY: This type ensures the function pointer is valid.
+            unsafe { apply_delta(plugin, undo, UndoDirection::Undo, format_version, delta) }
+        }
+
+        /// Asks the plugin to redo the change described by the given delta.
+        ///
+        /// # Errors
+        ///
+        /// Returns an [`UndoDeltaError`] if the plugin failed to apply the delta.
+        #[inline]
+        pub fn redo(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            format_version: ClapId,
+            delta: &[u8],
+        ) -> Result<(), UndoDeltaError> {
+            let redo = plugin.use_extension(&self.0).redo;
+            // SAFETY: This type ensures the function pointer is valid.
+            unsafe { apply_delta(plugin, redo, UndoDirection::Redo, format_version, delta) }
+        }
+    }
+
+    type ApplyDeltaFn = unsafe extern "C" fn(*const clap_plugin, u32, *const c_void, usize) -> bool;
+
+    /// # Safety
+    ///
+    /// The given function pointer must be valid for the given plugin.
+    unsafe fn apply_delta(
+        plugin: &mut PluginMainThreadHandle,
+        apply: Option<ApplyDeltaFn>,
+        direction: UndoDirection,
+        format_version: ClapId,
+        delta: &[u8],
+    ) -> Result<(), UndoDeltaError> {
+        let apply = apply.ok_or(UndoDeltaError { direction })?;
+
+        // SAFETY: The function pointer is guaranteed to be valid by the caller. The delta pointer
+        // comes from a reference, and is valid for the duration of the call.
+        let success = unsafe {
+            apply(
+                plugin.as_raw(),
+                format_version.get(),
+                delta.as_ptr().cast(),
+                delta.len(),
+            )
+        };
+
+        match success {
+            true => Ok(()),
+            false => Err(UndoDeltaError { direction }),
+        }
+    }
+
+    impl PluginUndoContext {
+        /// Indicates to the plugin whether the host can currently undo a change.
+        #[inline]
+        pub fn set_can_undo(&self, plugin: &mut PluginMainThreadHandle, can_undo: bool) {
+            if let Some(set_can_undo) = plugin.use_extension(&self.0).set_can_undo {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { set_can_undo(plugin.as_raw(), can_undo) }
+            }
+        }
+
+        /// Indicates to the plugin whether the host can currently redo a change.
+        #[inline]
+        pub fn set_can_redo(&self, plugin: &mut PluginMainThreadHandle, can_redo: bool) {
+            if let Some(set_can_redo) = plugin.use_extension(&self.0).set_can_redo {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { set_can_redo(plugin.as_raw(), can_redo) }
+            }
+        }
+
+        /// Sets the user-friendly name of the change that would be undone next, if any.
+        #[inline]
+        pub fn set_undo_name(&self, plugin: &mut PluginMainThreadHandle, name: Option<&CStr>) {
+            if let Some(set_undo_name) = plugin.use_extension(&self.0).set_undo_name {
+                // SAFETY: This type ensures the function pointer is valid. The name pointer comes
+                // from a reference, and is valid for the duration of the call.
+                unsafe { set_undo_name(plugin.as_raw(), cstr_to_nullable_ptr(name)) }
+            }
+        }
+
+        /// Sets the user-friendly name of the change that would be redone next, if any.
+        #[inline]
+        pub fn set_redo_name(&self, plugin: &mut PluginMainThreadHandle, name: Option<&CStr>) {
+            if let Some(set_redo_name) = plugin.use_extension(&self.0).set_redo_name {
+                // SAFETY: This type ensures the function pointer is valid. The name pointer comes
+                // from a reference, and is valid for the duration of the call.
+                unsafe { set_redo_name(plugin.as_raw(), cstr_to_nullable_ptr(name)) }
+            }
+        }
+    }
+}
+#[cfg(feature = "clack-host")]
+pub use host::*;
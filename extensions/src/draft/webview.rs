@@ -0,0 +1,606 @@
+#![deny(missing_docs)]
+
+//! Allows plugins to provide their GUI as a web page, which is rendered by the host in a webview.
+//!
+//! Instead of drawing into a native window, the plugin provides the URI of its web page, and the
+//! resources (HTML, scripts, images…) it is made of. The host loads that page in a webview it owns,
+//! and relays messages between the page and the plugin.
+//!
+//! This extension is used together with the `gui` extension, using the
+//! [`WINDOW_API_WEBVIEW`] window API.
+
+use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::stream::clap_ostream;
+use std::error::Error;
+use std::ffi::{CStr, c_char, c_void};
+use std::fmt::{Display, Formatter};
+
+/// The identifier of the Webview extension.
+pub const CLAP_EXT_WEBVIEW: &CStr = c"clap.webview/3";
+
+/// The name of the window API to use with the `gui` extension to display a webview GUI.
+pub const WINDOW_API_WEBVIEW: &CStr = c"webview";
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_plugin_webview {
+    get_uri: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            uri: *mut c_char,
+            uri_capacity: u32,
+        ) -> i32,
+    >,
+    get_resource: Option<
+        unsafe extern "C" fn(
+            plugin: *const clap_plugin,
+            path: *const c_char,
+            mime: *mut c_char,
+            mime_capacity: u32,
+            stream: *const clap_ostream,
+        ) -> bool,
+    >,
+    receive: Option<
+        unsafe extern "C" fn(plugin: *const clap_plugin, buffer: *const c_void, size: u32) -> bool,
+    >,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[allow(non_camel_case_types)]
+struct clap_host_webview {
+    send: Option<
+        unsafe extern "C" fn(host: *const clap_host, buffer: *const c_void, size: u32) -> bool,
+    >,
+}
+
+/// Plugin-side of the Webview extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginWebview(RawExtension<PluginExtensionSide, clap_plugin_webview>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginWebview {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_WEBVIEW];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Webview extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostWebview(RawExtension<HostExtensionSide, clap_host_webview>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostWebview {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_WEBVIEW];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Errors that can occur while using the Webview extension.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum WebviewError {
+    /// The plugin failed to provide the URI of its web page.
+    GetUri,
+    /// The plugin failed to provide the requested resource.
+    GetResource,
+    /// The MIME type of a resource doesn't fit in the buffer provided by the host.
+    MimeTypeTooLong,
+    /// The plugin failed to handle a message sent by the web page.
+    Receive,
+    /// The host failed to send a message to the web page.
+    Send,
+}
+
+impl Display for WebviewError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebviewError::GetUri => f.write_str("Failed to get the webview URI"),
+            WebviewError::GetResource => f.write_str("Failed to get a webview resource"),
+            WebviewError::MimeTypeTooLong => f.write_str("Webview resource MIME type is too long"),
+            WebviewError::Receive => f.write_str("Failed to receive a webview message"),
+            WebviewError::Send => f.write_str("Failed to send a webview message"),
+        }
+    }
+}
+
+impl Error for WebviewError {}
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use clack_common::stream::OutputStream;
+    use clack_plugin::extensions::prelude::*;
+    use std::io::Write;
+
+    /// A writer for a resource requested by the host.
+    ///
+    /// The resource's contents are written using the [`Write`] implementation, and its MIME type
+    /// with [`set_mime_type`](Self::set_mime_type).
+    pub struct WebviewResourceWriter<'a, 's> {
+        mime: *mut c_char,
+        mime_capacity: usize,
+        output: &'a mut OutputStream<'s>,
+    }
+
+    impl<'a, 's> WebviewResourceWriter<'a, 's> {
+        /// # Safety
+        ///
+        /// `mime` must be valid for writes of `mime_capacity` bytes, or null if the capacity is 0.
+        unsafe fn new(
+            mime: *mut c_char,
+            mime_capacity: u32,
+            output: &'a mut OutputStream<'s>,
+        ) -> Self {
+            let mime_capacity = if mime.is_null() {
+                0
+            } else {
+                mime_capacity as usize
+            };
+
+            if mime_capacity > 0 {
+                // SAFETY: we just checked the buffer can hold at least one byte
+                unsafe { mime.write(0) };
+            }
+
+            Self {
+                mime,
+                mime_capacity,
+                output,
+            }
+        }
+
+        /// Sets the MIME type of the resource, e.g. `text/html`.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`WebviewError::MimeTypeTooLong`] if the MIME type doesn't fit in the buffer
+        /// provided by the host.
+        pub fn set_mime_type(&mut self, mime_type: &str) -> Result<(), WebviewError> {
+            let bytes = mime_type.as_bytes();
+            if bytes.len() >= self.mime_capacity {
+                return Err(WebviewError::MimeTypeTooLong);
+            }
+
+            // SAFETY: we just checked the buffer can hold the string and its nul terminator
+            unsafe {
+                core::ptr::copy_nonoverlapping(bytes.as_ptr(), self.mime.cast(), bytes.len());
+                self.mime.add(bytes.len()).write(0);
+            }
+
+            Ok(())
+        }
+
+        /// Returns the stream the resource's contents are written to.
+        #[inline]
+        pub fn output(&mut self) -> &mut OutputStream<'s> {
+            self.output
+        }
+    }
+
+    impl Write for WebviewResourceWriter<'_, '_> {
+        #[inline]
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        #[inline]
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.output.flush()
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Webview extension.
+    pub trait PluginWebviewImpl {
+        /// Returns the URI of the web page to load in the webview.
+        ///
+        /// If this is an absolute path (e.g. `/index.html`), the host serves it and all relative
+        /// resources from the plugin, using [`get_resource`](Self::get_resource).
+        ///
+        /// Returns [`None`] if the plugin can't provide a web page.
+        fn get_uri(&mut self) -> Option<&str>;
+
+        /// Writes the resource at the given path into the given writer, along with its MIME type.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the resource doesn't exist or couldn't be written.
+        fn get_resource(
+            &mut self,
+            path: &str,
+            writer: &mut WebviewResourceWriter,
+        ) -> Result<(), PluginError>;
+
+        /// Receives a message sent by the web page.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the message couldn't be handled.
+        fn receive(&mut self, message: &[u8]) -> Result<(), PluginError>;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginWebview
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginWebviewImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_webview {
+                get_uri: Some(get_uri::<P>),
+                get_resource: Some(get_resource::<P>),
+                receive: Some(receive::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_uri<P>(
+        plugin: *const clap_plugin,
+        uri: *mut c_char,
+        uri_capacity: u32,
+    ) -> i32
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginWebviewImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let mut main_thread = p.main_thread();
+            let Some(value) = main_thread.as_mut().get_uri() else {
+                return Ok(-1);
+            };
+
+            let bytes = value.as_bytes();
+            if !uri.is_null() && uri_capacity > 0 {
+                let written = bytes.len().min(uri_capacity as usize - 1);
+                core::ptr::copy_nonoverlapping(bytes.as_ptr(), uri.cast(), written);
+                uri.add(written).write(0);
+            }
+
+            Ok(i32::try_from(bytes.len()).unwrap_or(-1))
+        })
+        .unwrap_or(-1)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_resource<P>(
+        plugin: *const clap_plugin,
+        path: *const c_char,
+        mime: *mut c_char,
+        mime_capacity: u32,
+        stream: *const clap_ostream,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginWebviewImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            if path.is_null() {
+                return Err(PluginWrapperError::NulPtr("Webview resource path"));
+            }
+            if stream.is_null() {
+                return Err(PluginWrapperError::NulPtr("Webview resource stream"));
+            }
+
+            let path = CStr::from_ptr(path)
+                .to_str()
+                .map_err(|_| PluginWrapperError::InvalidParameter("Invalid resource path"))?;
+
+            let output = OutputStream::from_raw_mut(&mut *(stream as *mut _));
+            let mut writer = WebviewResourceWriter::new(mime, mime_capacity, output);
+
+            p.main_thread().as_mut().get_resource(path, &mut writer)?;
+            Ok(())
+        })
+        .is_some()
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn receive<P>(
+        plugin: *const clap_plugin,
+        buffer: *const c_void,
+        size: u32,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginWebviewImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |p| {
+            let message = if size == 0 {
+                &[]
+            } else if buffer.is_null() {
+                return Err(PluginWrapperError::NulPtr("Webview message"));
+            } else {
+                core::slice::from_raw_parts(buffer.cast::<u8>(), size as usize)
+            };
+
+            p.main_thread().as_mut().receive(message)?;
+            Ok(())
+        })
+        .is_some()
+    }
+
+    impl HostWebview {
+        /// Sends a message to the web page.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`WebviewError::Send`] if the host failed to send the message.
+        pub fn send(
+            &self,
+            host: &mut HostMainThreadHandle,
+            message: &[u8],
+        ) -> Result<(), WebviewError> {
+            let send = host.use_extension(&self.0).send.ok_or(WebviewError::Send)?;
+            let size = u32::try_from(message.len()).map_err(|_| WebviewError::Send)?;
+
+            // SAFETY: This type ensures the function pointer is valid.
+            match unsafe { send(host.as_raw(), message.as_ptr().cast(), size) } {
+                true => Ok(()),
+                false => Err(WebviewError::Send),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_common::stream::OutputStream;
+    use clack_host::extensions::prelude::*;
+    use std::io::Write;
+
+    const MIME_CAPACITY: usize = 256;
+
+    /// A resource provided by the plugin, to be served to its web page.
+    #[derive(Clone, Debug, Default, Eq, PartialEq)]
+    pub struct WebviewResource {
+        /// The MIME type of the resource, e.g. `text/html`.
+        ///
+        /// This is empty if the plugin didn't provide any.
+        pub mime_type: String,
+        /// The contents of the resource.
+        pub data: Vec<u8>,
+    }
+
+    impl PluginWebview {
+        /// Returns the URI of the plugin's web page.
+        ///
+        /// If it is an absolute path (e.g. `/index.html`), it must be served from the plugin's
+        /// resources. [`WebviewOrigin`] can be used to do so.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`WebviewError::GetUri`] if the plugin failed to provide a valid URI, or if the
+        /// URI's reported length is inconsistent between calls.
+        pub fn get_uri(&self, plugin: &mut PluginMainThreadHandle) -> Result<String, WebviewError> {
+            let get_uri = plugin
+                .use_extension(&self.0)
+                .get_uri
+                .ok_or(WebviewError::GetUri)?;
+
+            let mut buffer = vec![0u8; 256];
+
+            // If the URI doesn't fit, retry once with a buffer of the size reported by the plugin.
+            for _ in 0..2 {
+                // SAFETY: This type ensures the function pointer is valid.
+                // The buffer is valid for writes of its whole length.
+                let len = unsafe {
+                    get_uri(
+                        plugin.as_raw(),
+                        buffer.as_mut_ptr().cast(),
+                        buffer.len() as u32,
+                    )
+                };
+
+                let len = usize::try_from(len).map_err(|_| WebviewError::GetUri)?;
+
+                if len < buffer.len() {
+                    buffer.truncate(len);
+                    return String::from_utf8(buffer).map_err(|_| WebviewError::GetUri);
+                }
+
+                buffer.resize(len + 1, 0);
+            }
+
+            Err(WebviewError::GetUri)
+        }
+
+        /// Writes the plugin's resource at the given path into the given writer, and returns its
+        /// MIME type.
+        ///
+        /// The returned MIME type is empty if the plugin didn't provide one.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`WebviewError::GetResource`] if the plugin failed to provide the resource.
+        pub fn get_resource<W: Write>(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            path: &CStr,
+            writer: &mut W,
+        ) -> Result<String, WebviewError> {
+            let get_resource = plugin
+                .use_extension(&self.0)
+                .get_resource
+                .ok_or(WebviewError::GetResource)?;
+
+            let mut mime = [0u8; MIME_CAPACITY];
+            let mut stream = OutputStream::from_writer(writer);
+
+            // SAFETY: This type ensures the function pointer is valid.
+            // The MIME buffer is valid for writes of its whole length.
+            let success = unsafe {
+                get_resource(
+                    plugin.as_raw(),
+                    path.as_ptr(),
+                    mime.as_mut_ptr().cast(),
+                    MIME_CAPACITY as u32,
+                    stream.as_raw_mut(),
+                )
+            };
+
+            if !success {
+                return Err(WebviewError::GetResource);
+            }
+
+            let mime = CStr::from_bytes_until_nul(&mime).map_err(|_| WebviewError::GetResource)?;
+            let mime = mime.to_str().map_err(|_| WebviewError::GetResource)?;
+            Ok(mime.to_owned())
+        }
+
+        /// Fetches the plugin's resource at the given path into memory.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`WebviewError::GetResource`] if the plugin failed to provide the resource.
+        pub fn fetch_resource(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            path: &CStr,
+        ) -> Result<WebviewResource, WebviewError> {
+            let mut data = Vec::new();
+            let mime_type = self.get_resource(plugin, path, &mut data)?;
+
+            Ok(WebviewResource { mime_type, data })
+        }
+
+        /// Sends a message from the web page to the plugin.
+        ///
+        /// # Errors
+        ///
+        /// Returns [`WebviewError::Receive`] if the plugin failed to handle the message.
+        pub fn receive(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            message: &[u8],
+        ) -> Result<(), WebviewError> {
+            let receive = plugin
+                .use_extension(&self.0)
+                .receive
+                .ok_or(WebviewError::Receive)?;
+            let size = u32::try_from(message.len()).map_err(|_| WebviewError::Receive)?;
+
+            // SAFETY: This type ensures the function pointer is valid.
+            match unsafe { receive(plugin.as_raw(), message.as_ptr().cast(), size) } {
+                true => Ok(()),
+                false => Err(WebviewError::Receive),
+            }
+        }
+    }
+
+    /// The local origin a host serves a plugin's web page from, e.g. `http://plugin.localhost`.
+    ///
+    /// Plugins usually provide the URI of their web page as an absolute path, such as
+    /// `/index.html`. The host makes it available under an origin it controls, and answers the
+    /// webview's requests to that origin using the plugin's resources.
+    #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+    pub struct WebviewOrigin {
+        origin: String,
+    }
+
+    impl WebviewOrigin {
+        /// Creates a new origin from its scheme and host, e.g. `http://plugin.localhost`.
+        ///
+        /// Any trailing `/` is ignored.
+        pub fn new(origin: impl Into<String>) -> Self {
+            let mut origin = origin.into();
+            while origin.ends_with('/') {
+                origin.pop();
+            }
+
+            Self { origin }
+        }
+
+        /// Returns the scheme and host of this origin.
+        #[inline]
+        pub fn as_str(&self) -> &str {
+            &self.origin
+        }
+
+        /// Resolves the URI provided by the plugin into the URI the webview must load.
+        ///
+        /// Absolute paths are served from this origin, while full URIs are returned as-is.
+        pub fn resolve(&self, plugin_uri: &str) -> String {
+            if plugin_uri.starts_with('/') {
+                format!("{}{plugin_uri}", self.origin)
+            } else {
+                plugin_uri.to_owned()
+            }
+        }
+
+        /// Returns the path of the plugin resource requested by the webview, or [`None`] if the
+        /// request doesn't target this origin.
+        ///
+        /// The query and fragment of the requested URI are ignored. Requests to the root of the
+        /// origin are mapped to `/`.
+        pub fn resource_path<'a>(&self, request_uri: &'a str) -> Option<&'a str> {
+            let path = request_uri.strip_prefix(self.origin.as_str())?;
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+
+            match path {
+                "" => Some("/"),
+                path if path.starts_with('/') => Some(path),
+                _ => None,
+            }
+        }
+    }
+
+    /// Implementation of the Host-side of the Webview extension.
+    pub trait HostWebviewImpl {
+        /// Sends the given message from the plugin to its web page.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if the message couldn't be sent, e.g. if the webview is closed.
+        fn send(&mut self, message: &[u8]) -> Result<(), HostError>;
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostWebview
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostWebviewImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_webview {
+                send: Some(send::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn send<H>(host: *const clap_host, buffer: *const c_void, size: u32) -> bool
+    where
+        for<'a> H: HostHandlers<MainThread<'a>: HostWebviewImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            let message = if size == 0 {
+                &[]
+            } else if buffer.is_null() {
+                return Err(HostWrapperError::InvalidParameter("Null webview message"));
+            } else {
+                core::slice::from_raw_parts(buffer.cast::<u8>(), size as usize)
+            };
+
+            host.main_thread().as_mut().send(message)?;
+            Ok(())
+        })
+        .is_some()
+    }
+}
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
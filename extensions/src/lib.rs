@@ -1,5 +1,7 @@
 #![doc(html_logo_url = "https://raw.githubusercontent.com/prokopyl/clack/main/logo.svg")]
 
+#[cfg(feature = "ambisonic")]
+pub mod ambisonic;
 #[cfg(feature = "audio-ports")]
 pub mod audio_ports;
 #[cfg(feature = "audio-ports-activation")]
@@ -10,6 +12,9 @@ pub mod audio_ports_config;
 pub mod clap_wrapper;
 #[cfg(feature = "configurable-audio-ports")]
 pub mod configurable_audio_ports;
+#[cfg(feature = "context-menu")]
+pub mod context_menu;
+pub mod draft;
 #[cfg(feature = "event-registry")]
 pub mod event_registry;
 #[cfg(feature = "gui")]
@@ -38,6 +43,8 @@ pub mod render;
 pub mod state;
 #[cfg(feature = "state-context")]
 pub mod state_context;
+#[cfg(feature = "surround")]
+pub mod surround;
 #[cfg(feature = "tail")]
 pub mod tail;
 #[cfg(feature = "thread-check")]
@@ -1,4 +1,5 @@
 use bitflags::bitflags;
+use clack_common::events::translation::NoteFormat;
 use clack_common::extensions::{Extension, HostExtensionSide, PluginExtensionSide, RawExtension};
 use clack_common::utils::ClapId;
 use clap_sys::ext::note_ports::*;
@@ -67,6 +68,21 @@ impl From<NoteDialect> for NoteDialects {
     }
 }
 
+impl From<NoteDialect> for NoteFormat {
+    /// Returns the format of the events of the given dialect.
+    ///
+    /// MIDI events are used for MPE, which requires enabling it in the
+    /// [`NoteTranslator`](clack_common::events::translation::NoteTranslator).
+    #[inline]
+    fn from(dialect: NoteDialect) -> Self {
+        match dialect {
+            NoteDialect::Clap => NoteFormat::Clap,
+            NoteDialect::Midi | NoteDialect::MidiMpe => NoteFormat::Midi,
+            NoteDialect::Midi2 => NoteFormat::Midi2,
+        }
+    }
+}
+
 // SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
 unsafe impl Extension for PluginNotePorts {
     const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_NOTE_PORTS];
@@ -1,5 +1,6 @@
 use super::*;
 use clack_host::extensions::prelude::*;
+use clack_host::midi_file::MidiFileDialect;
 use std::mem::MaybeUninit;
 
 #[derive(Clone)]
@@ -52,6 +53,32 @@ impl PluginNotePorts {
     }
 }
 
+impl NotePortInfo<'_> {
+    /// Returns the dialect a [`MidiFilePlayer`](clack_host::midi_file::MidiFilePlayer) should use
+    /// to send notes to this port, or `None` if the port supports neither MIDI nor CLAP notes.
+    ///
+    /// The port's preferred dialect is used if possible. MIDI is used otherwise, as it is the
+    /// only one that carries all the messages of a MIDI file. MPE ports also accept plain MIDI
+    /// channel messages, so they are sent MIDI as well.
+    pub fn midi_file_dialect(&self) -> Option<MidiFileDialect> {
+        match self.preferred_dialect {
+            Some(NoteDialect::Clap) => return Some(MidiFileDialect::Clap),
+            Some(NoteDialect::Midi | NoteDialect::MidiMpe) => return Some(MidiFileDialect::Midi),
+            _ => {}
+        }
+
+        if self.supported_dialects.supports(NoteDialect::Midi)
+            || self.supported_dialects.supports(NoteDialect::MidiMpe)
+        {
+            Some(MidiFileDialect::Midi)
+        } else if self.supported_dialects.supports(NoteDialect::Clap) {
+            Some(MidiFileDialect::Clap)
+        } else {
+            None
+        }
+    }
+}
+
 pub trait HostNotePortsImpl {
     fn supported_dialects(&self) -> NoteDialects;
     fn rescan(&mut self, flags: NotePortRescanFlags);
@@ -93,3 +120,47 @@ where
         Ok(())
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port_info(
+        supported_dialects: NoteDialects,
+        preferred_dialect: Option<NoteDialect>,
+    ) -> NotePortInfo<'static> {
+        NotePortInfo {
+            id: ClapId::new(0),
+            name: b"Notes",
+            supported_dialects,
+            preferred_dialect,
+        }
+    }
+
+    #[test]
+    fn picks_midi_file_dialect() {
+        let clap_and_midi = port_info(NoteDialects::CLAP | NoteDialects::MIDI, None);
+        assert_eq!(
+            clap_and_midi.midi_file_dialect(),
+            Some(MidiFileDialect::Midi)
+        );
+
+        let prefers_clap = port_info(
+            NoteDialects::CLAP | NoteDialects::MIDI,
+            Some(NoteDialect::Clap),
+        );
+        assert_eq!(
+            prefers_clap.midi_file_dialect(),
+            Some(MidiFileDialect::Clap)
+        );
+
+        let mpe_only = port_info(NoteDialects::MIDI_MPE, Some(NoteDialect::MidiMpe));
+        assert_eq!(mpe_only.midi_file_dialect(), Some(MidiFileDialect::Midi));
+
+        let mpe_only = port_info(NoteDialects::MIDI_MPE, None);
+        assert_eq!(mpe_only.midi_file_dialect(), Some(MidiFileDialect::Midi));
+
+        let midi2_only = port_info(NoteDialects::MIDI2, Some(NoteDialect::Midi2));
+        assert_eq!(midi2_only.midi_file_dialect(), None);
+    }
+}
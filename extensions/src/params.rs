@@ -250,3 +250,8 @@ pub use host::*;
 mod plugin;
 #[cfg(feature = "clack-plugin")]
 pub use plugin::*;
+
+#[cfg(feature = "clack-plugin")]
+mod set;
+#[cfg(feature = "clack-plugin")]
+pub use set::*;
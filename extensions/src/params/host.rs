@@ -1,5 +1,6 @@
 use super::*;
 use clack_common::events::io::{InputEvents, OutputEvents};
+use clack_host::automation::{AutomationLane, ParamRange};
 use clack_host::extensions::prelude::*;
 use std::mem::MaybeUninit;
 
@@ -24,6 +25,25 @@ impl ParamInfoBuffer {
     }
 }
 
+impl From<&ParamInfo<'_>> for ParamRange {
+    #[inline]
+    fn from(info: &ParamInfo<'_>) -> Self {
+        Self {
+            min: info.min_value,
+            max: info.max_value,
+            stepped: info.flags.contains(ParamInfoFlags::IS_STEPPED),
+        }
+    }
+}
+
+impl From<&ParamInfo<'_>> for AutomationLane {
+    /// Creates an empty automation lane for the given parameter, using its range and cookie.
+    #[inline]
+    fn from(info: &ParamInfo<'_>) -> Self {
+        AutomationLane::new(info.id, info.into()).with_cookie(info.cookie)
+    }
+}
+
 impl PluginParams {
     pub fn count(&self, plugin: &mut PluginMainThreadHandle) -> u32 {
         match plugin.use_extension(&self.0).count {
@@ -0,0 +1,689 @@
+//! A declarative parameter set, implementing the `params` extension for plugins.
+
+use super::*;
+use clack_common::events::UnknownEvent;
+use clack_common::events::event_types::ParamValueEvent;
+use clack_common::events::io::InputEvents;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A function formatting a parameter's plain value to text.
+pub type ParamFormatter = fn(value: f64, writer: &mut dyn std::fmt::Write) -> std::fmt::Result;
+
+/// A function parsing a parameter's plain value from text.
+pub type ParamParser = fn(text: &str) -> Option<f64>;
+
+/// The declaration of a single parameter of a [`ParamSet`].
+///
+/// Definitions can be built in `const` contexts, so that all of a plugin's parameters can be
+/// declared in a single place.
+///
+/// # Example
+///
+/// ```
+/// use clack_extensions::params::{ParamDefinition, ParamInfoFlags};
+/// use clack_plugin::prelude::*;
+///
+/// const VOLUME: ParamDefinition = ParamDefinition::new(ClapId::new(1), "Volume")
+///     .with_default(1.0)
+///     .with_formatter(
+///         |value, writer| write!(writer, "{:.2} %", value * 100.0),
+///         |text| Some(text.trim_end_matches('%').trim().parse::<f64>().ok()? / 100.0),
+///     );
+///
+/// const VOICES: ParamDefinition = ParamDefinition::new(ClapId::new(2), "Voices")
+///     .with_range(1.0, 16.0)
+///     .with_default(8.0)
+///     .with_flags(ParamInfoFlags::IS_STEPPED);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct ParamDefinition {
+    /// The stable identifier of the parameter.
+    pub id: ClapId,
+    /// The display name of the parameter.
+    pub name: &'static str,
+    /// The module path of the parameter, using `/` as a separator.
+    pub module: &'static str,
+    /// The flags of the parameter.
+    pub flags: ParamInfoFlags,
+    /// The minimum plain value of the parameter.
+    pub min_value: f64,
+    /// The maximum plain value of the parameter.
+    pub max_value: f64,
+    /// The default plain value of the parameter.
+    pub default_value: f64,
+    /// The function used to format values to text, if any.
+    ///
+    /// If this is `None`, values are displayed as numbers, with two decimals unless the
+    /// parameter is stepped.
+    pub formatter: Option<ParamFormatter>,
+    /// The function used to parse values from text, if any.
+    ///
+    /// If this is `None`, values are parsed as plain numbers.
+    pub parser: Option<ParamParser>,
+}
+
+impl ParamDefinition {
+    /// Creates a new automatable parameter definition, ranging from `0.0` to `1.0`, with a
+    /// default value of `0.0`.
+    #[inline]
+    pub const fn new(id: ClapId, name: &'static str) -> Self {
+        Self {
+            id,
+            name,
+            module: "",
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+            formatter: None,
+            parser: None,
+        }
+    }
+
+    /// Sets the module path of the parameter.
+    #[inline]
+    pub const fn with_module(mut self, module: &'static str) -> Self {
+        self.module = module;
+        self
+    }
+
+    /// Sets the flags of the parameter.
+    #[inline]
+    pub const fn with_flags(mut self, flags: ParamInfoFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the range of the parameter's plain values.
+    #[inline]
+    pub const fn with_range(mut self, min_value: f64, max_value: f64) -> Self {
+        self.min_value = min_value;
+        self.max_value = max_value;
+        self
+    }
+
+    /// Sets the default plain value of the parameter.
+    #[inline]
+    pub const fn with_default(mut self, default_value: f64) -> Self {
+        self.default_value = default_value;
+        self
+    }
+
+    /// Sets the functions used to convert the parameter's values to and from text.
+    #[inline]
+    pub const fn with_formatter(mut self, formatter: ParamFormatter, parser: ParamParser) -> Self {
+        self.formatter = Some(formatter);
+        self.parser = Some(parser);
+        self
+    }
+
+    /// Returns the [`ParamInfo`] describing this parameter to the host.
+    #[inline]
+    pub fn info(&self) -> ParamInfo<'static> {
+        ParamInfo {
+            id: self.id,
+            flags: self.flags,
+            cookie: Cookie::empty(),
+            name: self.name.as_bytes(),
+            module: self.module.as_bytes(),
+            min_value: self.min_value,
+            max_value: self.max_value,
+            default_value: self.default_value,
+        }
+    }
+
+    /// Clamps the given value to the range of this parameter, and rounds it to the nearest
+    /// integer if the parameter is stepped.
+    ///
+    /// NaN values are replaced by the parameter's default value.
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = if value.is_nan() {
+            self.default_value
+        } else {
+            value
+        };
+
+        let value = if self.flags.contains(ParamInfoFlags::IS_STEPPED) {
+            value.round()
+        } else {
+            value
+        };
+
+        // Unlike f64::clamp, this never panics, even if the range is invalid.
+        value.max(self.min_value).min(self.max_value)
+    }
+
+    /// Formats the given plain value to text.
+    pub fn format(&self, value: f64, writer: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        match self.formatter {
+            Some(formatter) => formatter(value, writer),
+            None if self.flags.contains(ParamInfoFlags::IS_STEPPED) => {
+                write!(writer, "{value:.0}")
+            }
+            None => write!(writer, "{value:.2}"),
+        }
+    }
+
+    /// Parses a plain value from the given text.
+    ///
+    /// The value is [clamped](Self::clamp) to the range of this parameter.
+    pub fn parse(&self, text: &str) -> Option<f64> {
+        let value = match self.parser {
+            Some(parser) => parser(text)?,
+            None => text.trim().parse().ok()?,
+        };
+
+        Some(self.clamp(value))
+    }
+}
+
+/// A set of parameters, along with their current values.
+///
+/// The values are stored in atomics, so that a parameter set can be shared between the main
+/// thread and the audio thread, and be read and written from both without locking.
+///
+/// The [`impl_param_set!`](crate::impl_param_set) macro generates the [`PluginMainThreadParams`]
+/// and [`PluginAudioProcessorParams`] implementations of a plugin from its parameter set, and
+/// optionally its `state` extension implementation. Plugins that need more control, e.g. to send
+/// output events when flushing, can instead call this set's methods from their own
+/// implementations.
+///
+/// # State
+///
+/// The values of a parameter set can be saved and loaded with [`save`](Self::save) and
+/// [`load`](Self::load). Values are stored along with the ID of their parameter, so that
+/// parameters can be added or removed without breaking previously saved states.
+pub struct ParamSet {
+    definitions: Vec<ParamDefinition>,
+    values: Box<[AtomicU64]>,
+    /// The index of each parameter, sorted by ID.
+    indices: Vec<(ClapId, usize)>,
+}
+
+impl ParamSet {
+    /// Creates a new parameter set from the given definitions, with all parameters set to their
+    /// default value.
+    ///
+    /// Parameters are presented to the host in the given order.
+    ///
+    /// # Panics
+    ///
+    /// This panics if multiple parameters share the same ID, or if the range or default value of
+    /// a parameter is invalid, i.e. if its minimum is greater than its maximum, or if any of these
+    /// values is NaN.
+    pub fn new(definitions: impl IntoIterator<Item = ParamDefinition>) -> Self {
+        let definitions: Vec<ParamDefinition> = definitions.into_iter().collect();
+
+        for d in &definitions {
+            if d.min_value.is_nan() || d.max_value.is_nan() || d.min_value > d.max_value {
+                panic!(
+                    "Invalid range for parameter {}: [{}, {}]",
+                    d.id, d.min_value, d.max_value
+                );
+            }
+
+            if d.default_value.is_nan() {
+                panic!("Invalid default value for parameter {}: NaN", d.id);
+            }
+        }
+
+        let values = definitions
+            .iter()
+            .map(|d| AtomicU64::new(d.clamp(d.default_value).to_bits()))
+            .collect();
+
+        let mut indices: Vec<_> = definitions
+            .iter()
+            .enumerate()
+            .map(|(index, d)| (d.id, index))
+            .collect();
+
+        indices.sort_unstable_by_key(|(id, _)| *id);
+
+        if let Some(pair) = indices.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            panic!("Duplicate parameter ID: {}", pair[0].0);
+        }
+
+        Self {
+            definitions,
+            values,
+            indices,
+        }
+    }
+
+    /// Returns the number of parameters in this set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    /// Returns the number of parameters in this set, as reported to the host.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.definitions.len() as u32
+    }
+
+    /// Returns `true` if this set has no parameters.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    /// Returns the definitions of all the parameters of this set.
+    #[inline]
+    pub fn definitions(&self) -> &[ParamDefinition] {
+        &self.definitions
+    }
+
+    /// Returns the index of the parameter with the given ID.
+    #[inline]
+    pub fn index_of(&self, id: ClapId) -> Option<usize> {
+        let position = self.indices.binary_search_by_key(&id, |(id, _)| *id).ok()?;
+        Some(self.indices[position].1)
+    }
+
+    /// Returns the definition of the parameter with the given ID.
+    #[inline]
+    pub fn definition(&self, id: ClapId) -> Option<&ParamDefinition> {
+        self.definitions.get(self.index_of(id)?)
+    }
+
+    /// Returns the current value of the parameter with the given ID.
+    #[inline]
+    pub fn get(&self, id: ClapId) -> Option<f64> {
+        Some(self.value_at(self.index_of(id)?))
+    }
+
+    /// Returns the current value of the parameter at the given index.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the index is out of bounds.
+    #[inline]
+    pub fn value_at(&self, index: usize) -> f64 {
+        f64::from_bits(self.values[index].load(Ordering::Relaxed))
+    }
+
+    /// Sets the value of the parameter with the given ID, and returns the value that was stored.
+    ///
+    /// The value is [clamped](ParamDefinition::clamp) to the range of the parameter. This returns
+    /// `None` if there is no parameter with this ID.
+    pub fn set(&self, id: ClapId, value: f64) -> Option<f64> {
+        let index = self.index_of(id)?;
+        let value = self.definitions[index].clamp(value);
+
+        self.values[index].store(value.to_bits(), Ordering::Relaxed);
+        Some(value)
+    }
+
+    /// Resets all parameters to their default value.
+    pub fn reset(&self) {
+        for (definition, value) in self.definitions.iter().zip(&self.values) {
+            let default_value = definition.clamp(definition.default_value);
+            value.store(default_value.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Applies the given event if it is a [`ParamValueEvent`] targeting one of the parameters of
+    /// this set, and returns `true` if it was applied.
+    ///
+    /// Only events applying to all notes, keys, channels and ports are applied, as polyphonic
+    /// values are not stored in this set.
+    pub fn handle_event(&self, event: &UnknownEvent) -> bool {
+        let Some(event) = event.as_event::<ParamValueEvent>() else {
+            return false;
+        };
+
+        match event.param_id() {
+            Some(id) if event.pckn().matches_all() => self.set(id, event.value()).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Applies all the [`ParamValueEvent`]s of the given events.
+    pub fn handle_events(&self, events: &InputEvents) {
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+
+    /// Writes the info of the parameter at the given index.
+    pub fn write_info(&self, index: u32, writer: &mut ParamInfoWriter) {
+        if let Some(definition) = self.definitions.get(index as usize) {
+            writer.set(&definition.info());
+        }
+    }
+
+    /// Formats the given value of the parameter with the given ID to text.
+    pub fn value_to_text(
+        &self,
+        id: ClapId,
+        value: f64,
+        writer: &mut dyn std::fmt::Write,
+    ) -> std::fmt::Result {
+        match self.definition(id) {
+            Some(definition) => definition.format(value, writer),
+            None => Err(std::fmt::Error),
+        }
+    }
+
+    /// Parses a value of the parameter with the given ID from text.
+    pub fn text_to_value(&self, id: ClapId, text: &str) -> Option<f64> {
+        self.definition(id)?.parse(text)
+    }
+
+    /// Writes the current values of all parameters to the given output.
+    pub fn save(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_all(&(self.len() as u32).to_le_bytes())?;
+
+        for (index, definition) in self.definitions.iter().enumerate() {
+            output.write_all(&definition.id.get().to_le_bytes())?;
+            output.write_all(&self.value_at(index).to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the values of all parameters from the given input, as written by
+    /// [`save`](Self::save).
+    ///
+    /// Parameters missing from the input are reset to their default value, and unknown
+    /// parameters are ignored. If the input is truncated or otherwise invalid, an error is
+    /// returned and no value is changed.
+    pub fn load(&self, input: &mut impl Read) -> std::io::Result<()> {
+        let mut count = [0; 4];
+        input.read_exact(&mut count)?;
+
+        let mut values = Vec::new();
+
+        for _ in 0..u32::from_le_bytes(count) {
+            let mut id = [0; 4];
+            let mut value = [0; 8];
+            input.read_exact(&mut id)?;
+            input.read_exact(&mut value)?;
+
+            values.push((u32::from_le_bytes(id), f64::from_le_bytes(value)));
+        }
+
+        self.reset();
+
+        for (id, value) in values {
+            if let Some(id) = ClapId::from_raw(id) {
+                self.set(id, value);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Implements the `params` extension for a plugin's main thread and audio processor types, using
+/// a [`ParamSet`].
+///
+/// The macro takes the main thread type, the audio processor type, and a closure-like expression
+/// returning a reference to the [`ParamSet`] from a reference to either of those types. The
+/// generated implementations describe the set's parameters to the host, and apply the
+/// [`ParamValueEvent`]s received when flushing.
+///
+/// If `state` is passed as a last argument, the `state` extension's
+/// [`PluginStateImpl`](crate::state::PluginStateImpl) is also implemented for the main thread
+/// type, using [`ParamSet::save`] and [`ParamSet::load`]. This requires the `state` feature.
+///
+/// # Example
+///
+/// ```
+/// use clack_extensions::impl_param_set;
+/// use clack_extensions::params::{ParamDefinition, ParamSet};
+/// use clack_plugin::prelude::*;
+///
+/// pub struct MyPluginShared {
+///     params: ParamSet,
+/// }
+///
+/// pub struct MyPluginMainThread<'a> {
+///     shared: &'a MyPluginShared,
+/// }
+///
+/// pub struct MyPluginAudioProcessor<'a> {
+///     shared: &'a MyPluginShared,
+/// }
+///
+/// impl_param_set!(
+///     MyPluginMainThread<'_>,
+///     MyPluginAudioProcessor<'_>,
+///     |p| &p.shared.params,
+///     state
+/// );
+/// ```
+#[macro_export]
+macro_rules! impl_param_set {
+    ($main_thread:ty, $audio_processor:ty, |$this:ident| $params:expr, state $(,)?) => {
+        $crate::impl_param_set!($main_thread, $audio_processor, |$this| $params);
+
+        impl $crate::state::PluginStateImpl for $main_thread {
+            fn save(
+                &mut self,
+                output: &mut $crate::params::__macro_support::OutputStream,
+            ) -> ::core::result::Result<(), $crate::params::__macro_support::PluginError> {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.save(output)?;
+                Ok(())
+            }
+
+            fn load(
+                &mut self,
+                input: &mut $crate::params::__macro_support::InputStream,
+            ) -> ::core::result::Result<(), $crate::params::__macro_support::PluginError> {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.load(input)?;
+                Ok(())
+            }
+        }
+    };
+    ($main_thread:ty, $audio_processor:ty, |$this:ident| $params:expr $(,)?) => {
+        impl $crate::params::PluginMainThreadParams for $main_thread {
+            fn count(&mut self) -> u32 {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.count()
+            }
+
+            fn get_info(&mut self, param_index: u32, info: &mut $crate::params::ParamInfoWriter) {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.write_info(param_index, info)
+            }
+
+            fn get_value(
+                &mut self,
+                param_id: $crate::params::__macro_support::ClapId,
+            ) -> ::core::option::Option<f64> {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.get(param_id)
+            }
+
+            fn value_to_text(
+                &mut self,
+                param_id: $crate::params::__macro_support::ClapId,
+                value: f64,
+                writer: &mut $crate::params::ParamDisplayWriter,
+            ) -> ::core::fmt::Result {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.value_to_text(param_id, value, writer)
+            }
+
+            fn text_to_value(
+                &mut self,
+                param_id: $crate::params::__macro_support::ClapId,
+                text: &::core::ffi::CStr,
+            ) -> ::core::option::Option<f64> {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.text_to_value(param_id, text.to_str().ok()?)
+            }
+
+            fn flush(
+                &mut self,
+                input_parameter_changes: &$crate::params::__macro_support::InputEvents,
+                _output_parameter_changes: &mut $crate::params::__macro_support::OutputEvents,
+            ) {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.handle_events(input_parameter_changes)
+            }
+        }
+
+        impl $crate::params::PluginAudioProcessorParams for $audio_processor {
+            fn flush(
+                &mut self,
+                input_parameter_changes: &$crate::params::__macro_support::InputEvents,
+                _output_parameter_changes: &mut $crate::params::__macro_support::OutputEvents,
+            ) {
+                let $this = &*self;
+                let params: &$crate::params::ParamSet = $params;
+                params.handle_events(input_parameter_changes)
+            }
+        }
+    };
+}
+
+/// Items used by the [`impl_param_set!`](crate::impl_param_set) macro.
+#[doc(hidden)]
+pub mod __macro_support {
+    pub use clack_common::events::io::{InputEvents, OutputEvents};
+    pub use clack_common::stream::{InputStream, OutputStream};
+    pub use clack_common::utils::ClapId;
+    pub use clack_plugin::prelude::PluginError;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clack_common::events::io::EventBuffer;
+    use clack_common::events::{Match, Pckn};
+
+    const GAIN: ParamDefinition = ParamDefinition::new(ClapId::new(4), "Gain")
+        .with_range(-60.0, 12.0)
+        .with_default(0.0)
+        .with_formatter(
+            |value, writer| write!(writer, "{value:.1} dB"),
+            |text| text.trim_end_matches("dB").trim().parse().ok(),
+        );
+
+    const MODE: ParamDefinition = ParamDefinition::new(ClapId::new(2), "Mode")
+        .with_range(0.0, 3.0)
+        .with_default(1.0)
+        .with_flags(ParamInfoFlags::IS_STEPPED);
+
+    #[test]
+    fn stores_clamped_values() {
+        let params = ParamSet::new([GAIN, MODE]);
+
+        assert_eq!(params.index_of(ClapId::new(2)), Some(1));
+        assert_eq!(params.get(ClapId::new(2)), Some(1.0));
+        assert_eq!(params.set(ClapId::new(4), 20.0), Some(12.0));
+        assert_eq!(params.set(ClapId::new(2), 1.6), Some(2.0));
+        assert_eq!(params.set(ClapId::new(3), 0.0), None);
+
+        params.reset();
+        assert_eq!(params.get(ClapId::new(4)), Some(0.0));
+    }
+
+    #[test]
+    fn formats_values() {
+        let params = ParamSet::new([GAIN, MODE]);
+        let mut text = String::new();
+
+        params
+            .value_to_text(ClapId::new(4), -6.02, &mut text)
+            .unwrap();
+        params
+            .value_to_text(ClapId::new(2), 2.0, &mut text)
+            .unwrap();
+        assert_eq!(text, "-6.0 dB2");
+
+        assert_eq!(params.text_to_value(ClapId::new(4), "-3 dB"), Some(-3.0));
+        assert_eq!(params.text_to_value(ClapId::new(2), "7"), Some(3.0));
+        assert_eq!(params.text_to_value(ClapId::new(2), "seven"), None);
+    }
+
+    #[test]
+    fn handles_param_value_events() {
+        let params = ParamSet::new([GAIN, MODE]);
+        let mut events = EventBuffer::new();
+
+        let global = Pckn::match_all();
+        let per_key = Pckn::new(0u16, 0u16, 60u16, Match::All);
+
+        events.push(&ParamValueEvent::new(
+            0,
+            ClapId::new(4),
+            global,
+            -12.0,
+            Cookie::empty(),
+        ));
+        events.push(&ParamValueEvent::new(
+            0,
+            ClapId::new(2),
+            per_key,
+            3.0,
+            Cookie::empty(),
+        ));
+        params.handle_events(&events.as_input());
+
+        assert_eq!(params.get(ClapId::new(4)), Some(-12.0));
+        assert_eq!(params.get(ClapId::new(2)), Some(1.0));
+    }
+
+    #[test]
+    fn saves_and_loads_state() {
+        let params = ParamSet::new([GAIN, MODE]);
+        params.set(ClapId::new(4), -24.0);
+        params.set(ClapId::new(2), 3.0);
+
+        let mut state = Vec::new();
+        params.save(&mut state).unwrap();
+
+        // Parameters that are missing from the state are reset to their default value.
+        let loaded = ParamSet::new([MODE]);
+        loaded.load(&mut state.as_slice()).unwrap();
+        assert_eq!(loaded.get(ClapId::new(2)), Some(3.0));
+
+        let loaded = ParamSet::new([GAIN, MODE.with_range(0.0, 2.0)]);
+        loaded.set(ClapId::new(4), 6.0);
+        loaded.load(&mut state.as_slice()).unwrap();
+        assert_eq!(loaded.get(ClapId::new(4)), Some(-24.0));
+        assert_eq!(loaded.get(ClapId::new(2)), Some(2.0));
+
+        // Truncated states are rejected without changing any value.
+        loaded.set(ClapId::new(4), 6.0);
+        assert!(loaded.load(&mut &state[..16]).is_err());
+        assert_eq!(loaded.get(ClapId::new(4)), Some(6.0));
+        assert_eq!(loaded.get(ClapId::new(2)), Some(2.0));
+    }
+
+    #[test]
+    fn replaces_nan_values_with_default() {
+        let params = ParamSet::new([GAIN, MODE]);
+
+        assert_eq!(params.set(ClapId::new(4), f64::NAN), Some(0.0));
+        assert_eq!(params.set(ClapId::new(2), f64::NAN), Some(1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid range for parameter 4: [12, -60]")]
+    fn rejects_invalid_ranges() {
+        ParamSet::new([GAIN.with_range(12.0, -60.0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate parameter ID: 2")]
+    fn rejects_duplicate_ids() {
+        ParamSet::new([MODE, MODE]);
+    }
+}
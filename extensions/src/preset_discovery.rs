@@ -76,6 +76,8 @@ pub use host::extension::{HostPresetLoadImpl, PresetLoadError};
 pub use plugin::extension::PluginPresetLoadImpl;
 
 mod descriptor;
+#[cfg(all(feature = "clack-plugin", feature = "state"))]
+pub mod files;
 pub mod preset_data;
 
 pub mod factory;
@@ -0,0 +1,557 @@
+//! A simple file-based preset format, and a matching preset provider.
+//!
+//! Each preset file starts with a small [`PresetFileHeader`] describing the preset's metadata
+//! (its name, creators, features and collection), directly followed by the plugin's state, as
+//! produced by its `state` extension implementation.
+//!
+//! The [`FilePresetProvider`] declares the preset file type and directories to the host, and reads
+//! the metadata of all the preset files the host discovers. The [`FilePresetDiscoveryFactory`]
+//! wraps a single such provider in a ready-to-use preset discovery factory.
+//!
+//! On the loading side, the [`load_preset_file`] function can be used to implement the
+//! [`PluginPresetLoadImpl`] trait, by feeding the preset file's state
+//! to the plugin's [`PluginStateImpl`] implementation.
+//!
+//! # Example
+//!
+//! ```
+//! use clack_extensions::preset_discovery::files::*;
+//! use clack_extensions::preset_discovery::prelude::*;
+//! use clack_extensions::state::PluginStateImpl;
+//! use clack_plugin::prelude::*;
+//! use clack_plugin::stream::{InputStream, OutputStream};
+//! use std::ffi::CStr;
+//! use std::io::{Read, Write};
+//!
+//! pub fn new_factory() -> FilePresetDiscoveryFactory {
+//!     let provider = FilePresetProvider::new(
+//!         c"org.example.my-plugin",
+//!         c"My Plugin preset",
+//!         c"mypreset",
+//!     )
+//!     .with_directory(c"Factory presets", c"/usr/share/my-plugin/presets", Flags::IS_FACTORY_CONTENT);
+//!
+//!     FilePresetDiscoveryFactory::new(
+//!         ProviderDescriptor::new("org.example.my-plugin.presets", "My Plugin presets"),
+//!         provider,
+//!     )
+//! }
+//!
+//! pub struct MyPluginMainThread {
+//!     gain: f32,
+//! }
+//!
+//! impl PluginStateImpl for MyPluginMainThread {
+//!     fn save(&mut self, output: &mut OutputStream) -> Result<(), PluginError> {
+//!         output.write_all(&self.gain.to_le_bytes())?;
+//!         Ok(())
+//!     }
+//!
+//!     fn load(&mut self, input: &mut InputStream) -> Result<(), PluginError> {
+//!         let mut gain = [0; 4];
+//!         input.read_exact(&mut gain)?;
+//!         self.gain = f32::from_le_bytes(gain);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! impl PluginPresetLoadImpl for MyPluginMainThread {
+//!     fn load_from_location(
+//!         &mut self,
+//!         location: Location,
+//!         load_key: Option<&CStr>,
+//!     ) -> Result<(), PluginError> {
+//!         load_preset_file(self, location, load_key)?;
+//!         Ok(())
+//!     }
+//! }
+//! ```
+
+#![deny(missing_docs)]
+
+use crate::preset_discovery::indexer::IndexerError;
+use crate::preset_discovery::prelude::*;
+use crate::state::PluginStateImpl;
+use clack_common::stream::{InputStream, OutputStream};
+use clack_plugin::prelude::PluginError;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// The magic bytes every preset file starts with.
+const MAGIC: [u8; 4] = *b"CLKP";
+
+/// The revision of the preset file header layout.
+const HEADER_REVISION: u16 = 1;
+
+/// The metadata header of a preset file.
+///
+/// The header is serialized as follows, with all integers being little-endian, and all strings
+/// being UTF-8 encoded and prefixed by their `u16` byte length:
+///
+/// * The `CLKP` magic bytes, followed by the header revision (`u16`);
+/// * The plugin ID, the preset name, the collection and the description strings, the last two
+///   being empty if they are not set;
+/// * The creators and the features, each as a `u16` count followed by the strings.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PresetFileHeader {
+    /// The ID of the plugin this preset is for.
+    pub plugin_id: String,
+    /// The user-friendly name of the preset.
+    pub name: String,
+    /// The creators of the preset.
+    pub creators: Vec<String>,
+    /// The features of the preset, e.g. `"lead"` or `"pad"`.
+    pub features: Vec<String>,
+    /// The name of the collection this preset belongs to, if any.
+    pub collection: Option<String>,
+    /// A description of the preset, if any.
+    pub description: Option<String>,
+}
+
+impl PresetFileHeader {
+    /// Creates a new header for a preset with the given name, for the plugin with the given ID.
+    pub fn new(plugin_id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            plugin_id: plugin_id.into(),
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Reads a header from the given reader.
+    ///
+    /// After this returns, the reader is positioned at the start of the preset's state data.
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC || read_u16(reader)? != HEADER_REVISION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a valid preset file",
+            ));
+        }
+
+        Ok(Self {
+            plugin_id: read_string(reader)?,
+            name: read_string(reader)?,
+            collection: Some(read_string(reader)?).filter(|s| !s.is_empty()),
+            description: Some(read_string(reader)?).filter(|s| !s.is_empty()),
+            creators: read_strings(reader)?,
+            features: read_strings(reader)?,
+        })
+    }
+
+    /// Writes this header to the given writer.
+    ///
+    /// The preset's state data must be written right after it.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&HEADER_REVISION.to_le_bytes())?;
+
+        write_string(writer, &self.plugin_id)?;
+        write_string(writer, &self.name)?;
+        write_string(writer, self.collection.as_deref().unwrap_or_default())?;
+        write_string(writer, self.description.as_deref().unwrap_or_default())?;
+        write_strings(writer, &self.creators)?;
+        write_strings(writer, &self.features)
+    }
+
+    /// Sends this header's metadata to the given receiver, as a new preset.
+    ///
+    /// The collection is sent as the `collection` extra information.
+    fn send_metadata(
+        &self,
+        receiver: &mut MetadataReceiver,
+        plugin_id: &CStr,
+        load_key: Option<&CStr>,
+    ) -> Result<(), PluginError> {
+        let name = CString::new(self.name.as_str())?;
+        receiver
+            .begin_preset(Some(&name), load_key)?
+            .add_plugin_id(UniversalPluginId::clap(plugin_id));
+
+        for creator in &self.creators {
+            receiver.add_creator(&CString::new(creator.as_str())?);
+        }
+
+        for feature in &self.features {
+            receiver.add_feature(&CString::new(feature.as_str())?);
+        }
+
+        if let Some(description) = &self.description {
+            receiver.set_description(&CString::new(description.as_str())?);
+        }
+
+        if let Some(collection) = &self.collection {
+            receiver.add_extra_info(c"collection", &CString::new(collection.as_str())?);
+        }
+
+        Ok(())
+    }
+}
+
+/// A directory containing preset files, declared to the host by a [`FilePresetProvider`].
+#[derive(Clone, Debug)]
+struct PresetDirectory {
+    name: CString,
+    path: CString,
+    flags: Flags,
+}
+
+/// A preset provider for preset files starting with a [`PresetFileHeader`].
+///
+/// Only the presets whose header matches this provider's plugin ID are reported to the host.
+#[derive(Clone, Debug)]
+pub struct FilePresetProvider {
+    plugin_id: CString,
+    file_type_name: CString,
+    file_extension: CString,
+    directories: Vec<PresetDirectory>,
+}
+
+impl FilePresetProvider {
+    /// Creates a new provider for the plugin with the given ID, reading preset files with the
+    /// given file type name and extension (excluding the `.`).
+    pub fn new(plugin_id: &CStr, file_type_name: &CStr, file_extension: &CStr) -> Self {
+        Self {
+            plugin_id: plugin_id.to_owned(),
+            file_type_name: file_type_name.to_owned(),
+            file_extension: file_extension.to_owned(),
+            directories: Vec::new(),
+        }
+    }
+
+    /// Adds a directory for the host to search preset files in.
+    ///
+    /// The given flags apply to all the presets found in the directory.
+    pub fn with_directory(mut self, name: &CStr, path: &CStr, flags: Flags) -> Self {
+        self.directories.push(PresetDirectory {
+            name: name.to_owned(),
+            path: path.to_owned(),
+            flags,
+        });
+        self
+    }
+
+    /// Declares this provider's file type and preset directories to the given indexer.
+    pub fn declare(&self, indexer: &mut Indexer) -> Result<(), IndexerError> {
+        indexer.declare_filetype(FileType {
+            name: &self.file_type_name,
+            description: None,
+            file_extension: Some(&self.file_extension),
+        })?;
+
+        for directory in &self.directories {
+            indexer.declare_location(LocationInfo {
+                name: &directory.name,
+                flags: directory.flags,
+                location: Location::File {
+                    path: &directory.path,
+                },
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the metadata of the preset file at the given path, and sends it to the receiver.
+    fn read_file(
+        &self,
+        path: &Path,
+        receiver: &mut MetadataReceiver,
+        load_key: Option<&CStr>,
+    ) -> Result<(), PluginError> {
+        let header = PresetFileHeader::read(&mut BufReader::new(File::open(path)?))?;
+
+        if header.plugin_id.as_bytes() == self.plugin_id.to_bytes() {
+            header.send_metadata(receiver, &self.plugin_id, load_key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Recursively reads all the preset files in the given directory.
+    ///
+    /// Each preset's load key is its path, relative to `root`. Symbolic links to directories are
+    /// not followed, and subdirectories that cannot be read are skipped.
+    fn read_directory(
+        &self,
+        root: &Path,
+        directory: &Path,
+        receiver: &mut MetadataReceiver,
+    ) -> Result<(), PluginError> {
+        let mut entries: Vec<_> = std::fs::read_dir(directory)?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                // Unlike Path::is_dir, this does not follow symbolic links.
+                let is_dir = entry.file_type().ok()?.is_dir();
+                Some((entry.path(), is_dir))
+            })
+            .collect();
+        entries.sort();
+
+        for (path, is_dir) in entries {
+            if is_dir {
+                let _ = self.read_directory(root, &path, receiver);
+                continue;
+            }
+
+            if !self.has_extension(&path) {
+                continue;
+            }
+
+            let Some(load_key) = path.strip_prefix(root).ok().and_then(Path::to_str) else {
+                continue;
+            };
+
+            // Invalid files or presets of other plugins in the directory are skipped.
+            let _ = self.read_file(&path, receiver, Some(&CString::new(load_key)?));
+        }
+
+        Ok(())
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        let extension = self.file_extension.to_bytes();
+        extension.is_empty()
+            || path
+                .extension()
+                .is_some_and(|e| e.as_encoded_bytes() == extension)
+    }
+}
+
+impl ProviderImpl<'_> for FilePresetProvider {
+    fn get_metadata(
+        &mut self,
+        location: Location,
+        receiver: &mut MetadataReceiver,
+    ) -> Result<(), PluginError> {
+        // This provider doesn't have any preset built into the plugin.
+        let Location::File { path } = location else {
+            return Ok(());
+        };
+
+        let path = Path::new(path.to_str()?);
+        if path.is_dir() {
+            self.read_directory(path, path, receiver)
+        } else {
+            self.read_file(path, receiver, None)
+        }
+    }
+}
+
+/// A preset discovery factory exposing a single [`FilePresetProvider`].
+pub struct FilePresetDiscoveryFactory {
+    descriptor: ProviderDescriptor,
+    provider: FilePresetProvider,
+}
+
+impl FilePresetDiscoveryFactory {
+    /// Creates a new factory, exposing the given provider with the given descriptor.
+    pub fn new(descriptor: ProviderDescriptor, provider: FilePresetProvider) -> Self {
+        Self {
+            descriptor,
+            provider,
+        }
+    }
+}
+
+impl PresetDiscoveryFactoryImpl for FilePresetDiscoveryFactory {
+    #[inline]
+    fn provider_count(&self) -> u32 {
+        1
+    }
+
+    #[inline]
+    fn provider_descriptor(&self, index: u32) -> Option<&ProviderDescriptor> {
+        (index == 0).then_some(&self.descriptor)
+    }
+
+    fn create_provider<'a>(
+        &'a self,
+        indexer_info: IndexerInfo<'a>,
+        provider_id: &CStr,
+    ) -> Option<ProviderInstance<'a>> {
+        if Some(provider_id) != self.descriptor.id() {
+            return None;
+        }
+
+        let provider = self.provider.clone();
+        Some(ProviderInstance::new(
+            indexer_info,
+            &self.descriptor,
+            move |mut indexer| {
+                provider.declare(&mut indexer)?;
+                Ok(provider)
+            },
+        ))
+    }
+}
+
+/// Resolves the path of the preset file at the given location and load key.
+fn preset_file_path(location: Location, load_key: Option<&CStr>) -> Result<PathBuf, PluginError> {
+    let Some(path) = location.file_path() else {
+        return Err(PluginError::Message("Unsupported preset location"));
+    };
+
+    let mut path = PathBuf::from(path.to_str()?);
+
+    if let Some(load_key) = load_key {
+        let load_key = Path::new(load_key.to_str()?);
+
+        // Load keys may only point inside of the location's directory.
+        if !load_key
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(PluginError::Message("Invalid load key"));
+        }
+
+        path.push(load_key);
+    }
+
+    Ok(path)
+}
+
+/// Loads the preset file at the given location and load key, as given by the host to the
+/// [`PluginPresetLoadImpl::load_from_location`] method.
+///
+/// The preset's state data is given to the plugin's [`PluginStateImpl::load`] implementation.
+/// The preset file's header is returned, so that it can be e.g. displayed by the plugin.
+pub fn load_preset_file(
+    state: &mut impl PluginStateImpl,
+    location: Location,
+    load_key: Option<&CStr>,
+) -> Result<PresetFileHeader, PluginError> {
+    let path = preset_file_path(location, load_key)?;
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let header = PresetFileHeader::read(&mut reader)?;
+    state.load(&mut InputStream::from_reader(&mut reader))?;
+
+    Ok(header)
+}
+
+/// Saves the current state of the plugin as a preset, with the given header.
+///
+/// The preset's state data is produced by the plugin's [`PluginStateImpl::save`] implementation.
+pub fn save_preset_file(
+    state: &mut impl PluginStateImpl,
+    header: &PresetFileHeader,
+    writer: &mut impl Write,
+) -> Result<(), PluginError> {
+    header.write(writer)?;
+    state.save(&mut OutputStream::from_writer(writer))?;
+
+    Ok(())
+}
+
+#[inline]
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let mut buf = vec![0; read_u16(reader)? as usize];
+    reader.read_exact(&mut buf)?;
+
+    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_strings(reader: &mut impl Read) -> io::Result<Vec<String>> {
+    (0..read_u16(reader)?)
+        .map(|_| read_string(reader))
+        .collect()
+}
+
+fn write_string(writer: &mut impl Write, string: &str) -> io::Result<()> {
+    let length: u16 = string
+        .len()
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Preset string is too long"))?;
+
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(string.as_bytes())
+}
+
+fn write_strings(writer: &mut impl Write, strings: &[String]) -> io::Result<()> {
+    let count: u16 = strings
+        .len()
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many preset strings"))?;
+
+    writer.write_all(&count.to_le_bytes())?;
+    strings.iter().try_for_each(|s| write_string(writer, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct GainState(f32);
+
+    impl PluginStateImpl for GainState {
+        fn save(&mut self, output: &mut OutputStream) -> Result<(), PluginError> {
+            output.write_all(&self.0.to_le_bytes())?;
+            Ok(())
+        }
+
+        fn load(&mut self, input: &mut InputStream) -> Result<(), PluginError> {
+            let mut gain = [0; 4];
+            input.read_exact(&mut gain)?;
+            self.0 = f32::from_le_bytes(gain);
+            Ok(())
+        }
+    }
+
+    fn header() -> PresetFileHeader {
+        PresetFileHeader {
+            creators: vec!["Me".into(), "Also me".into()],
+            features: vec!["bass".into()],
+            collection: Some("Basics".into()),
+            ..PresetFileHeader::new("org.rust-audio.clack.gain", "Quieter")
+        }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let mut buffer = Vec::new();
+        header().write(&mut buffer).unwrap();
+        buffer.extend_from_slice(b"state");
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(PresetFileHeader::read(&mut reader).unwrap(), header());
+        assert_eq!(reader, b"state");
+
+        assert!(PresetFileHeader::read(&mut &buffer[..10]).is_err());
+        assert!(PresetFileHeader::read(&mut &b"CLKS\x01\x00"[..]).is_err());
+    }
+
+    #[test]
+    fn saves_and_loads_state_through_preset_files() {
+        let directory = std::env::temp_dir().join(format!("clack-presets-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("basics")).unwrap();
+
+        let mut file = File::create(directory.join("basics/quieter.clackpreset")).unwrap();
+        save_preset_file(&mut GainState(0.5), &header(), &mut file).unwrap();
+        drop(file);
+
+        let root = CString::new(directory.to_str().unwrap()).unwrap();
+        let location = Location::File { path: &root };
+
+        let mut state = GainState(1.0);
+        let loaded =
+            load_preset_file(&mut state, location, Some(c"basics/quieter.clackpreset")).unwrap();
+        assert_eq!(loaded, header());
+        assert_eq!(state.0, 0.5);
+
+        assert!(load_preset_file(&mut state, location, Some(c"../quieter.clackpreset")).is_err());
+        assert!(load_preset_file(&mut state, Location::Plugin, None).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
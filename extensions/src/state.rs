@@ -12,6 +12,9 @@
 //! Plugins can also notify the host that their state has changed compared to the last time it was
 //! saved or loaded, using the `mark_dirty` call.
 //!
+//! To avoid inventing their own binary layout, plugins can use [`StateFormat`] and [`StateData`]
+//! to save versioned states, which can be upgraded when loaded by newer versions of the plugin.
+//!
 //! # Host-Side Example
 //!
 //! ```
@@ -159,6 +162,9 @@ impl Display for StateError {
 
 impl Error for StateError {}
 
+mod versioned;
+pub use versioned::*;
+
 #[cfg(feature = "clack-plugin")]
 mod plugin;
 #[cfg(feature = "clack-plugin")]
@@ -0,0 +1,671 @@
+//! A versioned, self-describing container for plugin state.
+//!
+//! The [`StateFormat`] type describes how a plugin's state is laid out: which plugin it belongs
+//! to, its current version, and how to upgrade states saved by older versions of the plugin. The
+//! state itself is held in a [`StateData`], which maps parameter values by their [`ClapId`], and
+//! stores any other data in tagged binary chunks.
+//!
+//! The serialized layout is the following, with all integers being little-endian:
+//!
+//! * The `CLKS` magic bytes, followed by the container revision (`u16`);
+//! * The plugin ID (`u16` length followed by UTF-8 bytes);
+//! * The state version (`u32`);
+//! * The raw state context type the state was saved for, or `u32::MAX` if there was none;
+//! * The chunk table: a chunk count (`u32`), followed by the ID (4 bytes) and length (`u32`) of
+//!   each chunk;
+//! * The chunk payloads, in the order of the chunk table.
+//!
+//! Parameter values are stored in the [`PARAMS_CHUNK`] chunk, as a sequence of (`u32` ID, `f64`
+//! value) pairs.
+//!
+//! # Example
+//!
+//! ```
+//! use clack_extensions::state::{StateData, StateFormat, StateFormatError};
+//! use clack_common::utils::ClapId;
+//!
+//! const GAIN: ClapId = ClapId::new(1);
+//!
+//! // Version 1 of the plugin stored the gain in decibels. Version 2 stores it linearly.
+//! fn upgrade_v1(data: &mut StateData) -> Result<(), StateFormatError> {
+//!     if let Some(db) = data.param(GAIN) {
+//!         data.set_param(GAIN, 10f64.powf(db / 20.0));
+//!     }
+//!
+//!     Ok(())
+//! }
+//!
+//! let format = StateFormat::new("org.rust-audio.clack.gain", 2).with_migration(1, upgrade_v1);
+//!
+//! let mut data = StateData::new();
+//! data.set_param(GAIN, 0.5);
+//! data.set_chunk(*b"NAME", b"My patch".to_vec());
+//!
+//! let mut buffer = Vec::new();
+//! format.save(&data, &mut buffer)?;
+//!
+//! let loaded = format.load(&mut buffer.as_slice())?;
+//! assert_eq!(loaded.param(GAIN), Some(0.5));
+//! assert_eq!(loaded.chunk(*b"NAME"), Some(b"My patch".as_slice()));
+//! # Ok::<_, StateFormatError>(())
+//! ```
+
+#![deny(missing_docs)]
+
+use clack_common::utils::ClapId;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "state-context")]
+use crate::state_context::StateContextType;
+
+/// The magic bytes every serialized state starts with.
+const MAGIC: [u8; 4] = *b"CLKS";
+
+/// The revision of the container layout itself.
+const CONTAINER_REVISION: u16 = 1;
+
+/// The raw context value used when a state was not saved for a specific context.
+const NO_CONTEXT: u32 = u32::MAX;
+
+/// The 4-byte identifier of a state chunk.
+pub type ChunkId = [u8; 4];
+
+/// The ID of the chunk that holds parameter values.
+pub const PARAMS_CHUNK: ChunkId = *b"PARM";
+
+/// A function upgrading a [`StateData`] from one version to the next.
+pub type StateMigration = fn(&mut StateData) -> Result<(), StateFormatError>;
+
+/// An error that occurred while saving or loading a versioned state.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StateFormatError {
+    /// An I/O error occurred while reading or writing the state.
+    Io(io::Error),
+    /// The data is not a valid state container, or it is corrupted.
+    InvalidData,
+    /// The state was saved by a different plugin.
+    PluginMismatch {
+        /// The ID of the plugin that saved the state.
+        plugin_id: String,
+    },
+    /// The state was saved by a newer version of the plugin, and cannot be safely loaded.
+    NewerVersion {
+        /// The version of the saved state.
+        version: u32,
+    },
+    /// A migration failed to upgrade the state.
+    Migration {
+        /// The version the migration was upgrading from.
+        from_version: u32,
+        /// The reason the migration failed.
+        reason: &'static str,
+    },
+}
+
+impl Display for StateFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error while processing plugin state: {e}"),
+            Self::InvalidData => f.write_str("Invalid or corrupted plugin state"),
+            Self::PluginMismatch { plugin_id } => {
+                write!(f, "Plugin state belongs to another plugin ({plugin_id})")
+            }
+            Self::NewerVersion { version } => write!(
+                f,
+                "Plugin state was saved by a newer version of the plugin (version {version})"
+            ),
+            Self::Migration {
+                from_version,
+                reason,
+            } => write!(
+                f,
+                "Failed to upgrade plugin state from version {from_version}: {reason}"
+            ),
+        }
+    }
+}
+
+impl Error for StateFormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StateFormatError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => Self::InvalidData,
+            _ => Self::Io(e),
+        }
+    }
+}
+
+/// The contents of a plugin's state: parameter values mapped by ID, and tagged binary chunks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateData {
+    version: u32,
+    context: u32,
+    params: BTreeMap<ClapId, f64>,
+    chunks: BTreeMap<ChunkId, Vec<u8>>,
+}
+
+impl StateData {
+    /// Creates a new, empty state.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            version: 0,
+            context: NO_CONTEXT,
+            params: BTreeMap::new(),
+            chunks: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the version of this state.
+    ///
+    /// For a freshly loaded state, this is the version the state was saved with, i.e. before any
+    /// migration was applied. During a migration, this is the version being upgraded from.
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the context type this state was saved for, if any.
+    #[cfg(feature = "state-context")]
+    #[inline]
+    pub fn context(&self) -> Option<StateContextType> {
+        StateContextType::from_raw(self.context)
+    }
+
+    /// Returns the stored value of the parameter with the given ID, if any.
+    #[inline]
+    pub fn param(&self, id: ClapId) -> Option<f64> {
+        self.params.get(&id).copied()
+    }
+
+    /// Sets the stored value of the parameter with the given ID.
+    #[inline]
+    pub fn set_param(&mut self, id: ClapId, value: f64) {
+        self.params.insert(id, value);
+    }
+
+    /// Removes the stored value of the parameter with the given ID, and returns it.
+    #[inline]
+    pub fn remove_param(&mut self, id: ClapId) -> Option<f64> {
+        self.params.remove(&id)
+    }
+
+    /// Returns an iterator over all the stored parameter values, ordered by ID.
+    #[inline]
+    pub fn params(&self) -> impl Iterator<Item = (ClapId, f64)> + '_ {
+        self.params.iter().map(|(id, value)| (*id, *value))
+    }
+
+    /// Returns the contents of the chunk with the given ID, if any.
+    #[inline]
+    pub fn chunk(&self, id: ChunkId) -> Option<&[u8]> {
+        self.chunks.get(&id).map(Vec::as_slice)
+    }
+
+    /// Sets the contents of the chunk with the given ID.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `id` is [`PARAMS_CHUNK`], which is reserved for parameter values.
+    #[inline]
+    pub fn set_chunk(&mut self, id: ChunkId, data: Vec<u8>) {
+        assert_ne!(
+            id, PARAMS_CHUNK,
+            "The PARM chunk is reserved for parameters"
+        );
+        self.chunks.insert(id, data);
+    }
+
+    /// Removes the chunk with the given ID, and returns its contents.
+    #[inline]
+    pub fn remove_chunk(&mut self, id: ChunkId) -> Option<Vec<u8>> {
+        self.chunks.remove(&id)
+    }
+
+    /// Returns an iterator over all the chunks of this state, ordered by ID.
+    ///
+    /// This does not include the [`PARAMS_CHUNK`].
+    #[inline]
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkId, &[u8])> + '_ {
+        self.chunks.iter().map(|(id, data)| (*id, data.as_slice()))
+    }
+
+    fn encode_params(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.params.len() * 12);
+        for (id, value) in &self.params {
+            buffer.extend_from_slice(&id.get().to_le_bytes());
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+
+        buffer
+    }
+
+    fn decode_params(&mut self, data: &[u8]) -> Result<(), StateFormatError> {
+        if data.len() % 12 != 0 {
+            return Err(StateFormatError::InvalidData);
+        }
+
+        for pair in data.chunks_exact(12) {
+            let (id, value) = pair.split_at(4);
+            let id = ClapId::from_raw(u32::from_le_bytes(id.try_into().unwrap()))
+                .ok_or(StateFormatError::InvalidData)?;
+
+            self.params
+                .insert(id, f64::from_le_bytes(value.try_into().unwrap()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for StateData {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(feature = "params", feature = "clack-plugin"))]
+impl StateData {
+    /// Stores the current values of all the parameters of the given
+    /// [`ParamSet`](crate::params::ParamSet).
+    pub fn store_param_set(&mut self, params: &crate::params::ParamSet) {
+        for (index, definition) in params.definitions().iter().enumerate() {
+            self.set_param(definition.id, params.value_at(index));
+        }
+    }
+
+    /// Restores the values of the given [`ParamSet`](crate::params::ParamSet) from this state.
+    ///
+    /// All parameters are first reset to their default values. Stored values are then clamped to
+    /// the range of their parameter, and values of parameters that do not exist in the set are
+    /// ignored.
+    pub fn restore_param_set(&self, params: &crate::params::ParamSet) {
+        params.reset();
+
+        for (id, value) in self.params() {
+            params.set(id, value);
+        }
+    }
+}
+
+/// The description of a plugin's versioned state format.
+///
+/// A state is serialized with a header holding the plugin ID, the state version and the context
+/// type it was saved for, followed by a table of all of its chunks.
+#[derive(Clone, Debug)]
+pub struct StateFormat {
+    plugin_id: String,
+    version: u32,
+    migrations: BTreeMap<u32, StateMigration>,
+    #[cfg(feature = "state-context")]
+    chunk_contexts: Vec<(ChunkId, &'static [StateContextType])>,
+}
+
+impl StateFormat {
+    /// Creates a new state format for the plugin with the given ID, at the given current version.
+    pub fn new(plugin_id: impl Into<String>, version: u32) -> Self {
+        Self {
+            plugin_id: plugin_id.into(),
+            version,
+            migrations: BTreeMap::new(),
+            #[cfg(feature = "state-context")]
+            chunk_contexts: Vec::new(),
+        }
+    }
+
+    /// Registers a migration that upgrades states from the given version to the next one.
+    ///
+    /// When loading a state, all the migrations between the saved version and the current version
+    /// are applied in order. Versions without a registered migration are considered to be
+    /// compatible with the next version as-is.
+    #[inline]
+    pub fn with_migration(mut self, from_version: u32, migration: StateMigration) -> Self {
+        self.migrations.insert(from_version, migration);
+        self
+    }
+
+    /// Restricts the chunk with the given ID to only be saved for the given context types.
+    ///
+    /// This allows e.g. to exclude some data from presets, while still storing it in projects.
+    /// Chunks without any restriction are saved in all contexts. Use [`PARAMS_CHUNK`] to restrict
+    /// parameter values.
+    #[cfg(feature = "state-context")]
+    #[inline]
+    pub fn with_chunk_contexts(
+        mut self,
+        chunk_id: ChunkId,
+        contexts: &'static [StateContextType],
+    ) -> Self {
+        self.chunk_contexts.push((chunk_id, contexts));
+        self
+    }
+
+    /// Returns the ID of the plugin this format belongs to.
+    #[inline]
+    pub fn plugin_id(&self) -> &str {
+        &self.plugin_id
+    }
+
+    /// Returns the current version of this format.
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Serializes the given state to the given writer, with all of its chunks.
+    #[inline]
+    pub fn save(&self, data: &StateData, writer: &mut impl Write) -> Result<(), StateFormatError> {
+        self.write(data, NO_CONTEXT, |_| true, writer)
+    }
+
+    /// Serializes the given state to the given writer, for the given context type.
+    ///
+    /// Only the chunks allowed for this context are saved. See
+    /// [`with_chunk_contexts`](Self::with_chunk_contexts).
+    #[cfg(feature = "state-context")]
+    pub fn save_for_context(
+        &self,
+        data: &StateData,
+        context: StateContextType,
+        writer: &mut impl Write,
+    ) -> Result<(), StateFormatError> {
+        let is_allowed = |id: ChunkId| {
+            self.chunk_contexts
+                .iter()
+                .filter(|(chunk_id, _)| *chunk_id == id)
+                .all(|(_, contexts)| contexts.contains(&context))
+        };
+
+        self.write(data, context.to_raw(), is_allowed, writer)
+    }
+
+    /// Deserializes a state from the given reader, and upgrades it to the current version.
+    ///
+    /// This fails if the state was saved by another plugin, or by a newer version of this plugin.
+    pub fn load(&self, reader: &mut impl Read) -> Result<StateData, StateFormatError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC || read_u16(reader)? != CONTAINER_REVISION {
+            return Err(StateFormatError::InvalidData);
+        }
+
+        let mut plugin_id = vec![0; read_u16(reader)? as usize];
+        reader.read_exact(&mut plugin_id)?;
+        let plugin_id = String::from_utf8(plugin_id).map_err(|_| StateFormatError::InvalidData)?;
+        if plugin_id != self.plugin_id {
+            return Err(StateFormatError::PluginMismatch { plugin_id });
+        }
+
+        let version = read_u32(reader)?;
+        if version > self.version {
+            return Err(StateFormatError::NewerVersion { version });
+        }
+
+        let mut data = StateData {
+            version,
+            context: read_u32(reader)?,
+            ..StateData::new()
+        };
+
+        let mut table = Vec::new();
+        for _ in 0..read_u32(reader)? {
+            let mut id = [0; 4];
+            reader.read_exact(&mut id)?;
+            table.push((id, read_u32(reader)? as usize));
+        }
+
+        for (id, length) in table {
+            let mut chunk = Vec::new();
+            reader.take(length as u64).read_to_end(&mut chunk)?;
+            if chunk.len() != length {
+                return Err(StateFormatError::InvalidData);
+            }
+
+            if id == PARAMS_CHUNK {
+                data.decode_params(&chunk)?;
+            } else {
+                data.chunks.insert(id, chunk);
+            }
+        }
+
+        self.migrate(data)
+    }
+
+    fn migrate(&self, mut data: StateData) -> Result<StateData, StateFormatError> {
+        let saved_version = data.version;
+
+        for (&from_version, migration) in self.migrations.range(saved_version..self.version) {
+            data.version = from_version;
+            migration(&mut data)?;
+        }
+
+        data.version = saved_version;
+        Ok(data)
+    }
+
+    fn write(
+        &self,
+        data: &StateData,
+        context: u32,
+        is_allowed: impl Fn(ChunkId) -> bool,
+        writer: &mut impl Write,
+    ) -> Result<(), StateFormatError> {
+        let plugin_id: u16 = self
+            .plugin_id
+            .len()
+            .try_into()
+            .map_err(|_| StateFormatError::InvalidData)?;
+
+        let params = is_allowed(PARAMS_CHUNK).then(|| data.encode_params());
+        let chunks: Vec<(ChunkId, &[u8])> = params
+            .as_deref()
+            .map(|params| (PARAMS_CHUNK, params))
+            .into_iter()
+            .chain(data.chunks().filter(|(id, _)| is_allowed(*id)))
+            .collect();
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&CONTAINER_REVISION.to_le_bytes())?;
+        writer.write_all(&plugin_id.to_le_bytes())?;
+        writer.write_all(self.plugin_id.as_bytes())?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&context.to_le_bytes())?;
+
+        writer.write_all(&(chunks.len() as u32).to_le_bytes())?;
+        for (id, chunk) in &chunks {
+            let length: u32 = chunk
+                .len()
+                .try_into()
+                .map_err(|_| StateFormatError::InvalidData)?;
+
+            writer.write_all(id)?;
+            writer.write_all(&length.to_le_bytes())?;
+        }
+
+        for (_, chunk) in &chunks {
+            writer.write_all(chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[inline]
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+#[inline]
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUTOFF: ClapId = ClapId::new(1);
+    const RESONANCE: ClapId = ClapId::new(2);
+
+    fn saved(format: &StateFormat, data: &StateData) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        format.save(data, &mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn round_trips_params_and_chunks() {
+        let format = StateFormat::new("org.rust-audio.clack.test", 3);
+
+        let mut data = StateData::new();
+        data.set_param(CUTOFF, 440.0);
+        data.set_param(RESONANCE, 0.5);
+        data.set_chunk(*b"WAVE", vec![1, 2, 3]);
+
+        let loaded = format.load(&mut saved(&format, &data).as_slice()).unwrap();
+        assert_eq!(loaded.version(), 3);
+        assert_eq!(loaded.param(CUTOFF), Some(440.0));
+        assert_eq!(loaded.chunk(*b"WAVE"), Some([1, 2, 3].as_slice()));
+        assert_eq!(loaded, StateData { version: 3, ..data });
+    }
+
+    #[test]
+    fn applies_migrations_in_order() {
+        fn v1_to_v2(data: &mut StateData) -> Result<(), StateFormatError> {
+            assert_eq!(data.version(), 1);
+            let cutoff = data.remove_param(RESONANCE).unwrap();
+            data.set_param(CUTOFF, cutoff);
+            Ok(())
+        }
+
+        fn v3_to_v4(data: &mut StateData) -> Result<(), StateFormatError> {
+            let cutoff = data.param(CUTOFF).unwrap();
+            data.set_param(CUTOFF, cutoff * 2.0);
+            Ok(())
+        }
+
+        let mut data = StateData::new();
+        data.set_param(RESONANCE, 100.0);
+        let old = saved(&StateFormat::new("test", 1), &data);
+
+        let format = StateFormat::new("test", 4)
+            .with_migration(0, |_| panic!("Should not be applied"))
+            .with_migration(1, v1_to_v2)
+            .with_migration(3, v3_to_v4)
+            .with_migration(4, |_| panic!("Should not be applied"));
+
+        let loaded = format.load(&mut old.as_slice()).unwrap();
+        assert_eq!(loaded.version(), 1);
+        assert_eq!(loaded.params().collect::<Vec<_>>(), [(CUTOFF, 200.0)]);
+    }
+
+    #[test]
+    fn rejects_foreign_newer_and_corrupted_states() {
+        let data = StateData::new();
+        let format = StateFormat::new("test", 2);
+
+        let foreign = saved(&StateFormat::new("other", 2), &data);
+        assert!(matches!(
+            format.load(&mut foreign.as_slice()),
+            Err(StateFormatError::PluginMismatch { plugin_id }) if plugin_id == "other"
+        ));
+
+        let newer = saved(&StateFormat::new("test", 3), &data);
+        assert!(matches!(
+            format.load(&mut newer.as_slice()),
+            Err(StateFormatError::NewerVersion { version: 3 })
+        ));
+
+        let mut data = StateData::new();
+        data.set_param(CUTOFF, 1.0);
+        let valid = saved(&format, &data);
+        for length in 0..valid.len() {
+            assert!(matches!(
+                format.load(&mut &valid[..length]),
+                Err(StateFormatError::InvalidData)
+            ));
+        }
+    }
+
+    #[cfg(all(feature = "params", feature = "clack-plugin"))]
+    #[test]
+    fn restores_param_sets_without_corrupting_them() {
+        use crate::params::{ParamDefinition, ParamSet};
+
+        let params = ParamSet::new([
+            ParamDefinition::new(CUTOFF, "Cutoff").with_range(20.0, 20_000.0),
+            ParamDefinition::new(RESONANCE, "Resonance").with_default(0.25),
+        ]);
+        params.set(RESONANCE, 0.75);
+
+        // An older build had no resonance parameter, but an extra one since removed.
+        let mut data = StateData::new();
+        data.set_param(CUTOFF, 50_000.0);
+        data.set_param(ClapId::new(42), 1.0);
+        data.restore_param_set(&params);
+
+        assert_eq!(params.get(CUTOFF), Some(20_000.0));
+        assert_eq!(params.get(RESONANCE), Some(0.25));
+
+        let mut stored = StateData::new();
+        stored.store_param_set(&params);
+        assert_eq!(
+            stored.params().collect::<Vec<_>>(),
+            [(CUTOFF, 20_000.0), (RESONANCE, 0.25)]
+        );
+    }
+
+    #[cfg(feature = "state-context")]
+    #[test]
+    fn filters_chunks_by_context() {
+        let format = StateFormat::new("test", 1)
+            .with_chunk_contexts(*b"PATH", &[StateContextType::ForProject])
+            .with_chunk_contexts(
+                PARAMS_CHUNK,
+                &[StateContextType::ForPreset, StateContextType::ForProject],
+            );
+
+        let mut data = StateData::new();
+        data.set_param(CUTOFF, 1.0);
+        data.set_chunk(*b"PATH", b"/tmp".to_vec());
+        data.set_chunk(*b"NAME", b"Bass".to_vec());
+
+        let mut buffer = Vec::new();
+        format
+            .save_for_context(&data, StateContextType::ForDuplicate, &mut buffer)
+            .unwrap();
+        let duplicate = format.load(&mut buffer.as_slice()).unwrap();
+        assert_eq!(duplicate.context(), Some(StateContextType::ForDuplicate));
+        assert_eq!(duplicate.params().count(), 0);
+        assert_eq!(duplicate.chunks().count(), 1);
+
+        buffer.clear();
+        format
+            .save_for_context(&data, StateContextType::ForProject, &mut buffer)
+            .unwrap();
+        let project = format.load(&mut buffer.as_slice()).unwrap();
+        assert_eq!(project.param(CUTOFF), Some(1.0));
+        assert_eq!(project.chunk(*b"PATH"), Some(b"/tmp".as_slice()));
+
+        let plain = format.load(&mut saved(&format, &data).as_slice()).unwrap();
+        assert_eq!(plain.context(), None);
+    }
+}
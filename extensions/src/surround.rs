@@ -0,0 +1,528 @@
+//! Allows plugins and hosts to exchange the channel mapping of surround audio ports.
+//!
+//! This extension only applies to audio ports whose [`AudioPortType`] is
+//! [`AudioPortType::SURROUND`]. Each channel of such a port is assigned a [`SurroundChannel`]
+//! position, which the host can retrieve using [`get_channel_map`](PluginSurround::get_channel_map).
+//!
+//! Hosts can also check if a given set of channels (represented as a [`SurroundChannelMask`])
+//! is supported by the plugin using [`is_channel_mask_supported`](PluginSurround::is_channel_mask_supported),
+//! e.g. before requesting a new port configuration through the
+//! [`configurable_audio_ports`](crate::configurable_audio_ports) extension.
+//!
+//! Plugins can notify the host that the channel maps of their ports have changed using
+//! [`HostSurround::changed`]. This can only happen while the plugin is deactivated.
+
+#![deny(missing_docs)]
+
+use crate::audio_ports::AudioPortType;
+use bitflags::bitflags;
+use clack_common::extensions::*;
+use clap_sys::ext::surround::*;
+use std::ffi::CStr;
+
+/// Plugin-side of the Surround extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct PluginSurround(RawExtension<PluginExtensionSide, clap_plugin_surround>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for PluginSurround {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_SURROUND, CLAP_EXT_SURROUND_COMPAT];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+/// Host-side of the Surround extension.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub struct HostSurround(RawExtension<HostExtensionSide, clap_host_surround>);
+
+// SAFETY: This type is repr(C) and ABI-compatible with the matching extension type.
+unsafe impl Extension for HostSurround {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_SURROUND, CLAP_EXT_SURROUND_COMPAT];
+    type ExtensionSide = HostExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+impl AudioPortType<'_> {
+    /// A surround audio port.
+    ///
+    /// The channel map of ports of this type can be queried using the [`PluginSurround`]
+    /// extension.
+    pub const SURROUND: AudioPortType<'static> = AudioPortType(CLAP_PORT_SURROUND);
+}
+
+/// The position of a single channel in a surround audio port.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[repr(u8)]
+pub enum SurroundChannel {
+    /// Front Left.
+    FrontLeft = CLAP_SURROUND_FL as u8,
+    /// Front Right.
+    FrontRight = CLAP_SURROUND_FR as u8,
+    /// Front Center.
+    FrontCenter = CLAP_SURROUND_FC as u8,
+    /// Low Frequency Effects.
+    LowFrequency = CLAP_SURROUND_LFE as u8,
+    /// Back Left.
+    BackLeft = CLAP_SURROUND_BL as u8,
+    /// Back Right.
+    BackRight = CLAP_SURROUND_BR as u8,
+    /// Front Left of Center.
+    FrontLeftCenter = CLAP_SURROUND_FLC as u8,
+    /// Front Right of Center.
+    FrontRightCenter = CLAP_SURROUND_FRC as u8,
+    /// Back Center.
+    BackCenter = CLAP_SURROUND_BC as u8,
+    /// Side Left.
+    SideLeft = CLAP_SURROUND_SL as u8,
+    /// Side Right.
+    SideRight = CLAP_SURROUND_SR as u8,
+    /// Top Center.
+    TopCenter = CLAP_SURROUND_TC as u8,
+    /// Top Front Left.
+    TopFrontLeft = CLAP_SURROUND_TFL as u8,
+    /// Top Front Center.
+    TopFrontCenter = CLAP_SURROUND_TFC as u8,
+    /// Top Front Right.
+    TopFrontRight = CLAP_SURROUND_TFR as u8,
+    /// Top Back Left.
+    TopBackLeft = CLAP_SURROUND_TBL as u8,
+    /// Top Back Center.
+    TopBackCenter = CLAP_SURROUND_TBC as u8,
+    /// Top Back Right.
+    TopBackRight = CLAP_SURROUND_TBR as u8,
+}
+
+impl SurroundChannel {
+    /// Returns the channel position as its raw, C-FFI compatible value.
+    #[inline]
+    pub const fn to_raw(self) -> u8 {
+        self as u8
+    }
+
+    /// Reads a channel position from its raw, C-FFI compatible value.
+    ///
+    /// This returns [`None`] if the given value doesn't match any known channel position.
+    #[inline]
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        use SurroundChannel::*;
+
+        const ALL: [SurroundChannel; 18] = [
+            FrontLeft,
+            FrontRight,
+            FrontCenter,
+            LowFrequency,
+            BackLeft,
+            BackRight,
+            FrontLeftCenter,
+            FrontRightCenter,
+            BackCenter,
+            SideLeft,
+            SideRight,
+            TopCenter,
+            TopFrontLeft,
+            TopFrontCenter,
+            TopFrontRight,
+            TopBackLeft,
+            TopBackCenter,
+            TopBackRight,
+        ];
+
+        if (raw as usize) < ALL.len() {
+            Some(ALL[raw as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the [`SurroundChannelMask`] that only contains this channel position.
+    #[inline]
+    pub const fn mask(self) -> SurroundChannelMask {
+        SurroundChannelMask::from_bits_retain(1 << self as u8)
+    }
+}
+
+bitflags! {
+    /// A set of surround channel positions.
+    ///
+    /// Each bit of the mask corresponds to a [`SurroundChannel`], as returned by
+    /// [`SurroundChannel::mask`].
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct SurroundChannelMask: u64 {
+        /// Front Left.
+        const FRONT_LEFT = 1 << CLAP_SURROUND_FL;
+        /// Front Right.
+        const FRONT_RIGHT = 1 << CLAP_SURROUND_FR;
+        /// Front Center.
+        const FRONT_CENTER = 1 << CLAP_SURROUND_FC;
+        /// Low Frequency Effects.
+        const LOW_FREQUENCY = 1 << CLAP_SURROUND_LFE;
+        /// Back Left.
+        const BACK_LEFT = 1 << CLAP_SURROUND_BL;
+        /// Back Right.
+        const BACK_RIGHT = 1 << CLAP_SURROUND_BR;
+        /// Front Left of Center.
+        const FRONT_LEFT_CENTER = 1 << CLAP_SURROUND_FLC;
+        /// Front Right of Center.
+        const FRONT_RIGHT_CENTER = 1 << CLAP_SURROUND_FRC;
+        /// Back Center.
+        const BACK_CENTER = 1 << CLAP_SURROUND_BC;
+        /// Side Left.
+        const SIDE_LEFT = 1 << CLAP_SURROUND_SL;
+        /// Side Right.
+        const SIDE_RIGHT = 1 << CLAP_SURROUND_SR;
+        /// Top Center.
+        const TOP_CENTER = 1 << CLAP_SURROUND_TC;
+        /// Top Front Left.
+        const TOP_FRONT_LEFT = 1 << CLAP_SURROUND_TFL;
+        /// Top Front Center.
+        const TOP_FRONT_CENTER = 1 << CLAP_SURROUND_TFC;
+        /// Top Front Right.
+        const TOP_FRONT_RIGHT = 1 << CLAP_SURROUND_TFR;
+        /// Top Back Left.
+        const TOP_BACK_LEFT = 1 << CLAP_SURROUND_TBL;
+        /// Top Back Center.
+        const TOP_BACK_CENTER = 1 << CLAP_SURROUND_TBC;
+        /// Top Back Right.
+        const TOP_BACK_RIGHT = 1 << CLAP_SURROUND_TBR;
+    }
+}
+
+impl SurroundChannelMask {
+    /// A stereo layout (L, R).
+    pub const STEREO: Self = Self::FRONT_LEFT.union(Self::FRONT_RIGHT);
+
+    /// A 5.1 layout (L, R, C, LFE, Ls, Rs), using side surround channels.
+    pub const SURROUND_5_1: Self = Self::STEREO
+        .union(Self::FRONT_CENTER)
+        .union(Self::LOW_FREQUENCY)
+        .union(Self::SIDE_LEFT)
+        .union(Self::SIDE_RIGHT);
+
+    /// A 7.1 layout (L, R, C, LFE, Ls, Rs, Lrs, Rrs).
+    pub const SURROUND_7_1: Self = Self::SURROUND_5_1
+        .union(Self::BACK_LEFT)
+        .union(Self::BACK_RIGHT);
+
+    /// Returns the set of channels used by the given channel map.
+    ///
+    /// Unknown channel positions in the map are ignored.
+    pub fn from_channel_map(channel_map: &[u8]) -> Self {
+        channel_map
+            .iter()
+            .filter_map(|c| SurroundChannel::from_raw(*c))
+            .fold(Self::empty(), |mask, c| mask | c.mask())
+    }
+}
+
+/// A channel map of a surround audio port, as provided by the plugin.
+///
+/// The channel map associates each channel index of the port to a [`SurroundChannel`] position.
+/// Positions that are unknown to this version of Clack are represented as [`None`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SurroundChannelMap<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> SurroundChannelMap<'a> {
+    /// Wraps a raw channel map, as provided by the plugin.
+    #[inline]
+    pub const fn from_raw(raw: &'a [u8]) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the raw channel map, as provided by the plugin.
+    #[inline]
+    pub const fn as_raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// Returns the number of channels in this channel map.
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Returns `true` if this channel map is empty.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Returns the position of the channel at the given index.
+    ///
+    /// This returns [`None`] if the index is out of bounds, or if the position is unknown.
+    #[inline]
+    pub fn get(&self, channel_index: usize) -> Option<SurroundChannel> {
+        SurroundChannel::from_raw(*self.raw.get(channel_index)?)
+    }
+
+    /// Returns an iterator over the positions of all the channels in this channel map.
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Option<SurroundChannel>> + 'a {
+        self.raw.iter().map(|c| SurroundChannel::from_raw(*c))
+    }
+
+    /// Returns the set of channels used by this channel map.
+    #[inline]
+    pub fn mask(&self) -> SurroundChannelMask {
+        SurroundChannelMask::from_channel_map(self.raw)
+    }
+}
+
+#[cfg(feature = "clack-host")]
+mod host {
+    use super::*;
+    use clack_host::extensions::prelude::*;
+
+    impl PluginSurround {
+        /// Returns `true` if the plugin supports the given set of channels.
+        pub fn is_channel_mask_supported(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            mask: SurroundChannelMask,
+        ) -> bool {
+            match plugin.use_extension(&self.0).is_channel_mask_supported {
+                None => false,
+                // SAFETY: This type ensures the function pointer is valid.
+                Some(supported) => unsafe { supported(plugin.as_raw(), mask.bits()) },
+            }
+        }
+
+        /// Retrieves the channel map of the given audio port.
+        ///
+        /// The plugin writes the channel map into the given buffer, which should be large enough
+        /// to hold all the channels of the port. If the plugin's channel map is larger than the
+        /// buffer, the returned channel map is truncated to the buffer's size.
+        ///
+        /// If the plugin failed to provide a channel map, an empty one is returned.
+        pub fn get_channel_map<'b>(
+            &self,
+            plugin: &mut PluginMainThreadHandle,
+            is_input: bool,
+            port_index: u32,
+            buffer: &'b mut [u8],
+        ) -> SurroundChannelMap<'b> {
+            let Some(get_channel_map) = plugin.use_extension(&self.0).get_channel_map else {
+                return SurroundChannelMap::from_raw(&[]);
+            };
+
+            let capacity = u32::try_from(buffer.len()).unwrap_or(u32::MAX);
+
+            // SAFETY: This type ensures the function pointer is valid.
+            let written = unsafe {
+                get_channel_map(
+                    plugin.as_raw(),
+                    is_input,
+                    port_index,
+                    buffer.as_mut_ptr(),
+                    capacity,
+                )
+            };
+
+            let written = (written as usize).min(buffer.len());
+            SurroundChannelMap::from_raw(&buffer[..written])
+        }
+    }
+
+    /// Implementation of the Host-side of the Surround extension.
+    pub trait HostSurroundImpl {
+        /// Informs the host that the channel maps of the plugin's ports have changed.
+        ///
+        /// The plugin can only call this while it is deactivated.
+        fn changed(&mut self);
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<H> ExtensionImplementation<H> for HostSurround
+    where
+        H: for<'a> HostHandlers<MainThread<'a>: HostSurroundImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_host_surround {
+                changed: Some(changed::<H>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn changed<H>(host: *const clap_host)
+    where
+        H: for<'a> HostHandlers<MainThread<'a>: HostSurroundImpl>,
+    {
+        HostWrapper::<H>::handle(host, |host| {
+            host.main_thread().as_mut().changed();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(feature = "clack-host")]
+pub use host::*;
+
+#[cfg(feature = "clack-plugin")]
+mod plugin {
+    use super::*;
+    use crate::utils::slice_from_external_parts_mut;
+    use clack_plugin::extensions::prelude::*;
+
+    impl HostSurround {
+        /// Informs the host that the channel maps of the plugin's ports have changed.
+        ///
+        /// This can only be called while the plugin is deactivated.
+        #[inline]
+        pub fn changed(&self, host: &mut HostMainThreadHandle) {
+            if let Some(changed) = host.use_extension(&self.0).changed {
+                // SAFETY: This type ensures the function pointer is valid.
+                unsafe { changed(host.as_raw()) }
+            }
+        }
+    }
+
+    /// A helper type that allows to safely write a channel map into a host-provided buffer.
+    ///
+    /// Writes are bounded by the capacity of the host's buffer: any channel that doesn't fit in
+    /// it is discarded.
+    pub struct SurroundChannelMapWriter<'a> {
+        buffer: &'a mut [u8],
+        written: usize,
+    }
+
+    impl<'a> SurroundChannelMapWriter<'a> {
+        #[inline]
+        fn new(buffer: &'a mut [u8]) -> Self {
+            Self { buffer, written: 0 }
+        }
+
+        /// Returns the maximum number of channels the host's buffer can hold.
+        #[inline]
+        pub fn capacity(&self) -> usize {
+            self.buffer.len()
+        }
+
+        /// Appends a single channel position to the channel map.
+        ///
+        /// Returns `false` if the host's buffer is full, in which case the channel is discarded.
+        #[inline]
+        pub fn push(&mut self, channel: SurroundChannel) -> bool {
+            let Some(slot) = self.buffer.get_mut(self.written) else {
+                return false;
+            };
+
+            *slot = channel.to_raw();
+            self.written += 1;
+            true
+        }
+
+        /// Writes the given channel map, replacing anything that was previously written.
+        ///
+        /// Returns the number of channels that were actually written, which may be lower than the
+        /// given map's length if the host's buffer is too small.
+        pub fn set(&mut self, channel_map: &[SurroundChannel]) -> usize {
+            self.written = 0;
+
+            for channel in channel_map {
+                if !self.push(*channel) {
+                    break;
+                }
+            }
+
+            self.written
+        }
+    }
+
+    /// Implementation of the Plugin-side of the Surround extension.
+    pub trait PluginSurroundImpl {
+        /// Returns `true` if the plugin supports the given set of channels.
+        fn is_channel_mask_supported(&mut self, mask: SurroundChannelMask) -> bool;
+
+        /// Writes the channel map of the given audio port into the given writer.
+        ///
+        /// Ports that don't exist or aren't surround ports should leave the writer untouched.
+        fn get_channel_map(
+            &mut self,
+            is_input: bool,
+            port_index: u32,
+            writer: &mut SurroundChannelMapWriter,
+        );
+    }
+
+    // SAFETY: The given struct is the CLAP extension struct for the matching side of this extension.
+    unsafe impl<P> ExtensionImplementation<P> for PluginSurround
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginSurroundImpl>,
+    {
+        #[doc(hidden)]
+        const IMPLEMENTATION: RawExtensionImplementation =
+            RawExtensionImplementation::new(&clap_plugin_surround {
+                is_channel_mask_supported: Some(is_channel_mask_supported::<P>),
+                get_channel_map: Some(get_channel_map::<P>),
+            });
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn is_channel_mask_supported<P>(
+        plugin: *const clap_plugin,
+        channel_mask: u64,
+    ) -> bool
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginSurroundImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |plugin| {
+            // Channels unknown to us can never be supported.
+            let Some(mask) = SurroundChannelMask::from_bits(channel_mask) else {
+                return Ok(false);
+            };
+
+            Ok(plugin
+                .main_thread()
+                .as_mut()
+                .is_channel_mask_supported(mask))
+        })
+        .unwrap_or(false)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    unsafe extern "C" fn get_channel_map<P>(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        channel_map: *mut u8,
+        channel_map_capacity: u32,
+    ) -> u32
+    where
+        for<'a> P: Plugin<MainThread<'a>: PluginSurroundImpl>,
+    {
+        PluginWrapper::<P>::handle(plugin, |plugin| {
+            if channel_map.is_null() && channel_map_capacity > 0 {
+                return Err(PluginWrapperError::NulPtr("channel_map"));
+            }
+
+            let buffer = slice_from_external_parts_mut(channel_map, channel_map_capacity as usize);
+            let mut writer = SurroundChannelMapWriter::new(buffer);
+
+            plugin
+                .main_thread()
+                .as_mut()
+                .get_channel_map(is_input, port_index, &mut writer);
+
+            Ok(writer.written as u32)
+        })
+        .unwrap_or(0)
+    }
+}
+
+#[cfg(feature = "clack-plugin")]
+pub use plugin::*;
@@ -1,7 +1,7 @@
 #![allow(dead_code)] // Those utilities are only used in *some* extensions.
 
 use core::ffi::c_char;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 
 /// # Safety
 ///
@@ -24,6 +24,160 @@ pub(crate) const fn cstr_to_nullable_ptr(str: Option<&CStr>) -> *const c_char {
     }
 }
 
+static EMPTY: &CStr = c"";
+
+/// # Safety Invariants
+///
+/// This type's inner pointer can either be :
+///
+/// * null;
+/// * pointing to `EMPTY`;
+/// * pointing to a string created by CString::into_raw.
+///
+/// This type alone cannot set its pointer to any other kind of value.
+///
+/// # Transmuting
+/// This type is `#[repr(C)]` and so *can* be transmuted from a raw `*const c_char`, in which case
+/// it may hold an arbitrary pointer.
+///
+/// In this case, you **MUST NOT** drop it, or call `set`, which would cause immediate UB.
+///
+/// `get` may be called, but **only** if the pointer points to a valid C String (nul-terminated), and
+/// the pointer's value is not changed for the lifetime of this type.
+///
+/// In any case, this type can always safely be transmuted *to* a raw `*const c_char`.
+#[repr(C)]
+pub(crate) struct OwnedCString(*const c_char);
+
+// SAFETY: OwnedCString is fully self-contained, the pointers refer to data owned by it.
+unsafe impl Send for OwnedCString {}
+
+// SAFETY: OwnedCString does not have any interior mutability.
+unsafe impl Sync for OwnedCString {}
+
+impl OwnedCString {
+    #[inline]
+    pub fn new(string: Option<CString>) -> Self {
+        let mut new = Self::empty();
+        new.set(string);
+        new
+    }
+
+    #[inline]
+    pub const fn empty() -> Self {
+        Self(EMPTY.as_ptr())
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<&CStr> {
+        if Self::is_allocated(self.0) {
+            // SAFETY: From our own invariants
+            Some(unsafe { CStr::from_ptr(self.0) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, new: Option<CString>) {
+        // We do this just in case *something* panics, in which case this instance remains valid and worst case we just leak some memory.
+        let old_ptr = core::mem::replace(&mut self.0, EMPTY.as_ptr());
+
+        // SAFETY: per our own invariants, this pointer is either null, EMPTY, or from to_raw.
+        unsafe { Self::deallocate(old_ptr) };
+
+        // If the string is not empty, we overwrite our EMPTY pointer with an owned one
+        if let Some(new) = new {
+            if !new.is_empty() {
+                // Note: this allocates, which implies it may panic. In that case we're good, because
+                // our previous inner value of EMPTY is completely valid.
+                self.0 = new.into_raw();
+            }
+        }
+
+        // If the string is empty, then we are already set to EMPTY, so we have nothing to do.
+    }
+
+    #[inline]
+    pub fn set_str(&mut self, string: &str) {
+        if string.is_empty() {
+            self.set(None);
+            return;
+        }
+
+        let string = CString::new(string).expect("Invalid C string.");
+        self.set(Some(string));
+    }
+
+    #[inline]
+    fn is_allocated(ptr: *const c_char) -> bool {
+        !ptr.is_null() && ptr != EMPTY.as_ptr()
+    }
+
+    /// # Safety
+    ///
+    /// This must ONLY be called on pointers that are either null, EMPTY, or from [`Self::allocate`].
+    #[inline]
+    unsafe fn deallocate(old_ptr: *const c_char) {
+        if Self::is_allocated(old_ptr) {
+            // SAFETY: From our own invariants, if it's not null or EMPTY, then it's from into_raw.
+            let _ = unsafe { CString::from_raw(old_ptr.cast_mut()) };
+        }
+    }
+}
+
+impl Clone for OwnedCString {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new(self.get().map(|s| s.to_owned()))
+    }
+}
+
+impl Drop for OwnedCString {
+    #[inline]
+    fn drop(&mut self) {
+        // Just in case.
+        let old_ptr = core::mem::replace(&mut self.0, EMPTY.as_ptr());
+
+        // SAFETY: per our own invariants, this pointer is either null, EMPTY, or from to_raw.
+        unsafe { Self::deallocate(old_ptr) };
+    }
+}
+
+/// Converts a path to the bytes of its C string representation.
+///
+/// CLAP paths are UTF-8 encoded, except on Unix platforms where they can be any byte sequence.
+/// Returns [`None`] if the path can't be represented on this platform.
+pub(crate) fn path_to_bytes(path: &std::path::Path) -> Option<&[u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(path.as_os_str().as_bytes())
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.to_str().map(str::as_bytes)
+    }
+}
+
+/// Converts the bytes of a C string to a path.
+///
+/// This is the reverse of [`path_to_bytes`]. Returns [`None`] if the bytes do not represent a
+/// valid path on this platform.
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> Option<&std::path::Path> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(std::path::Path::new(std::ffi::OsStr::from_bytes(bytes)))
+    }
+
+    #[cfg(not(unix))]
+    {
+        std::str::from_utf8(bytes).ok().map(std::path::Path::new)
+    }
+}
+
 pub(crate) fn data_from_array_buf<const N: usize>(data: &[c_char; N]) -> &[u8] {
     // SAFETY: casting from i8 to u8 is safe
     let data = unsafe { core::slice::from_raw_parts(data.as_ptr() as *const _, data.len()) };
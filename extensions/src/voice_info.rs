@@ -154,6 +154,26 @@ pub use host::*;
 mod plugin {
     use super::*;
     use clack_plugin::extensions::prelude::*;
+    use clack_plugin::voices::{StealingPolicy, Voice, VoiceManager};
+
+    impl<V: Voice> From<&VoiceManager<V>> for VoiceInfo {
+        /// Describes the current voice configuration of the given [`VoiceManager`].
+        ///
+        /// Overlapping notes are supported unless the manager uses the
+        /// [`SameKey`](StealingPolicy::SameKey) stealing policy.
+        fn from(manager: &VoiceManager<V>) -> Self {
+            let flags = match manager.stealing_policy() {
+                StealingPolicy::SameKey => VoiceInfoFlags::empty(),
+                _ => VoiceInfoFlags::SUPPORTS_OVERLAPPING_NOTES,
+            };
+
+            Self {
+                voice_count: manager.voice_count() as u32,
+                voice_capacity: manager.capacity() as u32,
+                flags,
+            }
+        }
+    }
 
     impl HostVoiceInfo {
         /// Indicates the plugin has changed its voice configuration, and the host needs to update
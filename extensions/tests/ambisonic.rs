@@ -0,0 +1,155 @@
+use clack_extensions::ambisonic::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+
+pub struct AmbisonicPlugin;
+pub struct AmbisonicPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+}
+
+impl<'a> PluginMainThread<'a, ()> for AmbisonicPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let ambisonic = self.host.get_extension::<HostAmbisonic>().unwrap();
+        ambisonic.changed(&mut self.host);
+    }
+}
+
+impl PluginAmbisonicImpl for AmbisonicPluginMainThread<'_> {
+    fn is_config_supported(&mut self, config: AmbisonicConfig) -> bool {
+        config.ordering == AmbisonicOrdering::Acn
+    }
+
+    fn get_config(&mut self, is_input: bool, port_index: u32) -> Option<AmbisonicConfig> {
+        match (is_input, port_index) {
+            (true, 0) => Some(AmbisonicConfig::new(
+                AmbisonicOrdering::Acn,
+                AmbisonicNormalization::Sn3d,
+            )),
+            (false, 0) => Some(AmbisonicConfig::new(
+                AmbisonicOrdering::Acn,
+                AmbisonicNormalization::N3d,
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Plugin for AmbisonicPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = AmbisonicPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginAmbisonic>();
+    }
+}
+
+impl DefaultPluginFactory for AmbisonicPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.ambisonic", "Ambisonic")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(AmbisonicPluginMainThread { host })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+struct MyHostMainThread {
+    changed_count: u32,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostAmbisonicImpl for MyHostMainThread {
+    fn changed(&mut self) {
+        self.changed_count += 1;
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostAmbisonic>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<AmbisonicPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread { changed_count: 0 },
+        &bundle,
+        c"org.rust-audio.clack.ambisonic",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn can_query_ambisonic_configs() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let ambisonic = plugin.get_extension::<PluginAmbisonic>().unwrap();
+
+    assert!(ambisonic.is_config_supported(
+        &mut plugin,
+        AmbisonicConfig::new(AmbisonicOrdering::Acn, AmbisonicNormalization::MaxN)
+    ));
+    assert!(!ambisonic.is_config_supported(
+        &mut plugin,
+        AmbisonicConfig::new(AmbisonicOrdering::FuMa, AmbisonicNormalization::MaxN)
+    ));
+
+    assert_eq!(
+        ambisonic.get_config(&mut plugin, true, 0),
+        Some(AmbisonicConfig::new(
+            AmbisonicOrdering::Acn,
+            AmbisonicNormalization::Sn3d
+        ))
+    );
+    assert_eq!(
+        ambisonic.get_config(&mut plugin, false, 0),
+        Some(AmbisonicConfig::new(
+            AmbisonicOrdering::Acn,
+            AmbisonicNormalization::N3d
+        ))
+    );
+    assert_eq!(ambisonic.get_config(&mut plugin, false, 1), None);
+}
+
+#[test]
+pub fn host_receives_changed_notifications() {
+    let mut instance = instantiate();
+
+    instance.call_on_main_thread_callback();
+    instance.call_on_main_thread_callback();
+
+    assert_eq!(instance.access_handler(|h| h.changed_count), 2);
+}
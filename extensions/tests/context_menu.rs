@@ -0,0 +1,311 @@
+use clack_extensions::context_menu::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+
+const RESET_ACTION: ClapId = ClapId::new(1);
+const BYPASS_ACTION: ClapId = ClapId::new(2);
+const HOST_ACTION: ClapId = ClapId::new(10);
+
+pub struct ContextMenuPlugin;
+pub struct ContextMenuPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+    performed: Vec<(ContextMenuTarget, ClapId)>,
+    host_menu: ContextMenu,
+}
+
+impl<'a> PluginMainThread<'a, ()> for ContextMenuPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let context_menu = self.host.get_extension::<HostContextMenu>().unwrap();
+        let target = ContextMenuTarget::Global;
+
+        context_menu
+            .populate(&mut self.host, target, &mut self.host_menu)
+            .unwrap();
+        assert_eq!(
+            self.host_menu.items(),
+            &[ContextMenuNode::Entry {
+                label: c"Show in browser".to_owned(),
+                is_enabled: true,
+                action_id: HOST_ACTION
+            }]
+        );
+
+        context_menu
+            .perform(&mut self.host, target, HOST_ACTION)
+            .unwrap();
+
+        assert!(context_menu.can_popup(&mut self.host));
+        context_menu
+            .popup(
+                &mut self.host,
+                ContextMenuTarget::Param(ClapId::new(5)),
+                0,
+                12,
+                34,
+            )
+            .unwrap();
+    }
+}
+
+impl PluginContextMenuImpl for ContextMenuPluginMainThread<'_> {
+    fn populate(
+        &mut self,
+        target: ContextMenuTarget,
+        builder: &ContextMenuBuilder,
+    ) -> Result<(), PluginError> {
+        let ContextMenuTarget::Param(_) = target else {
+            return Ok(());
+        };
+
+        builder
+            .add_item(ContextMenuItem::Title {
+                title: c"Gain",
+                is_enabled: true,
+            })?
+            .add_item(ContextMenuItem::Entry {
+                label: c"Reset",
+                is_enabled: true,
+                action_id: RESET_ACTION,
+            })?
+            .add_item(ContextMenuItem::Separator)?
+            .add_item(ContextMenuItem::BeginSubmenu {
+                label: c"More",
+                is_enabled: true,
+            })?
+            .add_item(ContextMenuItem::CheckEntry {
+                label: c"Bypass",
+                is_enabled: false,
+                is_checked: true,
+                action_id: BYPASS_ACTION,
+            })?
+            .add_item(ContextMenuItem::EndSubmenu)?;
+
+        Ok(())
+    }
+
+    fn perform(&mut self, target: ContextMenuTarget, action_id: ClapId) -> Result<(), PluginError> {
+        if action_id != RESET_ACTION && action_id != BYPASS_ACTION {
+            return Err(PluginError::Message("Unknown action"));
+        }
+
+        self.performed.push((target, action_id));
+        Ok(())
+    }
+}
+
+impl Plugin for ContextMenuPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = ContextMenuPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginContextMenu>();
+    }
+}
+
+impl DefaultPluginFactory for ContextMenuPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.context-menu", "Context Menu")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(ContextMenuPluginMainThread {
+            host,
+            performed: Vec::new(),
+            host_menu: ContextMenu::new(),
+        })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    performed: Vec<(ContextMenuTarget, ClapId)>,
+    popups: Vec<(ContextMenuTarget, i32, i32, i32)>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostContextMenuImpl for MyHostMainThread {
+    fn populate(
+        &mut self,
+        _target: ContextMenuTarget,
+        builder: &ContextMenuBuilder,
+    ) -> Result<(), HostError> {
+        if !builder.supports(ContextMenuItemKind::Entry) {
+            return Err(HostError::Message("Entries are not supported"));
+        }
+
+        builder.add_item(ContextMenuItem::Entry {
+            label: c"Show in browser",
+            is_enabled: true,
+            action_id: HOST_ACTION,
+        })?;
+
+        Ok(())
+    }
+
+    fn perform(&mut self, target: ContextMenuTarget, action_id: ClapId) -> Result<(), HostError> {
+        self.performed.push((target, action_id));
+        Ok(())
+    }
+
+    fn can_popup(&mut self) -> bool {
+        true
+    }
+
+    fn popup(
+        &mut self,
+        target: ContextMenuTarget,
+        screen_index: i32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), HostError> {
+        self.popups.push((target, screen_index, x, y));
+        Ok(())
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostContextMenu>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle =
+        PluginBundle::load_from_clack::<SinglePluginEntry<ContextMenuPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.context-menu",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn host_can_record_plugin_menu() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let context_menu = plugin.get_extension::<PluginContextMenu>().unwrap();
+
+    let mut menu = ContextMenu::new();
+    context_menu
+        .populate(
+            &mut plugin,
+            ContextMenuTarget::Param(ClapId::new(5)),
+            &mut menu,
+        )
+        .unwrap();
+
+    assert!(menu.is_complete());
+    assert_eq!(
+        menu.items(),
+        &[
+            ContextMenuNode::Title {
+                title: c"Gain".to_owned(),
+                is_enabled: true
+            },
+            ContextMenuNode::Entry {
+                label: c"Reset".to_owned(),
+                is_enabled: true,
+                action_id: RESET_ACTION
+            },
+            ContextMenuNode::Separator,
+            ContextMenuNode::Submenu {
+                label: c"More".to_owned(),
+                is_enabled: true,
+                items: vec![ContextMenuNode::CheckEntry {
+                    label: c"Bypass".to_owned(),
+                    is_enabled: false,
+                    is_checked: true,
+                    action_id: BYPASS_ACTION
+                }]
+            }
+        ]
+    );
+
+    menu.clear();
+    context_menu
+        .populate(&mut plugin, ContextMenuTarget::Global, &mut menu)
+        .unwrap();
+    assert!(menu.is_empty());
+}
+
+#[test]
+pub fn host_can_perform_plugin_actions() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let context_menu = plugin.get_extension::<PluginContextMenu>().unwrap();
+    let target = ContextMenuTarget::Param(ClapId::new(5));
+
+    assert_eq!(
+        context_menu.perform(&mut plugin, target, RESET_ACTION),
+        Ok(())
+    );
+    assert_eq!(
+        context_menu.perform(&mut plugin, target, HOST_ACTION),
+        Err(ContextMenuError::Perform)
+    );
+}
+
+#[test]
+pub fn unbalanced_submenus_are_rejected() {
+    let mut menu = ContextMenu::new();
+
+    assert_eq!(
+        menu.add_item(ContextMenuItem::EndSubmenu),
+        Err(ContextMenuError::AddItem)
+    );
+
+    menu.add_item(ContextMenuItem::BeginSubmenu {
+        label: c"Open",
+        is_enabled: true,
+    })
+    .unwrap();
+
+    assert!(!menu.is_complete());
+    assert!(menu.is_empty());
+}
+
+#[test]
+pub fn plugin_can_use_host_menus() {
+    let mut instance = instantiate();
+
+    instance.call_on_main_thread_callback();
+
+    let target = ContextMenuTarget::Param(ClapId::new(5));
+    instance.access_handler(|h| {
+        assert_eq!(h.performed, [(ContextMenuTarget::Global, HOST_ACTION)]);
+        assert_eq!(h.popups, [(target, 0, 12, 34)]);
+    });
+}
@@ -0,0 +1,190 @@
+use clack_extensions::draft::gain_adjustment_metering::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+
+const CEILING: f32 = 0.5;
+
+pub struct LimiterPlugin;
+pub struct LimiterPluginMainThread;
+
+impl<'a> PluginMainThread<'a, ()> for LimiterPluginMainThread {}
+
+pub struct LimiterPluginAudioProcessor {
+    adjustment: f64,
+}
+
+impl<'a> PluginAudioProcessor<'a, (), LimiterPluginMainThread> for LimiterPluginAudioProcessor {
+    fn activate(
+        _host: HostAudioProcessorHandle<'a>,
+        _main_thread: &mut LimiterPluginMainThread,
+        _shared: &'a (),
+        _audio_config: PluginAudioConfiguration,
+    ) -> Result<Self, PluginError> {
+        Ok(Self { adjustment: 0.0 })
+    }
+
+    fn process(
+        &mut self,
+        _process: Process,
+        mut audio: Audio,
+        _events: Events,
+    ) -> Result<ProcessStatus, PluginError> {
+        let mut port_pair = audio
+            .port_pair(0)
+            .ok_or(PluginError::Message("No input/output ports found"))?;
+
+        let mut channels = port_pair
+            .channels()?
+            .into_f32()
+            .ok_or(PluginError::Message("Expected f32 input/output"))?;
+
+        for pair in channels.iter_mut() {
+            let ChannelPair::InputOutput(input, output) = pair else {
+                continue;
+            };
+
+            for (input, output) in input.iter().zip(output.iter_mut()) {
+                *output = input.clamp(-CEILING, CEILING);
+                self.adjustment = if input.abs() > CEILING {
+                    20.0 * (CEILING / input.abs()).log10() as f64
+                } else {
+                    0.0
+                };
+            }
+        }
+
+        Ok(ProcessStatus::Continue)
+    }
+}
+
+impl PluginGainAdjustmentMeteringImpl for LimiterPluginAudioProcessor {
+    fn get_adjustment(&mut self) -> f64 {
+        self.adjustment
+    }
+}
+
+impl Plugin for LimiterPlugin {
+    type AudioProcessor<'a> = LimiterPluginAudioProcessor;
+    type Shared<'a> = ();
+    type MainThread<'a> = LimiterPluginMainThread;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginGainAdjustmentMetering>();
+    }
+}
+
+impl DefaultPluginFactory for LimiterPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.gain-adjustment-metering", "Limiter")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(LimiterPluginMainThread)
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+struct MyHostMainThread;
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+}
+
+#[test]
+pub fn host_reads_limiter_gain_reduction() {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<LimiterPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    let mut instance = PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread,
+        &bundle,
+        c"org.rust-audio.clack.gain-adjustment-metering",
+        &host_info,
+    )
+    .unwrap();
+
+    let metering = instance
+        .plugin_handle()
+        .get_extension::<PluginGainAdjustmentMetering>()
+        .unwrap();
+
+    let config = PluginAudioConfiguration {
+        sample_rate: 48_000.0,
+        min_frames_count: 4,
+        max_frames_count: 4,
+    };
+
+    let processor = instance.activate(|_, _| (), config).unwrap();
+    let mut processor = processor.start_processing().unwrap();
+
+    // Nothing was processed yet
+    assert_eq!(metering.get_adjustment(&mut processor.plugin_handle()), 0.0);
+
+    let mut input_ports = AudioPorts::with_capacity(1, 1);
+    let mut output_ports = AudioPorts::with_capacity(1, 1);
+
+    let mut process = |samples: [f32; 4]| {
+        let mut input_buffer = samples;
+        let mut output_buffer = [0.0f32; 4];
+
+        let input = input_ports.with_input_buffers([AudioPortBuffer {
+            latency: 0,
+            channels: AudioPortBufferType::f32_input_only([InputChannel::variable(
+                &mut input_buffer,
+            )]),
+        }]);
+        let mut output = output_ports.with_output_buffers([AudioPortBuffer {
+            latency: 0,
+            channels: AudioPortBufferType::f32_output_only([output_buffer.as_mut_slice()]),
+        }]);
+
+        processor
+            .process(
+                &input,
+                &mut output,
+                &InputEvents::empty(),
+                &mut OutputEvents::void(),
+                None,
+                None,
+            )
+            .unwrap();
+
+        let adjustment = metering.get_adjustment(&mut processor.plugin_handle());
+        (output_buffer, adjustment)
+    };
+
+    // The adjustment is the one applied to the last sample of the block
+    let (output, adjustment) = process([0.1, 2.0, 0.2, 1.0]);
+    assert_eq!(output, [0.1, 0.5, 0.2, 0.5]);
+    assert!((adjustment - -6.0206).abs() < 1e-3);
+
+    let (output, adjustment) = process([1.0, 0.2, -0.3, 0.4]);
+    assert_eq!(output, [0.5, 0.2, -0.3, 0.4]);
+    assert_eq!(adjustment, 0.0);
+}
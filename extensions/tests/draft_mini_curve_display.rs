@@ -0,0 +1,216 @@
+use clack_extensions::draft::mini_curve_display::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::fmt::Write;
+
+pub struct EqPlugin;
+pub struct EqPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+    is_observed: bool,
+    hints: Option<MiniCurveDisplayHints>,
+    unit: String,
+}
+
+impl<'a> PluginMainThread<'a, ()> for EqPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let display = self.host.get_extension::<HostMiniCurveDisplay>().unwrap();
+
+        self.hints = display.get_hints(&mut self.host);
+        display.set_dynamic(&mut self.host, false);
+
+        // Switch to a unit name that can't fit in the host's buffer
+        self.unit = "decibels relative to full scale ".repeat(10);
+        display.changed(
+            &mut self.host,
+            MiniCurveDisplayChangeFlags::CURVE_CHANGED
+                | MiniCurveDisplayChangeFlags::AXIS_NAME_CHANGED,
+        );
+    }
+}
+
+impl PluginMiniCurveDisplayImpl for EqPluginMainThread<'_> {
+    fn render(&mut self, curve: &mut MiniCurveWriter) -> bool {
+        if !self.is_observed || curve.is_empty() {
+            return false;
+        }
+
+        // A simple ramp, scaled to the host's vertical range if any
+        let y_max = self.hints.map_or(1.0, |h| h.y_max);
+        curve.fill_with(|x| x / y_max);
+        true
+    }
+
+    fn set_observed(&mut self, is_observed: bool) {
+        self.is_observed = is_observed;
+    }
+
+    fn get_axis_name(
+        &mut self,
+        x_name: &mut MiniCurveAxisNameWriter,
+        y_name: &mut MiniCurveAxisNameWriter,
+    ) -> std::fmt::Result {
+        x_name.write_str("Frequency")?;
+        write!(y_name, "Gain ({})", self.unit)
+    }
+}
+
+impl Plugin for EqPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = EqPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginMiniCurveDisplay>();
+    }
+}
+
+impl DefaultPluginFactory for EqPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.mini-curve-display", "EQ")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(EqPluginMainThread {
+            host,
+            is_observed: false,
+            hints: None,
+            unit: "dB".into(),
+        })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    is_dynamic: Option<bool>,
+    changes: Vec<MiniCurveDisplayChangeFlags>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostMiniCurveDisplayImpl for MyHostMainThread {
+    fn get_hints(&mut self) -> Option<MiniCurveDisplayHints> {
+        Some(MiniCurveDisplayHints {
+            x_min: 20.0,
+            x_max: 20_000.0,
+            y_min: -24.0,
+            y_max: 2.0,
+        })
+    }
+
+    fn set_dynamic(&mut self, is_dynamic: bool) {
+        self.is_dynamic = Some(is_dynamic);
+    }
+
+    fn changed(&mut self, flags: MiniCurveDisplayChangeFlags) {
+        self.changes.push(flags);
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostMiniCurveDisplay>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<EqPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.mini-curve-display",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn host_renders_observed_curve() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let display = plugin.get_extension::<PluginMiniCurveDisplay>().unwrap();
+
+    let mut values = [0u16; 5];
+    assert!(!display.render(&mut plugin, &mut values));
+
+    display.set_observed(&mut plugin, true);
+    assert!(display.render(&mut plugin, &mut values));
+    assert_eq!(values, [0, 16384, 32768, 49151, u16::MAX]);
+
+    assert!(!display.render(&mut plugin, &mut []));
+
+    display.set_observed(&mut plugin, false);
+    assert!(!display.render(&mut plugin, &mut values));
+}
+
+#[test]
+pub fn host_retrieves_axis_names() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let display = plugin.get_extension::<PluginMiniCurveDisplay>().unwrap();
+
+    assert_eq!(
+        display.get_axis_name(&mut plugin),
+        Some(MiniCurveAxisNames {
+            x_name: "Frequency".into(),
+            y_name: "Gain (dB)".into()
+        })
+    );
+
+    // Names that don't fit are not truncated, but rejected
+    instance.call_on_main_thread_callback();
+    let mut plugin = instance.plugin_handle();
+    assert_eq!(display.get_axis_name(&mut plugin), None);
+}
+
+#[test]
+pub fn plugin_uses_hints_and_notifies_changes() {
+    let mut instance = instantiate();
+    instance.call_on_main_thread_callback();
+
+    instance.access_handler(|h| {
+        assert_eq!(h.is_dynamic, Some(false));
+        assert_eq!(
+            h.changes,
+            [MiniCurveDisplayChangeFlags::CURVE_CHANGED
+                | MiniCurveDisplayChangeFlags::AXIS_NAME_CHANGED]
+        );
+    });
+
+    let mut plugin = instance.plugin_handle();
+    let display = plugin.get_extension::<PluginMiniCurveDisplay>().unwrap();
+    display.set_observed(&mut plugin, true);
+
+    // The curve is now scaled to the host's vertical range
+    let mut values = [0u16; 3];
+    assert!(display.render(&mut plugin, &mut values));
+    assert_eq!(values, [0, 16384, 32768]);
+}
@@ -0,0 +1,90 @@
+use clack_extensions::draft::plugin_invalidation::*;
+use clack_host::prelude::*;
+use clack_plugin::entry::{Entry, EntryFactories, EntryLoadError};
+use std::ffi::CStr;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+struct ScriptsInvalidationFactory {
+    sources: Vec<InvalidationSource>,
+    refresh_count: AtomicU32,
+}
+
+impl PluginInvalidationFactoryImpl for ScriptsInvalidationFactory {
+    fn source_count(&self) -> u32 {
+        self.sources.len() as u32
+    }
+
+    fn source(&self, index: u32) -> Option<&InvalidationSource> {
+        self.sources.get(index as usize)
+    }
+
+    fn refresh(&self) -> bool {
+        // Only the first refresh succeeds, the host has to reload the bundle afterwards
+        self.refresh_count.fetch_add(1, Ordering::Relaxed) == 0
+    }
+}
+
+struct MyEntry {
+    invalidation_factory: PluginInvalidationFactoryWrapper<ScriptsInvalidationFactory>,
+}
+
+impl Entry for MyEntry {
+    fn new(_bundle_path: &CStr) -> Result<Self, EntryLoadError> {
+        Ok(Self {
+            invalidation_factory: PluginInvalidationFactoryWrapper::new(
+                ScriptsInvalidationFactory {
+                    sources: vec![
+                        InvalidationSource::new(Path::new("/opt/my-synth/scripts"), "*.lua")
+                            .with_recursive_scan(true),
+                        InvalidationSource::new(Path::new("/opt/my-synth"), "plugins.json"),
+                    ],
+                    refresh_count: AtomicU32::new(0),
+                },
+            ),
+        })
+    }
+
+    fn declare_factories<'a>(&'a self, builder: &mut EntryFactories<'a>) {
+        builder.register_factory(&self.invalidation_factory);
+    }
+}
+
+#[test]
+pub fn host_lists_invalidation_sources() {
+    let bundle = PluginBundle::load_from_clack::<MyEntry>(c"").unwrap();
+    let factory = bundle.get_factory::<PluginInvalidationFactory>().unwrap();
+
+    assert_eq!(factory.source_count(), 2);
+
+    let scripts = factory.get_source(0).unwrap();
+    assert_eq!(scripts.directory(), Some(c"/opt/my-synth/scripts"));
+    assert_eq!(
+        scripts.directory_path(),
+        Some(Path::new("/opt/my-synth/scripts"))
+    );
+    assert_eq!(scripts.filename_glob(), Some(c"*.lua"));
+    assert!(scripts.recursive_scan());
+
+    assert!(factory.get_source(2).is_none());
+
+    let globs: Vec<_> = factory.sources().map(|s| s.filename_glob()).collect();
+    assert_eq!(globs, [Some(c"*.lua"), Some(c"plugins.json")]);
+
+    let non_recursive: Vec<_> = factory
+        .sources()
+        .rev()
+        .filter(|s| !s.recursive_scan())
+        .collect();
+    assert_eq!(non_recursive.len(), 1);
+    assert_eq!(non_recursive[0].directory(), Some(c"/opt/my-synth"));
+}
+
+#[test]
+pub fn host_refreshes_bundle() {
+    let bundle = PluginBundle::load_from_clack::<MyEntry>(c"").unwrap();
+    let factory = bundle.get_factory::<PluginInvalidationFactory>().unwrap();
+
+    assert!(factory.refresh());
+    assert!(!factory.refresh());
+}
@@ -0,0 +1,167 @@
+use clack_common::stream::{InputStream, OutputStream};
+use clack_common::utils::{ClapId, UniversalPluginId};
+use clack_extensions::draft::plugin_state_converter::*;
+use clack_host::prelude::*;
+use clack_plugin::entry::{Entry, EntryFactories, EntryLoadError};
+use clack_plugin::prelude::PluginError;
+use std::ffi::CStr;
+use std::io::{Read, Write};
+
+const GAIN_PARAM_ID: ClapId = ClapId::new(1);
+const NEW_GAIN_PARAM_ID: ClapId = ClapId::new(42);
+
+/// Converts the state of a plugin storing its gain as a u8 percentage, to a plugin storing it as
+/// a little-endian f32.
+struct GainConverter;
+
+impl StateConverterImpl for GainConverter {
+    fn convert_state(
+        &mut self,
+        src: &mut InputStream,
+        dst: &mut OutputStream,
+    ) -> Result<(), PluginError> {
+        let mut percentage = [0u8; 1];
+        src.read_exact(&mut percentage)?;
+
+        if percentage[0] > 100 {
+            return Err(PluginError::Message("Gain out of range"));
+        }
+
+        let gain = percentage[0] as f32 / 100.0;
+        dst.write_all(&gain.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn convert_normalized_value(
+        &mut self,
+        src_param_id: ClapId,
+        src_normalized_value: f64,
+    ) -> Option<(ClapId, f64)> {
+        (src_param_id == GAIN_PARAM_ID).then_some((NEW_GAIN_PARAM_ID, src_normalized_value))
+    }
+
+    fn convert_plain_value(
+        &mut self,
+        src_param_id: ClapId,
+        src_plain_value: f64,
+    ) -> Option<(ClapId, f64)> {
+        (src_param_id == GAIN_PARAM_ID).then_some((NEW_GAIN_PARAM_ID, src_plain_value / 100.0))
+    }
+}
+
+struct GainConverterFactory {
+    descriptor: StateConverterDescriptor,
+}
+
+impl PluginStateConverterFactoryImpl for GainConverterFactory {
+    fn converter_count(&self) -> u32 {
+        1
+    }
+
+    fn converter_descriptor(&self, index: u32) -> Option<&StateConverterDescriptor> {
+        (index == 0).then_some(&self.descriptor)
+    }
+
+    fn create_converter(&self, converter_id: &CStr) -> Option<StateConverterInstance<'_>> {
+        if Some(converter_id) == self.descriptor.id() {
+            Some(StateConverterInstance::new(&self.descriptor, GainConverter))
+        } else {
+            None
+        }
+    }
+}
+
+struct MyEntry {
+    converter_factory: PluginStateConverterFactoryWrapper<GainConverterFactory>,
+}
+
+impl Entry for MyEntry {
+    fn new(_bundle_path: &CStr) -> Result<Self, EntryLoadError> {
+        Ok(Self {
+            converter_factory: PluginStateConverterFactoryWrapper::new(GainConverterFactory {
+                descriptor: StateConverterDescriptor::new(
+                    "org.rust-audio.clack.gain-v1-to-v2",
+                    "Gain v1 to v2",
+                    UniversalPluginId::clap(c"org.rust-audio.clack.gain"),
+                    UniversalPluginId::clap(c"org.rust-audio.clack.gain-v2"),
+                )
+                .with_vendor("Clack")
+                .with_version("1.0.0"),
+            }),
+        })
+    }
+
+    fn declare_factories<'a>(&'a self, builder: &mut EntryFactories<'a>) {
+        builder.register_factory(&self.converter_factory);
+    }
+}
+
+#[test]
+pub fn host_lists_converters() {
+    let bundle = PluginBundle::load_from_clack::<MyEntry>(c"").unwrap();
+    let factory = bundle.get_factory::<PluginStateConverterFactory>().unwrap();
+
+    assert_eq!(factory.converter_count(), 1);
+    assert!(factory.get_descriptor(1).is_none());
+
+    let descriptors: Vec<_> = factory.descriptors().collect();
+    assert_eq!(descriptors.len(), 1);
+
+    let descriptor = descriptors[0];
+    assert_eq!(descriptor.id(), Some(c"org.rust-audio.clack.gain-v1-to-v2"));
+    assert_eq!(descriptor.name(), Some(c"Gain v1 to v2"));
+    assert_eq!(descriptor.vendor(), Some(c"Clack"));
+    assert_eq!(descriptor.version(), Some(c"1.0.0"));
+    assert_eq!(descriptor.description(), None);
+    assert_eq!(
+        descriptor.src_plugin_id(),
+        Some(UniversalPluginId::clap(c"org.rust-audio.clack.gain"))
+    );
+    assert_eq!(
+        descriptor.dst_plugin_id(),
+        Some(UniversalPluginId::clap(c"org.rust-audio.clack.gain-v2"))
+    );
+}
+
+#[test]
+pub fn host_converts_state_and_values() {
+    let bundle = PluginBundle::load_from_clack::<MyEntry>(c"").unwrap();
+    let factory = bundle.get_factory::<PluginStateConverterFactory>().unwrap();
+
+    assert_eq!(
+        factory.create(c"org.rust-audio.clack.unknown").err(),
+        Some(StateConverterError::CreationFailed)
+    );
+
+    let mut converter = factory
+        .create(c"org.rust-audio.clack.gain-v1-to-v2")
+        .unwrap();
+    assert_eq!(
+        converter.descriptor().and_then(|d| d.id()),
+        Some(c"org.rust-audio.clack.gain-v1-to-v2")
+    );
+
+    let mut converted = Vec::new();
+    converter
+        .convert_state(&mut [50u8].as_slice(), &mut converted)
+        .unwrap();
+    assert_eq!(converted, 0.5f32.to_le_bytes());
+
+    let error = converter
+        .convert_state(&mut [200u8].as_slice(), &mut Vec::new())
+        .unwrap_err();
+    let StateConverterError::ConversionFailed(message) = error else {
+        panic!("Unexpected error: {error}");
+    };
+    assert!(message.contains("Gain out of range"));
+
+    assert_eq!(
+        converter.convert_normalized_value(GAIN_PARAM_ID, 0.25),
+        Some((NEW_GAIN_PARAM_ID, 0.25))
+    );
+    assert_eq!(
+        converter.convert_plain_value(GAIN_PARAM_ID, 25.0),
+        Some((NEW_GAIN_PARAM_ID, 0.25))
+    );
+    assert_eq!(converter.convert_plain_value(ClapId::new(7), 25.0), None);
+}
@@ -0,0 +1,174 @@
+use clack_common::utils::Color;
+use clack_extensions::draft::project_location::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::ffi::CString;
+use std::sync::Mutex;
+
+const TRACK_COLOR: Color = Color {
+    alpha: 255,
+    red: 200,
+    green: 40,
+    blue: 10,
+};
+
+#[derive(Debug, PartialEq)]
+struct OwnedElement {
+    kind: Option<LocationKind>,
+    index_in_group: u32,
+    id: Option<CString>,
+    name: Option<CString>,
+    color: Option<(u8, u8, u8, u8)>,
+}
+
+// The last location received by the plugin, for the host to inspect
+static LOCATION: Mutex<Vec<OwnedElement>> = Mutex::new(Vec::new());
+
+pub struct LocationPlugin;
+pub struct LocationPluginMainThread;
+
+impl<'a> PluginMainThread<'a, ()> for LocationPluginMainThread {}
+
+impl PluginProjectLocationImpl for LocationPluginMainThread {
+    fn set_location(&mut self, path: &[LocationElement]) {
+        *LOCATION.lock().unwrap() = path
+            .iter()
+            .map(|e| OwnedElement {
+                kind: e.kind(),
+                index_in_group: e.index_in_group(),
+                id: e.id().map(Into::into),
+                name: e.name().map(Into::into),
+                color: e.color().map(|c| (c.alpha, c.red, c.green, c.blue)),
+            })
+            .collect();
+    }
+}
+
+impl Plugin for LocationPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = LocationPluginMainThread;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginProjectLocation>();
+    }
+}
+
+impl DefaultPluginFactory for LocationPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.project-location", "Location")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(LocationPluginMainThread)
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+struct MyHostMainThread;
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+}
+
+#[test]
+pub fn plugin_receives_its_location() {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<LocationPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    let mut instance = PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread,
+        &bundle,
+        c"org.rust-audio.clack.project-location",
+        &host_info,
+    )
+    .unwrap();
+
+    let mut plugin = instance.plugin_handle();
+    let location = plugin.get_extension::<PluginProjectLocation>().unwrap();
+
+    location.set_location(
+        &mut plugin,
+        &[
+            LocationElement::new(LocationKind::Project).with_name(Some(c"My Song")),
+            LocationElement::new(LocationKind::Track)
+                .with_id(Some(c"track-42"))
+                .with_index_in_group(3)
+                .with_name(Some(c"Bass"))
+                .with_color(Some(TRACK_COLOR)),
+            LocationElement::new(LocationKind::Device).with_index_in_group(1),
+        ],
+    );
+
+    assert_eq!(
+        *LOCATION.lock().unwrap(),
+        [
+            OwnedElement {
+                kind: Some(LocationKind::Project),
+                index_in_group: 0,
+                id: None,
+                name: Some(c"My Song".into()),
+                color: None,
+            },
+            OwnedElement {
+                kind: Some(LocationKind::Track),
+                index_in_group: 3,
+                id: Some(c"track-42".into()),
+                name: Some(c"Bass".into()),
+                color: Some((255, 200, 40, 10)),
+            },
+            OwnedElement {
+                kind: Some(LocationKind::Device),
+                index_in_group: 1,
+                id: None,
+                name: None,
+                color: None,
+            },
+        ]
+    );
+
+    location.set_location(&mut plugin, &[]);
+    assert!(LOCATION.lock().unwrap().is_empty());
+}
+
+#[test]
+pub fn location_kinds_round_trip() {
+    for kind in [
+        LocationKind::Project,
+        LocationKind::TrackGroup,
+        LocationKind::Track,
+        LocationKind::Device,
+        LocationKind::NestedDeviceChain,
+    ] {
+        assert_eq!(LocationKind::from_raw(kind.to_raw()), Some(kind));
+    }
+
+    assert_eq!(LocationKind::from_raw(0), None);
+    assert_eq!(LocationKind::from_raw(42), None);
+}
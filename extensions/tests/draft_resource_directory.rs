@@ -0,0 +1,239 @@
+use clack_extensions::draft::resource_directory::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::path::{Path, PathBuf};
+
+const SAMPLES: &[(&str, &[u8])] = &[("kick.wav", b"RIFF kick"), ("snare.wav", b"RIFF snare")];
+const FACTORY_SAMPLE: (&str, &[u8]) = ("factory-hat.wav", b"RIFF hat");
+
+pub struct ResourcePlugin;
+pub struct ResourcePluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+    shared_directory: Option<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl<'a> PluginMainThread<'a, ()> for ResourcePluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let resource_directory = self.host.get_extension::<HostResourceDirectory>().unwrap();
+
+        assert!(resource_directory.request_directory(&mut self.host, true));
+        assert!(!resource_directory.request_directory(&mut self.host, false));
+        resource_directory.release_directory(&mut self.host, false);
+    }
+}
+
+impl PluginResourceDirectoryImpl for ResourcePluginMainThread<'_> {
+    fn set_directory(&mut self, path: Option<&Path>, is_shared: bool) {
+        if is_shared {
+            self.shared_directory = path.map(Path::to_path_buf);
+            self.files.clear();
+        }
+    }
+
+    fn collect(&mut self, all: bool) {
+        let Some(directory) = &self.shared_directory else {
+            return;
+        };
+
+        let factory = all.then_some(&FACTORY_SAMPLE);
+        self.files.clear();
+
+        for (name, data) in SAMPLES.iter().chain(factory) {
+            let path = directory.join(name);
+            std::fs::write(&path, data).unwrap();
+            self.files.push(path);
+        }
+    }
+
+    fn get_files_count(&mut self) -> u32 {
+        self.files.len() as u32
+    }
+
+    fn get_file_path(&mut self, index: u32) -> Option<&Path> {
+        self.files.get(index as usize).map(PathBuf::as_path)
+    }
+}
+
+impl Plugin for ResourcePlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = ResourcePluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginResourceDirectory>();
+    }
+}
+
+impl DefaultPluginFactory for ResourcePlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.resource-directory", "Resources")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(ResourcePluginMainThread {
+            host,
+            shared_directory: None,
+            files: Vec::new(),
+        })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    requested: Vec<bool>,
+    released: Vec<bool>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostResourceDirectoryImpl for MyHostMainThread {
+    fn request_directory(&mut self, is_shared: bool) -> bool {
+        self.requested.push(is_shared);
+        is_shared
+    }
+
+    fn release_directory(&mut self, is_shared: bool) {
+        self.released.push(is_shared);
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostResourceDirectory>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<ResourcePlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.resource-directory",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn plugin_collects_files_into_shared_directory() {
+    let directory = std::env::temp_dir().join(format!(
+        "clack-resource-directory-test-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let resource_directory = plugin.get_extension::<PluginResourceDirectory>().unwrap();
+
+    resource_directory
+        .set_directory(&mut plugin, Some(&directory), true)
+        .unwrap();
+
+    resource_directory.collect(&mut plugin, false);
+    assert_eq!(resource_directory.get_files_count(&mut plugin), 2);
+
+    let paths = resource_directory.get_file_paths(&mut plugin);
+    assert_eq!(paths.len(), 2);
+
+    for (path, (name, data)) in paths.iter().zip(SAMPLES) {
+        assert_eq!(path, &directory.join(name));
+        assert_eq!(std::fs::read(path).unwrap(), *data);
+    }
+
+    resource_directory.collect(&mut plugin, true);
+    assert_eq!(resource_directory.get_files_count(&mut plugin), 3);
+    assert_eq!(
+        resource_directory.get_file_path(&mut plugin, 2),
+        Some(directory.join(FACTORY_SAMPLE.0))
+    );
+    assert_eq!(resource_directory.get_file_path(&mut plugin, 3), None);
+
+    resource_directory
+        .set_directory(&mut plugin, None, true)
+        .unwrap();
+    assert_eq!(resource_directory.get_files_count(&mut plugin), 0);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+pub fn long_file_paths_are_fully_retrieved() {
+    let directory = std::env::temp_dir().join(format!(
+        "clack-resource-directory-test-long-{}",
+        std::process::id()
+    ));
+
+    // Long enough not to fit in the host's initial buffer
+    let nested = directory.join("nested/".repeat(150));
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let resource_directory = plugin.get_extension::<PluginResourceDirectory>().unwrap();
+
+    resource_directory
+        .set_directory(&mut plugin, Some(&nested), true)
+        .unwrap();
+    resource_directory.collect(&mut plugin, false);
+
+    assert_eq!(
+        resource_directory.get_file_path(&mut plugin, 0),
+        Some(nested.join(SAMPLES[0].0))
+    );
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+pub fn invalid_paths_are_rejected() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let resource_directory = plugin.get_extension::<PluginResourceDirectory>().unwrap();
+
+    assert_eq!(
+        resource_directory.set_directory(&mut plugin, Some(Path::new("invalid\0path")), true),
+        Err(InvalidResourcePathError)
+    );
+}
+
+#[test]
+pub fn host_receives_directory_requests() {
+    let mut instance = instantiate();
+    instance.call_on_main_thread_callback();
+
+    instance.access_handler(|h| {
+        assert_eq!(h.requested, [true, false]);
+        assert_eq!(h.released, [false]);
+    });
+}
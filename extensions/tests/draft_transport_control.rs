@@ -0,0 +1,182 @@
+use clack_common::utils::BeatTime;
+use clack_extensions::draft::transport_control::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+
+pub struct LooperPlugin;
+pub struct LooperPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+}
+
+impl<'a> PluginMainThread<'a, ()> for LooperPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let transport = self.host.get_extension::<HostTransportControl>().unwrap();
+        let host = &mut self.host;
+
+        // Record a 4-bar loop, starting at bar 2
+        transport.request_stop(host);
+        transport.request_loop_region(host, BeatTime::from_int(4), BeatTime::from_int(16));
+        transport.request_enable_loop(host, true);
+        transport.request_jump(host, BeatTime::from_float(4.0));
+        transport.request_record(host, true);
+        transport.request_start(host);
+
+        transport.request_pause(host);
+        transport.request_continue(host);
+        transport.request_toggle_play(host);
+        transport.request_toggle_loop(host);
+        transport.request_toggle_record(host);
+    }
+}
+
+impl Plugin for LooperPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = LooperPluginMainThread<'a>;
+}
+
+impl DefaultPluginFactory for LooperPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.transport-control", "Looper")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(LooperPluginMainThread { host })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Request {
+    Start,
+    Stop,
+    Continue,
+    Pause,
+    TogglePlay,
+    Jump(BeatTime),
+    LoopRegion(BeatTime, BeatTime),
+    ToggleLoop,
+    EnableLoop(bool),
+    Record(bool),
+    ToggleRecord,
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    requests: Vec<Request>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostTransportControlImpl for MyHostMainThread {
+    fn request_start(&mut self) {
+        self.requests.push(Request::Start);
+    }
+
+    fn request_stop(&mut self) {
+        self.requests.push(Request::Stop);
+    }
+
+    fn request_continue(&mut self) {
+        self.requests.push(Request::Continue);
+    }
+
+    fn request_pause(&mut self) {
+        self.requests.push(Request::Pause);
+    }
+
+    fn request_toggle_play(&mut self) {
+        self.requests.push(Request::TogglePlay);
+    }
+
+    fn request_jump(&mut self, position: BeatTime) {
+        self.requests.push(Request::Jump(position));
+    }
+
+    fn request_loop_region(&mut self, start: BeatTime, duration: BeatTime) {
+        self.requests.push(Request::LoopRegion(start, duration));
+    }
+
+    fn request_toggle_loop(&mut self) {
+        self.requests.push(Request::ToggleLoop);
+    }
+
+    fn request_enable_loop(&mut self, is_enabled: bool) {
+        self.requests.push(Request::EnableLoop(is_enabled));
+    }
+
+    fn request_record(&mut self, is_recording: bool) {
+        self.requests.push(Request::Record(is_recording));
+    }
+
+    fn request_toggle_record(&mut self) {
+        self.requests.push(Request::ToggleRecord);
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostTransportControl>();
+    }
+}
+
+#[test]
+pub fn host_receives_transport_requests() {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<LooperPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    let mut instance = PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.transport-control",
+        &host_info,
+    )
+    .unwrap();
+
+    instance.call_on_main_thread_callback();
+
+    instance.access_handler(|h| {
+        assert_eq!(
+            h.requests,
+            [
+                Request::Stop,
+                Request::LoopRegion(BeatTime::from_int(4), BeatTime::from_int(16)),
+                Request::EnableLoop(true),
+                Request::Jump(BeatTime::from_int(4)),
+                Request::Record(true),
+                Request::Start,
+                Request::Pause,
+                Request::Continue,
+                Request::TogglePlay,
+                Request::ToggleLoop,
+                Request::ToggleRecord,
+            ]
+        )
+    });
+}
@@ -0,0 +1,204 @@
+use clack_common::events::event_types::NoteOnEvent;
+use clack_common::events::io::EventBuffer;
+use clack_common::events::spaces::EventSpaceId;
+use clack_common::events::{Match, Pckn};
+use clack_common::utils::Cookie;
+use clack_extensions::draft::triggers::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+
+const RETRIG_ID: ClapId = ClapId::new(7);
+const RESET_ID: ClapId = ClapId::new(12);
+
+pub struct TriggersPlugin;
+pub struct TriggersPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+}
+
+impl<'a> PluginMainThread<'a, ()> for TriggersPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let triggers = self.host.get_extension::<HostTriggers>().unwrap();
+        triggers.rescan(&mut self.host, TriggerRescanFlags::INFO);
+        triggers.clear(&mut self.host, RESET_ID, TriggerClearFlags::AUTOMATIONS);
+    }
+}
+
+impl PluginTriggersImpl for TriggersPluginMainThread<'_> {
+    fn count(&mut self) -> u32 {
+        2
+    }
+
+    fn get_info(&mut self, trigger_index: u32, writer: &mut TriggerInfoWriter) {
+        match trigger_index {
+            0 => writer.set(&TriggerInfo {
+                id: RETRIG_ID,
+                flags: TriggerInfoFlags::IS_AUTOMATABLE_PER_KEY,
+                cookie: Cookie::empty(),
+                name: b"Retrig",
+                module: b"Envelopes/Amp",
+            }),
+            1 => writer.set(&TriggerInfo {
+                id: RESET_ID,
+                flags: TriggerInfoFlags::empty(),
+                cookie: Cookie::empty(),
+                name: b"Reset",
+                module: b"",
+            }),
+            _ => {}
+        }
+    }
+}
+
+impl Plugin for TriggersPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = TriggersPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginTriggers>();
+    }
+}
+
+impl DefaultPluginFactory for TriggersPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.triggers", "Triggers")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(TriggersPluginMainThread { host })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    rescans: Vec<TriggerRescanFlags>,
+    clears: Vec<(ClapId, TriggerClearFlags)>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostTriggersImpl for MyHostMainThread {
+    fn rescan(&mut self, flags: TriggerRescanFlags) {
+        self.rescans.push(flags);
+    }
+
+    fn clear(&mut self, trigger_id: ClapId, flags: TriggerClearFlags) {
+        self.clears.push((trigger_id, flags));
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostTriggers>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<TriggersPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.triggers",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn can_query_trigger_info() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let triggers = plugin.get_extension::<PluginTriggers>().unwrap();
+
+    assert_eq!(triggers.count(&mut plugin), 2);
+
+    let mut buffer = TriggerInfoBuffer::new();
+    let info = triggers.get_info(&mut plugin, 0, &mut buffer).unwrap();
+    assert_eq!(info.id, RETRIG_ID);
+    assert_eq!(info.flags, TriggerInfoFlags::IS_AUTOMATABLE_PER_KEY);
+    assert_eq!(info.name, b"Retrig");
+    assert_eq!(info.module, b"Envelopes/Amp");
+
+    let info = triggers.get_info(&mut plugin, 1, &mut buffer).unwrap();
+    assert_eq!(info.id, RESET_ID);
+    assert_eq!(info.name, b"Reset");
+
+    assert!(triggers.get_info(&mut plugin, 2, &mut buffer).is_none());
+}
+
+#[test]
+pub fn host_receives_rescan_and_clear_requests() {
+    let mut instance = instantiate();
+
+    instance.call_on_main_thread_callback();
+
+    instance.access_handler(|h| {
+        assert_eq!(h.rescans, [TriggerRescanFlags::INFO]);
+        assert_eq!(h.clears, [(RESET_ID, TriggerClearFlags::AUTOMATIONS)]);
+    });
+}
+
+#[test]
+pub fn trigger_events_match_their_own_space() {
+    // SAFETY: This is a test event space ID, only used with trigger events
+    let space_id = unsafe { EventSpaceId::new(0x4242).unwrap().into_unchecked() };
+    let pckn = Pckn::new(0u16, 1u16, 60u16, Match::All);
+
+    let mut buffer = EventBuffer::new();
+    buffer.push(&TriggerEvent::new(
+        space_id,
+        5,
+        RETRIG_ID,
+        pckn,
+        Cookie::empty(),
+    ));
+    buffer.push(&NoteOnEvent::new(10, pckn, 1.0));
+
+    let trigger = buffer.get(0).unwrap();
+    assert!(trigger.as_core_event().is_none());
+    assert!(trigger.as_event::<NoteOnEvent>().is_none());
+
+    let Some(TriggerEventSpace::Trigger(event)) = trigger.as_event_space(space_id) else {
+        panic!("Expected a trigger event");
+    };
+    assert_eq!(event.trigger_id(), Some(RETRIG_ID));
+    assert_eq!(event.pckn(), pckn);
+    assert_eq!(event.key(), Match::Specific(60));
+    assert_eq!(
+        trigger.as_event_for_space::<TriggerEvent>(space_id),
+        Some(event)
+    );
+
+    let note = buffer.get(1).unwrap();
+    assert!(note.as_event_space(space_id).is_none());
+    assert!(note.as_event_for_space::<TriggerEvent>(space_id).is_none());
+}
@@ -0,0 +1,484 @@
+use clack_common::events::event_types::{NoteExpressionEvent, NoteExpressionType, NoteOnEvent};
+use clack_common::events::io::EventBuffer;
+use clack_common::events::spaces::{CoreEventSpace, EventSpaceId};
+use clack_common::events::{Match, Pckn};
+use clack_extensions::draft::tuning::scala::*;
+use clack_extensions::draft::tuning::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::path::PathBuf;
+
+const JUST_SCALE: &str = "! just.scl
+!
+5-limit just intonation
+ 12
+!
+ 16/15
+ 9/8
+ 6/5
+ 5/4
+ 4/3
+ 45/32
+ 3/2
+ 8/5
+ 5/3
+ 9/5
+ 15/8
+ 2/1
+";
+
+// Key 61 is left unmapped
+const JUST_MAPPING: &str = "! just.kbm
+12
+0
+127
+60
+69
+440.0
+12
+! Mapping
+0
+x
+2
+3
+4
+5
+6
+7
+8
+9
+10
+11
+";
+
+const EQUAL_SCALE: &str = "12-TET
+12
+100.0
+200.
+300.0
+400.0
+500.0
+600.0
+700.0
+800.0
+900.0
+1000.0
+1100.0
+2/1
+";
+
+fn tuning_space_id() -> EventSpaceId<TuningEventSpace<'static>> {
+    // SAFETY: This is a test event space ID, only used with tuning events
+    unsafe { EventSpaceId::new(0x4242).unwrap().into_unchecked() }
+}
+
+fn just_table() -> TuningTable {
+    let scale = Scale::parse(JUST_SCALE).unwrap();
+    let mapping = KeyboardMapping::parse(JUST_MAPPING).unwrap();
+    TuningTable::new(scale.description(), &scale, &mapping).unwrap()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {expected}, got {actual}"
+    );
+}
+
+pub struct TuningPlugin;
+pub struct TuningPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+    just_tuning: Option<ClapId>,
+}
+
+impl<'a> PluginMainThread<'a, ()> for TuningPluginMainThread<'a> {}
+
+impl PluginTuningImpl for TuningPluginMainThread<'_> {
+    fn changed(&mut self) {
+        let tuning = self.host.get_extension::<HostTuning>().unwrap();
+        let mut buffer = TuningInfoBuffer::new();
+
+        self.just_tuning = None;
+
+        for index in 0..tuning.get_tuning_count(&mut self.host) {
+            let Some(info) = tuning.get_info(&mut self.host, index, &mut buffer) else {
+                continue;
+            };
+
+            if info.name == b"5-limit just intonation" && !info.is_dynamic {
+                self.just_tuning = Some(info.tuning_id);
+            }
+        }
+    }
+}
+
+pub struct TuningPluginAudioProcessor<'a> {
+    host: HostAudioProcessorHandle<'a>,
+    tuning: HostTuning,
+    channels: ChannelTunings,
+}
+
+impl<'a> PluginAudioProcessor<'a, (), TuningPluginMainThread<'a>>
+    for TuningPluginAudioProcessor<'a>
+{
+    fn activate(
+        host: HostAudioProcessorHandle<'a>,
+        main_thread: &mut TuningPluginMainThread<'a>,
+        _shared: &'a (),
+        _audio_config: PluginAudioConfiguration,
+    ) -> Result<Self, PluginError> {
+        if main_thread.just_tuning.is_none() {
+            return Err(PluginError::Message("Just intonation tuning not found"));
+        }
+
+        Ok(Self {
+            tuning: host
+                .get_extension()
+                .ok_or(PluginError::Message("No tuning"))?,
+            host,
+            channels: ChannelTunings::new(1),
+        })
+    }
+
+    fn process(
+        &mut self,
+        _process: Process,
+        _audio: Audio,
+        events: Events,
+    ) -> Result<ProcessStatus, PluginError> {
+        for event in events.input {
+            if let Some(TuningEventSpace::Tuning(e)) = event.as_event_space(tuning_space_id()) {
+                self.channels.apply(e);
+            }
+
+            // Report the tuning of each note that should be played
+            if let Some(CoreEventSpace::NoteOn(e)) = event.as_core_event() {
+                let (Some(&channel), Some(&key)) =
+                    (e.channel().as_specific(), e.key().as_specific())
+                else {
+                    continue;
+                };
+
+                let tuning = &self.tuning;
+                if !self
+                    .channels
+                    .should_play(tuning, &mut self.host, 0, channel, key)
+                {
+                    continue;
+                }
+
+                let time = e.header().time();
+                let relative =
+                    self.channels
+                        .get_relative(tuning, &mut self.host, 0, channel, key, time);
+
+                events.output.try_push(NoteExpressionEvent::new(
+                    time,
+                    e.pckn(),
+                    NoteExpressionType::Tuning,
+                    relative,
+                ))?;
+            }
+        }
+
+        Ok(ProcessStatus::Sleep)
+    }
+}
+
+impl Plugin for TuningPlugin {
+    type AudioProcessor<'a> = TuningPluginAudioProcessor<'a>;
+    type Shared<'a> = ();
+    type MainThread<'a> = TuningPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginTuning>();
+    }
+}
+
+impl DefaultPluginFactory for TuningPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.tuning", "Tuning")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(TuningPluginMainThread {
+            host,
+            just_tuning: None,
+        })
+    }
+}
+
+struct MyHost;
+struct MyHostShared {
+    tables: TuningTables,
+}
+struct MyHostMainThread<'a> {
+    shared: &'a MyHostShared,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl HostTuningImplShared for MyHostShared {
+    fn get_relative(
+        &self,
+        tuning_id: Option<ClapId>,
+        _channel: u16,
+        key: u16,
+        _sample_offset: u32,
+    ) -> f64 {
+        self.tables.get_relative(tuning_id, key)
+    }
+
+    fn should_play(&self, tuning_id: Option<ClapId>, _channel: u16, key: u16) -> bool {
+        self.tables.should_play(tuning_id, key)
+    }
+}
+
+impl<'a> MainThreadHandler<'a> for MyHostMainThread<'a> {}
+
+impl HostTuningImplMainThread for MyHostMainThread<'_> {
+    fn get_tuning_count(&mut self) -> u32 {
+        self.shared.tables.len() as u32
+    }
+
+    fn get_info(&mut self, tuning_index: u32, writer: &mut TuningInfoWriter) {
+        if let Some(info) = self.shared.tables.info(tuning_index) {
+            writer.set(&info);
+        }
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread<'a>;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostTuning>();
+    }
+}
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("clack-tuning-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+pub fn equal_temperament_is_not_retuned() {
+    let scale = Scale::parse(EQUAL_SCALE).unwrap();
+    assert_eq!(scale.description(), "12-TET");
+    assert_eq!(scale.len(), 12);
+    assert_close(scale.period(), 1200.0);
+
+    let table = TuningTable::new("12-TET", &scale, &KeyboardMapping::default()).unwrap();
+
+    for key in 0..128 {
+        assert_close(table.relative(key), 0.0);
+        assert!(table.should_play(key));
+    }
+
+    assert_close(table.frequency(69), 440.0);
+    assert_close(table.frequency(81), 880.0);
+    assert!(!table.should_play(128));
+}
+
+#[test]
+pub fn keyboard_mapping_retunes_keys() {
+    let table = just_table();
+    assert_eq!(table.name(), "5-limit just intonation");
+
+    // The reference key keeps its frequency
+    assert_close(table.relative(69), 0.0);
+    assert_close(table.frequency(69), 440.0);
+
+    // Key 60 is a just major sixth below A4
+    assert_close(table.relative(60), 9.0 - 12.0 * (5.0f64 / 3.0).log2());
+    assert_close(table.relative(72), table.relative(60));
+    assert_close(table.relative(48), table.relative(60));
+    assert_close(table.frequency(72), 440.0 * 6.0 / 5.0);
+
+    // Key 64 is a just major third above key 60
+    assert_close(
+        table.relative(64) - table.relative(60),
+        12.0 * (5.0f64 / 4.0).log2() - 4.0,
+    );
+
+    assert!(table.should_play(60));
+    assert!(!table.should_play(61));
+    assert!(!table.should_play(73));
+}
+
+#[test]
+pub fn reports_malformed_files() {
+    assert!(matches!(
+        Scale::parse("Empty\n0\n"),
+        Err(ScalaError::EmptyScale)
+    ));
+    assert!(matches!(
+        Scale::parse("Truncated\n2\n3/2\n"),
+        Err(ScalaError::UnexpectedEnd { .. })
+    ));
+    assert!(matches!(
+        Scale::parse("! comment\nInvalid\n2\n3/0\n2/1\n"),
+        Err(ScalaError::InvalidLine { line: 4, .. })
+    ));
+    assert!(matches!(
+        KeyboardMapping::parse("0\n0\n128\n60\n69\n440.0\n0\n"),
+        Err(ScalaError::InvalidLine { line: 3, .. })
+    ));
+    assert!(matches!(
+        KeyboardMapping::parse("4000000000\n0\n"),
+        Err(ScalaError::InvalidLine { line: 1, .. })
+    ));
+    assert!(matches!(
+        KeyboardMapping::parse("0\n0\n127\n60\n69\n0.0\n0\n"),
+        Err(ScalaError::InvalidLine { line: 6, .. })
+    ));
+    assert!(matches!(
+        KeyboardMapping::parse("0\n0\n127\n60\n69\nNaN\n0\n"),
+        Err(ScalaError::InvalidLine { line: 6, .. })
+    ));
+
+    let scale = Scale::parse(EQUAL_SCALE).unwrap();
+    let mapping = KeyboardMapping {
+        mapping: vec![Some(0), None],
+        ..KeyboardMapping::default()
+    };
+    assert!(matches!(
+        TuningTable::new("Unmapped reference", &scale, &mapping),
+        Err(ScalaError::UnmappedReferenceKey)
+    ));
+}
+
+#[test]
+pub fn loads_tuning_table_from_files() {
+    let scale_path = temp_file("just.scl", JUST_SCALE);
+    let mapping_path = temp_file("just.kbm", JUST_MAPPING);
+
+    let table = TuningTable::load(&scale_path, Some(&mapping_path)).unwrap();
+    assert_eq!(table, just_table());
+
+    let table = TuningTable::load(&scale_path, None).unwrap();
+    assert!(table.should_play(61));
+
+    assert!(matches!(
+        TuningTable::load(scale_path.with_extension("missing"), None),
+        Err(ScalaError::Io(_))
+    ));
+}
+
+#[test]
+pub fn plugin_uses_host_tuning_tables() {
+    let mut tables = TuningTables::new();
+    let equal_id = tables.push(
+        TuningTable::new(
+            "12-TET",
+            &Scale::parse(EQUAL_SCALE).unwrap(),
+            &KeyboardMapping::default(),
+        )
+        .unwrap(),
+    );
+    let just_id = tables.push(just_table());
+    assert_ne!(equal_id, just_id);
+
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<TuningPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    let mut instance = PluginInstance::<MyHost>::new(
+        move |_| MyHostShared { tables },
+        |shared| MyHostMainThread { shared },
+        &bundle,
+        c"org.rust-audio.clack.tuning",
+        &host_info,
+    )
+    .unwrap();
+
+    let config = PluginAudioConfiguration {
+        sample_rate: 48_000.0,
+        min_frames_count: 4,
+        max_frames_count: 4,
+    };
+
+    // The plugin only activates once it found the tuning it wants
+    assert!(instance.activate(|_, _| (), config).is_err());
+
+    let mut plugin = instance.plugin_handle();
+    let tuning = plugin.get_extension::<PluginTuning>().unwrap();
+    tuning.changed(&mut plugin);
+
+    let processor = instance.activate(|_, _| (), config).unwrap();
+    let mut processor = processor.start_processing().unwrap();
+
+    let mut input_events = EventBuffer::new();
+    input_events.push(&TuningEvent::new(
+        tuning_space_id(),
+        0,
+        Match::All,
+        Match::Specific(0),
+        Some(just_id),
+    ));
+    input_events.push(&NoteOnEvent::new(
+        1,
+        Pckn::new(0u16, 0u16, 60u16, Match::All),
+        1.0,
+    ));
+    input_events.push(&NoteOnEvent::new(
+        2,
+        Pckn::new(0u16, 0u16, 61u16, Match::All),
+        1.0,
+    ));
+    input_events.push(&NoteOnEvent::new(
+        3,
+        Pckn::new(0u16, 1u16, 60u16, Match::All),
+        1.0,
+    ));
+
+    let mut output_events = EventBuffer::new();
+
+    processor
+        .process(
+            &InputAudioBuffers::empty(),
+            &mut OutputAudioBuffers::empty(),
+            &input_events.as_input(),
+            &mut output_events.as_output(),
+            None,
+            None,
+        )
+        .unwrap();
+
+    let relative: Vec<_> = output_events
+        .iter()
+        .map(|e| {
+            let e = e.as_event::<NoteExpressionEvent>().unwrap();
+            (e.header().time(), e.value())
+        })
+        .collect();
+
+    // Key 61 is unmapped by the just tuning, and channel 1 uses no tuning
+    assert_eq!(relative.len(), 2);
+    assert_eq!(relative[0].0, 1);
+    assert_close(relative[0].1, just_table().relative(60));
+    assert_eq!(relative[1], (3, 0.0));
+}
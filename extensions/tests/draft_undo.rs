@@ -0,0 +1,251 @@
+use clack_extensions::draft::undo::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::ffi::CStr;
+
+const DELTA_FORMAT: ClapId = ClapId::new(3);
+
+pub struct UndoPlugin;
+pub struct UndoPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+    gain: u8,
+    can_undo: bool,
+}
+
+impl<'a> PluginMainThread<'a, ()> for UndoPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        // Simulates the user changing the gain from the plugin's GUI
+        let undo = self.host.get_extension::<HostUndo>().unwrap();
+        let previous = self.gain;
+
+        undo.set_wants_context_updates(&mut self.host, true);
+        undo.begin_change(&mut self.host);
+        self.gain = 42;
+        undo.change_made(
+            &mut self.host,
+            c"Set gain to 42",
+            Some(&[previous, self.gain]),
+            true,
+        );
+        undo.request_undo(&mut self.host);
+    }
+}
+
+impl PluginUndoDeltaImpl for UndoPluginMainThread<'_> {
+    fn get_delta_properties(&mut self) -> UndoDeltaProperties {
+        UndoDeltaProperties {
+            has_delta: true,
+            are_deltas_persistent: true,
+            format_version: Some(DELTA_FORMAT),
+        }
+    }
+
+    fn can_use_delta_format_version(&mut self, format_version: ClapId) -> bool {
+        format_version == DELTA_FORMAT
+    }
+
+    fn apply_delta(
+        &mut self,
+        direction: UndoDirection,
+        format_version: ClapId,
+        delta: &[u8],
+    ) -> Result<(), PluginError> {
+        let (DELTA_FORMAT, &[before, after]) = (format_version, delta) else {
+            return Err(PluginError::Message("Invalid delta"));
+        };
+
+        match direction {
+            UndoDirection::Undo if self.can_undo && self.gain == after => self.gain = before,
+            UndoDirection::Redo if self.gain == before => self.gain = after,
+            _ => return Err(PluginError::Message("Delta does not match current state")),
+        }
+
+        Ok(())
+    }
+}
+
+impl PluginUndoContextImpl for UndoPluginMainThread<'_> {
+    fn set_can_undo(&mut self, can_undo: bool) {
+        self.can_undo = can_undo;
+    }
+
+    fn set_can_redo(&mut self, _can_redo: bool) {}
+
+    fn set_undo_name(&mut self, name: Option<&CStr>) {
+        assert_eq!(name, Some(c"Set gain to 42"));
+    }
+
+    fn set_redo_name(&mut self, _name: Option<&CStr>) {}
+}
+
+impl Plugin for UndoPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = UndoPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder
+            .register::<PluginUndoDelta>()
+            .register::<PluginUndoContext>();
+    }
+}
+
+impl DefaultPluginFactory for UndoPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.undo", "Undo")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(UndoPluginMainThread {
+            host,
+            gain: 10,
+            can_undo: false,
+        })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    in_change: bool,
+    wants_context_updates: bool,
+    undo_requests: u32,
+    history: Vec<UndoChange>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostUndoImpl for MyHostMainThread {
+    fn begin_change(&mut self) {
+        self.in_change = true;
+    }
+
+    fn cancel_change(&mut self) {
+        self.in_change = false;
+    }
+
+    fn change_made(&mut self, change: UndoChange) {
+        assert!(self.in_change);
+        self.in_change = false;
+        self.history.push(change);
+    }
+
+    fn request_undo(&mut self) {
+        self.undo_requests += 1;
+    }
+
+    fn request_redo(&mut self) {
+        unimplemented!()
+    }
+
+    fn set_wants_context_updates(&mut self, is_subscribed: bool) {
+        self.wants_context_updates = is_subscribed;
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostUndo>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<UndoPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.undo",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn can_query_delta_properties() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let undo_delta = plugin.get_extension::<PluginUndoDelta>().unwrap();
+
+    assert_eq!(
+        undo_delta.get_delta_properties(&mut plugin),
+        UndoDeltaProperties {
+            has_delta: true,
+            are_deltas_persistent: true,
+            format_version: Some(DELTA_FORMAT)
+        }
+    );
+
+    assert!(undo_delta.can_use_delta_format_version(&mut plugin, DELTA_FORMAT));
+    assert!(!undo_delta.can_use_delta_format_version(&mut plugin, ClapId::new(2)));
+}
+
+#[test]
+pub fn plugin_changes_land_on_host_undo_stack() {
+    let mut instance = instantiate();
+
+    instance.call_on_main_thread_callback();
+
+    let change = instance.access_handler(|h| {
+        assert!(h.wants_context_updates);
+        assert_eq!(h.undo_requests, 1);
+        assert_eq!(h.history.len(), 1);
+        h.history[0].clone()
+    });
+
+    assert_eq!(
+        change,
+        UndoChange {
+            name: c"Set gain to 42".to_owned(),
+            delta: Some(Box::new([10, 42])),
+            delta_can_undo: true
+        }
+    );
+
+    let mut plugin = instance.plugin_handle();
+    let undo_context = plugin.get_extension::<PluginUndoContext>().unwrap();
+    let undo_delta = plugin.get_extension::<PluginUndoDelta>().unwrap();
+    let delta = change.delta.as_deref().unwrap();
+
+    // The plugin only accepts undoing once the host told it it can
+    let error = undo_delta
+        .undo(&mut plugin, DELTA_FORMAT, delta)
+        .unwrap_err();
+    assert_eq!(error.direction(), UndoDirection::Undo);
+
+    undo_context.set_can_undo(&mut plugin, true);
+    undo_context.set_undo_name(&mut plugin, Some(&change.name));
+
+    assert_eq!(undo_delta.undo(&mut plugin, DELTA_FORMAT, delta), Ok(()));
+    assert!(undo_delta.undo(&mut plugin, DELTA_FORMAT, delta).is_err());
+    assert_eq!(undo_delta.redo(&mut plugin, DELTA_FORMAT, delta), Ok(()));
+    assert!(undo_delta.redo(&mut plugin, DELTA_FORMAT, delta).is_err());
+    assert!(undo_delta.undo(&mut plugin, ClapId::new(2), delta).is_err());
+}
@@ -0,0 +1,211 @@
+use clack_extensions::draft::webview::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::io::Write;
+
+const INDEX_HTML: &[u8] = b"<html><script src=\"app.js\"></script></html>";
+const APP_JS: &[u8] = b"window.clap.send('ready');";
+
+pub struct WebviewPlugin;
+pub struct WebviewPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+    uri: String,
+}
+
+impl<'a> PluginMainThread<'a, ()> for WebviewPluginMainThread<'a> {}
+
+impl PluginWebviewImpl for WebviewPluginMainThread<'_> {
+    fn get_uri(&mut self) -> Option<&str> {
+        Some(&self.uri)
+    }
+
+    fn get_resource(
+        &mut self,
+        path: &str,
+        writer: &mut WebviewResourceWriter,
+    ) -> Result<(), PluginError> {
+        let (mime_type, data) = match path {
+            "/" | "/index.html" => ("text/html", INDEX_HTML),
+            "/app.js" => ("text/javascript", APP_JS),
+            _ => return Err(PluginError::Message("Resource not found")),
+        };
+
+        writer.set_mime_type(mime_type)?;
+        writer.write_all(data)?;
+        Ok(())
+    }
+
+    fn receive(&mut self, message: &[u8]) -> Result<(), PluginError> {
+        if let Some(uri) = message.strip_prefix(b"navigate:") {
+            self.uri = String::from_utf8(uri.to_vec())?;
+            return Ok(());
+        }
+
+        if message != b"ready" {
+            return Err(PluginError::Message("Unknown message"));
+        }
+
+        let webview = self.host.get_extension::<HostWebview>().unwrap();
+        webview.send(&mut self.host, b"{\"gain\":0.5}")?;
+        Ok(())
+    }
+}
+
+impl Plugin for WebviewPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = WebviewPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginWebview>();
+    }
+}
+
+impl DefaultPluginFactory for WebviewPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.webview", "Webview")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(WebviewPluginMainThread {
+            host,
+            uri: "/index.html".into(),
+        })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+#[derive(Default)]
+struct MyHostMainThread {
+    sent: Vec<Vec<u8>>,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostWebviewImpl for MyHostMainThread {
+    fn send(&mut self, message: &[u8]) -> Result<(), HostError> {
+        self.sent.push(message.to_vec());
+        Ok(())
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostWebview>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<WebviewPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread::default(),
+        &bundle,
+        c"org.rust-audio.clack.webview",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn host_serves_plugin_resources_locally() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let webview = plugin.get_extension::<PluginWebview>().unwrap();
+
+    let origin = WebviewOrigin::new("http://plugin.localhost/");
+    let uri = webview.get_uri(&mut plugin).unwrap();
+    assert_eq!(uri, "/index.html");
+    assert_eq!(origin.resolve(&uri), "http://plugin.localhost/index.html");
+
+    // The webview then requests the page and its dependencies from the local origin
+    let path = origin
+        .resource_path("http://plugin.localhost/index.html?v=2#top")
+        .unwrap();
+    assert_eq!(path, "/index.html");
+
+    let path = std::ffi::CString::new(path).unwrap();
+    let page = webview.fetch_resource(&mut plugin, &path).unwrap();
+    assert_eq!(
+        page,
+        WebviewResource {
+            mime_type: "text/html".into(),
+            data: INDEX_HTML.to_vec()
+        }
+    );
+
+    let mut script = Vec::new();
+    let mime_type = webview
+        .get_resource(&mut plugin, c"/app.js", &mut script)
+        .unwrap();
+    assert_eq!(mime_type, "text/javascript");
+    assert_eq!(script, APP_JS);
+
+    assert_eq!(origin.resource_path("http://plugin.localhost"), Some("/"));
+    assert_eq!(origin.resource_path("https://example.com/app.js"), None);
+    assert_eq!(
+        webview.fetch_resource(&mut plugin, c"/missing.css"),
+        Err(WebviewError::GetResource)
+    );
+}
+
+#[test]
+pub fn full_uris_are_not_served_locally() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let webview = plugin.get_extension::<PluginWebview>().unwrap();
+
+    // Long enough not to fit in the host's initial buffer
+    let remote = format!("https://example.com/{}", "ui/".repeat(200));
+    let message = format!("navigate:{remote}");
+    webview.receive(&mut plugin, message.as_bytes()).unwrap();
+
+    let uri = webview.get_uri(&mut plugin).unwrap();
+    assert_eq!(uri, remote);
+    assert_eq!(
+        WebviewOrigin::new("http://plugin.localhost").resolve(&uri),
+        remote
+    );
+}
+
+#[test]
+pub fn messages_are_passed_both_ways() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let webview = plugin.get_extension::<PluginWebview>().unwrap();
+
+    assert_eq!(webview.receive(&mut plugin, b"ready"), Ok(()));
+    assert_eq!(
+        webview.receive(&mut plugin, b"unknown"),
+        Err(WebviewError::Receive)
+    );
+
+    instance.access_handler(|h| assert_eq!(h.sent, [b"{\"gain\":0.5}".to_vec()]));
+}
@@ -0,0 +1,224 @@
+use clack_extensions::impl_param_set;
+use clack_extensions::params::*;
+use clack_extensions::state::PluginState;
+use clack_host::events::event_types::ParamValueEvent;
+use clack_host::prelude::*;
+use clack_host::utils::Cookie;
+use clack_plugin::prelude::*;
+use std::mem::MaybeUninit;
+
+const CUTOFF: ParamDefinition = ParamDefinition::new(ClapId::new(10), "Cutoff")
+    .with_module("Filter")
+    .with_range(20.0, 20_000.0)
+    .with_default(1000.0)
+    .with_formatter(
+        |value, writer| write!(writer, "{value:.0} Hz"),
+        |text| text.trim_end_matches("Hz").trim().parse().ok(),
+    );
+
+const MODE: ParamDefinition = ParamDefinition::new(ClapId::new(3), "Mode")
+    .with_range(0.0, 2.0)
+    .with_flags(ParamInfoFlags::IS_AUTOMATABLE.union(ParamInfoFlags::IS_STEPPED));
+
+pub struct ParamsPlugin;
+
+pub struct ParamsPluginShared {
+    params: ParamSet,
+}
+
+impl PluginShared<'_> for ParamsPluginShared {}
+
+pub struct ParamsPluginMainThread<'a> {
+    shared: &'a ParamsPluginShared,
+}
+
+impl<'a> PluginMainThread<'a, ParamsPluginShared> for ParamsPluginMainThread<'a> {}
+
+impl Plugin for ParamsPlugin {
+    type AudioProcessor<'a> = ParamsPluginAudioProcessor<'a>;
+    type Shared<'a> = ParamsPluginShared;
+    type MainThread<'a> = ParamsPluginMainThread<'a>;
+
+    fn declare_extensions(
+        builder: &mut PluginExtensions<Self>,
+        _shared: Option<&ParamsPluginShared>,
+    ) {
+        builder.register::<PluginParams>().register::<PluginState>();
+    }
+}
+
+pub struct ParamsPluginAudioProcessor<'a> {
+    shared: &'a ParamsPluginShared,
+}
+
+impl<'a> PluginAudioProcessor<'a, ParamsPluginShared, ParamsPluginMainThread<'a>>
+    for ParamsPluginAudioProcessor<'a>
+{
+    fn activate(
+        _host: HostAudioProcessorHandle<'a>,
+        _main_thread: &mut ParamsPluginMainThread<'a>,
+        shared: &'a ParamsPluginShared,
+        _audio_config: PluginAudioConfiguration,
+    ) -> Result<Self, PluginError> {
+        Ok(Self { shared })
+    }
+
+    fn process(
+        &mut self,
+        _process: Process,
+        _audio: Audio,
+        events: Events,
+    ) -> Result<ProcessStatus, PluginError> {
+        self.shared.params.handle_events(events.input);
+        Ok(ProcessStatus::Continue)
+    }
+}
+
+impl_param_set!(
+    ParamsPluginMainThread<'_>,
+    ParamsPluginAudioProcessor<'_>,
+    |p| &p.shared.params,
+    state
+);
+
+impl DefaultPluginFactory for ParamsPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.params", "Params")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(ParamsPluginShared {
+            params: ParamSet::new([CUTOFF, MODE]),
+        })
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(ParamsPluginMainThread { shared })
+    }
+}
+
+fn instantiate() -> PluginInstance<()> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<ParamsPlugin>>(c"").unwrap();
+    let host_info = HostInfo::new("Params", "Clack", "https://example.com", "1.0.0").unwrap();
+
+    PluginInstance::<()>::new(
+        |_| (),
+        |_| (),
+        &bundle,
+        c"org.rust-audio.clack.params",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn exposes_param_set_to_host() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let params = plugin.get_extension::<PluginParams>().unwrap();
+
+    assert_eq!(params.count(&mut plugin), 2);
+
+    let mut buffer = ParamInfoBuffer::new();
+    let info = params.get_info(&mut plugin, 0, &mut buffer).unwrap();
+    assert_eq!(info.id, ClapId::new(10));
+    assert_eq!(info.name, b"Cutoff");
+    assert_eq!(info.module, b"Filter");
+    assert_eq!((info.min_value, info.max_value), (20.0, 20_000.0));
+    assert!(params.get_info(&mut plugin, 2, &mut buffer).is_none());
+
+    assert_eq!(params.get_value(&mut plugin, ClapId::new(10)), Some(1000.0));
+    assert_eq!(params.get_value(&mut plugin, ClapId::new(3)), Some(0.0));
+    assert_eq!(params.get_value(&mut plugin, ClapId::new(4)), None);
+
+    let mut text = [MaybeUninit::uninit(); 64];
+    let display = params
+        .value_to_text(&mut plugin, ClapId::new(10), 440.0, &mut text)
+        .unwrap();
+    assert_eq!(display, b"440 Hz");
+
+    assert_eq!(
+        params.text_to_value(&mut plugin, ClapId::new(10), c"5000 Hz"),
+        Some(5000.0)
+    );
+    assert_eq!(
+        params.text_to_value(&mut plugin, ClapId::new(3), c"1.2"),
+        Some(1.0)
+    );
+}
+
+#[test]
+pub fn flushes_param_value_events() {
+    let mut instance = instantiate();
+    let params = instance
+        .plugin_handle()
+        .get_extension::<PluginParams>()
+        .unwrap();
+
+    let mut events = EventBuffer::new();
+    events.push(&ParamValueEvent::new(
+        0,
+        ClapId::new(3),
+        Pckn::match_all(),
+        2.0,
+        Cookie::empty(),
+    ));
+
+    events.push(&ParamValueEvent::new(
+        1,
+        ClapId::new(10),
+        Pckn::match_all(),
+        50_000.0,
+        Cookie::empty(),
+    ));
+
+    let mut plugin = instance.inactive_plugin_handle().unwrap();
+    params.flush(
+        &mut plugin,
+        &events.as_input(),
+        &mut EventBuffer::new().as_output(),
+    );
+
+    let mut plugin = instance.plugin_handle();
+    assert_eq!(params.get_value(&mut plugin, ClapId::new(3)), Some(2.0));
+    assert_eq!(
+        params.get_value(&mut plugin, ClapId::new(10)),
+        Some(20_000.0)
+    );
+}
+
+#[test]
+pub fn saves_and_loads_param_set_state() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let params = plugin.get_extension::<PluginParams>().unwrap();
+    let state = plugin.get_extension::<PluginState>().unwrap();
+
+    let mut saved = Vec::new();
+    state.save(&mut plugin, &mut saved).unwrap();
+
+    let mut events = EventBuffer::new();
+    events.push(&ParamValueEvent::new(
+        0,
+        ClapId::new(10),
+        Pckn::match_all(),
+        440.0,
+        Cookie::empty(),
+    ));
+
+    let mut inactive = instance.inactive_plugin_handle().unwrap();
+    params.flush(
+        &mut inactive,
+        &events.as_input(),
+        &mut EventBuffer::new().as_output(),
+    );
+
+    let mut plugin = instance.plugin_handle();
+    assert_eq!(params.get_value(&mut plugin, ClapId::new(10)), Some(440.0));
+
+    state.load(&mut plugin, &mut saved.as_slice()).unwrap();
+    assert_eq!(params.get_value(&mut plugin, ClapId::new(10)), Some(1000.0));
+}
@@ -0,0 +1,213 @@
+use clack_extensions::preset_discovery::files::*;
+use clack_extensions::preset_discovery::prelude::*;
+use clack_host::prelude::*;
+use clack_plugin::entry::prelude::{Entry, EntryFactories, EntryLoadError};
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::path::PathBuf;
+
+pub struct PresetsEntry {
+    factory: PresetDiscoveryFactoryWrapper<FilePresetDiscoveryFactory>,
+}
+
+impl Entry for PresetsEntry {
+    fn new(_bundle_path: &CStr) -> Result<Self, EntryLoadError> {
+        let path = CString::new(preset_directory().to_str().unwrap()).unwrap();
+        let provider =
+            FilePresetProvider::new(c"org.rust-audio.clack.gain", c"Gain preset", c"gainpreset")
+                .with_directory(c"Factory", &path, Flags::IS_FACTORY_CONTENT);
+
+        Ok(Self {
+            factory: PresetDiscoveryFactoryWrapper::new(FilePresetDiscoveryFactory::new(
+                ProviderDescriptor::new("org.rust-audio.clack.gain.files", "Gain preset files"),
+                provider,
+            )),
+        })
+    }
+
+    fn declare_factories<'a>(&'a self, builder: &mut EntryFactories<'a>) {
+        builder.register_factory(&self.factory);
+    }
+}
+
+fn preset_directory() -> PathBuf {
+    std::env::temp_dir().join(format!("clack-preset-files-{}", std::process::id()))
+}
+
+#[derive(Default)]
+struct Indexer {
+    file_types: Vec<Option<CString>>,
+    locations: Vec<CString>,
+}
+
+impl IndexerImpl for Indexer {
+    fn declare_filetype(&mut self, file_type: FileType) -> Result<(), HostError> {
+        self.file_types
+            .push(file_type.file_extension.map(CStr::to_owned));
+        Ok(())
+    }
+
+    fn declare_location(&mut self, location: LocationInfo) -> Result<(), HostError> {
+        self.locations
+            .push(location.location.file_path().unwrap().to_owned());
+        Ok(())
+    }
+
+    fn declare_soundpack(&mut self, _soundpack: Soundpack) -> Result<(), HostError> {
+        unimplemented!()
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Preset {
+    name: String,
+    load_key: Option<String>,
+    creators: Vec<String>,
+    features: Vec<String>,
+    extra_info: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct Receiver {
+    presets: Vec<Preset>,
+}
+
+fn string(s: &CStr) -> String {
+    s.to_str().unwrap().to_owned()
+}
+
+impl Receiver {
+    fn current(&mut self) -> &mut Preset {
+        self.presets.last_mut().unwrap()
+    }
+}
+
+impl MetadataReceiverImpl for Receiver {
+    fn on_error(&mut self, _error_code: i32, error_message: Option<&CStr>) {
+        panic!("Unexpected error: {error_message:?}")
+    }
+
+    fn begin_preset(
+        &mut self,
+        name: Option<&CStr>,
+        load_key: Option<&CStr>,
+    ) -> Result<(), HostError> {
+        self.presets.push(Preset {
+            name: string(name.unwrap()),
+            load_key: load_key.map(string),
+            ..Preset::default()
+        });
+        Ok(())
+    }
+
+    fn add_plugin_id(&mut self, plugin_id: UniversalPluginId) {
+        assert_eq!(plugin_id.id, c"org.rust-audio.clack.gain");
+    }
+
+    fn set_soundpack_id(&mut self, _soundpack_id: &CStr) {}
+    fn set_flags(&mut self, _flags: Flags) {}
+
+    fn add_creator(&mut self, creator: &CStr) {
+        self.current().creators.push(string(creator));
+    }
+
+    fn set_description(&mut self, _description: &CStr) {}
+
+    fn set_timestamps(
+        &mut self,
+        _creation_time: Option<Timestamp>,
+        _modification_time: Option<Timestamp>,
+    ) {
+    }
+
+    fn add_feature(&mut self, feature: &CStr) {
+        self.current().features.push(string(feature));
+    }
+
+    fn add_extra_info(&mut self, key: &CStr, value: &CStr) {
+        let info = (string(key), string(value));
+        self.current().extra_info.push(info);
+    }
+}
+
+fn write_preset(path: &str, header: PresetFileHeader) {
+    let mut file = File::create(preset_directory().join(path)).unwrap();
+    header.write(&mut file).unwrap();
+}
+
+#[test]
+pub fn discovers_preset_files_in_directories() {
+    let directory = preset_directory();
+    std::fs::create_dir_all(directory.join("bass")).unwrap();
+
+    write_preset(
+        "bass/deep.gainpreset",
+        PresetFileHeader {
+            creators: vec!["Me".into()],
+            features: vec!["bass".into()],
+            collection: Some("Basics".into()),
+            ..PresetFileHeader::new("org.rust-audio.clack.gain", "Deep")
+        },
+    );
+    write_preset(
+        "unity.gainpreset",
+        PresetFileHeader::new("org.rust-audio.clack.gain", "Unity"),
+    );
+    write_preset(
+        "other.gainpreset",
+        PresetFileHeader::new("org.rust-audio.clack.other", "Other"),
+    );
+    write_preset(
+        "ignored.txt",
+        PresetFileHeader::new("org.rust-audio.clack.gain", "Ignored"),
+    );
+    std::fs::write(directory.join("corrupted.gainpreset"), b"garbage").unwrap();
+
+    // Symbolic links to directories are not followed, so this cycle is never entered.
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&directory, directory.join("bass/loop")).unwrap();
+
+    let bundle = PluginBundle::load_from_clack::<PresetsEntry>(c"").unwrap();
+    let host_info = HostInfo::new("Presets", "Clack", "https://example.com", "1.0.0").unwrap();
+    let mut provider = Provider::instantiate(
+        Indexer::default(),
+        &bundle,
+        c"org.rust-audio.clack.gain.files",
+        &host_info,
+    )
+    .unwrap();
+
+    assert_eq!(
+        provider.indexer().file_types,
+        [Some(c"gainpreset".to_owned())]
+    );
+    let location = provider.indexer().locations[0].clone();
+
+    let mut receiver = Receiver::default();
+    provider.get_metadata(Location::File { path: &location }, &mut receiver);
+
+    assert_eq!(
+        receiver.presets,
+        [
+            Preset {
+                name: "Deep".into(),
+                load_key: Some("bass/deep.gainpreset".into()),
+                creators: vec!["Me".into()],
+                features: vec!["bass".into()],
+                extra_info: vec![("collection".into(), "Basics".into())],
+            },
+            Preset {
+                name: "Unity".into(),
+                load_key: Some("unity.gainpreset".into()),
+                ..Preset::default()
+            },
+        ]
+    );
+
+    let file = CString::new(directory.join("unity.gainpreset").to_str().unwrap()).unwrap();
+    let mut receiver = Receiver::default();
+    provider.get_metadata(Location::File { path: &file }, &mut receiver);
+    assert_eq!(receiver.presets[0].load_key, None);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
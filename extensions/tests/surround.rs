@@ -0,0 +1,173 @@
+use clack_extensions::surround::*;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+
+const MAP_5_1: [SurroundChannel; 6] = [
+    SurroundChannel::FrontLeft,
+    SurroundChannel::FrontRight,
+    SurroundChannel::FrontCenter,
+    SurroundChannel::LowFrequency,
+    SurroundChannel::SideLeft,
+    SurroundChannel::SideRight,
+];
+
+pub struct SurroundPlugin;
+pub struct SurroundPluginMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
+}
+
+impl<'a> PluginMainThread<'a, ()> for SurroundPluginMainThread<'a> {
+    fn on_main_thread(&mut self) {
+        let surround = self.host.get_extension::<HostSurround>().unwrap();
+        surround.changed(&mut self.host);
+    }
+}
+
+impl PluginSurroundImpl for SurroundPluginMainThread<'_> {
+    fn is_channel_mask_supported(&mut self, mask: SurroundChannelMask) -> bool {
+        SurroundChannelMask::SURROUND_7_1.contains(mask)
+    }
+
+    fn get_channel_map(
+        &mut self,
+        _is_input: bool,
+        port_index: u32,
+        writer: &mut SurroundChannelMapWriter,
+    ) {
+        if port_index == 0 {
+            writer.set(&MAP_5_1);
+        }
+    }
+}
+
+impl Plugin for SurroundPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = SurroundPluginMainThread<'a>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginSurround>();
+    }
+}
+
+impl DefaultPluginFactory for SurroundPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.surround", "Surround")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(SurroundPluginMainThread { host })
+    }
+}
+
+struct MyHost;
+struct MyHostShared;
+struct MyHostMainThread {
+    changed_count: u32,
+}
+
+impl SharedHandler<'_> for MyHostShared {
+    fn request_restart(&self) {
+        unimplemented!()
+    }
+    fn request_process(&self) {
+        unimplemented!()
+    }
+    fn request_callback(&self) {
+        unimplemented!()
+    }
+}
+
+impl MainThreadHandler<'_> for MyHostMainThread {}
+
+impl HostSurroundImpl for MyHostMainThread {
+    fn changed(&mut self) {
+        self.changed_count += 1;
+    }
+}
+
+impl HostHandlers for MyHost {
+    type Shared<'a> = MyHostShared;
+    type MainThread<'a> = MyHostMainThread;
+    type AudioProcessor<'a> = ();
+
+    fn declare_extensions(builder: &mut HostExtensions<Self>, _shared: &Self::Shared<'_>) {
+        builder.register::<HostSurround>();
+    }
+}
+
+fn instantiate() -> PluginInstance<MyHost> {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<SurroundPlugin>>(c"").unwrap();
+    let host_info =
+        HostInfo::new("Legit Studio", "Legit Ltd.", "https://example.com", "4.3.2").unwrap();
+
+    PluginInstance::<MyHost>::new(
+        |_| MyHostShared,
+        |_| MyHostMainThread { changed_count: 0 },
+        &bundle,
+        c"org.rust-audio.clack.surround",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn can_query_channel_masks() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let surround = plugin.get_extension::<PluginSurround>().unwrap();
+
+    assert!(surround.is_channel_mask_supported(&mut plugin, SurroundChannelMask::SURROUND_5_1));
+    assert!(surround.is_channel_mask_supported(&mut plugin, SurroundChannelMask::SURROUND_7_1));
+    assert!(!surround.is_channel_mask_supported(
+        &mut plugin,
+        SurroundChannelMask::SURROUND_7_1 | SurroundChannelMask::TOP_CENTER
+    ));
+}
+
+#[test]
+pub fn can_read_channel_maps() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let surround = plugin.get_extension::<PluginSurround>().unwrap();
+
+    let mut buffer = [0u8; 8];
+    let map = surround.get_channel_map(&mut plugin, false, 0, &mut buffer);
+
+    assert_eq!(map.len(), 6);
+    assert_eq!(map.get(3), Some(SurroundChannel::LowFrequency));
+    assert_eq!(map.mask(), SurroundChannelMask::SURROUND_5_1);
+    assert!(map.iter().eq(MAP_5_1.iter().copied().map(Some)));
+
+    let map = surround.get_channel_map(&mut plugin, false, 1, &mut buffer);
+    assert!(map.is_empty());
+}
+
+#[test]
+pub fn channel_maps_are_bounded_by_host_buffer() {
+    let mut instance = instantiate();
+    let mut plugin = instance.plugin_handle();
+    let surround = plugin.get_extension::<PluginSurround>().unwrap();
+
+    let mut buffer = [0u8; 2];
+    let map = surround.get_channel_map(&mut plugin, true, 0, &mut buffer);
+
+    assert_eq!(map.as_raw(), &[0, 1]);
+    assert_eq!(map.mask(), SurroundChannelMask::STEREO);
+}
+
+#[test]
+pub fn host_receives_changed_notifications() {
+    let mut instance = instantiate();
+
+    instance.call_on_main_thread_callback();
+
+    assert_eq!(instance.access_handler(|h| h.changed_count), 1);
+}
@@ -0,0 +1,85 @@
+use clack_extensions::voice_info::*;
+use clack_host::prelude::*;
+use clack_plugin::events::event_types::{NoteOffEvent, NoteOnEvent};
+use clack_plugin::prelude::*;
+use clack_plugin::voices::{StealingPolicy, Voice, VoiceManager};
+
+#[derive(Default)]
+pub struct SilentVoice;
+
+impl Voice for SilentVoice {
+    fn start(&mut self, _event: &NoteOnEvent) {}
+    fn release(&mut self, _event: &NoteOffEvent) {}
+
+    fn is_finished(&self) -> bool {
+        true
+    }
+}
+
+pub struct VoiceInfoPlugin;
+
+pub struct VoiceInfoPluginMainThread {
+    voices: VoiceManager<SilentVoice>,
+}
+
+impl PluginMainThread<'_, ()> for VoiceInfoPluginMainThread {}
+
+impl PluginVoiceInfoImpl for VoiceInfoPluginMainThread {
+    fn get(&self) -> Option<VoiceInfo> {
+        Some(VoiceInfo::from(&self.voices))
+    }
+}
+
+impl Plugin for VoiceInfoPlugin {
+    type AudioProcessor<'a> = ();
+    type Shared<'a> = ();
+    type MainThread<'a> = VoiceInfoPluginMainThread;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginVoiceInfo>();
+    }
+}
+
+impl DefaultPluginFactory for VoiceInfoPlugin {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.voice-info", "Voice Info")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        let mut voices = VoiceManager::new(16, SilentVoice::default)
+            .with_stealing_policy(StealingPolicy::SameKey);
+        voices.set_voice_count(8);
+
+        Ok(VoiceInfoPluginMainThread { voices })
+    }
+}
+
+#[test]
+pub fn reports_voice_manager_info() {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<VoiceInfoPlugin>>(c"").unwrap();
+    let host_info = HostInfo::new("Voice Info", "Clack", "https://example.com", "1.0.0").unwrap();
+
+    let mut instance = PluginInstance::<()>::new(
+        |_| (),
+        |_| (),
+        &bundle,
+        c"org.rust-audio.clack.voice-info",
+        &host_info,
+    )
+    .unwrap();
+
+    let mut plugin = instance.plugin_handle();
+    let voice_info = plugin.get_extension::<PluginVoiceInfo>().unwrap();
+    let info = voice_info.get(&mut plugin).unwrap();
+
+    assert_eq!(info.voice_count, 8);
+    assert_eq!(info.voice_capacity, 16);
+    assert_eq!(info.flags, VoiceInfoFlags::empty());
+}
@@ -2,6 +2,7 @@
 #![allow(unsafe_code)]
 
 use clack_host::bundle::PluginBundleError;
+use clack_host::plugin::PluginDescriptorOwned;
 use clack_host::prelude::*;
 use rayon::prelude::*;
 use std::error::Error;
@@ -13,7 +14,7 @@ use walkdir::{DirEntry, WalkDir};
 /// as its path.
 pub struct FoundBundlePlugin {
     /// The plugin's descriptor.
-    pub plugin: PluginDescriptor,
+    pub plugin: PluginDescriptorOwned,
     /// The bundle the descriptor was loaded from.
     pub bundle: PluginBundle,
     /// The path of the bundle's file.
@@ -128,7 +129,7 @@ fn scan_plugin(path: &Path, searched_id: &str) -> Option<FoundBundlePlugin> {
         return None;
     };
     for plugin in bundle.get_plugin_factory()?.plugin_descriptors() {
-        let Some(plugin) = PluginDescriptor::try_from(plugin) else {
+        let Some(plugin) = PluginDescriptorOwned::from_descriptor(plugin) else {
             continue;
         };
 
@@ -144,42 +145,13 @@ fn scan_plugin(path: &Path, searched_id: &str) -> Option<FoundBundlePlugin> {
     None
 }
 
-/// Simple description of a plugin.
-///
-/// This is a simplified and owned version of Clack's `PluginDescriptor`.
-#[derive(Debug)]
-pub struct PluginDescriptor {
-    /// The ID of the plugin.
-    pub id: String,
-    /// (Optional) the user-friendly name of this plugin.
-    pub name: Option<String>,
-    /// (Optional) the version of this plugin.
-    pub version: Option<String>,
-}
-
-impl PluginDescriptor {
-    /// Reads the description from a given plugin descriptor.
-    ///
-    /// If the plugin somehow doesn't have a valid ID, this returns `None`.
-    pub fn try_from(p: &clack_host::plugin::PluginDescriptor) -> Option<Self> {
-        Some(PluginDescriptor {
-            id: p.id()?.to_str().ok()?.to_string(),
-            version: p.version().map(|v| v.to_string_lossy().to_string()),
-            name: p.name().map(|v| v.to_string_lossy().to_string()),
-        })
-    }
-}
-
-impl Display for PluginDescriptor {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match (&self.name, &self.version) {
-            (None, None) => write!(f, "{}", &self.id),
-            (Some(name), None) => write!(f, "{} ({})", name, &self.id),
-            (None, Some(version)) => write!(f, "{} <version {}>", &self.id, version),
-            (Some(name), Some(version)) => {
-                write!(f, "{} ({}) <version {}>", name, &self.id, version)
-            }
-        }
+/// Formats a plugin's description for display, including its name and version if available.
+pub fn describe_plugin(plugin: &PluginDescriptorOwned) -> String {
+    match (&plugin.name, &plugin.version) {
+        (None, None) => plugin.id.clone(),
+        (Some(name), None) => format!("{} ({})", name, &plugin.id),
+        (None, Some(version)) => format!("{} <version {}>", &plugin.id, version),
+        (Some(name), Some(version)) => format!("{} ({}) <version {}>", name, &plugin.id, version),
     }
 }
 
@@ -194,7 +166,7 @@ pub fn list_plugins_in_bundle(
 
     Ok(plugin_factory
         .plugin_descriptors()
-        .filter_map(PluginDescriptor::try_from)
+        .filter_map(PluginDescriptorOwned::from_descriptor)
         .map(|plugin| FoundBundlePlugin {
             bundle: bundle.clone(),
             path: bundle_path.to_path_buf(),
@@ -241,7 +213,7 @@ pub fn load_plugin_id_from_path(
 
     Ok(plugin_factory
         .plugin_descriptors()
-        .filter_map(PluginDescriptor::try_from)
+        .filter_map(PluginDescriptorOwned::from_descriptor)
         .find(|p| p.id == id)
         .map(|plugin| FoundBundlePlugin {
             plugin,
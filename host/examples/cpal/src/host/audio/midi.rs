@@ -1,12 +1,13 @@
 use crate::host::CpalHost;
 use clack_extensions::note_ports::{NoteDialects, NotePortInfoBuffer, PluginNotePorts};
-use clack_host::events::event_types::{MidiEvent, NoteChokeEvent, NoteOffEvent, NoteOnEvent};
-use clack_host::events::{EventFlags, Match};
+use clack_host::events::event_types::{MidiEvent, NoteChokeEvent};
+use clack_host::events::translation::{NoteFormat, NoteTranslator};
+use clack_host::events::{Event, EventFlags};
 use clack_host::prelude::*;
 use midir::{Ignore, MidiInput, MidiInputConnection};
 use rtrb::{Consumer, RingBuffer};
 use std::error::Error;
-use wmidi::{MidiMessage, Velocity};
+use wmidi::MidiMessage;
 
 /// A MIDI message that was received at a given time.
 struct MidiEventMessage {
@@ -175,7 +176,8 @@ fn micro_timestamp_to_sample_time(
     relative_sample.min(sample_count)
 }
 
-/// Pushes a MIDI event to the given Clack event buffer.
+/// Pushes a MIDI event to the given Clack event buffer, translating it to CLAP note events if
+/// the plugin supports them.
 fn push_midi_to_buffer(
     message: MidiMessage,
     sample_time: u32,
@@ -183,32 +185,20 @@ fn push_midi_to_buffer(
     port_index: u16,
     prefers_midi: bool,
 ) {
-    match message {
-        MidiMessage::NoteOff(channel, note, velocity) if !prefers_midi => buffer.push(
-            &NoteOffEvent::new(
-                sample_time,
-                Pckn::new(port_index, channel.index(), note as u16, Match::All),
-                u8::from(velocity) as f64 / (u8::from(Velocity::MAX) as f64),
-            )
-            .with_flags(EventFlags::IS_LIVE),
-        ),
-        MidiMessage::NoteOn(channel, note, velocity) if !prefers_midi => buffer.push(
-            &NoteOnEvent::new(
-                sample_time,
-                Pckn::new(port_index, channel.index(), note as u16, Match::All),
-                u8::from(velocity) as f64 / (u8::from(Velocity::MAX) as f64),
-            )
-            .with_flags(EventFlags::IS_LIVE),
-        ),
-        m => {
-            let mut buf = [0; 3];
-            if m.copy_to_slice(&mut buf).is_ok() {
-                buffer.push(
-                    &MidiEvent::new(sample_time, port_index, buf).with_flags(EventFlags::IS_LIVE),
-                )
-            }
-        }
+    let mut data = [0; 3];
+    if message.copy_to_slice(&mut data).is_err() {
+        return;
     }
+
+    let event = MidiEvent::new(sample_time, port_index, data).with_flags(EventFlags::IS_LIVE);
+    let format = if prefers_midi {
+        NoteFormat::Midi
+    } else {
+        NoteFormat::Clap
+    };
+
+    // The buffer grows as needed, so pushing to it never fails.
+    let _ = NoteTranslator::new().translate(event.as_unknown(), format, &mut buffer.as_output());
 }
 
 /// Tries to find the ID of the main note port of a plugin, and whether it supports CLAP note events
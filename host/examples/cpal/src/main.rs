@@ -71,7 +71,7 @@ fn run_from_path(path: &Path) -> Result<(), Box<dyn Error>> {
     );
 
     for p in &plugins {
-        println!("\t > {}", &p.plugin)
+        println!("\t > {}", discovery::describe_plugin(&p.plugin))
     }
 
     if plugins.len() == 1 {
@@ -95,7 +95,11 @@ fn run_from_id(id: &str) -> Result<(), Box<dyn Error>> {
     println!("Found {} CLAP plugins with id {}:", plugins.len(), id);
 
     for p in &plugins {
-        println!("\t > {} in {}", &p.plugin, p.path.display())
+        println!(
+            "\t > {} in {}",
+            discovery::describe_plugin(&p.plugin),
+            p.path.display()
+        )
     }
 
     if plugins.len() == 1 {
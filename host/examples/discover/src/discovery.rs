@@ -1,6 +1,7 @@
+use clack_host::plugin::PluginDescriptorOwned;
 use clack_host::prelude::*;
+pub use clack_host::scan::standard_clap_paths;
 use rayon::prelude::*;
-use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
 use walkdir::{DirEntry, WalkDir};
 
@@ -8,55 +9,13 @@ use walkdir::{DirEntry, WalkDir};
 /// as its path.
 pub struct FoundBundlePlugin {
     /// The plugin's descriptor.
-    pub plugins: Vec<PluginDescriptor>,
+    pub plugins: Vec<PluginDescriptorOwned>,
     /// The bundle the descriptor was loaded from.
     pub bundle: PluginBundle,
     /// The path of the bundle's file.
     pub path: PathBuf,
 }
 
-/// Returns a list of all the standard CLAP search paths, per the CLAP specification.
-pub fn standard_clap_paths() -> Vec<PathBuf> {
-    let mut paths = vec![];
-
-    if let Some(home_dir) = dirs::home_dir() {
-        paths.push(home_dir.join(".clap"));
-
-        #[cfg(target_os = "macos")]
-        {
-            paths.push(home_dir.join("Library/Audio/Plug-Ins/CLAP"));
-        }
-    }
-
-    #[cfg(windows)]
-    {
-        if let Some(val) = std::env::var_os("CommonProgramFiles") {
-            paths.push(PathBuf::from(val).join("CLAP"))
-        }
-
-        if let Some(dir) = dirs::config_local_dir() {
-            paths.push(dir.join("Programs\\Common\\CLAP"));
-        }
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        paths.push(PathBuf::from("/Library/Audio/Plug-Ins/CLAP"));
-    }
-
-    #[cfg(target_family = "unix")]
-    {
-        paths.push("/usr/lib/clap".into());
-        paths.push("/usr/lib64/clap".into());
-    }
-
-    if let Some(env_var) = std::env::var_os("CLAP_PATH") {
-        paths.extend(std::env::split_paths(&env_var))
-    }
-
-    paths
-}
-
 /// Returns `true` if the given entry could refer to a CLAP bundle.
 ///
 /// CLAP bundles are files that end with the `.clap` extension.
@@ -107,48 +66,19 @@ pub fn scan_plugin(path: &Path) -> Option<FoundBundlePlugin> {
         plugins: bundle
             .get_plugin_factory()?
             .plugin_descriptors()
-            .filter_map(PluginDescriptor::try_from)
+            .filter_map(PluginDescriptorOwned::from_descriptor)
             .collect(),
         bundle,
         path: path.to_path_buf(),
     })
 }
 
-/// Simple description of a plugin.
-///
-/// This is a simplified and owned version of Clack's `PluginDescriptor`.
-#[derive(Debug)]
-pub struct PluginDescriptor {
-    /// The ID of the plugin.
-    pub id: String,
-    /// (Optional) the user-friendly name of this plugin.
-    pub name: Option<String>,
-    /// (Optional) the version of this plugin.
-    pub version: Option<String>,
-}
-
-impl PluginDescriptor {
-    /// Reads the description from a given plugin descriptor.
-    ///
-    /// If the plugin somehow doesn't have a valid ID, this returns `None`.
-    pub fn try_from(p: &clack_host::plugin::PluginDescriptor) -> Option<Self> {
-        Some(PluginDescriptor {
-            id: p.id()?.to_str().ok()?.to_string(),
-            version: p.version().map(|v| v.to_string_lossy().to_string()),
-            name: p.name().map(|v| v.to_string_lossy().to_string()),
-        })
-    }
-}
-
-impl Display for PluginDescriptor {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match (&self.name, &self.version) {
-            (None, None) => write!(f, "{}", &self.id),
-            (Some(name), None) => write!(f, "{} ({})", name, &self.id),
-            (None, Some(version)) => write!(f, "{} <version {}>", &self.id, version),
-            (Some(name), Some(version)) => {
-                write!(f, "{} ({}) <version {}>", name, &self.id, version)
-            }
-        }
+/// Formats a plugin's description for display, including its name and version if available.
+pub fn describe_plugin(plugin: &PluginDescriptorOwned) -> String {
+    match (&plugin.name, &plugin.version) {
+        (None, None) => plugin.id.clone(),
+        (Some(name), None) => format!("{} ({})", name, &plugin.id),
+        (None, Some(version)) => format!("{} <version {}>", &plugin.id, version),
+        (Some(name), Some(version)) => format!("{} ({}) <version {}>", name, &plugin.id, version),
     }
 }
@@ -66,7 +66,7 @@ pub fn list_plugins() {
     for bundle in scan_bundles(&found_bundles) {
         println!("  > At {}", bundle.path.to_string_lossy());
         for plugin in &bundle.plugins {
-            println!("\t- {}", plugin)
+            println!("\t- {}", describe_plugin(plugin))
         }
     }
 }
@@ -119,7 +119,7 @@ pub fn scan_bundle(path: Option<&Path>, id: Option<&str>) -> Result<(), ExitCode
             for bundle in bundles {
                 println!("  > At {}", bundle.path.to_string_lossy());
                 for plugin in &bundle.plugins {
-                    println!("\t- {}", plugin)
+                    println!("\t- {}", describe_plugin(plugin))
                 }
             }
         }
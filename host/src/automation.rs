@@ -0,0 +1,549 @@
+//! Parameter automation, for hosts.
+//!
+//! An [`AutomationLane`] holds a list of [breakpoints](Breakpoint) for a single parameter, and
+//! turns them into the [`ParamValueEvent`]s to send to a plugin for each processed block. The
+//! values between breakpoints are interpolated, and ramps are sent as a series of events spaced
+//! by the lane's [resolution](AutomationLane::with_resolution).
+//!
+//! An [`AutomationLanes`] collection can be used to automate multiple parameters at once, in
+//! which case the events of all lanes are interleaved in time order.
+//!
+//! Editing breakpoints may allocate, and should be done outside the audio thread. Producing
+//! events from them, using [`AutomationLane::process`] or [`AutomationLanes::process`], never
+//! allocates.
+//!
+//! # Example
+//!
+//! ```
+//! use clack_host::automation::{AutomationLane, Breakpoint, Interpolation, ParamRange};
+//! use clack_host::prelude::*;
+//!
+//! let mut lane = AutomationLane::new(ClapId::new(1), ParamRange::new(0.0, 1.0))
+//!     .with_resolution(16);
+//!
+//! lane.insert(Breakpoint::new(0, 0.0, Interpolation::Linear));
+//! lane.insert(Breakpoint::new(64, 1.0, Interpolation::Step));
+//!
+//! // Produce the events of the first block of 32 samples.
+//! let mut events = EventBuffer::new();
+//! lane.process(0, 32, &mut events.as_output()).unwrap();
+//!
+//! // One event at the start of the block, then one every 16 samples.
+//! assert_eq!(events.len(), 2);
+//! ```
+
+#![deny(missing_docs)]
+
+use clack_common::events::Pckn;
+use clack_common::events::event_types::ParamValueEvent;
+use clack_common::events::io::{OutputEvents, TryPushError};
+use clack_common::utils::{ClapId, Cookie};
+
+/// The default resolution of automation ramps, in samples.
+const DEFAULT_RESOLUTION: u32 = 64;
+
+/// How the value of a parameter changes from a breakpoint to the next one.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Interpolation {
+    /// The value changes linearly.
+    #[default]
+    Linear,
+    /// The value changes exponentially, i.e. it is multiplied by a constant factor over time.
+    ///
+    /// This is only possible if both values are non-zero and of the same sign. Otherwise, the
+    /// value changes linearly instead.
+    Exponential,
+    /// The value stays constant until the next breakpoint, then jumps to its value.
+    Step,
+}
+
+/// A point of an [`AutomationLane`], setting the value of the parameter at a given time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Breakpoint {
+    /// The time of the breakpoint, in samples since the start of the timeline.
+    pub time: u64,
+    /// The plain value of the parameter at this time.
+    pub value: f64,
+    /// How the value changes from this breakpoint to the next one.
+    pub interpolation: Interpolation,
+}
+
+impl Breakpoint {
+    /// Creates a new breakpoint.
+    #[inline]
+    pub const fn new(time: u64, value: f64, interpolation: Interpolation) -> Self {
+        Self {
+            time,
+            value,
+            interpolation,
+        }
+    }
+}
+
+/// The range of values an automated parameter can take.
+///
+/// This matches the `min_value`, `max_value` and `IS_STEPPED` flag of the parameter's info, as
+/// reported by the plugin's `params` extension.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParamRange {
+    /// The minimum plain value of the parameter.
+    pub min: f64,
+    /// The maximum plain value of the parameter.
+    pub max: f64,
+    /// Whether the parameter only takes integer values.
+    pub stepped: bool,
+}
+
+impl ParamRange {
+    /// Creates a new, continuous range.
+    #[inline]
+    pub const fn new(min: f64, max: f64) -> Self {
+        Self {
+            min,
+            max,
+            stepped: false,
+        }
+    }
+
+    /// Creates a new range, for a parameter that only takes integer values.
+    #[inline]
+    pub const fn stepped(min: f64, max: f64) -> Self {
+        Self {
+            min,
+            max,
+            stepped: true,
+        }
+    }
+
+    /// Clamps the given value to this range, rounding it to the nearest integer if the parameter
+    /// is stepped.
+    pub fn apply(&self, value: f64) -> f64 {
+        let value = if self.stepped { value.round() } else { value };
+
+        if self.min <= self.max {
+            value.clamp(self.min, self.max)
+        } else {
+            value
+        }
+    }
+}
+
+/// The automation of a single parameter.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Clone, Debug)]
+pub struct AutomationLane {
+    param_id: ClapId,
+    pckn: Pckn,
+    cookie: Cookie,
+    range: ParamRange,
+    resolution: u32,
+    breakpoints: Vec<Breakpoint>,
+    last_value: Option<f64>,
+    /// The next time to check for a value change, within the current block.
+    cursor: Option<u64>,
+}
+
+impl AutomationLane {
+    /// Creates a new, empty lane for the given parameter.
+    ///
+    /// By default, the events target all notes, ports, keys and channels, and ramps are sent with
+    /// a resolution of 64 samples.
+    pub fn new(param_id: ClapId, range: ParamRange) -> Self {
+        Self {
+            param_id,
+            pckn: Pckn::match_all(),
+            cookie: Cookie::empty(),
+            range,
+            resolution: DEFAULT_RESOLUTION,
+            breakpoints: Vec::new(),
+            last_value: None,
+            cursor: None,
+        }
+    }
+
+    /// Sets the cookie of the parameter, as given by the plugin's parameter info.
+    #[inline]
+    pub fn with_cookie(mut self, cookie: Cookie) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// Sets the note, port, key and channel targeted by the events of this lane.
+    #[inline]
+    pub fn with_pckn(mut self, pckn: Pckn) -> Self {
+        self.pckn = pckn;
+        self
+    }
+
+    /// Sets the number of samples between the events sent during ramps.
+    ///
+    /// Events are always sent at breakpoints, as well as at every multiple of the resolution
+    /// during ramps. A resolution of zero is treated as one sample.
+    #[inline]
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    /// Returns the ID of the automated parameter.
+    #[inline]
+    pub fn param_id(&self) -> ClapId {
+        self.param_id
+    }
+
+    /// Returns the range of the automated parameter.
+    #[inline]
+    pub fn range(&self) -> ParamRange {
+        self.range
+    }
+
+    /// Returns the breakpoints of this lane, sorted by time.
+    #[inline]
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Inserts a breakpoint, replacing any existing breakpoint at the same time.
+    pub fn insert(&mut self, breakpoint: Breakpoint) {
+        let index = self
+            .breakpoints
+            .partition_point(|b| b.time < breakpoint.time);
+
+        match self.breakpoints.get_mut(index) {
+            Some(existing) if existing.time == breakpoint.time => *existing = breakpoint,
+            _ => self.breakpoints.insert(index, breakpoint),
+        }
+    }
+
+    /// Removes the breakpoint at the given time, and returns it if it existed.
+    pub fn remove(&mut self, time: u64) -> Option<Breakpoint> {
+        let index = self
+            .breakpoints
+            .binary_search_by_key(&time, |b| b.time)
+            .ok()?;
+        Some(self.breakpoints.remove(index))
+    }
+
+    /// Removes all breakpoints.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Forgets the last value that was sent, so that the current value is sent again at the
+    /// start of the next block.
+    ///
+    /// This should be called when the playback position jumps, or when the plugin is reset.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.last_value = None;
+    }
+
+    /// Returns the value of the parameter at the given time, or `None` if the lane is empty.
+    ///
+    /// Before the first breakpoint and after the last one, the value of the closest breakpoint is
+    /// used.
+    pub fn value_at(&self, time: u64) -> Option<f64> {
+        let index = self.breakpoints.partition_point(|b| b.time <= time);
+
+        let value = match (index.checked_sub(1), self.breakpoints.get(index)) {
+            (None, next) => next?.value,
+            (Some(previous), None) => self.breakpoints[previous].value,
+            (Some(previous), Some(next)) => interpolate(&self.breakpoints[previous], next, time),
+        };
+
+        Some(self.range.apply(value))
+    }
+
+    /// Pushes the events for the block starting at `start` and lasting `frames` samples to
+    /// `output`, with their times being relative to the start of the block.
+    ///
+    /// An event is sent at the start of the block if the value changed since the last one that
+    /// was sent. Events are only sent when the value changes, which means stepped parameters
+    /// only receive an event when they reach a new step.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the output event queue is full.
+    pub fn process(
+        &mut self,
+        start: u64,
+        frames: u32,
+        output: &mut OutputEvents,
+    ) -> Result<(), TryPushError> {
+        let end = start + frames as u64;
+        self.cursor = Some(start);
+
+        while let Some((time, value)) = self.seek(end) {
+            output.try_push(self.event(time - start, value))?;
+            self.consume(time, value);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the cursor to the next value change before `end`, and returns its time and value.
+    fn seek(&mut self, end: u64) -> Option<(u64, f64)> {
+        while let Some(time) = self.cursor.filter(|&t| t < end) {
+            let value = self.value_at(time)?;
+
+            if self.last_value != Some(value) {
+                return Some((time, value));
+            }
+
+            self.cursor = self.next_candidate(time);
+        }
+
+        None
+    }
+
+    fn consume(&mut self, time: u64, value: f64) {
+        self.last_value = Some(value);
+        self.cursor = self.next_candidate(time);
+    }
+
+    /// Returns the next time after `time` where the value may change.
+    fn next_candidate(&self, time: u64) -> Option<u64> {
+        let index = self.breakpoints.partition_point(|b| b.time <= time);
+        let next_breakpoint = self.breakpoints.get(index)?.time;
+
+        let is_ramp = index
+            .checked_sub(1)
+            .is_some_and(|i| self.breakpoints[i].interpolation != Interpolation::Step);
+
+        if is_ramp {
+            let resolution = self.resolution as u64;
+            let next_step = (time / resolution + 1) * resolution;
+            Some(next_step.min(next_breakpoint))
+        } else {
+            Some(next_breakpoint)
+        }
+    }
+
+    fn event(&self, time: u64, value: f64) -> ParamValueEvent {
+        ParamValueEvent::new(time as u32, self.param_id, self.pckn, value, self.cookie)
+    }
+}
+
+fn interpolate(previous: &Breakpoint, next: &Breakpoint, time: u64) -> f64 {
+    let position = (time - previous.time) as f64 / (next.time - previous.time) as f64;
+    let (from, to) = (previous.value, next.value);
+
+    match previous.interpolation {
+        Interpolation::Step => from,
+        Interpolation::Exponential if from * to > 0.0 => from * (to / from).powf(position),
+        Interpolation::Linear | Interpolation::Exponential => from + (to - from) * position,
+    }
+}
+
+/// A collection of [`AutomationLane`]s, each automating a different parameter.
+#[derive(Clone, Debug, Default)]
+pub struct AutomationLanes {
+    lanes: Vec<AutomationLane>,
+}
+
+impl AutomationLanes {
+    /// Creates a new, empty collection.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { lanes: Vec::new() }
+    }
+
+    /// Adds a lane to the collection, replacing any lane for the same parameter.
+    pub fn insert(&mut self, lane: AutomationLane) {
+        match self.lane_mut(lane.param_id) {
+            Some(existing) => *existing = lane,
+            None => self.lanes.push(lane),
+        }
+    }
+
+    /// Removes the lane of the given parameter, and returns it if it existed.
+    pub fn remove(&mut self, param_id: ClapId) -> Option<AutomationLane> {
+        let index = self.lanes.iter().position(|l| l.param_id == param_id)?;
+        Some(self.lanes.remove(index))
+    }
+
+    /// Returns the lane of the given parameter.
+    pub fn lane(&self, param_id: ClapId) -> Option<&AutomationLane> {
+        self.lanes.iter().find(|l| l.param_id == param_id)
+    }
+
+    /// Returns the lane of the given parameter, mutably.
+    pub fn lane_mut(&mut self, param_id: ClapId) -> Option<&mut AutomationLane> {
+        self.lanes.iter_mut().find(|l| l.param_id == param_id)
+    }
+
+    /// Returns an iterator over all the lanes of this collection.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &AutomationLane> {
+        self.lanes.iter()
+    }
+
+    /// Returns the number of lanes in this collection.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// Returns `true` if this collection has no lanes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    /// [Resets](AutomationLane::reset) all lanes.
+    pub fn reset(&mut self) {
+        for lane in &mut self.lanes {
+            lane.reset();
+        }
+    }
+
+    /// Pushes the events of all lanes for the given block to `output`, sorted by time.
+    ///
+    /// See [`AutomationLane::process`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the output event queue is full.
+    pub fn process(
+        &mut self,
+        start: u64,
+        frames: u32,
+        output: &mut OutputEvents,
+    ) -> Result<(), TryPushError> {
+        let end = start + frames as u64;
+
+        for lane in &mut self.lanes {
+            lane.cursor = Some(start);
+        }
+
+        loop {
+            let mut next: Option<(usize, u64, f64)> = None;
+
+            for (index, lane) in self.lanes.iter_mut().enumerate() {
+                if let Some((time, value)) = lane.seek(end) {
+                    if next.is_none_or(|(_, next_time, _)| time < next_time) {
+                        next = Some((index, time, value));
+                    }
+                }
+            }
+
+            let Some((index, time, value)) = next else {
+                return Ok(());
+            };
+
+            let lane = &mut self.lanes[index];
+            output.try_push(lane.event(time - start, value))?;
+            lane.consume(time, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clack_common::events::Event;
+    use clack_common::events::io::EventBuffer;
+
+    fn collect(events: &EventBuffer) -> Vec<(u32, u32, f64)> {
+        events
+            .iter()
+            .map(|e| {
+                let event = e.as_event::<ParamValueEvent>().unwrap();
+                (
+                    event.header().time(),
+                    event.param_id().unwrap().get(),
+                    event.value(),
+                )
+            })
+            .collect()
+    }
+
+    fn ramp(id: u32, interpolation: Interpolation) -> AutomationLane {
+        let mut lane =
+            AutomationLane::new(ClapId::new(id), ParamRange::new(0.0, 10.0)).with_resolution(4);
+
+        lane.insert(Breakpoint::new(2, 1.0, interpolation));
+        lane.insert(Breakpoint::new(10, 9.0, Interpolation::Step));
+        lane
+    }
+
+    #[test]
+    fn interpolates_between_breakpoints() {
+        let linear = ramp(0, Interpolation::Linear);
+        assert_eq!(linear.value_at(0), Some(1.0));
+        assert_eq!(linear.value_at(6), Some(5.0));
+        assert_eq!(linear.value_at(100), Some(9.0));
+
+        let exponential = ramp(0, Interpolation::Exponential);
+        assert_eq!(exponential.value_at(6), Some(3.0));
+
+        let step = ramp(0, Interpolation::Step);
+        assert_eq!(step.value_at(9), Some(1.0));
+        assert_eq!(step.value_at(10), Some(9.0));
+
+        let empty = AutomationLane::new(ClapId::new(0), ParamRange::new(0.0, 1.0));
+        assert_eq!(empty.value_at(0), None);
+    }
+
+    #[test]
+    fn emits_ramps_at_resolution() {
+        let mut lane = ramp(3, Interpolation::Linear);
+        let mut events = EventBuffer::new();
+
+        lane.process(0, 8, &mut events.as_output()).unwrap();
+        assert_eq!(collect(&events), [(0, 3, 1.0), (4, 3, 3.0)]);
+
+        events.clear();
+        lane.process(8, 8, &mut events.as_output()).unwrap();
+        assert_eq!(collect(&events), [(0, 3, 7.0), (2, 3, 9.0)]);
+
+        // Nothing changes after the last breakpoint.
+        events.clear();
+        lane.process(16, 8, &mut events.as_output()).unwrap();
+        assert!(events.is_empty());
+
+        lane.reset();
+        lane.process(16, 8, &mut events.as_output()).unwrap();
+        assert_eq!(collect(&events), [(0, 3, 9.0)]);
+    }
+
+    #[test]
+    fn honors_stepped_ranges() {
+        let mut lane =
+            AutomationLane::new(ClapId::new(1), ParamRange::stepped(0.0, 2.0)).with_resolution(1);
+
+        lane.insert(Breakpoint::new(0, 0.0, Interpolation::Linear));
+        lane.insert(Breakpoint::new(8, 4.0, Interpolation::Linear));
+
+        let mut events = EventBuffer::new();
+        lane.process(0, 16, &mut events.as_output()).unwrap();
+
+        // The value is rounded and clamped, so an event is only sent for each new step.
+        assert_eq!(collect(&events), [(0, 1, 0.0), (1, 1, 1.0), (3, 1, 2.0)]);
+    }
+
+    #[test]
+    fn interleaves_lanes() {
+        let mut lanes = AutomationLanes::new();
+        lanes.insert(ramp(1, Interpolation::Step));
+        lanes.insert(ramp(2, Interpolation::Linear));
+        assert_eq!(lanes.len(), 2);
+
+        let mut events = EventBuffer::new();
+        lanes.process(0, 12, &mut events.as_output()).unwrap();
+
+        assert_eq!(
+            collect(&events),
+            [
+                (0, 1, 1.0),
+                (0, 2, 1.0),
+                (4, 2, 3.0),
+                (8, 2, 7.0),
+                (10, 1, 9.0),
+                (10, 2, 9.0),
+            ]
+        );
+    }
+}
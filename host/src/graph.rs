@@ -0,0 +1,929 @@
+//! Processing of multiple plugins connected together in a graph.
+//!
+//! A [`PluginGraph`] owns the audio processors of several plugin instances, and routes audio and
+//! note events between them. Plugins can be chained in series, or run in parallel: when multiple
+//! connections lead to the same audio input port, their signals are summed together.
+//!
+//! Every graph has two special nodes, [`NodeId::INPUT`] and [`NodeId::OUTPUT`], which represent
+//! the audio and events the graph receives and produces, respectively.
+//!
+//! All the buffers needed to process the graph are allocated by [`PluginGraph::activate`], so
+//! that [`PluginGraph::process`] can run on the audio thread without allocating. The graph is
+//! processed in topological order, and the latency of each plugin is compensated for, so that
+//! all signals reaching a node are aligned with each other.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use clack_host::graph::{NodeId, NodePorts, PluginGraph, plugin_latency};
+//! use clack_host::prelude::*;
+//!
+//! # fn chain(
+//! #     mut first: PluginInstance<()>,
+//! #     mut second: PluginInstance<()>,
+//! # ) -> Result<(), Box<dyn std::error::Error>> {
+//! let configuration = PluginAudioConfiguration {
+//!     sample_rate: 48_000.0,
+//!     min_frames_count: 1,
+//!     max_frames_count: 256,
+//! };
+//!
+//! let stereo_effect = NodePorts {
+//!     audio_inputs: vec![2],
+//!     audio_outputs: vec![2],
+//!     note_inputs: 1,
+//!     ..NodePorts::default()
+//! };
+//!
+//! // A graph with a single stereo input and output.
+//! let mut graph = PluginGraph::new(&[2], &[2]);
+//!
+//! let first_processor = first.activate(|_, _| (), configuration)?.start_processing()?;
+//! let first = graph.add_plugin(
+//!     first_processor,
+//!     NodePorts { latency: plugin_latency(&mut first), ..stereo_effect.clone() },
+//! );
+//!
+//! let second_processor = second.activate(|_, _| (), configuration)?.start_processing()?;
+//! let second = graph.add_plugin(
+//!     second_processor,
+//!     NodePorts { latency: plugin_latency(&mut second), ..stereo_effect },
+//! );
+//!
+//! graph.connect_audio(NodeId::INPUT, 0, first, 0)?;
+//! graph.connect_audio(first, 0, second, 0)?;
+//! graph.connect_audio(second, 0, NodeId::OUTPUT, 0)?;
+//! graph.connect_notes(NodeId::INPUT, 0, second, 0)?;
+//!
+//! graph.activate(configuration.sample_rate, configuration.max_frames_count)?;
+//!
+//! // Then, on the audio thread:
+//! let input = [[0.0f32; 256], [0.0f32; 256]];
+//! let mut output = [[0.0f32; 256], [0.0f32; 256]];
+//! let mut output_events = EventBuffer::new();
+//!
+//! graph.process(
+//!     &input,
+//!     &mut output,
+//!     &InputEvents::empty(),
+//!     &mut output_events.as_output(),
+//!     256,
+//!     None,
+//!     None,
+//! )?;
+//! # Ok(()) }
+//! ```
+
+#![deny(missing_docs)]
+
+use crate::host::HostHandlers;
+use crate::latency::{DelayLine, EventDelay, delay_transport};
+use crate::plugin::PluginInstanceError;
+use crate::prelude::{
+    AudioPortBuffer, AudioPortBufferType, AudioPorts, InputAudioBuffers, InputChannel,
+    OutputAudioBuffers,
+};
+use crate::process::StartedPluginAudioProcessor;
+use clack_common::events::UnknownEvent;
+use clack_common::events::event_types::TransportEvent;
+use clack_common::events::io::{EventBuffer, InputEvents, OutputEvents};
+use clack_common::events::spaces::CoreEventSpace;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub use crate::latency::plugin_latency;
+
+/// The number of events each node's event buffers can hold before having to allocate, by default.
+const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// The identifier of a node in a [`PluginGraph`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The node representing the inputs of the graph.
+    ///
+    /// Its audio output ports carry the audio given to [`PluginGraph::process`], and its single
+    /// note output port carries the input events given to it.
+    pub const INPUT: NodeId = NodeId(0);
+    /// The node representing the outputs of the graph.
+    ///
+    /// Its audio input ports are written to the output buffers given to [`PluginGraph::process`],
+    /// and the events reaching its single note input port are written to the output events.
+    pub const OUTPUT: NodeId = NodeId(1);
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            NodeId::INPUT => f.write_str("graph input"),
+            NodeId::OUTPUT => f.write_str("graph output"),
+            NodeId(index) => write!(f, "node #{index}"),
+        }
+    }
+}
+
+/// The ports and latency of a node in a [`PluginGraph`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NodePorts {
+    /// The number of channels of each of the node's audio input ports.
+    pub audio_inputs: Vec<usize>,
+    /// The number of channels of each of the node's audio output ports.
+    pub audio_outputs: Vec<usize>,
+    /// The number of note input ports of the node.
+    pub note_inputs: usize,
+    /// The number of note output ports of the node.
+    pub note_outputs: usize,
+    /// The latency of the node, in samples.
+    ///
+    /// For plugins, this can be retrieved using [`plugin_latency`].
+    pub latency: u32,
+}
+
+/// Errors that can occur while building or processing a [`PluginGraph`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum GraphError {
+    /// The given node does not exist in the graph.
+    UnknownNode(NodeId),
+    /// The given node does not have a port with the given index.
+    UnknownPort {
+        /// The node that was connected.
+        node: NodeId,
+        /// The index of the port that does not exist.
+        port: usize,
+    },
+    /// The connection would create a cycle in the graph.
+    Cycle,
+    /// The graph was not activated, or was modified since it was last activated.
+    NotActivated,
+    /// The block to process is larger than the maximum block size the graph was activated with.
+    BlockTooLarge {
+        /// The number of frames that were requested to be processed.
+        frames_count: u32,
+        /// The maximum number of frames the graph was activated with.
+        max_frames_count: u32,
+    },
+    /// The plugin of the given node failed to process.
+    Process {
+        /// The node whose plugin failed to process.
+        node: NodeId,
+        /// The error returned by the plugin instance.
+        error: PluginInstanceError,
+    },
+}
+
+impl Display for GraphError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::UnknownNode(node) => write!(f, "Unknown graph node: {node}"),
+            GraphError::UnknownPort { node, port } => {
+                write!(f, "Unknown port #{port} on {node}")
+            }
+            GraphError::Cycle => f.write_str("Connection would create a cycle in the graph"),
+            GraphError::NotActivated => f.write_str("Graph is not activated"),
+            GraphError::BlockTooLarge {
+                frames_count,
+                max_frames_count,
+            } => write!(
+                f,
+                "Block of {frames_count} frames is larger than the maximum of {max_frames_count}"
+            ),
+            GraphError::Process { node, error } => write!(f, "Failed to process {node}: {error}"),
+        }
+    }
+}
+
+impl Error for GraphError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GraphError::Process { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A graph of plugins, with their audio and note ports connected together.
+///
+/// See the [module documentation](self) for more information and an example.
+pub struct PluginGraph<H: HostHandlers> {
+    nodes: Vec<Option<Node<H>>>,
+    audio_connections: Vec<Connection>,
+    note_connections: Vec<Connection>,
+    event_capacity: usize,
+    state: Option<GraphState>,
+}
+
+impl<H: HostHandlers> PluginGraph<H> {
+    /// Creates a new, empty graph.
+    ///
+    /// The graph has an audio input port for each item of `audio_inputs`, and an audio output
+    /// port for each item of `audio_outputs`, each item being the port's number of channels.
+    pub fn new(audio_inputs: &[usize], audio_outputs: &[usize]) -> Self {
+        let input = NodePorts {
+            audio_outputs: audio_inputs.to_vec(),
+            note_outputs: 1,
+            ..NodePorts::default()
+        };
+
+        let output = NodePorts {
+            audio_inputs: audio_outputs.to_vec(),
+            note_inputs: 1,
+            ..NodePorts::default()
+        };
+
+        Self {
+            nodes: vec![Some(Node::new(input, None)), Some(Node::new(output, None))],
+            audio_connections: Vec::new(),
+            note_connections: Vec::new(),
+            event_capacity: DEFAULT_EVENT_CAPACITY,
+            state: None,
+        }
+    }
+
+    /// Sets the number of events each node can receive or send per block without allocating.
+    ///
+    /// This defaults to 1024 events.
+    #[inline]
+    pub fn with_event_capacity(mut self, event_capacity: usize) -> Self {
+        self.event_capacity = event_capacity;
+        self.state = None;
+        self
+    }
+
+    /// Adds a plugin's audio processor to the graph, and returns the identifier of its new node.
+    ///
+    /// The given `ports` must match the audio and note ports the plugin has been configured with.
+    /// The graph must be [activated](Self::activate) again after being modified.
+    pub fn add_plugin(
+        &mut self,
+        processor: StartedPluginAudioProcessor<H>,
+        ports: NodePorts,
+    ) -> NodeId {
+        self.state = None;
+        self.nodes.push(Some(Node::new(ports, Some(processor))));
+
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Removes a plugin's node from the graph, as well as all of its connections.
+    ///
+    /// This returns the plugin's audio processor, so that it can be deactivated. If the node does
+    /// not exist, or is one of the graph's input and output nodes, this returns `None`.
+    pub fn remove_plugin(&mut self, node: NodeId) -> Option<StartedPluginAudioProcessor<H>> {
+        let processor = self.nodes.get_mut(node.0)?.as_mut()?.processor.take()?;
+
+        self.nodes[node.0] = None;
+        self.audio_connections
+            .retain(|c| c.source != node && c.destination != node);
+        self.note_connections
+            .retain(|c| c.source != node && c.destination != node);
+        self.state = None;
+
+        Some(processor)
+    }
+
+    /// Returns the audio processor of the plugin in the given node.
+    ///
+    /// This returns `None` if the node does not exist, or is one of the graph's input and output
+    /// nodes.
+    pub fn plugin_mut(&mut self, node: NodeId) -> Option<&mut StartedPluginAudioProcessor<H>> {
+        self.nodes.get_mut(node.0)?.as_mut()?.processor.as_mut()
+    }
+
+    /// Returns the ports of the given node, if it exists.
+    pub fn ports(&self, node: NodeId) -> Option<&NodePorts> {
+        Some(&self.nodes.get(node.0)?.as_ref()?.ports)
+    }
+
+    /// Connects an audio output port of the `source` node to an audio input port of the
+    /// `destination` node.
+    ///
+    /// Channels are connected one-to-one. If the source port only has a single channel, it is
+    /// connected to all the channels of the destination port instead. The graph must be
+    /// [activated](Self::activate) again after being modified.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if either node or port does not exist, or if the connection would
+    /// create a cycle.
+    pub fn connect_audio(
+        &mut self,
+        source: NodeId,
+        source_port: usize,
+        destination: NodeId,
+        destination_port: usize,
+    ) -> Result<(), GraphError> {
+        let connection = self.validate_connection(
+            Connection {
+                source,
+                source_port,
+                destination,
+                destination_port,
+            },
+            |ports| ports.audio_outputs.len(),
+            |ports| ports.audio_inputs.len(),
+        )?;
+
+        if !self.audio_connections.contains(&connection) {
+            self.audio_connections.push(connection);
+            self.state = None;
+        }
+
+        Ok(())
+    }
+
+    /// Connects a note output port of the `source` node to a note input port of the
+    /// `destination` node.
+    ///
+    /// Note and MIDI events sent by the source port are forwarded to the destination port. The
+    /// graph must be [activated](Self::activate) again after being modified.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if either node or port does not exist, or if the connection would
+    /// create a cycle.
+    pub fn connect_notes(
+        &mut self,
+        source: NodeId,
+        source_port: usize,
+        destination: NodeId,
+        destination_port: usize,
+    ) -> Result<(), GraphError> {
+        let connection = self.validate_connection(
+            Connection {
+                source,
+                source_port,
+                destination,
+                destination_port,
+            },
+            |ports| ports.note_outputs,
+            |ports| ports.note_inputs,
+        )?;
+
+        if !self.note_connections.contains(&connection) {
+            self.note_connections.push(connection);
+            self.state = None;
+        }
+
+        Ok(())
+    }
+
+    /// Removes an audio connection, returning whether it existed.
+    pub fn disconnect_audio(
+        &mut self,
+        source: NodeId,
+        source_port: usize,
+        destination: NodeId,
+        destination_port: usize,
+    ) -> bool {
+        let connection = Connection {
+            source,
+            source_port,
+            destination,
+            destination_port,
+        };
+
+        Self::disconnect(&mut self.audio_connections, &mut self.state, connection)
+    }
+
+    /// Removes a note connection, returning whether it existed.
+    pub fn disconnect_notes(
+        &mut self,
+        source: NodeId,
+        source_port: usize,
+        destination: NodeId,
+        destination_port: usize,
+    ) -> bool {
+        let connection = Connection {
+            source,
+            source_port,
+            destination,
+            destination_port,
+        };
+
+        Self::disconnect(&mut self.note_connections, &mut self.state, connection)
+    }
+
+    /// Sorts the graph's nodes, and allocates all the buffers needed to process blocks of up to
+    /// `max_frames_count` frames, at the given sample rate.
+    ///
+    /// This must be called after the graph has been modified, and before it is processed.
+    ///
+    /// # Errors
+    ///
+    /// This returns [`GraphError::Cycle`] if the graph contains a cycle.
+    pub fn activate(&mut self, sample_rate: f64, max_frames_count: u32) -> Result<(), GraphError> {
+        let order = self.sorted_nodes()?;
+
+        let mut input_latencies = vec![0u32; self.nodes.len()];
+        let mut output_latencies = vec![0u32; self.nodes.len()];
+
+        for &index in &order {
+            let input_latency = self
+                .audio_connections
+                .iter()
+                .chain(&self.note_connections)
+                .filter(|c| c.destination.0 == index)
+                .map(|c| output_latencies[c.source.0])
+                .max()
+                .unwrap_or(0);
+
+            let latency = self.nodes[index].as_ref().map_or(0, |n| n.ports.latency);
+            input_latencies[index] = input_latency;
+            output_latencies[index] = input_latency.saturating_add(latency);
+        }
+
+        let frames = max_frames_count as usize;
+        let capacity = self.event_capacity;
+
+        let mut inputs = Vec::with_capacity(self.nodes.len());
+        let mut outputs = Vec::with_capacity(self.nodes.len());
+
+        for node in &self.nodes {
+            let ports = node.as_ref().map(|n| &n.ports);
+            let (audio_inputs, audio_outputs) =
+                ports.map_or((&[][..], &[][..]), |p| (&p.audio_inputs, &p.audio_outputs));
+
+            inputs.push(NodeBuffers::new(audio_inputs, frames, capacity));
+            outputs.push(NodeBuffers::new(audio_outputs, frames, capacity));
+        }
+
+        let audio_delays = self
+            .audio_connections
+            .iter()
+            .map(|c| {
+                let delay = input_latencies[c.destination.0] - output_latencies[c.source.0];
+                let channels = inputs[c.destination.0].audio[c.destination_port].len();
+
+                let mut line = DelayLine::new(channels, delay as usize);
+                line.set_delay(delay as usize);
+                line
+            })
+            .collect();
+
+        let note_delays = self
+            .note_connections
+            .iter()
+            .map(|c| {
+                let delay = input_latencies[c.destination.0] - output_latencies[c.source.0];
+                EventDelay::new(delay, capacity)
+            })
+            .collect();
+
+        self.state = Some(GraphState {
+            sample_rate,
+            max_frames_count,
+            latency: input_latencies[NodeId::OUTPUT.0],
+            input_latencies,
+            order,
+            inputs,
+            outputs,
+            queued_events: (0..self.nodes.len())
+                .map(|_| EventBuffer::with_capacity(capacity))
+                .collect(),
+            audio_delays,
+            note_delays,
+        });
+
+        Ok(())
+    }
+
+    /// Returns `true` if the graph is activated, and ready to be processed.
+    #[inline]
+    pub fn is_activated(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Returns the total latency of the graph, in samples, if it is activated.
+    ///
+    /// This is the latency of the longest path from the graph's input to its output.
+    #[inline]
+    pub fn latency(&self) -> Option<u32> {
+        self.state.as_ref().map(|s| s.latency)
+    }
+
+    /// Queues an event to be sent to the given node during the next processed block.
+    ///
+    /// This can be used to send parameter changes to a specific plugin. The event's time is
+    /// relative to the start of the next block.
+    ///
+    /// This only allocates if the node's event capacity is exceeded.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the graph is not activated, or if the node does not exist.
+    pub fn push_event<E: AsRef<UnknownEvent> + ?Sized>(
+        &mut self,
+        node: NodeId,
+        event: &E,
+    ) -> Result<(), GraphError> {
+        let state = self.state.as_mut().ok_or(GraphError::NotActivated)?;
+
+        match state.queued_events.get_mut(node.0) {
+            Some(queue) if self.nodes[node.0].is_some() => {
+                queue.push(event);
+                Ok(())
+            }
+            _ => Err(GraphError::UnknownNode(node)),
+        }
+    }
+
+    /// Returns all the events the given node sent during the last processed block.
+    ///
+    /// This includes events that are not forwarded through note connections, such as parameter
+    /// changes. This returns `None` if the graph is not activated, or if the node does not exist.
+    pub fn output_events(&self, node: NodeId) -> Option<&EventBuffer> {
+        Some(&self.state.as_ref()?.outputs.get(node.0)?.events)
+    }
+
+    /// Processes a block of `frames_count` frames through the whole graph.
+    ///
+    /// `inputs` and `outputs` contain one buffer per channel of the graph's input and output
+    /// ports respectively, with the channels of each port following those of the previous one.
+    /// Missing or short input channels are treated as silence.
+    ///
+    /// The given input events are sent through the note output port of [`NodeId::INPUT`], and the
+    /// events reaching [`NodeId::OUTPUT`] are written to `output_events`.
+    ///
+    /// Each node receives the given transport delayed by the latency of its inputs, so that it
+    /// matches the audio and events reaching that node.
+    ///
+    /// This method does not allocate, unless a node's event capacity is exceeded.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the graph is not activated, if the block is larger than the
+    /// maximum block size, or if any of the plugins failed to process.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        inputs: &[impl AsRef<[f32]>],
+        outputs: &mut [impl AsMut<[f32]>],
+        input_events: &InputEvents,
+        output_events: &mut OutputEvents,
+        frames_count: u32,
+        steady_time: Option<u64>,
+        transport: Option<&TransportEvent>,
+    ) -> Result<(), GraphError> {
+        let Self {
+            nodes,
+            audio_connections,
+            note_connections,
+            state,
+            ..
+        } = self;
+
+        let state = state.as_mut().ok_or(GraphError::NotActivated)?;
+
+        if frames_count > state.max_frames_count {
+            return Err(GraphError::BlockTooLarge {
+                frames_count,
+                max_frames_count: state.max_frames_count,
+            });
+        }
+
+        let frames = frames_count as usize;
+
+        let graph_input = &mut state.outputs[NodeId::INPUT.0];
+        for (index, channel) in graph_input.audio.iter_mut().flatten().enumerate() {
+            let input = inputs.get(index).map_or(&[][..], |i| i.as_ref());
+            let input = &input[..input.len().min(frames)];
+
+            channel[..input.len()].copy_from_slice(input);
+            channel[input.len()..frames].fill(0.0);
+        }
+
+        graph_input.events.clear();
+        graph_input.events.push_all(input_events);
+
+        for &index in &state.order {
+            if index == NodeId::INPUT.0 {
+                continue;
+            }
+
+            let node_inputs = &mut state.inputs[index];
+
+            for channel in node_inputs.audio.iter_mut().flatten() {
+                channel[..frames].fill(0.0);
+            }
+
+            for (connection, delay) in audio_connections.iter().zip(&mut state.audio_delays) {
+                if connection.destination.0 == index {
+                    mix_delayed(
+                        delay,
+                        &state.outputs[connection.source.0].audio[connection.source_port],
+                        &mut node_inputs.audio[connection.destination_port],
+                        frames,
+                    );
+                }
+            }
+
+            node_inputs.events.clear();
+            node_inputs.events.push_all(&state.queued_events[index]);
+            state.queued_events[index].clear();
+
+            for (connection, delay) in note_connections.iter().zip(&mut state.note_delays) {
+                if connection.destination.0 == index {
+                    let source = &state.outputs[connection.source.0].events;
+                    delay.process(
+                        source.iter().filter(|e| is_note_event(e)),
+                        &mut node_inputs.events,
+                        frames_count,
+                    );
+                }
+            }
+
+            if !node_inputs
+                .events
+                .iter()
+                .map(|e| e.header().time())
+                .is_sorted()
+            {
+                node_inputs.events.sort();
+            }
+
+            if index == NodeId::OUTPUT.0 {
+                let channels = node_inputs.audio.iter().flatten();
+                for (output, channel) in outputs.iter_mut().zip(channels) {
+                    let output = output.as_mut();
+                    let len = output.len().min(frames);
+
+                    output[..len].copy_from_slice(&channel[..len]);
+                }
+
+                for event in &node_inputs.events {
+                    // Events that do not fit in the output buffer are dropped.
+                    let _ = output_events.try_push(event);
+                }
+
+                continue;
+            }
+
+            let Some(processor) = nodes[index].as_mut().and_then(|n| n.processor.as_mut()) else {
+                continue;
+            };
+
+            let node_outputs = &mut state.outputs[index];
+            node_outputs.events.clear();
+
+            let audio_inputs = if node_inputs.audio.is_empty() {
+                InputAudioBuffers::empty()
+            } else {
+                node_inputs
+                    .ports
+                    .with_input_buffers(node_inputs.audio.iter_mut().map(|port| {
+                        AudioPortBuffer {
+                            channels: AudioPortBufferType::f32_input_only(
+                                port.iter_mut()
+                                    .map(|c| InputChannel::variable(&mut c[..frames])),
+                            ),
+                            latency: 0,
+                        }
+                    }))
+            };
+
+            let mut audio_outputs = if node_outputs.audio.is_empty() {
+                OutputAudioBuffers::empty()
+            } else {
+                node_outputs
+                    .ports
+                    .with_output_buffers(node_outputs.audio.iter_mut().map(|port| {
+                        AudioPortBuffer {
+                            channels: AudioPortBufferType::f32_output_only(
+                                port.iter_mut().map(|c| &mut c[..frames]),
+                            ),
+                            latency: 0,
+                        }
+                    }))
+            };
+
+            let transport = transport
+                .map(|t| delay_transport(t, state.input_latencies[index], state.sample_rate));
+
+            processor
+                .process(
+                    &audio_inputs,
+                    &mut audio_outputs,
+                    &node_inputs.events.as_input(),
+                    &mut node_outputs.events.as_output(),
+                    steady_time,
+                    transport.as_ref(),
+                )
+                .map_err(|error| GraphError::Process {
+                    node: NodeId(index),
+                    error,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_connection(
+        &self,
+        connection: Connection,
+        output_count: impl Fn(&NodePorts) -> usize,
+        input_count: impl Fn(&NodePorts) -> usize,
+    ) -> Result<Connection, GraphError> {
+        let source = self
+            .ports(connection.source)
+            .ok_or(GraphError::UnknownNode(connection.source))?;
+        let destination = self
+            .ports(connection.destination)
+            .ok_or(GraphError::UnknownNode(connection.destination))?;
+
+        if connection.source_port >= output_count(source) {
+            return Err(GraphError::UnknownPort {
+                node: connection.source,
+                port: connection.source_port,
+            });
+        }
+
+        if connection.destination_port >= input_count(destination) {
+            return Err(GraphError::UnknownPort {
+                node: connection.destination,
+                port: connection.destination_port,
+            });
+        }
+
+        if self.is_reachable(connection.destination, connection.source) {
+            return Err(GraphError::Cycle);
+        }
+
+        Ok(connection)
+    }
+
+    /// Returns whether `to` can be reached from `from` by following the graph's connections.
+    fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![from];
+
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+
+            if std::mem::replace(&mut visited[node.0], true) {
+                continue;
+            }
+
+            stack.extend(
+                self.connections()
+                    .filter(|c| c.source == node)
+                    .map(|c| c.destination),
+            );
+        }
+
+        false
+    }
+
+    /// Returns the indices of all the graph's nodes, sorted so that each node comes after all
+    /// the nodes connected to its inputs.
+    fn sorted_nodes(&self) -> Result<Vec<usize>, GraphError> {
+        let mut incoming = vec![0usize; self.nodes.len()];
+        for connection in self.connections() {
+            incoming[connection.destination.0] += 1;
+        }
+
+        let mut ready: Vec<usize> = (0..self.nodes.len())
+            .rev()
+            .filter(|&i| self.nodes[i].is_some() && incoming[i] == 0)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(index) = ready.pop() {
+            order.push(index);
+
+            for connection in self.connections().filter(|c| c.source.0 == index) {
+                let count = &mut incoming[connection.destination.0];
+                *count -= 1;
+
+                if *count == 0 {
+                    ready.push(connection.destination.0);
+                }
+            }
+        }
+
+        if order.len() != self.nodes.iter().flatten().count() {
+            return Err(GraphError::Cycle);
+        }
+
+        Ok(order)
+    }
+
+    fn connections(&self) -> impl Iterator<Item = &Connection> {
+        self.audio_connections.iter().chain(&self.note_connections)
+    }
+
+    fn disconnect(
+        connections: &mut Vec<Connection>,
+        state: &mut Option<GraphState>,
+        connection: Connection,
+    ) -> bool {
+        let Some(position) = connections.iter().position(|c| *c == connection) else {
+            return false;
+        };
+
+        connections.remove(position);
+        *state = None;
+        true
+    }
+}
+
+struct Node<H: HostHandlers> {
+    ports: NodePorts,
+    processor: Option<StartedPluginAudioProcessor<H>>,
+}
+
+impl<H: HostHandlers> Node<H> {
+    fn new(ports: NodePorts, processor: Option<StartedPluginAudioProcessor<H>>) -> Self {
+        Self { ports, processor }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Connection {
+    source: NodeId,
+    source_port: usize,
+    destination: NodeId,
+    destination_port: usize,
+}
+
+/// All the buffers allocated when the graph is activated.
+struct GraphState {
+    sample_rate: f64,
+    max_frames_count: u32,
+    latency: u32,
+    /// The latency of the signals reaching each node, in samples.
+    input_latencies: Vec<u32>,
+    order: Vec<usize>,
+    /// The buffers of each node's input ports.
+    inputs: Vec<NodeBuffers>,
+    /// The buffers of each node's output ports.
+    outputs: Vec<NodeBuffers>,
+    queued_events: Vec<EventBuffer>,
+    /// The delay of each audio connection, in the same order as the connections.
+    audio_delays: Vec<DelayLine>,
+    /// The delay of each note connection, in the same order as the connections.
+    note_delays: Vec<EventDelay>,
+}
+
+/// The audio and event buffers of either the inputs or the outputs of a node.
+struct NodeBuffers {
+    /// The buffers of each channel of each port.
+    audio: Vec<Vec<Vec<f32>>>,
+    ports: AudioPorts,
+    events: EventBuffer,
+}
+
+impl NodeBuffers {
+    fn new(ports: &[usize], frames: usize, event_capacity: usize) -> Self {
+        Self {
+            audio: ports
+                .iter()
+                .map(|&channels| vec![vec![0.0; frames]; channels])
+                .collect(),
+            ports: AudioPorts::with_capacity(ports.iter().sum(), ports.len()),
+            events: EventBuffer::with_capacity(event_capacity),
+        }
+    }
+}
+
+/// Adds the delayed `source` channels to the `destination` channels.
+///
+/// If the source only has a single channel, it is added to all destination channels.
+fn mix_delayed(
+    delay: &mut DelayLine,
+    source: &[Vec<f32>],
+    destination: &mut [Vec<f32>],
+    frames: usize,
+) {
+    for (index, output) in destination.iter_mut().enumerate() {
+        let input = match source {
+            [mono] => mono,
+            _ => match source.get(index) {
+                Some(input) => input,
+                None => continue,
+            },
+        };
+
+        delay.mix_channel(index, &input[..frames], &mut output[..frames]);
+    }
+}
+
+fn is_note_event(event: &UnknownEvent) -> bool {
+    matches!(
+        event.as_core_event(),
+        Some(
+            CoreEventSpace::NoteOn(_)
+                | CoreEventSpace::NoteOff(_)
+                | CoreEventSpace::NoteChoke(_)
+                | CoreEventSpace::NoteEnd(_)
+                | CoreEventSpace::NoteExpression(_)
+                | CoreEventSpace::Midi(_)
+                | CoreEventSpace::Midi2(_)
+                | CoreEventSpace::MidiSysEx(_)
+        )
+    )
+}
@@ -0,0 +1,709 @@
+//! Utilities to compensate for the latency of plugins.
+//!
+//! Plugins report their latency through the `latency` extension. When a plugin's output is
+//! mixed with signals that did not go through it (e.g. parallel effect chains, or a dry signal),
+//! the other paths must be delayed by the same amount, so that everything stays aligned.
+//!
+//! This module provides the building blocks to do so:
+//!
+//! * [`DelayLine`] delays audio channels, using preallocated ring buffers;
+//! * [`EventDelay`] delays events, such as notes and parameter changes;
+//! * [`delay_transport`] shifts the transport positions given to a delayed plugin;
+//! * [`LatencyCompensator`] combines all of the above to align multiple parallel paths;
+//! * [`LatencyTracker`] keeps track of latency changes reported by plugins.
+//!
+//! None of these types allocate after being created, which makes them usable on the audio thread.
+//!
+//! # Reacting to latency changes
+//!
+//! A plugin can only change its latency while it is being activated. If its latency changes while
+//! it is active, the plugin has to be restarted (deactivated, then re-activated) so that the new
+//! latency can be applied.
+//!
+//! A [`LatencyTracker`] can be stored in the host's [`SharedHandler`](crate::host::SharedHandler)
+//! to keep track of this. Both latency changes and restart requests schedule a restart, which the
+//! host can then perform on the main thread:
+//!
+//! ```
+//! use clack_extensions::latency::HostLatencyImpl;
+//! use clack_host::latency::LatencyTracker;
+//! use clack_host::prelude::*;
+//!
+//! struct MyHostShared {
+//!     latency: LatencyTracker,
+//! }
+//!
+//! impl SharedHandler<'_> for MyHostShared {
+//!     fn request_restart(&self) {
+//!         self.latency.request_restart();
+//!     }
+//!
+//!     fn request_process(&self) { /* ... */ }
+//!     fn request_callback(&self) { /* ... */ }
+//! }
+//!
+//! struct MyHostMainThread<'a> {
+//!     shared: &'a MyHostShared,
+//! }
+//!
+//! impl<'a> MainThreadHandler<'a> for MyHostMainThread<'a> {}
+//!
+//! impl HostLatencyImpl for MyHostMainThread<'_> {
+//!     fn changed(&mut self) {
+//!         self.shared.latency.changed();
+//!     }
+//! }
+//!
+//! // Then, periodically on the main thread:
+//! # let tracker = LatencyTracker::new();
+//! if tracker.take_restart_request() {
+//!     // Deactivate and re-activate the plugin, then query its new latency using
+//!     // `plugin_latency` and update the compensation delays.
+//! }
+//! ```
+
+#![deny(missing_docs)]
+
+use crate::host::HostHandlers;
+use crate::plugin::PluginInstance;
+use clack_common::events::UnknownEvent;
+use clack_common::events::event_types::{TransportEvent, TransportFlags};
+use clack_common::events::io::EventBuffer;
+use clack_common::extensions::{Extension, PluginExtensionSide, RawExtension};
+use clack_common::utils::{BeatTime, SecondsTime};
+use clap_sys::ext::latency::{CLAP_EXT_LATENCY, clap_plugin_latency};
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Returns the latency of the given plugin instance, in samples.
+///
+/// This uses the plugin's implementation of the `latency` extension, and returns zero if it does
+/// not implement it. As per the CLAP specification, the plugin's latency is only known while
+/// it is activated.
+pub fn plugin_latency<H: HostHandlers>(instance: &mut PluginInstance<H>) -> u32 {
+    let handle = instance.plugin_handle();
+
+    handle
+        .get_extension::<RawPluginLatency>()
+        .and_then(|latency| handle.use_extension(&latency.0).get)
+        // SAFETY: This type ensures the function pointer is valid, and this is called on the
+        // main thread.
+        .map_or(0, |get| unsafe { get(handle.as_raw()) })
+}
+
+/// Keeps track of the latency changes and restart requests of a plugin instance.
+///
+/// This type is thread-safe, and meant to be shared between the host's handlers. See the
+/// [module documentation](self) for an example.
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    latency_changed: AtomicBool,
+    restart_requested: AtomicBool,
+}
+
+impl LatencyTracker {
+    /// Creates a new tracker, with no pending change or restart.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            latency_changed: AtomicBool::new(false),
+            restart_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Records that the plugin's latency changed, and schedules a restart of the plugin.
+    ///
+    /// This is meant to be called from the host's implementation of the `latency` extension's
+    /// `changed` callback.
+    #[inline]
+    pub fn changed(&self) {
+        self.latency_changed.store(true, Ordering::Release);
+        self.request_restart();
+    }
+
+    /// Schedules a restart of the plugin.
+    ///
+    /// This is meant to be called from the host's
+    /// [`request_restart`](crate::host::SharedHandler::request_restart) callback.
+    #[inline]
+    pub fn request_restart(&self) {
+        self.restart_requested.store(true, Ordering::Release);
+    }
+
+    /// Returns `true` if a restart was scheduled since this was last called.
+    #[inline]
+    pub fn take_restart_request(&self) -> bool {
+        self.restart_requested.swap(false, Ordering::AcqRel)
+    }
+
+    /// Returns `true` if the plugin reported a latency change since this was last called.
+    ///
+    /// This can be used to re-query the latency of plugins that are activated without having
+    /// requested a restart.
+    #[inline]
+    pub fn take_latency_change(&self) -> bool {
+        self.latency_changed.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// A multichannel delay line, which delays audio by a fixed number of samples.
+///
+/// All of its buffers are allocated when it is created, for a given maximum delay. Changing the
+/// delay afterwards does not allocate.
+#[derive(Clone, Debug)]
+pub struct DelayLine {
+    channels: Vec<DelayChannel>,
+    delay: usize,
+    max_delay: usize,
+}
+
+#[derive(Clone, Debug)]
+struct DelayChannel {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    /// Creates a new delay line for the given number of channels, which can delay them by up to
+    /// `max_delay` samples.
+    ///
+    /// The delay is initially zero.
+    pub fn new(channel_count: usize, max_delay: usize) -> Self {
+        Self {
+            channels: vec![
+                DelayChannel {
+                    buffer: vec![0.0; max_delay],
+                    position: 0,
+                };
+                channel_count
+            ],
+            delay: 0,
+            max_delay,
+        }
+    }
+
+    /// Returns the number of channels of this delay line.
+    #[inline]
+    pub fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Returns the current delay, in samples.
+    #[inline]
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    /// Returns the maximum delay this delay line can apply, in samples.
+    #[inline]
+    pub fn max_delay(&self) -> usize {
+        self.max_delay
+    }
+
+    /// Sets the delay, in samples.
+    ///
+    /// The delay is capped to the [maximum delay](Self::max_delay). If the delay changes, the
+    /// delay line is [reset](Self::reset).
+    pub fn set_delay(&mut self, delay: usize) {
+        let delay = delay.min(self.max_delay);
+
+        if delay != self.delay {
+            self.delay = delay;
+            self.reset();
+        }
+    }
+
+    /// Clears all the samples currently stored in the delay line.
+    pub fn reset(&mut self) {
+        for channel in &mut self.channels {
+            channel.buffer.fill(0.0);
+            channel.position = 0;
+        }
+    }
+
+    /// Delays the given channel buffers in place.
+    ///
+    /// Buffers past this delay line's channel count are left untouched.
+    pub fn process(&mut self, buffers: &mut [impl AsMut<[f32]>]) {
+        for (channel, buffer) in self.channels.iter_mut().zip(buffers) {
+            for sample in buffer.as_mut() {
+                *sample = channel.delay_sample(self.delay, *sample);
+            }
+        }
+    }
+
+    /// Writes the delayed samples of `input` to `output`, for the given channel.
+    ///
+    /// Only the first `min(input.len(), output.len())` samples are processed.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `channel` is out of bounds.
+    pub fn process_channel(&mut self, channel: usize, input: &[f32], output: &mut [f32]) {
+        let channel = &mut self.channels[channel];
+
+        for (output, input) in output.iter_mut().zip(input) {
+            *output = channel.delay_sample(self.delay, *input);
+        }
+    }
+
+    /// Adds the delayed samples of `input` to `output`, for the given channel.
+    ///
+    /// Only the first `min(input.len(), output.len())` samples are processed.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `channel` is out of bounds.
+    pub fn mix_channel(&mut self, channel: usize, input: &[f32], output: &mut [f32]) {
+        let channel = &mut self.channels[channel];
+
+        for (output, input) in output.iter_mut().zip(input) {
+            *output += channel.delay_sample(self.delay, *input);
+        }
+    }
+}
+
+impl DelayChannel {
+    #[inline]
+    fn delay_sample(&mut self, delay: usize, sample: f32) -> f32 {
+        if delay == 0 {
+            return sample;
+        }
+
+        let delayed = std::mem::replace(&mut self.buffer[self.position], sample);
+
+        self.position += 1;
+        if self.position == delay {
+            self.position = 0;
+        }
+
+        delayed
+    }
+}
+
+/// Delays events by a fixed number of samples.
+///
+/// Events that are delayed past the end of the current block are kept until the block they fall
+/// in is processed. This works for all kinds of events, including notes and parameter changes.
+///
+/// Storing pending events only allocates if more events than the capacity given to
+/// [`new`](Self::new) are pending at the same time.
+pub struct EventDelay {
+    delay: u32,
+    pending: EventBuffer,
+    scratch: EventBuffer,
+}
+
+impl EventDelay {
+    /// Creates a new event delay, which delays events by `delay` samples, and can keep up to
+    /// `capacity` events pending without allocating.
+    pub fn new(delay: u32, capacity: usize) -> Self {
+        Self {
+            delay,
+            pending: EventBuffer::with_capacity(capacity),
+            scratch: EventBuffer::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the current delay, in samples.
+    #[inline]
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    /// Sets the delay, in samples.
+    ///
+    /// This only applies to events processed after this call: events that are already pending
+    /// will still be delivered at their originally scheduled time.
+    #[inline]
+    pub fn set_delay(&mut self, delay: u32) {
+        self.delay = delay;
+    }
+
+    /// Returns `true` if some events are waiting to be delivered in later blocks.
+    #[inline]
+    pub fn has_pending_events(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Drops all pending events.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Delays the given events, for a block of `frames_count` frames.
+    ///
+    /// The events that were delayed into this block, followed by the given events that still fall
+    /// within it after being delayed, are pushed at the end of `output`, with their times being
+    /// relative to the start of the block. If the given events are sorted, so are the events
+    /// pushed to `output`.
+    pub fn process<'a, E: AsRef<UnknownEvent> + ?Sized + 'a>(
+        &mut self,
+        events: impl IntoIterator<Item = &'a E>,
+        output: &mut EventBuffer,
+        frames_count: u32,
+    ) {
+        self.scratch.clear();
+
+        let pending = self.pending.iter().map(|e| (e, e.header().time()));
+        let new = events.into_iter().map(|e| {
+            let event = e.as_ref();
+            (event, event.header().time().saturating_add(self.delay))
+        });
+
+        for (event, time) in pending.chain(new) {
+            if time < frames_count {
+                output.push_with_time(event, time);
+            } else {
+                self.scratch.push_with_time(event, time - frames_count);
+            }
+        }
+
+        std::mem::swap(&mut self.pending, &mut self.scratch);
+    }
+}
+
+/// Returns a copy of the given transport, as seen by a path delayed by `delay` samples.
+///
+/// If the transport is playing, its song positions are moved back by the duration of the delay,
+/// using its tempo for the position in beats. The bar start and number are updated accordingly
+/// if the position moves back before the start of the bar.
+pub fn delay_transport(transport: &TransportEvent, delay: u32, sample_rate: f64) -> TransportEvent {
+    let mut delayed = *transport;
+
+    if delay == 0 || !transport.flags.contains(TransportFlags::IS_PLAYING) {
+        return delayed;
+    }
+
+    let seconds = delay as f64 / sample_rate;
+
+    if transport
+        .flags
+        .contains(TransportFlags::HAS_SECONDS_TIMELINE)
+    {
+        delayed.song_pos_seconds =
+            SecondsTime::from_float(transport.song_pos_seconds.to_float() - seconds);
+    }
+
+    if transport
+        .flags
+        .contains(TransportFlags::HAS_BEATS_TIMELINE | TransportFlags::HAS_TEMPO)
+    {
+        let beats = seconds * transport.tempo / 60.0;
+        let position = transport.song_pos_beats.to_float() - beats;
+        delayed.song_pos_beats = BeatTime::from_float(position);
+
+        if transport.flags.contains(TransportFlags::HAS_TIME_SIGNATURE)
+            && transport.time_signature_denominator > 0
+        {
+            let bar_length = transport.time_signature_numerator as f64 * 4.0
+                / transport.time_signature_denominator as f64;
+
+            let mut bar_start = transport.bar_start.to_float();
+            while bar_length > 0.0 && position < bar_start {
+                bar_start -= bar_length;
+                delayed.bar_number -= 1;
+            }
+
+            delayed.bar_start = BeatTime::from_float(bar_start);
+        }
+    }
+
+    delayed
+}
+
+/// Aligns multiple parallel paths that have different latencies.
+///
+/// Each path is delayed by the difference between the highest latency and its own, so that all
+/// paths end up with the same total [latency](Self::latency). The audio, events and transport of
+/// each path can be delayed accordingly.
+///
+/// All buffers are allocated when the compensator is created, for a given maximum latency. Updating
+/// the latencies afterwards does not allocate.
+pub struct LatencyCompensator {
+    paths: Vec<CompensatedPath>,
+    latency: u32,
+}
+
+struct CompensatedPath {
+    latency: u32,
+    audio: DelayLine,
+    events: EventDelay,
+}
+
+impl LatencyCompensator {
+    /// Creates a new compensator for the given number of paths, each having `channel_count`
+    /// audio channels.
+    ///
+    /// Paths can be delayed by up to `max_latency` samples, and keep up to `event_capacity`
+    /// events pending without allocating. All path latencies are initially zero.
+    pub fn new(
+        path_count: usize,
+        channel_count: usize,
+        max_latency: u32,
+        event_capacity: usize,
+    ) -> Self {
+        let paths = (0..path_count)
+            .map(|_| CompensatedPath {
+                latency: 0,
+                audio: DelayLine::new(channel_count, max_latency as usize),
+                events: EventDelay::new(0, event_capacity),
+            })
+            .collect();
+
+        Self { paths, latency: 0 }
+    }
+
+    /// Returns the number of paths.
+    #[inline]
+    pub fn path_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Returns the total latency of all paths, once compensated.
+    #[inline]
+    pub fn latency(&self) -> u32 {
+        self.latency
+    }
+
+    /// Returns the delay applied to the given path to compensate its latency, or `None` if the
+    /// path does not exist.
+    #[inline]
+    pub fn compensation(&self, path: usize) -> Option<u32> {
+        self.paths.get(path).map(|p| p.audio.delay() as u32)
+    }
+
+    /// Sets the latency of each path, in samples, and updates their compensation delays.
+    ///
+    /// Paths past the end of `latencies` are considered to have no latency. Compensation delays
+    /// are capped to the maximum latency given to [`new`](Self::new).
+    pub fn set_latencies(&mut self, latencies: impl IntoIterator<Item = u32>) {
+        let mut latencies = latencies.into_iter();
+
+        for path in &mut self.paths {
+            path.latency = latencies.next().unwrap_or(0);
+        }
+
+        self.latency = self.paths.iter().map(|p| p.latency).max().unwrap_or(0);
+
+        for path in &mut self.paths {
+            let compensation = self.latency - path.latency;
+            path.audio.set_delay(compensation as usize);
+            path.events.set_delay(path.audio.delay() as u32);
+        }
+    }
+
+    /// Clears all audio and events currently delayed on all paths.
+    pub fn reset(&mut self) {
+        for path in &mut self.paths {
+            path.audio.reset();
+            path.events.reset();
+        }
+    }
+
+    /// Delays the given audio buffers of a path in place.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `path` is out of bounds.
+    #[inline]
+    pub fn process_audio(&mut self, path: usize, buffers: &mut [impl AsMut<[f32]>]) {
+        self.paths[path].audio.process(buffers);
+    }
+
+    /// Delays the given events of a path, for a block of `frames_count` frames.
+    ///
+    /// See [`EventDelay::process`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `path` is out of bounds.
+    #[inline]
+    pub fn process_events<'a, E: AsRef<UnknownEvent> + ?Sized + 'a>(
+        &mut self,
+        path: usize,
+        events: impl IntoIterator<Item = &'a E>,
+        output: &mut EventBuffer,
+        frames_count: u32,
+    ) {
+        self.paths[path]
+            .events
+            .process(events, output, frames_count);
+    }
+
+    /// Returns the transport as seen by the given path, once delayed.
+    ///
+    /// See [`delay_transport`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `path` is out of bounds.
+    #[inline]
+    pub fn transport(
+        &self,
+        path: usize,
+        transport: &TransportEvent,
+        sample_rate: f64,
+    ) -> TransportEvent {
+        delay_transport(
+            transport,
+            self.paths[path].audio.delay() as u32,
+            sample_rate,
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
+struct RawPluginLatency(RawExtension<PluginExtensionSide, clap_plugin_latency>);
+
+// SAFETY: The identifier matches the raw extension type.
+unsafe impl Extension for RawPluginLatency {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_LATENCY];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clack_common::events::event_types::{NoteOnEvent, ParamValueEvent};
+    use clack_common::events::{EventFlags, EventHeader, Pckn};
+    use clack_common::utils::{ClapId, Cookie};
+
+    #[test]
+    fn tracker_schedules_restarts() {
+        let tracker = LatencyTracker::new();
+        assert!(!tracker.take_restart_request());
+
+        tracker.request_restart();
+        assert!(!tracker.take_latency_change());
+        assert!(tracker.take_restart_request());
+        assert!(!tracker.take_restart_request());
+
+        tracker.changed();
+        assert!(tracker.take_latency_change());
+        assert!(tracker.take_restart_request());
+    }
+
+    #[test]
+    fn delay_line_delays_across_blocks() {
+        let mut line = DelayLine::new(2, 8);
+        line.set_delay(3);
+
+        let mut output = Vec::new();
+        for block in 0..3 {
+            let mut buffers = [[0.0f32; 4]; 2];
+            buffers[0] = std::array::from_fn(|i| (block * 4 + i + 1) as f32);
+            buffers[1] = buffers[0].map(|s| -s);
+
+            line.process(&mut buffers);
+            output.extend(buffers[0].iter().zip(&buffers[1]).map(|(l, r)| (*l, *r)));
+        }
+
+        let expected: Vec<_> = (0..12)
+            .map(|i| if i < 3 { 0.0 } else { (i - 2) as f32 })
+            .map(|s| (s, -s))
+            .collect();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn delay_line_mixes_and_caps_delay() {
+        let mut line = DelayLine::new(1, 2);
+        line.set_delay(10);
+        assert_eq!(line.delay(), 2);
+
+        let mut output = [1.0f32; 4];
+        line.mix_channel(0, &[1.0, 2.0, 3.0, 4.0], &mut output);
+        assert_eq!(output, [1.0, 1.0, 2.0, 3.0]);
+
+        line.process_channel(0, &[0.0; 4], &mut output);
+        assert_eq!(output, [3.0, 4.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn event_delay_keeps_events_until_their_block() {
+        let note = NoteOnEvent::new(2, Pckn::new(0u16, 0u16, 60u16, 0u32), 1.0);
+        let param =
+            ParamValueEvent::new(6, ClapId::new(1), Pckn::match_all(), 0.5, Cookie::empty());
+
+        let mut delay = EventDelay::new(5, 16);
+        let mut output = EventBuffer::new();
+
+        delay.process([note.as_ref(), param.as_ref()], &mut output, 8);
+        let times: Vec<u32> = output.iter().map(|e| e.header().time()).collect();
+        assert_eq!(times, [7]);
+        assert!(delay.has_pending_events());
+
+        output.clear();
+        delay.process([] as [&UnknownEvent; 0], &mut output, 8);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output.get(0).unwrap().header().time(), 3);
+        assert_eq!(
+            output
+                .get(0)
+                .unwrap()
+                .as_event::<ParamValueEvent>()
+                .unwrap()
+                .value(),
+            0.5
+        );
+        assert!(!delay.has_pending_events());
+    }
+
+    #[test]
+    fn transport_is_moved_back() {
+        let mut transport = TransportEvent {
+            header: EventHeader::new_core(0, EventFlags::empty()),
+            flags: TransportFlags::IS_PLAYING
+                | TransportFlags::HAS_TEMPO
+                | TransportFlags::HAS_BEATS_TIMELINE
+                | TransportFlags::HAS_SECONDS_TIMELINE
+                | TransportFlags::HAS_TIME_SIGNATURE,
+            song_pos_beats: BeatTime::from_float(8.5),
+            song_pos_seconds: SecondsTime::from_float(4.25),
+            tempo: 120.0,
+            tempo_inc: 0.0,
+            loop_start_beats: BeatTime::from_int(0),
+            loop_end_beats: BeatTime::from_int(0),
+            loop_start_seconds: SecondsTime::from_int(0),
+            loop_end_seconds: SecondsTime::from_int(0),
+            bar_start: BeatTime::from_int(8),
+            bar_number: 2,
+            time_signature_numerator: 4,
+            time_signature_denominator: 4,
+        };
+
+        // One second at 120 BPM is two beats.
+        let delayed = delay_transport(&transport, 48_000, 48_000.0);
+        assert_eq!(delayed.song_pos_seconds.to_float(), 3.25);
+        assert_eq!(delayed.song_pos_beats.to_float(), 6.5);
+        assert_eq!(delayed.bar_start.to_float(), 4.0);
+        assert_eq!(delayed.bar_number, 1);
+
+        transport.flags.remove(TransportFlags::IS_PLAYING);
+        assert_eq!(delay_transport(&transport, 48_000, 48_000.0), transport);
+    }
+
+    #[test]
+    fn compensator_aligns_paths() {
+        let mut compensator = LatencyCompensator::new(3, 1, 64, 16);
+        compensator.set_latencies([10, 0, 4]);
+
+        assert_eq!(compensator.latency(), 10);
+        assert_eq!(compensator.compensation(0), Some(0));
+        assert_eq!(compensator.compensation(1), Some(10));
+        assert_eq!(compensator.compensation(2), Some(6));
+        assert_eq!(compensator.compensation(3), None);
+
+        let mut buffers = [[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]];
+        compensator.process_audio(2, &mut buffers);
+        assert_eq!(buffers, [[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0]]);
+    }
+}
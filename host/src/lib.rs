@@ -259,12 +259,19 @@
 //! # Ok(()) }
 //! ```
 
+pub mod automation;
 pub mod bundle;
 pub mod extensions;
 pub use clack_common::factory;
+pub mod graph;
 pub mod host;
+pub mod latency;
+pub mod midi_file;
+pub mod offline;
 pub mod plugin;
 pub mod process;
+#[cfg(feature = "libloading")]
+pub mod scan;
 mod util;
 
 pub use clack_common::events;
@@ -0,0 +1,739 @@
+//! Playback of Standard MIDI Files, for hosts.
+//!
+//! A [`MidiFile`] can be parsed from the contents of a Standard MIDI File (SMF) of type 0 or 1.
+//! Its events are timed in ticks, which are converted to seconds using a [`TempoMap`]: either
+//! the one stored in the file itself, or a custom one.
+//!
+//! A [`MidiFilePlayer`] then converts all the events of the file to sample-timed events, and
+//! produces the events for each processed block. Depending on the [`MidiFileDialect`] supported
+//! by the plugin's note port, those are either sent as raw MIDI events, or as CLAP note events.
+//!
+//! Combined with the [`OfflineRenderer`](crate::offline::OfflineRenderer), this allows to render
+//! a MIDI file through an instrument plugin.
+//!
+//! # Example
+//!
+//! ```
+//! use clack_host::midi_file::{MidiFile, MidiFileDialect, MidiFilePlayer, TempoMap};
+//! use clack_host::prelude::*;
+//!
+//! # fn play(data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+//! let file = MidiFile::parse(data)?;
+//! let tempo_map = TempoMap::from_file(&file);
+//! let player = MidiFilePlayer::new(&file, &tempo_map, 48_000.0, MidiFileDialect::Clap);
+//!
+//! // Then, for each block:
+//! let mut events = EventBuffer::new();
+//! player.process(0, 256, &mut events.as_output())?;
+//! # Ok(())
+//! # }
+//! ```
+
+#![deny(missing_docs)]
+
+use clack_common::events::event_types::{MidiEvent, MidiSysExEvent, NoteOffEvent, NoteOnEvent};
+use clack_common::events::io::{OutputEvents, TryPushError};
+use clack_common::events::{Match, Pckn};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// The tempo used by MIDI files that don't specify one, in microseconds per quarter note.
+/// This is 120 beats per minute.
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// An error that occurred while parsing a [`MidiFile`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MidiFileError {
+    /// The data does not start with a valid `MThd` header chunk.
+    InvalidHeader,
+    /// The file is of an unsupported format. Only formats 0 and 1 are supported.
+    UnsupportedFormat(u16),
+    /// The data ended in the middle of a chunk or event.
+    UnexpectedEnd,
+    /// A track contains an event without a status byte, and there is no running status to use.
+    MissingStatus {
+        /// The index of the track containing the event.
+        track: usize,
+    },
+}
+
+impl Display for MidiFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiFileError::InvalidHeader => f.write_str("Invalid MIDI file header"),
+            MidiFileError::UnsupportedFormat(format) => {
+                write!(f, "Unsupported MIDI file format: {format}")
+            }
+            MidiFileError::UnexpectedEnd => f.write_str("Unexpected end of MIDI file data"),
+            MidiFileError::MissingStatus { track } => {
+                write!(f, "Missing status byte in MIDI file track #{track}")
+            }
+        }
+    }
+}
+
+impl Error for MidiFileError {}
+
+/// How the ticks of a [`MidiFile`] relate to time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Timing {
+    /// Ticks are subdivisions of a quarter note, whose duration depends on the tempo.
+    TicksPerQuarterNote(u16),
+    /// Ticks are subdivisions of an SMPTE frame, and do not depend on the tempo.
+    Smpte {
+        /// The number of frames per second. A value of 29 stands for 29.97 (drop-frame).
+        frames_per_second: u8,
+        /// The number of ticks per frame.
+        ticks_per_frame: u8,
+    },
+}
+
+/// An event of a [`MidiTrack`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TrackEventKind {
+    /// A MIDI channel message. Messages that are shorter than 3 bytes are padded with zeroes.
+    Midi([u8; 3]),
+    /// A system exclusive message, including its leading `0xF0` byte.
+    SysEx(Vec<u8>),
+    /// A tempo change, in microseconds per quarter note.
+    Tempo(u32),
+}
+
+/// A timed event of a [`MidiTrack`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrackEvent {
+    /// The time of the event, in ticks since the start of the track.
+    pub tick: u64,
+    /// The contents of the event.
+    pub kind: TrackEventKind,
+}
+
+/// A track of a [`MidiFile`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MidiTrack {
+    /// The events of the track, sorted by time.
+    ///
+    /// Meta events other than tempo changes are not included.
+    pub events: Vec<TrackEvent>,
+    /// The time of the end of the track, in ticks.
+    pub end_tick: u64,
+}
+
+/// The contents of a Standard MIDI File.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MidiFile {
+    /// The format of the file: 0 for a single track, 1 for multiple simultaneous tracks.
+    pub format: u16,
+    /// How the ticks of the file relate to time.
+    pub timing: Timing,
+    /// The tracks of the file.
+    pub tracks: Vec<MidiTrack>,
+}
+
+impl MidiFile {
+    /// Parses the contents of a Standard MIDI File of format 0 or 1.
+    ///
+    /// Unknown chunks are skipped.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the data is not a valid MIDI file, or if its format is
+    /// unsupported.
+    pub fn parse(data: &[u8]) -> Result<Self, MidiFileError> {
+        let mut reader = Reader::new(data);
+
+        let (id, header) = reader.chunk()?;
+        if id != *b"MThd" || header.len() < 6 {
+            return Err(MidiFileError::InvalidHeader);
+        }
+
+        let mut header = Reader::new(header);
+        let format = header.u16()?;
+        let track_count = header.u16()?;
+        let division = header.u16()?;
+
+        if format > 1 {
+            return Err(MidiFileError::UnsupportedFormat(format));
+        }
+
+        let timing = if division & 0x8000 != 0 {
+            Timing::Smpte {
+                frames_per_second: ((division >> 8) as u8 as i8).unsigned_abs(),
+                ticks_per_frame: division as u8,
+            }
+        } else {
+            Timing::TicksPerQuarterNote(division)
+        };
+
+        let mut tracks = Vec::with_capacity(track_count as usize);
+
+        while tracks.len() < track_count as usize {
+            let (id, track) = reader.chunk()?;
+            if id == *b"MTrk" {
+                tracks.push(parse_track(track, tracks.len())?);
+            }
+        }
+
+        Ok(Self {
+            format,
+            timing,
+            tracks,
+        })
+    }
+
+    /// Returns the time of the end of the longest track, in ticks.
+    pub fn end_tick(&self) -> u64 {
+        self.tracks.iter().map(|t| t.end_tick).max().unwrap_or(0)
+    }
+}
+
+fn parse_track(data: &[u8], track: usize) -> Result<MidiTrack, MidiFileError> {
+    let mut reader = Reader::new(data);
+    let mut events = Vec::new();
+    let mut tick = 0u64;
+    let mut running_status = None;
+
+    while !reader.is_empty() {
+        tick += reader.variable_length()? as u64;
+
+        let status = match reader.peek()? {
+            status if status & 0x80 != 0 => {
+                reader.u8()?;
+                status
+            }
+            _ => running_status.ok_or(MidiFileError::MissingStatus { track })?,
+        };
+
+        let kind = match status {
+            0x80..=0xEF => {
+                running_status = Some(status);
+
+                let first = reader.u8()?;
+                let second = match status & 0xF0 {
+                    0xC0 | 0xD0 => 0,
+                    _ => reader.u8()?,
+                };
+
+                TrackEventKind::Midi([status, first, second])
+            }
+            0xF0 | 0xF7 => {
+                running_status = None;
+
+                let length = reader.variable_length()?;
+                let data = reader.bytes(length as usize)?;
+
+                // Escaped (0xF7) packets are sent as is, while the leading byte of regular
+                // messages is not stored in the file.
+                let mut message = Vec::with_capacity(data.len() + 1);
+                if status == 0xF0 {
+                    message.push(0xF0);
+                }
+                message.extend_from_slice(data);
+
+                TrackEventKind::SysEx(message)
+            }
+            0xFF => {
+                running_status = None;
+
+                let meta_type = reader.u8()?;
+                let length = reader.variable_length()?;
+                let data = reader.bytes(length as usize)?;
+
+                match (meta_type, data) {
+                    // End of track.
+                    (0x2F, _) => break,
+                    (0x51, &[a, b, c]) => TrackEventKind::Tempo(u32::from_be_bytes([0, a, b, c])),
+                    _ => continue,
+                }
+            }
+            // System common and realtime messages are not allowed in MIDI files.
+            _ => return Err(MidiFileError::MissingStatus { track }),
+        };
+
+        events.push(TrackEvent { tick, kind });
+    }
+
+    Ok(MidiTrack {
+        events,
+        end_tick: tick,
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek(&self) -> Result<u8, MidiFileError> {
+        self.data
+            .first()
+            .copied()
+            .ok_or(MidiFileError::UnexpectedEnd)
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], MidiFileError> {
+        if length > self.data.len() {
+            return Err(MidiFileError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, MidiFileError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, MidiFileError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, MidiFileError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads a variable-length quantity, which is at most 4 bytes long.
+    fn variable_length(&mut self) -> Result<u32, MidiFileError> {
+        let mut value = 0;
+
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn chunk(&mut self) -> Result<([u8; 4], &'a [u8]), MidiFileError> {
+        let id = self.bytes(4)?;
+        let length = self.u32()?;
+        let data = self.bytes(length as usize)?;
+
+        Ok(([id[0], id[1], id[2], id[3]], data))
+    }
+}
+
+/// A change of tempo in a [`TempoMap`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TempoChange {
+    /// The time of the change, in ticks.
+    pub tick: u64,
+    /// The new tempo, in microseconds per quarter note.
+    pub micros_per_quarter: u32,
+}
+
+/// The tempo changes of a MIDI file, used to convert its ticks to seconds.
+///
+/// Until the first tempo change, the tempo is 120 beats per minute. Files using
+/// [SMPTE timing](Timing::Smpte) do not depend on the tempo, and their tempo changes are ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TempoMap {
+    timing: Timing,
+    changes: Vec<TempoChange>,
+}
+
+impl TempoMap {
+    /// Creates a new tempo map with the given timing, and without any tempo change.
+    #[inline]
+    pub const fn new(timing: Timing) -> Self {
+        Self {
+            timing,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Creates a tempo map from all the tempo changes of the given file.
+    pub fn from_file(file: &MidiFile) -> Self {
+        let mut map = Self::new(file.timing);
+
+        for track in &file.tracks {
+            for event in &track.events {
+                if let TrackEventKind::Tempo(micros_per_quarter) = event.kind {
+                    map.set_tempo(event.tick, micros_per_quarter);
+                }
+            }
+        }
+
+        map
+    }
+
+    /// Returns the timing of this tempo map.
+    #[inline]
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Returns the tempo changes of this map, sorted by time.
+    #[inline]
+    pub fn changes(&self) -> &[TempoChange] {
+        &self.changes
+    }
+
+    /// Sets the tempo from the given tick onwards, in microseconds per quarter note.
+    ///
+    /// This replaces any existing tempo change at the same tick.
+    pub fn set_tempo(&mut self, tick: u64, micros_per_quarter: u32) {
+        let change = TempoChange {
+            tick,
+            micros_per_quarter,
+        };
+        let index = self.changes.partition_point(|c| c.tick < tick);
+
+        match self.changes.get_mut(index) {
+            Some(existing) if existing.tick == tick => *existing = change,
+            _ => self.changes.insert(index, change),
+        }
+    }
+
+    /// Sets the tempo from the given tick onwards, in beats per minute.
+    #[inline]
+    pub fn set_bpm(&mut self, tick: u64, bpm: f64) {
+        self.set_tempo(tick, (60_000_000.0 / bpm).round() as u32);
+    }
+
+    /// Returns the time of the given tick, in seconds.
+    pub fn seconds_at(&self, tick: u64) -> f64 {
+        let ticks_per_quarter = match self.timing {
+            Timing::TicksPerQuarterNote(ticks) => ticks.max(1) as f64,
+            Timing::Smpte {
+                frames_per_second,
+                ticks_per_frame,
+            } => {
+                let frames_per_second = match frames_per_second {
+                    29 => 29.97,
+                    fps => fps.max(1) as f64,
+                };
+
+                return tick as f64 / (frames_per_second * ticks_per_frame.max(1) as f64);
+            }
+        };
+
+        let mut seconds = 0.0;
+        let mut previous = TempoChange {
+            tick: 0,
+            micros_per_quarter: DEFAULT_TEMPO,
+        };
+
+        for change in self.changes.iter().take_while(|c| c.tick < tick) {
+            seconds += segment_seconds(&previous, change.tick, ticks_per_quarter);
+            previous = *change;
+        }
+
+        seconds + segment_seconds(&previous, tick, ticks_per_quarter)
+    }
+}
+
+fn segment_seconds(start: &TempoChange, end_tick: u64, ticks_per_quarter: f64) -> f64 {
+    let quarters = end_tick.saturating_sub(start.tick) as f64 / ticks_per_quarter;
+    quarters * start.micros_per_quarter as f64 / 1_000_000.0
+}
+
+/// How a [`MidiFilePlayer`] sends notes to a plugin.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MidiFileDialect {
+    /// All MIDI messages are sent as is, using [`MidiEvent`] and [`MidiSysExEvent`].
+    Midi,
+    /// Note On and Note Off messages are sent as [`NoteOnEvent`] and [`NoteOffEvent`].
+    ///
+    /// All other messages are dropped.
+    Clap,
+}
+
+#[derive(Clone, Debug)]
+enum PlayerEventKind {
+    Midi([u8; 3]),
+    SysEx(Range<usize>),
+}
+
+#[derive(Clone, Debug)]
+struct PlayerEvent {
+    sample: u64,
+    kind: PlayerEventKind,
+}
+
+/// Produces the sample-timed events of a [`MidiFile`], for each processed block.
+///
+/// All events are converted when the player is created, so that [`process`](Self::process)
+/// never allocates and can be called on the audio thread.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Clone, Debug)]
+pub struct MidiFilePlayer {
+    events: Vec<PlayerEvent>,
+    sysex_data: Vec<u8>,
+    dialect: MidiFileDialect,
+    port_index: u16,
+    length: u64,
+}
+
+impl MidiFilePlayer {
+    /// Creates a player for the given file, using the given tempo map and sample rate.
+    ///
+    /// The events of all tracks are merged together. Events happening at the same time are sent
+    /// in track order.
+    pub fn new(
+        file: &MidiFile,
+        tempo_map: &TempoMap,
+        sample_rate: f64,
+        dialect: MidiFileDialect,
+    ) -> Self {
+        let to_sample = |tick| (tempo_map.seconds_at(tick) * sample_rate).round() as u64;
+
+        let mut events = Vec::new();
+        let mut sysex_data = Vec::new();
+
+        for track in &file.tracks {
+            for event in &track.events {
+                let kind = match &event.kind {
+                    TrackEventKind::Midi(data) => PlayerEventKind::Midi(*data),
+                    TrackEventKind::SysEx(data) => {
+                        let start = sysex_data.len();
+                        sysex_data.extend_from_slice(data);
+                        PlayerEventKind::SysEx(start..sysex_data.len())
+                    }
+                    TrackEventKind::Tempo(_) => continue,
+                };
+
+                events.push(PlayerEvent {
+                    sample: to_sample(event.tick),
+                    kind,
+                });
+            }
+        }
+
+        // This sort is stable, which preserves the order of events within each track.
+        events.sort_by_key(|e| e.sample);
+
+        Self {
+            events,
+            sysex_data,
+            dialect,
+            port_index: 0,
+            length: to_sample(file.end_tick()),
+        }
+    }
+
+    /// Sets the index of the note port the events are sent to. This is zero by default.
+    #[inline]
+    pub fn with_port_index(mut self, port_index: u16) -> Self {
+        self.port_index = port_index;
+        self
+    }
+
+    /// Returns the dialect the events are sent with.
+    #[inline]
+    pub fn dialect(&self) -> MidiFileDialect {
+        self.dialect
+    }
+
+    /// Returns the length of the file, in samples.
+    #[inline]
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Pushes the events for the block starting at sample `start` and lasting `frames` samples
+    /// to `output`, with their times being relative to the start of the block.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the output event queue is full.
+    pub fn process(
+        &self,
+        start: u64,
+        frames: u32,
+        output: &mut OutputEvents,
+    ) -> Result<(), TryPushError> {
+        let end = start + frames as u64;
+        let first = self.events.partition_point(|e| e.sample < start);
+
+        for event in self.events[first..].iter().take_while(|e| e.sample < end) {
+            let time = (event.sample - start) as u32;
+
+            match (&event.kind, self.dialect) {
+                (PlayerEventKind::Midi(data), MidiFileDialect::Midi) => {
+                    output.try_push(MidiEvent::new(time, self.port_index, *data))?
+                }
+                (PlayerEventKind::SysEx(range), MidiFileDialect::Midi) => output.try_push(
+                    MidiSysExEvent::new(time, self.port_index, &self.sysex_data[range.clone()]),
+                )?,
+                (PlayerEventKind::Midi(data), MidiFileDialect::Clap) => {
+                    self.push_note(time, *data, output)?
+                }
+                (PlayerEventKind::SysEx(_), MidiFileDialect::Clap) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_note(
+        &self,
+        time: u32,
+        [status, key, velocity]: [u8; 3],
+        output: &mut OutputEvents,
+    ) -> Result<(), TryPushError> {
+        let channel = (status & 0x0F) as u16;
+        let pckn = Pckn::new(self.port_index, channel, key as u16, Match::All);
+        let velocity_f64 = velocity as f64 / 127.0;
+
+        match status & 0xF0 {
+            0x90 if velocity > 0 => output.try_push(NoteOnEvent::new(time, pckn, velocity_f64)),
+            0x80 | 0x90 => output.try_push(NoteOffEvent::new(time, pckn, velocity_f64)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clack_common::events::io::EventBuffer;
+    use clack_common::events::{Event, UnknownEvent};
+
+    /// A type 1 file at 96 ticks per quarter note, with a tempo track going from 120 to 60 BPM
+    /// after one quarter note, and a track with two notes and a SysEx message.
+    const FILE: &[u8] = &[
+        b'M', b'T', b'h', b'd', 0, 0, 0, 6, //
+        0, 1, 0, 2, 0, 96, //
+        b'M', b'T', b'r', b'k', 0, 0, 0, 18, //
+        0x00, 0xFF, 0x51, 3, 0x07, 0xA1, 0x20, // 120 BPM
+        0x60, 0xFF, 0x51, 3, 0x0F, 0x42, 0x40, // 60 BPM
+        0x00, 0xFF, 0x2F, 0, //
+        b'M', b'T', b'r', b'k', 0, 0, 0, 26, //
+        0x00, 0x91, 60, 100, // Note On
+        0x60, 60, 0, // Running status, Note Off
+        0x00, 0xF0, 3, 0x7E, 0x01, 0xF7, // SysEx
+        0x81, 0x40, 0x91, 64, 127, // Note On, 192 ticks later
+        0x30, 0x81, 64, 64, // Note Off
+        0x00, 0xFF, 0x2F, 0, //
+    ];
+
+    fn times(events: &EventBuffer) -> Vec<(u32, u16)> {
+        events
+            .iter()
+            .map(|e: &UnknownEvent| (e.header().time(), e.header().type_id()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_files() {
+        let file = MidiFile::parse(FILE).unwrap();
+
+        assert_eq!(file.format, 1);
+        assert_eq!(file.timing, Timing::TicksPerQuarterNote(96));
+        assert_eq!(file.tracks.len(), 2);
+        assert_eq!(file.tracks[0].events.len(), 2);
+        assert_eq!(file.end_tick(), 336);
+
+        let events = &file.tracks[1].events;
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[1].kind, TrackEventKind::Midi([0x91, 60, 0]));
+        assert_eq!(
+            events[2].kind,
+            TrackEventKind::SysEx(vec![0xF0, 0x7E, 0x01, 0xF7])
+        );
+        assert_eq!(events[3].tick, 288);
+
+        assert_eq!(
+            MidiFile::parse(&FILE[..40]),
+            Err(MidiFileError::UnexpectedEnd)
+        );
+        assert_eq!(
+            MidiFile::parse(b"RIFF\0\0\0\0"),
+            Err(MidiFileError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn converts_ticks_with_tempo_map() {
+        let file = MidiFile::parse(FILE).unwrap();
+        let tempo_map = TempoMap::from_file(&file);
+
+        assert_eq!(tempo_map.seconds_at(48), 0.25);
+        assert_eq!(tempo_map.seconds_at(96), 0.5);
+        assert_eq!(tempo_map.seconds_at(192), 1.5);
+
+        let mut constant = TempoMap::new(file.timing);
+        constant.set_bpm(0, 240.0);
+        assert_eq!(constant.seconds_at(192), 0.5);
+
+        let smpte = TempoMap::new(Timing::Smpte {
+            frames_per_second: 25,
+            ticks_per_frame: 40,
+        });
+        assert_eq!(smpte.seconds_at(500), 0.5);
+    }
+
+    #[test]
+    fn plays_midi_events() {
+        let file = MidiFile::parse(FILE).unwrap();
+        let tempo_map = TempoMap::from_file(&file);
+        let player = MidiFilePlayer::new(&file, &tempo_map, 1000.0, MidiFileDialect::Midi);
+
+        assert_eq!(player.length(), 3000);
+
+        let mut events = EventBuffer::new();
+        player.process(0, 1000, &mut events.as_output()).unwrap();
+        assert_eq!(
+            times(&events),
+            [
+                (0, MidiEvent::TYPE_ID),
+                (500, MidiEvent::TYPE_ID),
+                (500, MidiSysExEvent::TYPE_ID)
+            ]
+        );
+
+        events.clear();
+        player.process(2000, 1000, &mut events.as_output()).unwrap();
+        assert_eq!(times(&events), [(500, MidiEvent::TYPE_ID)]);
+
+        let note = events.get(0).unwrap().as_event::<MidiEvent>().unwrap();
+        assert_eq!(note.data(), [0x91, 64, 127]);
+    }
+
+    #[test]
+    fn plays_clap_note_events() {
+        let file = MidiFile::parse(FILE).unwrap();
+        let tempo_map = TempoMap::from_file(&file);
+        let player = MidiFilePlayer::new(&file, &tempo_map, 1000.0, MidiFileDialect::Clap)
+            .with_port_index(1);
+
+        let mut events = EventBuffer::new();
+        player.process(0, 4000, &mut events.as_output()).unwrap();
+
+        assert_eq!(
+            times(&events),
+            [
+                (0, NoteOnEvent::TYPE_ID),
+                (500, NoteOffEvent::TYPE_ID),
+                (2500, NoteOnEvent::TYPE_ID),
+                (3000, NoteOffEvent::TYPE_ID),
+            ]
+        );
+
+        let note = events.get(2).unwrap().as_event::<NoteOnEvent>().unwrap();
+        assert_eq!(note.port_index(), Match::Specific(1));
+        assert_eq!(note.channel(), Match::Specific(1));
+        assert_eq!(note.key(), Match::Specific(64));
+        assert_eq!(note.velocity(), 1.0);
+    }
+}
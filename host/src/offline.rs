@@ -0,0 +1,417 @@
+//! Faster-than-realtime rendering of a plugin's output.
+//!
+//! The [`OfflineRenderer`] drives a whole processing session on its own: it activates a
+//! [`PluginInstance`], feeds it fixed-size blocks of an input signal alongside a timeline of
+//! events, and collects everything the plugin outputs into owned buffers. This is useful for
+//! bouncing or exporting audio, as well as for regression tests.
+//!
+//! If the plugin implements the `render` extension, it is switched to offline rendering mode for
+//! the duration of the session. Once the input has been fully processed, rendering continues
+//! for as long as the plugin's `tail` requires, or until it reports it can go to sleep.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use clack_host::events::event_types::NoteOnEvent;
+//! use clack_host::offline::OfflineRenderer;
+//! use clack_host::prelude::*;
+//!
+//! # fn render(mut instance: PluginInstance<()>) -> Result<(), PluginInstanceError> {
+//! // One second of silence, in stereo.
+//! let input = [vec![0.0f32; 48_000], vec![0.0f32; 48_000]];
+//!
+//! // The event times are sample positions, relative to the start of the input.
+//! let mut timeline = EventBuffer::new();
+//! timeline.push(&NoteOnEvent::new(24_000, Pckn::new(0u16, 0u16, 60u16, 0u32), 1.0));
+//!
+//! let rendered = OfflineRenderer::new(48_000.0, 512)
+//!     .with_output_channels(2)
+//!     .render(&mut instance, |_, _| (), &input, &timeline)?;
+//!
+//! println!(
+//!     "Rendered {} frames, including {} frames of tail",
+//!     rendered.frames, rendered.tail_frames
+//! );
+//! # Ok(()) }
+//! ```
+
+use crate::host::HostHandlers;
+use crate::plugin::{PluginInstance, PluginInstanceError};
+use crate::prelude::{
+    AudioPortBuffer, AudioPortBufferType, AudioPorts, InputAudioBuffers, InputChannel,
+    OutputAudioBuffers, PluginAudioConfiguration,
+};
+use crate::process::{ProcessStatus, StartedPluginAudioProcessor};
+use clack_common::events::UnknownEvent;
+use clack_common::events::io::EventBuffer;
+use clack_common::extensions::{Extension, PluginExtensionSide, RawExtension};
+use clap_sys::ext::render::{
+    CLAP_EXT_RENDER, CLAP_RENDER_OFFLINE, CLAP_RENDER_REALTIME, clap_plugin_render,
+    clap_plugin_render_mode,
+};
+use clap_sys::ext::tail::{CLAP_EXT_TAIL, clap_plugin_tail};
+use std::ffi::CStr;
+
+/// Output samples whose absolute value is under this threshold are considered silent.
+const SILENCE_THRESHOLD: f32 = 1.0e-5;
+
+/// Renders a plugin's output offline, as fast as possible.
+///
+/// See the [module documentation](self) for more information and an example.
+#[derive(Clone, Debug)]
+pub struct OfflineRenderer {
+    sample_rate: f64,
+    block_size: u32,
+    output_channels: usize,
+    min_length: u64,
+    max_tail: u64,
+}
+
+impl OfflineRenderer {
+    /// Creates a new renderer, which processes audio at the given sample rate, in blocks of at
+    /// most `block_size` frames.
+    ///
+    /// By default, the renderer produces a single stereo output, and renders at most 10 seconds
+    /// of tail.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `block_size` is zero.
+    pub fn new(sample_rate: f64, block_size: u32) -> Self {
+        assert!(block_size > 0, "Block size must not be zero.");
+
+        Self {
+            sample_rate,
+            block_size,
+            output_channels: 2,
+            min_length: 0,
+            max_tail: (sample_rate * 10.0) as u64,
+        }
+    }
+
+    /// Sets the number of channels of the plugin's main output port.
+    #[inline]
+    pub fn with_output_channels(mut self, output_channels: usize) -> Self {
+        self.output_channels = output_channels;
+        self
+    }
+
+    /// Sets the minimum number of frames to render before the tail, even if the input signal is
+    /// shorter.
+    ///
+    /// This is useful for plugins that do not take audio input, such as instruments.
+    #[inline]
+    pub fn with_min_length(mut self, frames: u64) -> Self {
+        self.min_length = frames;
+        self
+    }
+
+    /// Sets the maximum number of frames of tail to render after the end of the input.
+    ///
+    /// This limit is used for plugins that report an infinite tail, or that do not implement the
+    /// `tail` extension but keep requesting to be processed.
+    #[inline]
+    pub fn with_max_tail(mut self, frames: u64) -> Self {
+        self.max_tail = frames;
+        self
+    }
+
+    /// Renders the output of the given plugin instance.
+    ///
+    /// The plugin is activated using the given `audio_processor` constructor, and is deactivated
+    /// before this method returns. It therefore must not be active when this is called.
+    ///
+    /// The `input` signal is fed to the plugin's main input port, one buffer per channel. If it
+    /// has no channels, the plugin is not given any input port. Channels that are shorter than
+    /// the others are padded with silence.
+    ///
+    /// The `timeline` contains the events to send to the plugin, with their times being sample
+    /// positions relative to the start of the input. The events do not need to be sorted.
+    ///
+    /// The plugin is processed until the end of the input, the end of the last event, or the
+    /// [minimum length](Self::with_min_length), whichever comes last. Processing then continues
+    /// until the plugin returns [`ProcessStatus::Sleep`], or its tail ends.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the plugin could not be activated, if it failed to start
+    /// processing, or if processing failed.
+    pub fn render<H, FA>(
+        &self,
+        instance: &mut PluginInstance<H>,
+        audio_processor: FA,
+        input: &[impl AsRef<[f32]>],
+        timeline: &EventBuffer,
+    ) -> Result<OfflineRender, PluginInstanceError>
+    where
+        H: HostHandlers,
+        FA: for<'a> FnOnce(
+            &'a <H as HostHandlers>::Shared<'a>,
+            &mut <H as HostHandlers>::MainThread<'a>,
+        ) -> <H as HostHandlers>::AudioProcessor<'a>,
+    {
+        let offline = set_render_mode(instance, CLAP_RENDER_OFFLINE);
+        let result = self.render_activated(instance, audio_processor, input, timeline);
+
+        if offline {
+            set_render_mode(instance, CLAP_RENDER_REALTIME);
+        }
+
+        result.map(|render| OfflineRender { offline, ..render })
+    }
+
+    fn render_activated<H, FA>(
+        &self,
+        instance: &mut PluginInstance<H>,
+        audio_processor: FA,
+        input: &[impl AsRef<[f32]>],
+        timeline: &EventBuffer,
+    ) -> Result<OfflineRender, PluginInstanceError>
+    where
+        H: HostHandlers,
+        FA: for<'a> FnOnce(
+            &'a <H as HostHandlers>::Shared<'a>,
+            &mut <H as HostHandlers>::MainThread<'a>,
+        ) -> <H as HostHandlers>::AudioProcessor<'a>,
+    {
+        let configuration = PluginAudioConfiguration {
+            sample_rate: self.sample_rate,
+            min_frames_count: 1,
+            max_frames_count: self.block_size,
+        };
+
+        let processor = instance.activate(audio_processor, configuration)?;
+        let mut processor = match processor.start_processing() {
+            Ok(processor) => processor,
+            Err(e) => {
+                instance.deactivate(e.into_stopped_processor());
+                return Err(PluginInstanceError::StartProcessingFailed);
+            }
+        };
+
+        let result = self.process_all(&mut processor, input, timeline);
+        instance.deactivate(processor.stop_processing());
+
+        result
+    }
+
+    fn process_all<H: HostHandlers>(
+        &self,
+        processor: &mut StartedPluginAudioProcessor<H>,
+        input: &[impl AsRef<[f32]>],
+        timeline: &EventBuffer,
+    ) -> Result<OfflineRender, PluginInstanceError> {
+        let mut events: Vec<&UnknownEvent> = timeline.iter().collect();
+        events.sort_by_key(|e| e.header().time());
+
+        let input_length = input.iter().map(|c| c.as_ref().len()).max().unwrap_or(0) as u64;
+        let events_length = events.last().map_or(0, |e| e.header().time() as u64 + 1);
+        let length = input_length.max(events_length).max(self.min_length);
+
+        let block_size = self.block_size as usize;
+        let mut input_block = vec![vec![0.0f32; block_size]; input.len()];
+        let mut output_block = vec![vec![0.0f32; block_size]; self.output_channels];
+        let mut input_ports = AudioPorts::with_capacity(input.len(), 1);
+        let mut output_ports = AudioPorts::with_capacity(self.output_channels, 1);
+        let mut block_events = EventBuffer::with_capacity(events.len().min(block_size));
+        let mut block_output_events = EventBuffer::with_capacity(block_size);
+
+        let mut render = OfflineRender {
+            channels: vec![Vec::with_capacity(length as usize); self.output_channels],
+            output_events: EventBuffer::new(),
+            frames: 0,
+            tail_frames: 0,
+            offline: false,
+        };
+
+        let mut position = 0u64;
+        let mut next_event = 0;
+        let mut tail_end = None;
+        let mut status = ProcessStatus::Continue;
+        let mut frames = 0;
+
+        loop {
+            let end = if position < length {
+                length
+            } else {
+                let is_quiet = status == ProcessStatus::ContinueIfNotQuiet
+                    && output_block
+                        .iter()
+                        .all(|c| c[..frames].iter().all(|s| s.abs() < SILENCE_THRESHOLD));
+
+                if status == ProcessStatus::Sleep || is_quiet {
+                    break;
+                }
+
+                *tail_end.get_or_insert_with(|| length.saturating_add(self.tail_length(processor)))
+            };
+
+            if position >= end {
+                break;
+            }
+
+            frames = (end - position).min(block_size as u64) as usize;
+            let block_end = position + frames as u64;
+
+            for (block, channel) in input_block.iter_mut().zip(input) {
+                let channel = channel.as_ref();
+                let start = (position as usize).min(channel.len());
+                let available = &channel[start..(start + frames).min(channel.len())];
+
+                block[..available.len()].copy_from_slice(available);
+                block[available.len()..frames].fill(0.0);
+            }
+
+            block_events.clear();
+            while let Some(event) = events.get(next_event) {
+                let time = event.header().time() as u64;
+                if time >= block_end {
+                    break;
+                }
+
+                block_events.push_with_time(*event, (time - position) as u32);
+                next_event += 1;
+            }
+
+            let inputs = if input_block.is_empty() {
+                InputAudioBuffers::empty()
+            } else {
+                input_ports.with_input_buffers([AudioPortBuffer {
+                    channels: AudioPortBufferType::f32_input_only(
+                        input_block
+                            .iter_mut()
+                            .map(|b| InputChannel::variable(&mut b[..frames])),
+                    ),
+                    latency: 0,
+                }])
+            };
+
+            let mut outputs = if output_block.is_empty() {
+                OutputAudioBuffers::empty()
+            } else {
+                output_ports.with_output_buffers([AudioPortBuffer {
+                    channels: AudioPortBufferType::f32_output_only(
+                        output_block.iter_mut().map(|b| &mut b[..frames]),
+                    ),
+                    latency: 0,
+                }])
+            };
+
+            block_output_events.clear();
+            status = processor.process(
+                &inputs,
+                &mut outputs,
+                &block_events.as_input(),
+                &mut block_output_events.as_output(),
+                Some(position),
+                None,
+            )?;
+
+            for (channel, block) in render.channels.iter_mut().zip(&output_block) {
+                channel.extend_from_slice(&block[..frames]);
+            }
+
+            for event in &block_output_events {
+                let time = position + event.header().time() as u64;
+                let time = u32::try_from(time).unwrap_or(u32::MAX);
+                render.output_events.push_with_time(event, time);
+            }
+
+            if position >= length {
+                render.tail_frames += frames as u64;
+            }
+
+            position = block_end;
+        }
+
+        render.frames = position;
+        Ok(render)
+    }
+
+    /// Returns the number of frames of tail to render, capped to the maximum tail length.
+    fn tail_length<H: HostHandlers>(&self, processor: &mut StartedPluginAudioProcessor<H>) -> u64 {
+        let handle = processor.plugin_handle();
+
+        let tail = handle
+            .get_extension::<RawPluginTail>()
+            .and_then(|tail| handle.use_extension(&tail.0).get)
+            // SAFETY: This type ensures the function pointer is valid, and this is called on the
+            // audio thread.
+            .map(|get| unsafe { get(handle.as_raw()) });
+
+        match tail {
+            Some(tail) if tail != u32::MAX => (tail as u64).min(self.max_tail),
+            _ => self.max_tail,
+        }
+    }
+}
+
+/// The output of an [`OfflineRenderer`].
+#[derive(Debug)]
+pub struct OfflineRender {
+    /// The samples of each channel of the plugin's main output port.
+    ///
+    /// All channels are [`frames`](Self::frames) long.
+    pub channels: Vec<Vec<f32>>,
+    /// All the events the plugin produced, with their times being sample positions relative to
+    /// the start of the render.
+    pub output_events: EventBuffer,
+    /// The total number of frames that were rendered.
+    pub frames: u64,
+    /// The number of frames that were rendered after the end of the input, as part of the
+    /// plugin's tail.
+    pub tail_frames: u64,
+    /// Whether the plugin was switched to offline rendering mode.
+    ///
+    /// This is `false` if the plugin does not implement the `render` extension, or if it refused
+    /// to switch modes.
+    pub offline: bool,
+}
+
+fn set_render_mode<H: HostHandlers>(
+    instance: &mut PluginInstance<H>,
+    mode: clap_plugin_render_mode,
+) -> bool {
+    let handle = instance.plugin_handle();
+
+    let Some(render) = handle.get_extension::<RawPluginRender>() else {
+        return false;
+    };
+
+    match handle.use_extension(&render.0).set {
+        // SAFETY: This type ensures the function pointer is valid, and this is called on the
+        // main thread.
+        Some(set) => unsafe { set(handle.as_raw(), mode) },
+        None => false,
+    }
+}
+
+#[derive(Copy, Clone)]
+struct RawPluginRender(RawExtension<PluginExtensionSide, clap_plugin_render>);
+
+// SAFETY: The identifier matches the raw extension type.
+unsafe impl Extension for RawPluginRender {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_RENDER];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
+
+#[derive(Copy, Clone)]
+struct RawPluginTail(RawExtension<PluginExtensionSide, clap_plugin_tail>);
+
+// SAFETY: The identifier matches the raw extension type.
+unsafe impl Extension for RawPluginTail {
+    const IDENTIFIERS: &[&CStr] = &[CLAP_EXT_TAIL];
+    type ExtensionSide = PluginExtensionSide;
+
+    #[inline]
+    unsafe fn from_raw(raw: RawExtension<Self::ExtensionSide>) -> Self {
+        // SAFETY: the guarantee that this pointer is of the correct type is upheld by the caller.
+        Self(unsafe { raw.cast() })
+    }
+}
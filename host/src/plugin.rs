@@ -5,10 +5,12 @@ use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
 use std::sync::Arc;
 
+mod descriptor;
 mod error;
 mod handle;
 pub(crate) mod instance;
 
+pub use descriptor::*;
 pub use error::PluginInstanceError;
 pub use handle::*;
 use instance::*;
@@ -0,0 +1,377 @@
+use clack_common::plugin::PluginDescriptor;
+use clack_common::plugin::features;
+use clack_common::utils::ClapVersion;
+use std::convert::Infallible;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// An owned copy of a [`PluginDescriptor`].
+///
+/// Unlike [`PluginDescriptor`], which borrows from the bundle it was read from, this type owns all
+/// of its data. It can therefore be kept around after the bundle is unloaded, e.g. to display a
+/// list of available plugins, or to store it in a database.
+///
+/// All strings are stored as UTF-8 Rust [`String`]s. The plugin's [`features`](Self::features)
+/// are parsed into [`PluginFeature`]s.
+///
+/// If the `serde` feature is enabled, this type can be serialized and deserialized using `serde`.
+///
+/// # Example
+///
+/// ```
+/// use clack_host::plugin::features::{AUDIO_EFFECT, STEREO};
+/// use clack_host::plugin::{PluginDescriptor, PluginDescriptorOwned, PluginFeature};
+///
+/// let descriptor = PluginDescriptor::new("org.rust-audio.clack.gain", "Clack Gain Example")
+///     .with_features([AUDIO_EFFECT, STEREO, c"clack:custom"]);
+///
+/// let owned = PluginDescriptorOwned::from_descriptor(&descriptor).unwrap();
+///
+/// assert_eq!(owned.id, "org.rust-audio.clack.gain");
+/// assert_eq!(owned.name.as_deref(), Some("Clack Gain Example"));
+/// assert!(owned.has_feature(&PluginFeature::AudioEffect));
+/// assert_eq!(owned.features[2], PluginFeature::Other("clack:custom".into()));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PluginDescriptorOwned {
+    /// The version of the CLAP API the plugin was built against.
+    #[cfg_attr(feature = "serde", serde(with = "ClapVersionDef"))]
+    pub clap_version: ClapVersion,
+    /// The unique ID of the plugin.
+    pub id: String,
+    /// The user-friendly name of the plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: Option<String>,
+    /// The vendor of the plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vendor: Option<String>,
+    /// The URL of the plugin's homepage.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub url: Option<String>,
+    /// The URL of the plugin's user manual.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub manual_url: Option<String>,
+    /// The URL of the plugin's support page.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub support_url: Option<String>,
+    /// The version string of the plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: Option<String>,
+    /// A short description of the plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
+    /// The features of the plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub features: Vec<PluginFeature>,
+}
+
+impl PluginDescriptorOwned {
+    /// Creates a new descriptor for a plugin with the given ID, and no other information.
+    ///
+    /// The [`clap_version`](Self::clap_version) is set to [`ClapVersion::CURRENT`].
+    #[inline]
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            clap_version: ClapVersion::CURRENT,
+            id: id.into(),
+            name: None,
+            vendor: None,
+            url: None,
+            manual_url: None,
+            support_url: None,
+            version: None,
+            description: None,
+            features: Vec::new(),
+        }
+    }
+
+    /// Copies all the information of the given plugin descriptor.
+    ///
+    /// Non-UTF-8 strings are converted lossily. However, if the plugin's ID is missing or is not
+    /// valid UTF-8, this returns [`None`].
+    pub fn from_descriptor(descriptor: &PluginDescriptor) -> Option<Self> {
+        let to_string = |s: &CStr| s.to_string_lossy().into_owned();
+
+        Some(Self {
+            clap_version: ClapVersion::from_raw(descriptor.as_raw().clap_version),
+            id: descriptor.id()?.to_str().ok()?.to_owned(),
+            name: descriptor.name().map(to_string),
+            vendor: descriptor.vendor().map(to_string),
+            url: descriptor.url().map(to_string),
+            manual_url: descriptor.manual_url().map(to_string),
+            support_url: descriptor.support_url().map(to_string),
+            version: descriptor.version().map(to_string),
+            description: descriptor.description().map(to_string),
+            features: descriptor
+                .features()
+                .map(PluginFeature::from_c_str)
+                .collect(),
+        })
+    }
+
+    /// Returns `true` if the plugin declared the given feature.
+    #[inline]
+    pub fn has_feature(&self, feature: &PluginFeature) -> bool {
+        self.features.contains(feature)
+    }
+}
+
+impl TryFrom<&PluginDescriptor> for PluginDescriptorOwned {
+    type Error = InvalidPluginId;
+
+    #[inline]
+    fn try_from(value: &PluginDescriptor) -> Result<Self, Self::Error> {
+        Self::from_descriptor(value).ok_or(InvalidPluginId)
+    }
+}
+
+/// An error returned when converting a [`PluginDescriptor`] into a [`PluginDescriptorOwned`], if
+/// the plugin's ID is missing or is not valid UTF-8.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct InvalidPluginId;
+
+impl Display for InvalidPluginId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Plugin ID is missing or is not valid UTF-8")
+    }
+}
+
+impl std::error::Error for InvalidPluginId {}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(remote = "ClapVersion")]
+struct ClapVersionDef {
+    major: u32,
+    minor: u32,
+    revision: u32,
+}
+
+macro_rules! plugin_features {
+    ($($variant:ident => $constant:ident,)*) => {
+        /// A feature of a plugin, as declared in its descriptor.
+        ///
+        /// All the standard features listed in the [`features`] module have their own variant.
+        /// Any other feature is stored as-is in the [`Other`](PluginFeature::Other) variant.
+        ///
+        /// If the `serde` feature is enabled, features are serialized as their string value.
+        #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+        #[non_exhaustive]
+        pub enum PluginFeature {
+            $(
+                #[doc = concat!("The [`", stringify!($constant), "`](features::", stringify!($constant), ") feature.")]
+                $variant,
+            )*
+            /// A non-standard feature, usually formatted as `"$namespace:$feature"`.
+            Other(String),
+        }
+
+        impl PluginFeature {
+            /// Returns the C string value of this feature, if it is a standard feature.
+            pub fn as_c_str(&self) -> Option<&'static CStr> {
+                match self {
+                    $(Self::$variant => Some(features::$constant),)*
+                    Self::Other(_) => None,
+                }
+            }
+
+            fn from_standard(feature: &[u8]) -> Option<Self> {
+                $(
+                    if feature == features::$constant.to_bytes() {
+                        return Some(Self::$variant);
+                    }
+                )*
+
+                None
+            }
+        }
+    };
+}
+
+plugin_features! {
+    Instrument => INSTRUMENT,
+    AudioEffect => AUDIO_EFFECT,
+    NoteEffect => NOTE_EFFECT,
+    Analyzer => ANALYZER,
+    Synthesizer => SYNTHESIZER,
+    Sampler => SAMPLER,
+    Drum => DRUM,
+    DrumMachine => DRUM_MACHINE,
+    Filter => FILTER,
+    Phaser => PHASER,
+    Equalizer => EQUALIZER,
+    DeEsser => DEESSER,
+    PhaseVocoder => PHASE_VOCODER,
+    Granular => GRANULAR,
+    FrequencyShifter => FREQUENCY_SHIFTER,
+    PitchShifter => PITCH_SHIFTER,
+    Distortion => DISTORTION,
+    TransientShaper => TRANSIENT_SHAPER,
+    Compressor => COMPRESSOR,
+    Limiter => LIMITER,
+    Flanger => FLANGER,
+    Chorus => CHORUS,
+    Delay => DELAY,
+    Reverb => REVERB,
+    Tremolo => TREMOLO,
+    Glitch => GLITCH,
+    Utility => UTILITY,
+    PitchCorrection => PITCH_CORRECTION,
+    Restoration => RESTORATION,
+    MultiEffects => MULTI_EFFECTS,
+    Mixing => MIXING,
+    Mastering => MASTERING,
+    Mono => MONO,
+    Stereo => STEREO,
+    Surround => SURROUND,
+    Ambisonic => AMBISONIC,
+}
+
+impl PluginFeature {
+    /// Parses a feature from its C string value.
+    ///
+    /// Non-UTF-8 non-standard features are converted lossily.
+    pub fn from_c_str(feature: &CStr) -> Self {
+        Self::from_standard(feature.to_bytes())
+            .unwrap_or_else(|| Self::Other(feature.to_string_lossy().into_owned()))
+    }
+
+    /// Returns the string value of this feature.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Other(feature) => feature,
+            // Standard features are always ASCII.
+            _ => self.as_c_str().and_then(|s| s.to_str().ok()).unwrap_or(""),
+        }
+    }
+
+    /// Returns `true` if this is one of the standard features listed in the [`features`] module.
+    #[inline]
+    pub fn is_standard(&self) -> bool {
+        !matches!(self, Self::Other(_))
+    }
+}
+
+impl From<&str> for PluginFeature {
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self::from_standard(value.as_bytes()).unwrap_or_else(|| Self::Other(value.to_owned()))
+    }
+}
+
+impl From<String> for PluginFeature {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::from_standard(value.as_bytes()).unwrap_or(Self::Other(value))
+    }
+}
+
+impl From<&CStr> for PluginFeature {
+    #[inline]
+    fn from(value: &CStr) -> Self {
+        Self::from_c_str(value)
+    }
+}
+
+impl FromStr for PluginFeature {
+    type Err = Infallible;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl Display for PluginFeature {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PluginFeature {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PluginFeature {
+    #[inline]
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn parses_all_standard_features() {
+        let standard = [
+            features::INSTRUMENT,
+            features::AUDIO_EFFECT,
+            features::NOTE_EFFECT,
+            features::ANALYZER,
+            features::SYNTHESIZER,
+            features::SAMPLER,
+            features::DRUM,
+            features::DRUM_MACHINE,
+            features::FILTER,
+            features::PHASER,
+            features::EQUALIZER,
+            features::DEESSER,
+            features::PHASE_VOCODER,
+            features::GRANULAR,
+            features::FREQUENCY_SHIFTER,
+            features::PITCH_SHIFTER,
+            features::DISTORTION,
+            features::TRANSIENT_SHAPER,
+            features::COMPRESSOR,
+            features::LIMITER,
+            features::FLANGER,
+            features::CHORUS,
+            features::DELAY,
+            features::REVERB,
+            features::TREMOLO,
+            features::GLITCH,
+            features::UTILITY,
+            features::PITCH_CORRECTION,
+            features::RESTORATION,
+            features::MULTI_EFFECTS,
+            features::MIXING,
+            features::MASTERING,
+            features::MONO,
+            features::STEREO,
+            features::SURROUND,
+            features::AMBISONIC,
+        ];
+
+        for constant in standard {
+            let feature = PluginFeature::from_c_str(constant);
+            assert!(feature.is_standard(), "{constant:?} was not recognized");
+            assert_eq!(feature.as_c_str(), Some(constant));
+            assert_eq!(feature.as_str().as_bytes(), constant.to_bytes());
+            assert_eq!(PluginFeature::from(feature.as_str()), feature);
+        }
+    }
+
+    #[test]
+    pub fn keeps_non_standard_features() {
+        let feature: PluginFeature = "clack:custom".parse().unwrap();
+
+        assert_eq!(feature, PluginFeature::Other("clack:custom".into()));
+        assert!(!feature.is_standard());
+        assert_eq!(feature.as_c_str(), None);
+        assert_eq!(feature.to_string(), "clack:custom");
+        assert_eq!(
+            PluginFeature::from("stereo".to_string()),
+            PluginFeature::Stereo
+        );
+    }
+}
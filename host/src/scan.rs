@@ -0,0 +1,299 @@
+//! Discovery of the CLAP plugin bundles installed on the system.
+//!
+//! CLAP bundles are installed in a set of standard locations, which depend on the platform (see
+//! [`standard_clap_paths`]). Users can also add their own locations using the `CLAP_PATH`
+//! environment variable.
+//!
+//! Discovering the plugins a bundle contains requires loading it, which can be slow. Therefore,
+//! the [`PluginScanner`] stores its results into a [`ScanCache`], which can be persisted between
+//! runs: bundles that did not change since they were last scanned are not loaded again.
+//!
+//! The scan results are stored as owned [`ScannedBundle`] and [`PluginDescriptorOwned`] records,
+//! which remain available after the bundles are unloaded. Bundles that failed to load are reported
+//! individually in the [`ScanReport`], instead of aborting the whole scan.
+//!
+//! Because loading a bundle runs arbitrary code, a faulty bundle may crash or hang the host while
+//! it is being scanned. To avoid this, bundles can be scanned in separate worker processes using
+//! an [`IsolatedScanner`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! # pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use clack_host::scan::{PluginScanner, ScanCache};
+//!
+//! let mut cache = ScanCache::load("/home/user/.cache/my-host/plugins")?;
+//!
+//! // SAFETY: we trust the plugins installed on this system.
+//! let report = unsafe { PluginScanner::new().scan(&mut cache) };
+//!
+//! for error in &report.errors {
+//!     eprintln!("Failed to scan {}: {}", error.path.display(), error.error);
+//! }
+//!
+//! for (bundle, plugin) in cache.plugins() {
+//!     println!("Found {} in {}", plugin.id, bundle.path.display());
+//! }
+//!
+//! cache.save("/home/user/.cache/my-host/plugins")?;
+//! # Ok(()) }
+//! ```
+
+use crate::bundle::{PluginBundle, PluginBundleError};
+use crate::plugin::PluginDescriptorOwned;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::{Duration, SystemTime};
+
+mod cache;
+mod isolated;
+mod paths;
+
+pub use cache::ScanCache;
+pub use isolated::{IsolatedScanner, run_worker, run_worker_if_requested};
+pub use paths::{find_bundles, standard_clap_paths};
+
+/// The results of scanning a single bundle.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScannedBundle {
+    /// The path of the bundle.
+    pub path: PathBuf,
+    /// The modification time of the bundle at the time it was scanned.
+    pub modified: SystemTime,
+    /// All the plugins found in the bundle.
+    pub plugins: Vec<PluginDescriptorOwned>,
+}
+
+/// Loads the bundle at the given path, and reads the descriptors of all the plugins it contains.
+///
+/// Plugins with a missing or invalid ID are skipped.
+///
+/// # Errors
+///
+/// This returns an error if the bundle's modification time can't be read, if the bundle can't be
+/// loaded, or if it does not expose a plugin factory.
+///
+/// # Safety
+///
+/// This loads the bundle in the current process, which runs arbitrary code. See
+/// [`PluginBundle::load`] for more information.
+pub unsafe fn scan_bundle(path: &Path) -> Result<ScannedBundle, ScanError> {
+    let modified = std::fs::metadata(path)?.modified()?;
+
+    // SAFETY: upheld by the caller.
+    let bundle = unsafe { PluginBundle::load(path)? };
+    let factory = bundle
+        .get_plugin_factory()
+        .ok_or(ScanError::MissingPluginFactory)?;
+
+    Ok(ScannedBundle {
+        path: path.to_path_buf(),
+        modified,
+        plugins: factory
+            .plugin_descriptors()
+            .filter_map(PluginDescriptorOwned::from_descriptor)
+            .collect(),
+    })
+}
+
+/// Scans a set of directories for CLAP bundles, and the plugins they contain.
+///
+/// See the [module documentation](self) for more information and an example.
+#[derive(Clone, Debug)]
+pub struct PluginScanner {
+    search_paths: Vec<PathBuf>,
+}
+
+impl PluginScanner {
+    /// Creates a new scanner, which searches the [standard CLAP paths](standard_clap_paths).
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_search_paths(standard_clap_paths())
+    }
+
+    /// Creates a new scanner, which only searches the given directories.
+    #[inline]
+    pub fn with_search_paths(search_paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            search_paths: search_paths.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns the directories this scanner searches.
+    #[inline]
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// Searches all the directories for bundles, and updates the given cache with their contents.
+    ///
+    /// Bundles that were not modified since they were cached are not loaded again. Bundles that
+    /// could not be found anymore, or that now fail to be scanned, are removed from the cache.
+    ///
+    /// # Safety
+    ///
+    /// This loads all the bundles that changed in the current process, which runs arbitrary code.
+    /// See [`PluginBundle::load`] for more information.
+    pub unsafe fn scan(&self, cache: &mut ScanCache) -> ScanReport {
+        // SAFETY: upheld by the caller.
+        self.scan_with(cache, |path| unsafe { scan_bundle(path) })
+    }
+
+    /// Searches all the directories for bundles, and updates the given cache with their contents,
+    /// scanning each bundle in a separate worker process.
+    ///
+    /// This behaves like [`scan`](Self::scan), except bundles are never loaded in the current
+    /// process. Bundles whose worker crashed or timed out are reported as errors.
+    pub fn scan_isolated(&self, cache: &mut ScanCache, scanner: &IsolatedScanner) -> ScanReport {
+        self.scan_with(cache, |path| scanner.scan_bundle(path))
+    }
+
+    fn scan_with(
+        &self,
+        cache: &mut ScanCache,
+        mut scan_bundle: impl FnMut(&Path) -> Result<ScannedBundle, ScanError>,
+    ) -> ScanReport {
+        let mut report = ScanReport::default();
+        let bundles = find_bundles(&self.search_paths);
+
+        for path in &bundles {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified());
+
+            if let Ok(modified) = modified {
+                if cache.get_up_to_date(path, modified).is_some() {
+                    report.cached.push(path.clone());
+                    continue;
+                }
+            }
+
+            match scan_bundle(path) {
+                Ok(bundle) => {
+                    cache.insert(bundle);
+                    report.scanned.push(path.clone());
+                }
+                Err(error) => {
+                    cache.remove(path);
+                    report.errors.push(BundleScanError {
+                        path: path.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+
+        cache.retain(|bundle| {
+            let exists = bundles.binary_search(&bundle.path).is_ok();
+            if !exists {
+                report.removed.push(bundle.path.clone());
+            }
+
+            exists
+        });
+
+        report
+    }
+}
+
+impl Default for PluginScanner {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A summary of what happened during a [scan](PluginScanner::scan).
+///
+/// All paths are bundle paths.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// The bundles that were loaded and scanned.
+    pub scanned: Vec<PathBuf>,
+    /// The bundles that were not modified, and whose cached results were kept.
+    pub cached: Vec<PathBuf>,
+    /// The bundles that were removed from the cache, because they could not be found anymore.
+    pub removed: Vec<PathBuf>,
+    /// The bundles that failed to be scanned, alongside the reason why.
+    pub errors: Vec<BundleScanError>,
+}
+
+/// An error that occurred while scanning a specific bundle.
+#[derive(Debug)]
+pub struct BundleScanError {
+    /// The path of the bundle that failed to be scanned.
+    pub path: PathBuf,
+    /// The error that occurred.
+    pub error: ScanError,
+}
+
+impl Display for BundleScanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for BundleScanError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Errors that can occur while scanning a bundle.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ScanError {
+    /// The bundle's file could not be accessed.
+    Io(io::Error),
+    /// The bundle could not be loaded.
+    Load(PluginBundleError),
+    /// The bundle does not expose a plugin factory.
+    MissingPluginFactory,
+    /// The worker process scanning the bundle failed, with the given message.
+    Worker(String),
+    /// The worker process scanning the bundle did not finish within the given duration.
+    Timeout(Duration),
+    /// The worker process scanning the bundle crashed, and exited with the given status.
+    Crashed(ExitStatus),
+}
+
+impl Display for ScanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanError::Io(e) => write!(f, "Failed to access plugin bundle: {e}"),
+            ScanError::Load(e) => write!(f, "Failed to load plugin bundle: {e}"),
+            ScanError::MissingPluginFactory => f.write_str("Bundle has no plugin factory"),
+            ScanError::Worker(e) => write!(f, "Scan worker failed: {e}"),
+            ScanError::Timeout(timeout) => {
+                write!(f, "Scan worker timed out after {}s", timeout.as_secs_f64())
+            }
+            ScanError::Crashed(status) => write!(f, "Scan worker crashed ({status})"),
+        }
+    }
+}
+
+impl Error for ScanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScanError::Io(e) => Some(e),
+            ScanError::Load(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ScanError {
+    #[inline]
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<PluginBundleError> for ScanError {
+    #[inline]
+    fn from(value: PluginBundleError) -> Self {
+        Self::Load(value)
+    }
+}
@@ -0,0 +1,501 @@
+use super::*;
+use clack_common::utils::ClapVersion;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+const HEADER: &str = "clack-scan-cache 1";
+
+/// A persistent cache of the results of previous plugin scans.
+///
+/// This stores a [`ScannedBundle`] for each successfully scanned bundle, keyed by the bundle's
+/// path. Each entry also records the bundle's modification time at the time it was scanned, so
+/// that a [`PluginScanner`] can skip bundles that did not change since.
+///
+/// The cache can be persisted to a file using [`save`](ScanCache::save), and read back with
+/// [`load`](ScanCache::load). The file format is a simple, line-based text format, which is
+/// versioned so that incompatible future changes can be detected.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanCache {
+    bundles: BTreeMap<PathBuf, ScannedBundle>,
+}
+
+impl ScanCache {
+    /// Creates a new, empty cache.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a cache from the file at the given path.
+    ///
+    /// If the file does not exist, this returns an empty cache.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the file could not be read, or if it is not a valid cache file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match std::fs::File::open(path) {
+            Ok(file) => Self::read_from(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Saves this cache to a file at the given path, replacing it if it already exists.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the file could not be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Reads a cache from the given reader.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if reading failed, or if the data is not a valid cache.
+    pub fn read_from(reader: impl Read) -> io::Result<Self> {
+        let mut lines = BufReader::new(reader).lines();
+
+        match lines.next() {
+            Some(Ok(header)) if header == HEADER => {}
+            Some(Err(e)) => return Err(e),
+            _ => return Err(invalid_data("Invalid or unsupported scan cache header")),
+        }
+
+        let mut cache = Self::new();
+        let mut current: Option<ScannedBundle> = None;
+
+        for line in lines {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((&line, ""));
+
+            if key == "bundle" {
+                if let Some(bundle) = current.take() {
+                    cache.insert(bundle);
+                }
+
+                current = Some(parse_bundle_header(value)?);
+                continue;
+            }
+
+            let bundle = current
+                .as_mut()
+                .ok_or_else(|| invalid_data("Scan cache entry outside of a bundle"))?;
+
+            if key == "plugin" {
+                bundle
+                    .plugins
+                    .push(PluginDescriptorOwned::new(decode_string(value)?));
+                continue;
+            }
+
+            let plugin = bundle
+                .plugins
+                .last_mut()
+                .ok_or_else(|| invalid_data("Scan cache entry outside of a plugin"))?;
+
+            if key == "clap_version" {
+                plugin.clap_version = parse_clap_version(value)?;
+                continue;
+            }
+
+            let value = decode_string(value)?;
+            match key {
+                "name" => plugin.name = Some(value),
+                "vendor" => plugin.vendor = Some(value),
+                "url" => plugin.url = Some(value),
+                "manual_url" => plugin.manual_url = Some(value),
+                "support_url" => plugin.support_url = Some(value),
+                "version" => plugin.version = Some(value),
+                "description" => plugin.description = Some(value),
+                "feature" => plugin.features.push(value.into()),
+                // Ignore unknown keys, they may have been added by a newer, compatible version.
+                _ => {}
+            }
+        }
+
+        if let Some(bundle) = current {
+            cache.insert(bundle);
+        }
+
+        Ok(cache)
+    }
+
+    /// Writes this cache to the given writer.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if writing failed.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{HEADER}")?;
+
+        for bundle in self.bundles.values() {
+            let (secs, nanos) = time_to_raw(bundle.modified);
+            writeln!(
+                writer,
+                "bundle {} {secs} {nanos}",
+                encode(&path_to_bytes(&bundle.path))
+            )?;
+
+            for plugin in &bundle.plugins {
+                writeln!(writer, "plugin {}", encode(plugin.id.as_bytes()))?;
+
+                let version = plugin.clap_version;
+                writeln!(
+                    writer,
+                    "clap_version {} {} {}",
+                    version.major, version.minor, version.revision
+                )?;
+
+                let fields = [
+                    ("name", &plugin.name),
+                    ("vendor", &plugin.vendor),
+                    ("url", &plugin.url),
+                    ("manual_url", &plugin.manual_url),
+                    ("support_url", &plugin.support_url),
+                    ("version", &plugin.version),
+                    ("description", &plugin.description),
+                ];
+
+                for (key, value) in fields {
+                    if let Some(value) = value {
+                        writeln!(writer, "{key} {}", encode(value.as_bytes()))?;
+                    }
+                }
+
+                for feature in &plugin.features {
+                    writeln!(writer, "feature {}", encode(feature.as_str().as_bytes()))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the cached scan results for the bundle at the given path, if any.
+    #[inline]
+    pub fn get(&self, bundle_path: &Path) -> Option<&ScannedBundle> {
+        self.bundles.get(bundle_path)
+    }
+
+    /// Returns the cached scan results for the bundle at the given path, but only if the bundle
+    /// was not modified since it was cached, i.e. its modification time is still `modified`.
+    #[inline]
+    pub fn get_up_to_date(
+        &self,
+        bundle_path: &Path,
+        modified: SystemTime,
+    ) -> Option<&ScannedBundle> {
+        self.get(bundle_path)
+            .filter(|b| time_to_raw(b.modified) == time_to_raw(modified))
+    }
+
+    /// Inserts the scan results of a bundle in this cache, replacing any previous results for the
+    /// same bundle path.
+    #[inline]
+    pub fn insert(&mut self, bundle: ScannedBundle) {
+        self.bundles.insert(bundle.path.clone(), bundle);
+    }
+
+    /// Removes the scan results of the bundle at the given path from this cache, and returns them.
+    #[inline]
+    pub fn remove(&mut self, bundle_path: &Path) -> Option<ScannedBundle> {
+        self.bundles.remove(bundle_path)
+    }
+
+    /// Only keeps the bundles for which the given predicate returns `true`.
+    #[inline]
+    pub fn retain(&mut self, mut f: impl FnMut(&ScannedBundle) -> bool) {
+        self.bundles.retain(|_, bundle| f(bundle))
+    }
+
+    /// Returns an iterator over all the cached bundles, sorted by path.
+    #[inline]
+    pub fn bundles(&self) -> impl Iterator<Item = &ScannedBundle> {
+        self.bundles.values()
+    }
+
+    /// Returns an iterator over all the cached plugins, alongside the bundle they were found in.
+    #[inline]
+    pub fn plugins(&self) -> impl Iterator<Item = (&ScannedBundle, &PluginDescriptorOwned)> {
+        self.bundles()
+            .flat_map(|bundle| bundle.plugins.iter().map(move |plugin| (bundle, plugin)))
+    }
+
+    /// Returns the number of bundles in this cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bundles.len()
+    }
+
+    /// Returns `true` if this cache contains no bundles.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bundles.is_empty()
+    }
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_bundle_header(value: &str) -> io::Result<ScannedBundle> {
+    let mut parts = value.split(' ');
+    let (Some(path), Some(secs), Some(nanos), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid_data("Invalid scan cache bundle entry"));
+    };
+
+    let path = path_from_bytes(decode(path)?)?;
+    let secs = secs
+        .parse()
+        .map_err(|_| invalid_data("Invalid bundle modification time"))?;
+    let nanos = nanos
+        .parse()
+        .map_err(|_| invalid_data("Invalid bundle modification time"))?;
+
+    Ok(ScannedBundle {
+        path,
+        modified: time_from_raw(secs, nanos)
+            .ok_or_else(|| invalid_data("Invalid bundle modification time"))?,
+        plugins: Vec::new(),
+    })
+}
+
+fn parse_clap_version(value: &str) -> io::Result<ClapVersion> {
+    let mut parts = value.split(' ').map(str::parse);
+    let (Some(Ok(major)), Some(Ok(minor)), Some(Ok(revision)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid_data("Invalid plugin CLAP version"));
+    };
+
+    Ok(ClapVersion {
+        major,
+        minor,
+        revision,
+    })
+}
+
+/// Converts a time to a signed number of whole seconds since the UNIX epoch (rounded down), and
+/// the number of nanoseconds past that second.
+fn time_to_raw(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                nanos => (-(d.as_secs() as i64) - 1, 1_000_000_000 - nanos),
+            }
+        }
+    }
+}
+
+fn time_from_raw(secs: i64, nanos: u32) -> Option<SystemTime> {
+    if nanos >= 1_000_000_000 {
+        return None;
+    }
+
+    let whole_seconds = Duration::from_secs(secs.unsigned_abs());
+    let time = if secs < 0 {
+        UNIX_EPOCH.checked_sub(whole_seconds)?
+    } else {
+        UNIX_EPOCH.checked_add(whole_seconds)?
+    };
+
+    time.checked_add(Duration::from_nanos(nanos.into()))
+}
+
+/// Percent-encodes all bytes that are not printable ASCII, as well as spaces and `%` signs.
+pub(super) fn encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    let mut encoded = String::with_capacity(bytes.len());
+    for &b in bytes {
+        if b.is_ascii_graphic() && b != b'%' {
+            encoded.push(b as char);
+        } else {
+            let _ = write!(encoded, "%{b:02X}");
+        }
+    }
+
+    encoded
+}
+
+fn decode(encoded: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut chars = encoded.bytes();
+
+    while let Some(b) = chars.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+
+        let (Some(high), Some(low)) = (chars.next(), chars.next()) else {
+            return Err(invalid_data("Truncated escape sequence in scan cache"));
+        };
+
+        let hex = [high, low];
+        let byte = std::str::from_utf8(&hex)
+            .ok()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| invalid_data("Invalid escape sequence in scan cache"))?;
+
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+pub(super) fn decode_string(encoded: &str) -> io::Result<String> {
+    String::from_utf8(decode(encoded)?).map_err(|_| invalid_data("Invalid UTF-8 in scan cache"))
+}
+
+fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+
+    #[cfg(not(unix))]
+    {
+        match path.to_string_lossy() {
+            Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+            Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+        }
+    }
+}
+
+fn path_from_bytes(bytes: Vec<u8>) -> io::Result<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+    }
+
+    #[cfg(not(unix))]
+    {
+        String::from_utf8(bytes)
+            .map(PathBuf::from)
+            .map_err(|_| invalid_data("Invalid UTF-8 in scan cache"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::plugin::PluginFeature;
+
+    fn sample_cache() -> ScanCache {
+        let mut plugin = PluginDescriptorOwned::new("org.rust-audio.clack.gain");
+        plugin.name = Some("Clack Gain 100%".into());
+        plugin.description = Some("A line\nbreak, and some ünicode".into());
+        plugin.version = Some(String::new());
+        plugin.features = vec![PluginFeature::AudioEffect, "clack:custom".into()];
+        plugin.clap_version = ClapVersion {
+            major: 1,
+            minor: 0,
+            revision: 0,
+        };
+
+        let mut cache = ScanCache::new();
+        cache.insert(ScannedBundle {
+            path: PathBuf::from("/home/user/.clap/My Plugins/gain.clap"),
+            modified: UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+            plugins: vec![
+                plugin,
+                PluginDescriptorOwned::new("org.rust-audio.clack.other"),
+            ],
+        });
+        cache.insert(ScannedBundle {
+            path: PathBuf::from("/usr/lib/clap/empty.clap"),
+            modified: UNIX_EPOCH - Duration::new(10, 5),
+            plugins: vec![],
+        });
+
+        cache
+    }
+
+    #[test]
+    fn cache_round_trips() {
+        let cache = sample_cache();
+
+        let mut buf = Vec::new();
+        cache.write_to(&mut buf).unwrap();
+        let read = ScanCache::read_from(buf.as_slice()).unwrap();
+
+        assert_eq!(read, cache);
+        assert_eq!(read.plugins().count(), 2);
+    }
+
+    #[test]
+    fn up_to_date_entries_match_modification_time() {
+        let cache = sample_cache();
+        let path = Path::new("/home/user/.clap/My Plugins/gain.clap");
+        let modified = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+
+        assert!(cache.get_up_to_date(path, modified).is_some());
+        assert!(
+            cache
+                .get_up_to_date(path, modified + Duration::from_secs(1))
+                .is_none()
+        );
+        assert!(
+            cache
+                .get_up_to_date(Path::new("/nope.clap"), modified)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn times_round_trip() {
+        let times = [
+            UNIX_EPOCH,
+            UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+            UNIX_EPOCH + Duration::from_nanos(500),
+            UNIX_EPOCH - Duration::from_nanos(500),
+            UNIX_EPOCH - Duration::from_secs(3),
+            UNIX_EPOCH - Duration::new(10, 5),
+        ];
+
+        for time in times {
+            let (secs, nanos) = time_to_raw(time);
+            assert!(nanos < 1_000_000_000);
+            assert_eq!(time_from_raw(secs, nanos), Some(time));
+        }
+
+        assert_ne!(
+            time_to_raw(UNIX_EPOCH + Duration::from_nanos(500)),
+            time_to_raw(UNIX_EPOCH - Duration::from_nanos(500))
+        );
+        assert_eq!(time_from_raw(0, 1_000_000_000), None);
+    }
+
+    #[test]
+    fn invalid_caches_are_rejected() {
+        assert!(ScanCache::read_from(b"".as_slice()).is_err());
+        assert!(ScanCache::read_from(b"clack-scan-cache 2\n".as_slice()).is_err());
+        assert!(ScanCache::read_from(b"clack-scan-cache 1\nplugin foo\n".as_slice()).is_err());
+        assert!(
+            ScanCache::read_from(b"clack-scan-cache 1\nbundle /a.clap 1 2\nname %4\n".as_slice())
+                .is_err()
+        );
+
+        let empty = ScanCache::read_from(b"clack-scan-cache 1\n".as_slice()).unwrap();
+        assert!(empty.is_empty());
+    }
+}
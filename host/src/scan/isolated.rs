@@ -0,0 +1,289 @@
+use super::cache::{decode_string, encode};
+use super::*;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::process::{Child, Command, ExitCode, Stdio};
+use std::time::{Duration, Instant};
+
+/// The line written by the worker right before its result.
+const RESULT_BEGIN: &str = "--- clack-scan-result-begin ---";
+/// The line written by the worker right after its result.
+const RESULT_END: &str = "--- clack-scan-result-end ---";
+
+const ERROR_PREFIX: &str = "clack-scan-error ";
+const ERROR_MISSING_FACTORY: &str = "missing-plugin-factory";
+const ERROR_OTHER: &str = "error";
+
+/// Scans bundles in separate worker processes, so that crashing or hanging bundles cannot take
+/// down the host.
+///
+/// Each bundle is scanned in its own child process, which loads the bundle, reads its plugin
+/// descriptors, and sends them back to the host over its standard output, between two marker
+/// lines so that anything else the bundle prints is ignored. If the worker crashes,
+/// or does not finish before the configured timeout, the error is reported for that bundle only.
+///
+/// By default, the worker process is the host's own executable, started with the
+/// [`WORKER_ARG`](Self::WORKER_ARG) argument followed by the bundle path. For this to work, the
+/// host must call [`run_worker_if_requested`] at the very start of its `main` function.
+///
+/// # Example
+///
+/// ```no_run
+/// use clack_host::scan::{IsolatedScanner, PluginScanner, ScanCache, run_worker_if_requested};
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     // SAFETY: we trust the plugins installed on this system.
+///     if let Some(exit_code) = unsafe { run_worker_if_requested() } {
+///         return exit_code;
+///     }
+///
+///     let isolated = IsolatedScanner::new().unwrap();
+///     let mut cache = ScanCache::new();
+///     let report = PluginScanner::new().scan_isolated(&mut cache, &isolated);
+///
+///     for error in &report.errors {
+///         eprintln!("Failed to scan {error}");
+///     }
+///
+///     ExitCode::SUCCESS
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct IsolatedScanner {
+    program: PathBuf,
+    args: Vec<OsString>,
+    timeout: Duration,
+}
+
+impl IsolatedScanner {
+    /// The command-line argument that makes [`run_worker_if_requested`] run the scan worker.
+    pub const WORKER_ARG: &'static str = "--clack-scan-worker";
+
+    /// The default time a worker has to scan a single bundle.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Creates a new isolated scanner, which uses the current executable as the worker process.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the path of the current executable can't be determined.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self::with_command(
+            std::env::current_exe()?,
+            [Self::WORKER_ARG],
+        ))
+    }
+
+    /// Creates a new isolated scanner, which uses the given program as the worker process.
+    ///
+    /// The worker is started with the given arguments, followed by the path of the bundle to
+    /// scan. It must then call [`run_worker`] with that path.
+    pub fn with_command(
+        program: impl Into<PathBuf>,
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the time a worker has to scan a single bundle, before it is killed.
+    #[inline]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the time a worker has to scan a single bundle, before it is killed.
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Scans the bundle at the given path in a new worker process.
+    ///
+    /// # Errors
+    ///
+    /// On top of the errors [`scan_bundle`] can return, this returns [`ScanError::Timeout`] if
+    /// the worker took too long, [`ScanError::Crashed`] if it crashed, and
+    /// [`ScanError::Worker`] if it failed in any other way.
+    pub fn scan_bundle(&self, path: &Path) -> Result<ScannedBundle, ScanError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        // Read the output on a separate thread, so that the worker never blocks on a full pipe.
+        let mut stdout = child.stdout.take().expect("Worker stdout should be piped");
+        let reader = std::thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+
+        let status = match wait_timeout(&mut child, self.timeout)? {
+            Some(status) => status,
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                // The reader thread is not joined: the pipe may still be held open by any process
+                // the worker spawned.
+                return Err(ScanError::Timeout(self.timeout));
+            }
+        };
+
+        let output = reader
+            .join()
+            .map_err(|_| ScanError::Worker("Failed to read worker output".into()))??;
+
+        if !status.success() {
+            return Err(ScanError::Crashed(status));
+        }
+
+        parse_worker_output(&output, path)
+    }
+}
+
+/// Waits for the given child process to exit, for at most `timeout`.
+///
+/// Returns `None` if the process is still running after the timeout.
+fn wait_timeout(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<std::process::ExitStatus>> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+fn parse_worker_output(output: &[u8], path: &Path) -> Result<ScannedBundle, ScanError> {
+    let mut lines = output.split(|b| *b == b'\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, *offset, line))
+    });
+
+    // Bundles may print anything to the standard output, before or after the result, so only
+    // the text between the last begin marker and the following end marker is considered.
+    let mut bounds = None;
+    while let Some((_, end, line)) = lines.next() {
+        if line != RESULT_BEGIN.as_bytes() {
+            continue;
+        }
+
+        bounds = lines
+            .by_ref()
+            .find(|(_, _, line)| *line == RESULT_END.as_bytes())
+            .map(|(start, _, _)| (end.min(output.len()), start));
+    }
+
+    let (start, end) =
+        bounds.ok_or_else(|| ScanError::Worker("The worker did not return any result".into()))?;
+
+    let output = &output[start..end];
+
+    if let Some(error) = output.strip_prefix(ERROR_PREFIX.as_bytes()) {
+        let error = String::from_utf8_lossy(error);
+        let (kind, message) = error.trim_end().split_once(' ').unwrap_or((&error, ""));
+
+        return Err(match kind {
+            ERROR_MISSING_FACTORY => ScanError::MissingPluginFactory,
+            _ => ScanError::Worker(decode_string(message).unwrap_or_else(|_| message.into())),
+        });
+    }
+
+    let mut cache = ScanCache::read_from(output)
+        .map_err(|e| ScanError::Worker(format!("Invalid worker output: {e}")))?;
+
+    cache
+        .remove(path)
+        .ok_or_else(|| ScanError::Worker("The worker scanned the wrong bundle".into()))
+}
+
+/// Scans the bundle at the given path, and writes the results to the given output, in the format
+/// expected by an [`IsolatedScanner`].
+///
+/// This is what the worker process of an [`IsolatedScanner`] must call. Scan errors are also
+/// written to the output: the returned error is only about failing to write the results.
+///
+/// # Errors
+///
+/// This returns an error if writing to the output failed.
+///
+/// # Safety
+///
+/// This loads the bundle in the current process, which runs arbitrary code. See
+/// [`PluginBundle::load`] for more information.
+pub unsafe fn run_worker(bundle_path: &Path, mut output: impl Write) -> io::Result<()> {
+    // SAFETY: upheld by the caller.
+    let result = unsafe { scan_bundle(bundle_path) };
+
+    writeln!(output, "\n{RESULT_BEGIN}")?;
+
+    match result {
+        Ok(bundle) => {
+            let mut cache = ScanCache::new();
+            cache.insert(bundle);
+            cache.write_to(&mut output)?;
+        }
+        Err(ScanError::MissingPluginFactory) => {
+            writeln!(output, "{ERROR_PREFIX}{ERROR_MISSING_FACTORY}")?;
+        }
+        Err(e) => {
+            let message = encode(e.to_string().as_bytes());
+            writeln!(output, "{ERROR_PREFIX}{ERROR_OTHER} {message}")?;
+        }
+    }
+
+    writeln!(output, "{RESULT_END}")?;
+    output.flush()
+}
+
+/// Runs the scan worker if the current process was started by an [`IsolatedScanner`], and
+/// returns the exit code the process should exit with.
+///
+/// This checks if the process was started with the [`IsolatedScanner::WORKER_ARG`] argument,
+/// followed by a bundle path, and if so scans that bundle using [`run_worker`], writing the
+/// results to the standard output. Otherwise, this does nothing and returns [`None`].
+///
+/// Hosts using the default [`IsolatedScanner`] must call this at the very start of their `main`
+/// function, and exit with the returned code if any.
+///
+/// # Safety
+///
+/// If the process was started by an [`IsolatedScanner`], this loads the bundle in the current
+/// process, which runs arbitrary code. See [`PluginBundle::load`] for more information.
+pub unsafe fn run_worker_if_requested() -> Option<ExitCode> {
+    let mut args = std::env::args_os().skip(1);
+
+    if args.next()? != IsolatedScanner::WORKER_ARG {
+        return None;
+    }
+
+    let (Some(bundle_path), None) = (args.next(), args.next()) else {
+        return Some(ExitCode::FAILURE);
+    };
+
+    // SAFETY: upheld by the caller.
+    match unsafe { run_worker(Path::new(&bundle_path), io::stdout().lock()) } {
+        Ok(()) => Some(ExitCode::SUCCESS),
+        Err(_) => Some(ExitCode::FAILURE),
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Returns a list of all the standard CLAP search paths, per the CLAP specification.
+///
+/// This includes the paths listed in the `CLAP_PATH` environment variable, if it is set. The
+/// returned paths may not exist on the filesystem.
+pub fn standard_clap_paths() -> Vec<PathBuf> {
+    let mut paths = vec![];
+
+    #[cfg(all(target_family = "unix", not(target_os = "macos")))]
+    {
+        if let Some(home_dir) = home_dir() {
+            paths.push(home_dir.join(".clap"));
+        }
+
+        paths.push("/usr/lib/clap".into());
+        paths.push("/usr/lib64/clap".into());
+    }
+
+    #[cfg(windows)]
+    {
+        if let Some(val) = std::env::var_os("CommonProgramFiles") {
+            paths.push(PathBuf::from(val).join("CLAP"))
+        }
+
+        if let Some(val) = std::env::var_os("LOCALAPPDATA") {
+            paths.push(PathBuf::from(val).join("Programs\\Common\\CLAP"));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(home_dir) = home_dir() {
+            paths.push(home_dir.join("Library/Audio/Plug-Ins/CLAP"));
+        }
+
+        paths.push(PathBuf::from("/Library/Audio/Plug-Ins/CLAP"));
+    }
+
+    if let Some(env_var) = std::env::var_os("CLAP_PATH") {
+        paths.extend(std::env::split_paths(&env_var))
+    }
+
+    paths
+}
+
+#[cfg(target_family = "unix")]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Recursively searches the given directories, and returns the paths of all the potential CLAP
+/// bundles they contain.
+///
+/// CLAP bundles are directories with a `.clap` extension on macOS, and files with a `.clap`
+/// extension everywhere else. Symbolic links are followed, and directories that do not exist or
+/// cannot be read are skipped.
+///
+/// The returned paths are sorted, and do not contain duplicates.
+pub fn find_bundles(search_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut bundles = Vec::new();
+    let mut visited = HashSet::new();
+
+    for path in search_paths {
+        search_directory(path, &mut visited, &mut bundles);
+    }
+
+    bundles.sort();
+    bundles.dedup();
+    bundles
+}
+
+fn search_directory(dir: &Path, visited: &mut HashSet<PathBuf>, bundles: &mut Vec<PathBuf>) {
+    // Guard against symbolic link loops, and search paths that are contained in one another.
+    let Ok(canonical) = dir.canonicalize() else {
+        return;
+    };
+
+    if !visited.insert(canonical) {
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+
+        // This follows symbolic links.
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+
+        if is_bundle_path(&path, metadata.is_dir()) {
+            bundles.push(path);
+        } else if metadata.is_dir() {
+            search_directory(&path, visited, bundles);
+        }
+    }
+}
+
+/// Returns `true` if the given path could refer to a CLAP bundle.
+fn is_bundle_path(path: &Path, is_dir: bool) -> bool {
+    let is_bundle_type = if cfg!(target_os = "macos") {
+        is_dir
+    } else {
+        !is_dir
+    };
+
+    is_bundle_type && path.extension() == Some(OsStr::new("clap"))
+}
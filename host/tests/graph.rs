@@ -0,0 +1,521 @@
+use clack_extensions::latency::{PluginLatency, PluginLatencyImpl};
+use clack_host::events::Match;
+use clack_host::events::event_types::{
+    NoteOffEvent, NoteOnEvent, ParamValueEvent, TransportEvent, TransportFlags,
+};
+use clack_host::events::{EventFlags, EventHeader};
+use clack_host::graph::{GraphError, NodeId, NodePorts, PluginGraph, plugin_latency};
+use clack_host::prelude::*;
+use clack_host::utils::{BeatTime, Cookie, SecondsTime};
+use clack_plugin::prelude::*;
+
+/// A test plugin that delays its stereo input by `DELAY` frames, reports that delay as its
+/// latency, and echoes all the events it receives, as well as the transport.
+pub struct DelayPlugin<const DELAY: u32>;
+
+pub struct DelayPluginMainThread<const DELAY: u32>;
+
+impl<const DELAY: u32> PluginMainThread<'_, ()> for DelayPluginMainThread<DELAY> {}
+
+impl<const DELAY: u32> PluginLatencyImpl for DelayPluginMainThread<DELAY> {
+    fn get(&mut self) -> u32 {
+        DELAY
+    }
+}
+
+pub struct DelayPluginAudioProcessor {
+    channels: Vec<Vec<f32>>,
+    position: usize,
+}
+
+impl<const DELAY: u32> Plugin for DelayPlugin<DELAY> {
+    type AudioProcessor<'a> = DelayPluginAudioProcessor;
+    type Shared<'a> = ();
+    type MainThread<'a> = DelayPluginMainThread<DELAY>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&()>) {
+        builder.register::<PluginLatency>();
+    }
+}
+
+impl<const DELAY: u32> DefaultPluginFactory for DelayPlugin<DELAY> {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.delay", "Delay")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(())
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        _shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(DelayPluginMainThread)
+    }
+}
+
+impl<'a, const DELAY: u32> PluginAudioProcessor<'a, (), DelayPluginMainThread<DELAY>>
+    for DelayPluginAudioProcessor
+{
+    fn activate(
+        _host: HostAudioProcessorHandle<'a>,
+        _main_thread: &mut DelayPluginMainThread<DELAY>,
+        _shared: &'a (),
+        _audio_config: PluginAudioConfiguration,
+    ) -> Result<Self, PluginError> {
+        Ok(Self {
+            channels: vec![vec![0.0; DELAY as usize]; 2],
+            position: 0,
+        })
+    }
+
+    fn process(
+        &mut self,
+        process: Process,
+        mut audio: Audio,
+        events: Events,
+    ) -> Result<ProcessStatus, PluginError> {
+        let mut port_pair = audio
+            .port_pair(0)
+            .ok_or(PluginError::Message("No input/output ports found"))?;
+        let mut channels = port_pair
+            .channels()?
+            .into_f32()
+            .ok_or(PluginError::Message("Expected f32 input/output"))?;
+
+        let mut position = self.position;
+
+        for (pair, ring) in channels.iter_mut().zip(&mut self.channels) {
+            let ChannelPair::InputOutput(input, output) = pair else {
+                return Err(PluginError::Message("Expected separate input/output"));
+            };
+
+            if ring.is_empty() {
+                output.copy_from_slice(input);
+                continue;
+            }
+
+            position = self.position;
+            for (input, output) in input.iter().zip(output) {
+                *output = ring[position];
+                ring[position] = *input;
+                position = (position + 1) % ring.len();
+            }
+        }
+
+        self.position = position;
+
+        for event in events.input {
+            events.output.try_push(event).unwrap();
+        }
+
+        if let Some(transport) = process.transport {
+            events.output.try_push(transport).unwrap();
+        }
+
+        Ok(ProcessStatus::Continue)
+    }
+}
+
+fn instantiate<const DELAY: u32>() -> (PluginBundle, PluginInstance<()>) {
+    let bundle =
+        PluginBundle::load_from_clack::<SinglePluginEntry<DelayPlugin<DELAY>>>(c"").unwrap();
+    let host_info = HostInfo::new("Graph", "Clack", "https://example.com", "1.0.0").unwrap();
+
+    let instance = PluginInstance::<()>::new(
+        |_| (),
+        |_| (),
+        &bundle,
+        c"org.rust-audio.clack.delay",
+        &host_info,
+    )
+    .unwrap();
+
+    (bundle, instance)
+}
+
+fn add_to_graph(graph: &mut PluginGraph<()>, instance: &mut PluginInstance<()>) -> NodeId {
+    let configuration = PluginAudioConfiguration {
+        sample_rate: 44_100.0,
+        min_frames_count: 1,
+        max_frames_count: 32,
+    };
+
+    let processor = instance
+        .activate(|_, _| (), configuration)
+        .unwrap()
+        .start_processing()
+        .unwrap();
+
+    let ports = NodePorts {
+        audio_inputs: vec![2],
+        audio_outputs: vec![2],
+        note_inputs: 1,
+        note_outputs: 1,
+        latency: plugin_latency(instance),
+    };
+
+    graph.add_plugin(processor, ports)
+}
+
+fn remove_from_graph(graph: &mut PluginGraph<()>, node: NodeId, instance: &mut PluginInstance<()>) {
+    let processor = graph.remove_plugin(node).unwrap();
+    instance.deactivate(processor.stop_processing());
+}
+
+#[test]
+pub fn compensates_parallel_latency() {
+    let (_delayed_bundle, mut delayed) = instantiate::<16>();
+    let (_direct_bundle, mut direct) = instantiate::<0>();
+
+    let mut graph = PluginGraph::new(&[1], &[2]);
+    let delayed_node = add_to_graph(&mut graph, &mut delayed);
+    let direct_node = add_to_graph(&mut graph, &mut direct);
+
+    assert_eq!(graph.ports(delayed_node).unwrap().latency, 16);
+    assert_eq!(graph.ports(direct_node).unwrap().latency, 0);
+
+    // The mono input is sent to both channels of both plugins, whose outputs are summed.
+    graph
+        .connect_audio(NodeId::INPUT, 0, delayed_node, 0)
+        .unwrap();
+    graph
+        .connect_audio(NodeId::INPUT, 0, direct_node, 0)
+        .unwrap();
+    graph
+        .connect_audio(delayed_node, 0, NodeId::OUTPUT, 0)
+        .unwrap();
+    graph
+        .connect_audio(direct_node, 0, NodeId::OUTPUT, 0)
+        .unwrap();
+
+    assert_eq!(graph.latency(), None);
+    graph.activate(44_100.0, 32).unwrap();
+    assert_eq!(graph.latency(), Some(16));
+
+    let mut output_events = EventBuffer::new();
+    let mut rendered = [Vec::new(), Vec::new()];
+
+    for block in 0..4 {
+        let input: Vec<f32> = (0..32).map(|i| (block * 32 + i + 1) as f32).collect();
+        let mut output = [[0.0f32; 32]; 2];
+
+        graph
+            .process(
+                &[input],
+                &mut output,
+                &InputEvents::empty(),
+                &mut output_events.as_output(),
+                32,
+                None,
+                None,
+            )
+            .unwrap();
+
+        for (rendered, output) in rendered.iter_mut().zip(output) {
+            rendered.extend_from_slice(&output);
+        }
+    }
+
+    // Both branches are aligned: the direct one is delayed to match the other one.
+    let expected: Vec<f32> = (0..128)
+        .map(|i| if i < 16 { 0.0 } else { 2.0 * (i - 15) as f32 })
+        .collect();
+
+    assert_eq!(rendered, [expected.clone(), expected]);
+    assert!(output_events.is_empty());
+
+    remove_from_graph(&mut graph, delayed_node, &mut delayed);
+    remove_from_graph(&mut graph, direct_node, &mut direct);
+}
+
+#[test]
+pub fn delays_events_to_match_audio() {
+    let (_delayed_bundle, mut delayed) = instantiate::<16>();
+    let (_direct_bundle, mut direct) = instantiate::<0>();
+
+    let mut graph = PluginGraph::new(&[2], &[2]);
+    let delayed_node = add_to_graph(&mut graph, &mut delayed);
+    let direct_node = add_to_graph(&mut graph, &mut direct);
+
+    graph
+        .connect_audio(NodeId::INPUT, 0, delayed_node, 0)
+        .unwrap();
+    graph
+        .connect_audio(delayed_node, 0, direct_node, 0)
+        .unwrap();
+    graph
+        .connect_audio(direct_node, 0, NodeId::OUTPUT, 0)
+        .unwrap();
+    graph
+        .connect_notes(NodeId::INPUT, 0, direct_node, 0)
+        .unwrap();
+    graph
+        .connect_notes(direct_node, 0, NodeId::OUTPUT, 0)
+        .unwrap();
+    graph.activate(44_100.0, 32).unwrap();
+
+    let note = NoteOnEvent::new(20, Pckn::new(0u16, 0u16, 60u16, 0u32), 1.0);
+    let param = ParamValueEvent::new(3, ClapId::new(7), Pckn::match_all(), 0.5, Cookie::empty());
+
+    let mut input_events = EventBuffer::new();
+    input_events.push(&note);
+    graph.push_event(direct_node, &param).unwrap();
+
+    let mut output_events = EventBuffer::new();
+    let silence = [[0.0f32; 32]; 2];
+    let mut output = [[0.0f32; 32]; 2];
+
+    graph
+        .process(
+            &silence,
+            &mut output,
+            &input_events.as_input(),
+            &mut output_events.as_output(),
+            32,
+            None,
+            None,
+        )
+        .unwrap();
+
+    // The parameter event was only sent to the plugin, which echoed it back.
+    let node_events = graph.output_events(direct_node).unwrap();
+    assert_eq!(node_events.len(), 1);
+    assert_eq!(
+        node_events.get(0).unwrap().as_event::<ParamValueEvent>(),
+        Some(&param)
+    );
+
+    // The note is delayed by the latency of the plugin before it, which pushes it to the next block.
+    assert!(output_events.is_empty());
+
+    graph
+        .process(
+            &silence,
+            &mut output,
+            &InputEvents::empty(),
+            &mut output_events.as_output(),
+            32,
+            None,
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(output_events.len(), 1);
+    let output_note = output_events
+        .get(0)
+        .unwrap()
+        .as_event::<NoteOnEvent>()
+        .unwrap();
+    assert_eq!(output_note.header().time(), 4);
+    assert_eq!(output_note.key(), Match::Specific(60));
+
+    remove_from_graph(&mut graph, delayed_node, &mut delayed);
+    remove_from_graph(&mut graph, direct_node, &mut direct);
+}
+
+#[test]
+pub fn delays_transport_to_match_audio() {
+    let (_delayed_bundle, mut delayed) = instantiate::<11025>();
+    let (_direct_bundle, mut direct) = instantiate::<0>();
+
+    let mut graph = PluginGraph::new(&[2], &[2]);
+    let delayed_node = add_to_graph(&mut graph, &mut delayed);
+    let direct_node = add_to_graph(&mut graph, &mut direct);
+
+    graph
+        .connect_audio(NodeId::INPUT, 0, delayed_node, 0)
+        .unwrap();
+    graph
+        .connect_audio(delayed_node, 0, direct_node, 0)
+        .unwrap();
+    graph
+        .connect_audio(direct_node, 0, NodeId::OUTPUT, 0)
+        .unwrap();
+    graph.activate(44_100.0, 32).unwrap();
+
+    let transport = TransportEvent {
+        header: EventHeader::new_core(0, EventFlags::empty()),
+        flags: TransportFlags::IS_PLAYING
+            | TransportFlags::HAS_TEMPO
+            | TransportFlags::HAS_BEATS_TIMELINE
+            | TransportFlags::HAS_SECONDS_TIMELINE,
+        song_pos_beats: BeatTime::from_float(8.0),
+        song_pos_seconds: SecondsTime::from_float(4.0),
+        tempo: 120.0,
+        tempo_inc: 0.0,
+        loop_start_beats: BeatTime::from_int(0),
+        loop_end_beats: BeatTime::from_int(0),
+        loop_start_seconds: SecondsTime::from_int(0),
+        loop_end_seconds: SecondsTime::from_int(0),
+        bar_start: BeatTime::from_int(8),
+        bar_number: 2,
+        time_signature_numerator: 4,
+        time_signature_denominator: 4,
+    };
+
+    let mut output_events = EventBuffer::new();
+    let silence = [[0.0f32; 32]; 2];
+    let mut output = [[0.0f32; 32]; 2];
+
+    graph
+        .process(
+            &silence,
+            &mut output,
+            &InputEvents::empty(),
+            &mut output_events.as_output(),
+            32,
+            None,
+            Some(&transport),
+        )
+        .unwrap();
+
+    let received_transport = |node| {
+        *graph
+            .output_events(node)
+            .unwrap()
+            .iter()
+            .find_map(|e| e.as_event::<TransportEvent>())
+            .unwrap()
+    };
+
+    // The first node is not delayed, but the second one is behind by 11025 samples (250ms).
+    assert_eq!(received_transport(delayed_node), transport);
+
+    let delayed_transport = received_transport(direct_node);
+    assert_eq!(delayed_transport.song_pos_seconds.to_float(), 3.75);
+    assert_eq!(delayed_transport.song_pos_beats.to_float(), 7.5);
+
+    remove_from_graph(&mut graph, delayed_node, &mut delayed);
+    remove_from_graph(&mut graph, direct_node, &mut direct);
+}
+
+#[test]
+pub fn keeps_simultaneous_events_in_order() {
+    let (_bundle, mut instance) = instantiate::<0>();
+
+    let mut graph = PluginGraph::new(&[2], &[2]);
+    let node = add_to_graph(&mut graph, &mut instance);
+
+    graph.connect_notes(node, 0, NodeId::OUTPUT, 0).unwrap();
+    graph.activate(44_100.0, 32).unwrap();
+
+    // Retrigger each key on the same sample, pushing the retriggers in reverse time order.
+    for key in 0..32u16 {
+        let time = 31 - key as u32;
+        let pckn = Pckn::new(0u16, 0u16, key, 0u32);
+
+        graph
+            .push_event(node, &NoteOffEvent::new(time, pckn, 0.0))
+            .unwrap();
+        graph
+            .push_event(node, &NoteOnEvent::new(time, pckn, 1.0))
+            .unwrap();
+    }
+
+    let mut output_events = EventBuffer::new();
+    let silence = [[0.0f32; 32]; 2];
+    let mut output = [[0.0f32; 32]; 2];
+
+    graph
+        .process(
+            &silence,
+            &mut output,
+            &InputEvents::empty(),
+            &mut output_events.as_output(),
+            32,
+            None,
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(output_events.len(), 64);
+
+    for (time, pair) in output_events
+        .iter()
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .enumerate()
+    {
+        let note_off = pair[0].as_event::<NoteOffEvent>().unwrap();
+        let note_on = pair[1].as_event::<NoteOnEvent>().unwrap();
+
+        assert_eq!(note_off.header().time(), time as u32);
+        assert_eq!(note_on.header().time(), time as u32);
+        assert_eq!(note_off.key(), note_on.key());
+    }
+
+    remove_from_graph(&mut graph, node, &mut instance);
+}
+
+#[test]
+pub fn rejects_invalid_graphs() {
+    let (_first_bundle, mut first) = instantiate::<0>();
+    let (_second_bundle, mut second) = instantiate::<0>();
+
+    let mut graph = PluginGraph::new(&[2], &[2]);
+    let first_node = add_to_graph(&mut graph, &mut first);
+    let second_node = add_to_graph(&mut graph, &mut second);
+
+    assert_eq!(
+        graph.connect_audio(first_node, 1, second_node, 0),
+        Err(GraphError::UnknownPort {
+            node: first_node,
+            port: 1
+        })
+    );
+    assert_eq!(
+        graph.connect_notes(NodeId::OUTPUT, 0, first_node, 0),
+        Err(GraphError::UnknownPort {
+            node: NodeId::OUTPUT,
+            port: 0
+        })
+    );
+
+    graph.connect_audio(first_node, 0, second_node, 0).unwrap();
+    assert_eq!(
+        graph.connect_notes(second_node, 0, first_node, 0),
+        Err(GraphError::Cycle)
+    );
+    assert_eq!(
+        graph.connect_audio(first_node, 0, first_node, 0),
+        Err(GraphError::Cycle)
+    );
+
+    let mut output = [[0.0f32; 64]; 2];
+    let mut process = |graph: &mut PluginGraph<()>, frames_count| {
+        graph.process(
+            &[[0.0f32; 64]; 2],
+            &mut output,
+            &InputEvents::empty(),
+            &mut EventBuffer::new().as_output(),
+            frames_count,
+            None,
+            None,
+        )
+    };
+
+    assert_eq!(process(&mut graph, 32), Err(GraphError::NotActivated));
+
+    graph.activate(44_100.0, 32).unwrap();
+    assert_eq!(process(&mut graph, 32), Ok(()));
+    assert_eq!(
+        process(&mut graph, 64),
+        Err(GraphError::BlockTooLarge {
+            frames_count: 64,
+            max_frames_count: 32
+        })
+    );
+
+    remove_from_graph(&mut graph, first_node, &mut first);
+    assert!(!graph.is_activated());
+    assert!(graph.remove_plugin(first_node).is_none());
+    assert!(graph.remove_plugin(NodeId::INPUT).is_none());
+    assert_eq!(
+        graph.connect_audio(first_node, 0, second_node, 0),
+        Err(GraphError::UnknownNode(first_node))
+    );
+
+    remove_from_graph(&mut graph, second_node, &mut second);
+}
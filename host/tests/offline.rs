@@ -0,0 +1,256 @@
+use clack_extensions::render::{PluginRender, PluginRenderImpl, RenderMode};
+use clack_extensions::tail::{PluginTail, PluginTailImpl, TailLength};
+use clack_host::events::event_types::NoteOnEvent;
+use clack_host::offline::OfflineRenderer;
+use clack_host::prelude::*;
+use clack_plugin::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A test plugin that outputs its input at half volume in offline mode (full volume otherwise),
+/// writes an impulse and echoes each event it receives, and has a tail of `TAIL` frames.
+///
+/// A `TAIL` of zero makes the plugin request to sleep, and an infinite tail makes it request to
+/// always be processed.
+pub struct TailPlugin<const TAIL: u32>;
+
+pub struct TailPluginShared {
+    offline: AtomicBool,
+}
+
+impl PluginShared<'_> for TailPluginShared {}
+
+pub struct TailPluginMainThread<'a> {
+    shared: &'a TailPluginShared,
+}
+
+impl<'a> PluginMainThread<'a, TailPluginShared> for TailPluginMainThread<'a> {}
+
+impl PluginRenderImpl for TailPluginMainThread<'_> {
+    fn has_hard_realtime_requirement(&self) -> bool {
+        false
+    }
+
+    fn set(&mut self, mode: RenderMode) -> Result<(), PluginError> {
+        self.shared
+            .offline
+            .store(mode == RenderMode::Offline, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+pub struct TailPluginAudioProcessor<'a, const TAIL: u32> {
+    shared: &'a TailPluginShared,
+}
+
+impl<const TAIL: u32> Plugin for TailPlugin<TAIL> {
+    type AudioProcessor<'a> = TailPluginAudioProcessor<'a, TAIL>;
+    type Shared<'a> = TailPluginShared;
+    type MainThread<'a> = TailPluginMainThread<'a>;
+
+    fn declare_extensions(
+        builder: &mut PluginExtensions<Self>,
+        _shared: Option<&TailPluginShared>,
+    ) {
+        builder.register::<PluginRender>().register::<PluginTail>();
+    }
+}
+
+impl<const TAIL: u32> DefaultPluginFactory for TailPlugin<TAIL> {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new("org.rust-audio.clack.tail", "Tail")
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
+        Ok(TailPluginShared {
+            offline: AtomicBool::new(false),
+        })
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        shared: &'a Self::Shared<'a>,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(TailPluginMainThread { shared })
+    }
+}
+
+impl<'a, const TAIL: u32> PluginAudioProcessor<'a, TailPluginShared, TailPluginMainThread<'a>>
+    for TailPluginAudioProcessor<'a, TAIL>
+{
+    fn activate(
+        _host: HostAudioProcessorHandle<'a>,
+        _main_thread: &mut TailPluginMainThread<'a>,
+        shared: &'a TailPluginShared,
+        _audio_config: PluginAudioConfiguration,
+    ) -> Result<Self, PluginError> {
+        Ok(Self { shared })
+    }
+
+    fn process(
+        &mut self,
+        _process: Process,
+        mut audio: Audio,
+        events: Events,
+    ) -> Result<ProcessStatus, PluginError> {
+        let gain = if self.shared.offline.load(Ordering::Relaxed) {
+            0.5
+        } else {
+            1.0
+        };
+
+        let mut port_pair = audio
+            .port_pair(0)
+            .ok_or(PluginError::Message("No input/output ports found"))?;
+        let mut channels = port_pair
+            .channels()?
+            .into_f32()
+            .ok_or(PluginError::Message("Expected f32 input/output"))?;
+
+        for pair in channels.iter_mut() {
+            let output = match pair {
+                ChannelPair::InputOnly(_) => continue,
+                ChannelPair::OutputOnly(o) => {
+                    o.fill(0.0);
+                    o
+                }
+                ChannelPair::InPlace(b) => b,
+                ChannelPair::InputOutput(i, o) => {
+                    o.copy_from_slice(i);
+                    o
+                }
+            };
+
+            for sample in output.iter_mut() {
+                *sample *= gain;
+            }
+
+            for event in events.input {
+                output[event.header().time() as usize] = 1.0;
+            }
+        }
+
+        for event in events.input {
+            events.output.try_push(event).unwrap();
+        }
+
+        Ok(match TAIL {
+            0 => ProcessStatus::Sleep,
+            u32::MAX => ProcessStatus::Continue,
+            _ => ProcessStatus::Tail,
+        })
+    }
+}
+
+impl<const TAIL: u32> PluginTailImpl for TailPluginAudioProcessor<'_, TAIL> {
+    fn get(&self) -> TailLength {
+        TailLength::from_raw(TAIL)
+    }
+}
+
+fn instantiate<const TAIL: u32>(bundle: &PluginBundle) -> PluginInstance<()> {
+    let host_info = HostInfo::new("Offline", "Clack", "https://example.com", "1.0.0").unwrap();
+
+    PluginInstance::<()>::new(
+        |_| (),
+        |_| (),
+        bundle,
+        c"org.rust-audio.clack.tail",
+        &host_info,
+    )
+    .unwrap()
+}
+
+#[test]
+pub fn renders_input_events_and_tail() {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<TailPlugin<100>>>(c"").unwrap();
+    let mut instance = instantiate::<100>(&bundle);
+
+    let input = [vec![1.0f32; 1000], vec![1.0f32; 900]];
+    let mut timeline = EventBuffer::new();
+    // Events are given out of order, and one is past the end of the input.
+    timeline.push(&NoteOnEvent::new(
+        1050,
+        Pckn::new(0u16, 0u16, 64u16, 0u32),
+        1.0,
+    ));
+    timeline.push(&NoteOnEvent::new(
+        500,
+        Pckn::new(0u16, 0u16, 60u16, 0u32),
+        1.0,
+    ));
+
+    let render = OfflineRenderer::new(48_000.0, 64)
+        .render(&mut instance, |_, _| (), &input, &timeline)
+        .unwrap();
+
+    assert!(render.offline);
+    assert!(!instance.is_active());
+
+    // The input ends after the last event, then the tail is rendered.
+    assert_eq!(render.frames, 1051 + 100);
+    assert_eq!(render.tail_frames, 100);
+    assert_eq!(render.channels.len(), 2);
+
+    for (index, channel) in render.channels.iter().enumerate() {
+        assert_eq!(channel.len(), 1151);
+        assert_eq!(channel[0], 0.5);
+        assert_eq!(channel[499], 0.5);
+        assert_eq!(channel[500], 1.0);
+        assert_eq!(channel[501], 0.5);
+        assert_eq!(channel[1050], 1.0);
+        assert_eq!(channel[1051..], [0.0; 100]);
+
+        // The second channel is padded with silence.
+        let input_end = if index == 0 { 1000 } else { 900 };
+        assert_eq!(channel[input_end - 1], 0.5);
+        assert_eq!(channel[input_end], 0.0);
+    }
+
+    let output_times: Vec<u32> = render
+        .output_events
+        .iter()
+        .map(|e| e.header().time())
+        .collect();
+    assert_eq!(output_times, [500, 1050]);
+}
+
+#[test]
+pub fn stops_when_plugin_sleeps() {
+    let bundle = PluginBundle::load_from_clack::<SinglePluginEntry<TailPlugin<0>>>(c"").unwrap();
+    let mut instance = instantiate::<0>(&bundle);
+
+    let render = OfflineRenderer::new(48_000.0, 64)
+        .with_output_channels(1)
+        .with_min_length(100)
+        .render(
+            &mut instance,
+            |_, _| (),
+            &[] as &[Vec<f32>],
+            &EventBuffer::new(),
+        )
+        .unwrap();
+
+    assert_eq!(render.frames, 100);
+    assert_eq!(render.tail_frames, 0);
+    assert_eq!(render.channels, [vec![0.0; 100]]);
+}
+
+#[test]
+pub fn caps_infinite_tails() {
+    let bundle =
+        PluginBundle::load_from_clack::<SinglePluginEntry<TailPlugin<{ u32::MAX }>>>(c"").unwrap();
+    let mut instance = instantiate::<{ u32::MAX }>(&bundle);
+
+    let render = OfflineRenderer::new(48_000.0, 64)
+        .with_max_tail(300)
+        .render(
+            &mut instance,
+            |_, _| (),
+            &[vec![1.0f32; 10]],
+            &EventBuffer::new(),
+        )
+        .unwrap();
+
+    assert_eq!(render.frames, 310);
+    assert_eq!(render.tail_frames, 300);
+}
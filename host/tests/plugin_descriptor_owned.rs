@@ -0,0 +1,62 @@
+use clack_host::plugin::{PluginDescriptorOwned, PluginFeature};
+use clack_host::prelude::*;
+
+fn gain_plugin_path() -> String {
+    format!(
+        "{}/../target/debug/{}clack_plugin_gain{}",
+        env!("CARGO_MANIFEST_DIR"),
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    )
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri does not support calling foreign function (dlopen)
+pub fn outlives_bundle() {
+    // SAFETY: we made this plugin, we know it's safe.
+    let bundle = unsafe { PluginBundle::load(gain_plugin_path()).unwrap() };
+
+    let descriptors: Vec<PluginDescriptorOwned> = bundle
+        .get_plugin_factory()
+        .unwrap()
+        .plugin_descriptors()
+        .map(|d| d.try_into().unwrap())
+        .collect();
+
+    drop(bundle);
+
+    assert_eq!(descriptors.len(), 1);
+    let descriptor = &descriptors[0];
+
+    assert_eq!(descriptor.id, "org.rust-audio.clack.gain");
+    assert_eq!(descriptor.name.as_deref(), Some("Clack Gain Example"));
+    assert!(descriptor.clap_version.is_compatible());
+    assert!(descriptor.has_feature(&PluginFeature::AudioEffect));
+    assert!(descriptor.has_feature(&PluginFeature::Stereo));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+pub fn serde_round_trips() {
+    let mut descriptor = PluginDescriptorOwned::new("org.rust-audio.clack.gain");
+    descriptor.name = Some("Clack Gain Example".into());
+    descriptor.features = vec![PluginFeature::AudioEffect, "clack:custom".into()];
+
+    let json = serde_json::to_value(&descriptor).unwrap();
+    assert_eq!(
+        json["features"],
+        serde_json::json!(["audio-effect", "clack:custom"])
+    );
+    assert_eq!(json["clap_version"]["major"], 1);
+
+    let read: PluginDescriptorOwned = serde_json::from_value(json).unwrap();
+    assert_eq!(read, descriptor);
+
+    let minimal: PluginDescriptorOwned = serde_json::from_str(
+        r#"{ "clap_version": { "major": 1, "minor": 2, "revision": 0 }, "id": "a.b" }"#,
+    )
+    .unwrap();
+    assert_eq!(minimal.id, "a.b");
+    assert_eq!(minimal.name, None);
+    assert!(minimal.features.is_empty());
+}
@@ -0,0 +1,92 @@
+use clack_host::scan::{PluginScanner, ScanCache, ScanError, find_bundles};
+use std::path::PathBuf;
+
+fn gain_plugin_path() -> PathBuf {
+    format!(
+        "{}/../target/debug/{}clack_plugin_gain{}",
+        env!("CARGO_MANIFEST_DIR"),
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    )
+    .into()
+}
+
+fn make_search_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("clack-scan-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    std::fs::create_dir_all(dir.join("vendor/nested")).unwrap();
+    std::fs::copy(gain_plugin_path(), dir.join("vendor/nested/gain.clap")).unwrap();
+    std::fs::write(dir.join("broken.clap"), b"definitely not a plugin").unwrap();
+    std::fs::write(dir.join("readme.txt"), b"not a bundle either").unwrap();
+
+    dir
+}
+
+#[test]
+#[cfg_attr(target_os = "macos", ignore)] // Bundles are directories on macOS
+pub fn finds_bundles_recursively() {
+    let dir = make_search_dir("find");
+
+    // Searching the same directory twice doesn't produce duplicates
+    let bundles = find_bundles(&[dir.clone(), dir.join("vendor")]);
+    assert_eq!(
+        bundles,
+        [dir.join("broken.clap"), dir.join("vendor/nested/gain.clap")]
+    );
+
+    assert!(find_bundles(&[dir.join("does-not-exist")]).is_empty());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri does not support calling foreign function (dlopen)
+#[cfg_attr(target_os = "macos", ignore)] // Bundles are directories on macOS
+pub fn scans_and_caches_bundles() {
+    let dir = make_search_dir("cache");
+    let gain_path = dir.join("vendor/nested/gain.clap");
+    let broken_path = dir.join("broken.clap");
+
+    let scanner = PluginScanner::with_search_paths([&dir]);
+    let mut cache = ScanCache::new();
+
+    // SAFETY: we made the plugin, and the broken bundle can't even be loaded.
+    let report = unsafe { scanner.scan(&mut cache) };
+    assert_eq!(report.scanned, [gain_path.as_path()]);
+    assert!(report.cached.is_empty());
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].path, broken_path);
+    assert!(matches!(report.errors[0].error, ScanError::Load(_)));
+
+    let (bundle, plugin) = cache.plugins().next().unwrap();
+    assert_eq!(bundle.path, gain_path);
+    assert_eq!(plugin.id, "org.rust-audio.clack.gain");
+
+    // Persist the cache, and scan again: the unchanged bundle is not loaded again
+    let cache_path = dir.join("cache.txt");
+    cache.save(&cache_path).unwrap();
+    let mut cache = ScanCache::load(&cache_path).unwrap();
+
+    // SAFETY: same as above
+    let report = unsafe { scanner.scan(&mut cache) };
+    assert!(report.scanned.is_empty());
+    assert_eq!(report.cached, [gain_path.as_path()]);
+    assert_eq!(report.errors.len(), 1);
+
+    // Removed bundles are removed from the cache as well
+    std::fs::remove_file(&gain_path).unwrap();
+
+    // SAFETY: same as above
+    let report = unsafe { scanner.scan(&mut cache) };
+    assert_eq!(report.removed, [gain_path]);
+    assert!(cache.is_empty());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+pub fn missing_cache_file_is_empty() {
+    let cache = ScanCache::load(std::env::temp_dir().join("clack-scan-no-such-cache")).unwrap();
+    assert!(cache.is_empty());
+}
@@ -0,0 +1,175 @@
+use clack_host::scan::{IsolatedScanner, PluginScanner, ScanCache, ScanError, run_worker};
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn gain_plugin_path() -> PathBuf {
+    format!(
+        "{}/../target/debug/{}clack_plugin_gain{}",
+        env!("CARGO_MANIFEST_DIR"),
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    )
+    .into()
+}
+
+fn make_search_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("clack-isolated-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(gain_plugin_path(), dir.join("gain.clap")).unwrap();
+    std::fs::write(dir.join("broken.clap"), b"definitely not a plugin").unwrap();
+
+    dir
+}
+
+/// Returns a scanner that runs the given test of this binary as its worker.
+fn worker_scanner(worker_test: &str) -> IsolatedScanner {
+    IsolatedScanner::with_command(
+        std::env::current_exe().unwrap(),
+        [worker_test, "--exact", "--quiet", "--test-threads=1"],
+    )
+}
+
+/// Returns the bundle path this test binary was given, if it was started as a scan worker.
+fn requested_bundle_path() -> Option<PathBuf> {
+    let path = PathBuf::from(std::env::args_os().last()?);
+    (path.extension()? == "clap").then_some(path)
+}
+
+// The following "tests" are the worker processes used by the actual tests below. They don't do
+// anything when run normally.
+
+#[test]
+pub fn worker() {
+    if let Some(path) = requested_bundle_path() {
+        // SAFETY: the tests only scan the gain plugin and a bundle that can't be loaded.
+        unsafe { run_worker(&path, std::io::stdout()) }.unwrap();
+        std::process::exit(0);
+    }
+}
+
+#[test]
+pub fn noisy_worker() {
+    if let Some(path) = requested_bundle_path() {
+        print!("Some plugin logging, without a newline");
+
+        // SAFETY: the tests only scan the gain plugin and a bundle that can't be loaded.
+        unsafe { run_worker(&path, std::io::stdout()) }.unwrap();
+
+        println!("--- clack-scan-result-begin ---");
+        println!("clack-scan-error error Printed%20after%20the%20result");
+        std::process::exit(0);
+    }
+}
+
+#[test]
+pub fn crashing_worker() {
+    if requested_bundle_path().is_some() {
+        std::process::abort();
+    }
+}
+
+#[test]
+pub fn hanging_worker() {
+    if requested_bundle_path().is_some() {
+        std::thread::sleep(Duration::from_secs(60));
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri does not support spawning processes
+pub fn scans_bundle_in_worker() {
+    let dir = make_search_dir("scan");
+    let gain_path = dir.join("gain.clap");
+
+    let bundle = worker_scanner("worker").scan_bundle(&gain_path).unwrap();
+    assert_eq!(bundle.path, gain_path);
+    assert_eq!(
+        bundle.modified,
+        std::fs::metadata(&gain_path).unwrap().modified().unwrap()
+    );
+    assert_eq!(bundle.plugins.len(), 1);
+    assert_eq!(bundle.plugins[0].id, "org.rust-audio.clack.gain");
+    assert_eq!(
+        bundle.plugins[0].name.as_deref(),
+        Some("Clack Gain Example")
+    );
+
+    let error = worker_scanner("worker")
+        .scan_bundle(&dir.join("broken.clap"))
+        .unwrap_err();
+    assert!(matches!(error, ScanError::Worker(_)), "{error}");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri does not support spawning processes
+pub fn ignores_output_around_the_result() {
+    let dir = make_search_dir("noisy");
+    let gain_path = dir.join("gain.clap");
+
+    let bundle = worker_scanner("noisy_worker")
+        .scan_bundle(&gain_path)
+        .unwrap();
+    assert_eq!(bundle.path, gain_path);
+    assert_eq!(bundle.plugins.len(), 1);
+    assert_eq!(bundle.plugins[0].id, "org.rust-audio.clack.gain");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri does not support spawning processes
+pub fn reports_crashes_and_timeouts() {
+    let dir = make_search_dir("errors");
+    let gain_path = dir.join("gain.clap");
+
+    let error = worker_scanner("crashing_worker")
+        .scan_bundle(&gain_path)
+        .unwrap_err();
+    assert!(matches!(error, ScanError::Crashed(_)), "{error}");
+
+    let timeout = Duration::from_millis(500);
+    let error = worker_scanner("hanging_worker")
+        .with_timeout(timeout)
+        .scan_bundle(&gain_path)
+        .unwrap_err();
+    assert!(
+        matches!(error, ScanError::Timeout(t) if t == timeout),
+        "{error}"
+    );
+
+    let error = IsolatedScanner::with_command(dir.join("does-not-exist"), [""; 0])
+        .scan_bundle(&gain_path)
+        .unwrap_err();
+    assert!(matches!(error, ScanError::Io(_)), "{error}");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri does not support spawning processes
+#[cfg_attr(target_os = "macos", ignore)] // Bundles are directories on macOS
+pub fn scans_and_caches_bundles_in_workers() {
+    let dir = make_search_dir("cache");
+    let gain_path = dir.join("gain.clap");
+    let broken_path = dir.join("broken.clap");
+
+    let scanner = PluginScanner::with_search_paths([&dir]);
+    let isolated = worker_scanner("worker");
+    let mut cache = ScanCache::new();
+
+    let report = scanner.scan_isolated(&mut cache, &isolated);
+    assert_eq!(report.scanned, [gain_path.as_path()]);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].path, broken_path);
+    assert_eq!(cache.len(), 1);
+
+    let report = scanner.scan_isolated(&mut cache, &isolated);
+    assert!(report.scanned.is_empty());
+    assert_eq!(report.cached, [gain_path.as_path()]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -2,7 +2,7 @@
 #![deny(missing_docs, clippy::missing_docs_in_private_items, unsafe_code)]
 #![doc = include_str!("../README.md")]
 
-use crate::params::GainParams;
+use crate::params::PARAM_VOLUME_ID;
 use clack_extensions::state::PluginState;
 use clack_extensions::{audio_ports::*, params::*};
 use clack_plugin::prelude::*;
@@ -40,7 +40,7 @@ impl DefaultPluginFactory for GainPlugin {
 
     fn new_shared(_host: HostSharedHandle<'_>) -> Result<Self::Shared<'_>, PluginError> {
         Ok(GainPluginShared {
-            params: GainParams::new(),
+            params: params::new_params(),
         })
     }
 
@@ -114,11 +114,11 @@ impl<'a> PluginAudioProcessor<'a, GainPluginShared, GainPluginMainThread<'a>>
         for event_batch in events.input.batch() {
             // Process all param events in this batch
             for event in event_batch.events() {
-                self.shared.params.handle_event(event)
+                self.shared.params.handle_event(event);
             }
 
             // Get the volume value after all parameter changes have been handled.
-            let volume = self.shared.params.get_volume();
+            let volume = self.shared.params.get(PARAM_VOLUME_ID).unwrap_or(1.0) as f32;
 
             for buf in channel_buffers.iter_mut().flatten() {
                 for sample in buf.iter_mut() {
@@ -153,7 +153,7 @@ impl PluginAudioPortsImpl for GainPluginMainThread<'_> {
 /// The plugin data that gets shared between the Main Thread and the Audio Thread.
 pub struct GainPluginShared {
     /// The plugin's parameter values.
-    params: GainParams,
+    params: ParamSet,
 }
 
 impl PluginShared<'_> for GainPluginShared {}
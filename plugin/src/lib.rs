@@ -10,6 +10,8 @@ pub mod factory;
 pub mod host;
 pub mod plugin;
 pub mod process;
+pub mod smoothing;
+pub mod voices;
 
 pub(crate) mod internal_utils;
 
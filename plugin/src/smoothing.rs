@@ -0,0 +1,564 @@
+//! Parameter smoothing utilities.
+//!
+//! Abruptly changing a parameter's value (e.g. a volume or a filter cutoff) in the middle of an
+//! audio stream usually causes audible clicks or zipper noise. This module provides [`Smoother`],
+//! which ramps a value towards its target over a configurable amount of time, and
+//! [`SmoothedParam`], which drives a [`Smoother`] from the [`ParamValueEvent`]s and
+//! [`ParamModEvent`]s received by the plugin.
+//!
+//! None of the types in this module allocate, and all of them can be used on the audio thread.
+//!
+//! # Example
+//!
+//! ```
+//! use clack_plugin::events::io::InputEvents;
+//! use clack_plugin::smoothing::SmoothedParam;
+//!
+//! fn process(input_events: &InputEvents, output: &mut [f32], gain: &mut SmoothedParam) {
+//!     for batch in input_events.batch() {
+//!         // Apply all the value and modulation events of this batch...
+//!         gain.handle_batch(&batch);
+//!
+//!         // ...then ramp towards the new value over the samples covered by the batch.
+//!         for (sample, gain) in output[batch.sample_bounds()].iter_mut().zip(gain.values()) {
+//!             *sample *= gain;
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`ParamValueEvent`]: crate::events::event_types::ParamValueEvent
+//! [`ParamModEvent`]: crate::events::event_types::ParamModEvent
+
+#![deny(missing_docs)]
+
+use crate::events::io::EventBatch;
+use crate::events::spaces::CoreEventSpace;
+use crate::events::{Pckn, UnknownEvent};
+use crate::utils::ClapId;
+
+/// The remaining distance to the target a [`SmoothingStyle::OnePole`] smoother settles at, before
+/// snapping to the target.
+const ONE_POLE_SETTLE_RATIO: f32 = 1e-4;
+
+/// The curve a [`Smoother`] follows to reach its target.
+///
+/// All durations are expressed in milliseconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SmoothingStyle {
+    /// No smoothing: the target value is applied immediately.
+    None,
+    /// Linearly ramps to the target over the given duration.
+    Linear(f32),
+    /// Exponentially approaches the target using a one-pole low-pass filter.
+    ///
+    /// The given duration is the time it takes to cover 99.99% of the distance to the target,
+    /// after which the smoother snaps to the target.
+    OnePole(f32),
+    /// Ramps to the target over the given duration by multiplying the value by a constant factor
+    /// at every sample.
+    ///
+    /// This is best suited for frequencies or gain amounts, as the change is perceived linearly.
+    /// If the current value and the target do not have the same sign, or if either of them is zero,
+    /// this falls back to a [`Linear`](SmoothingStyle::Linear) ramp.
+    Multiplicative(f32),
+}
+
+/// How a [`Smoother`] advances at each sample.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Ramp {
+    Add(f32),
+    Multiply(f32),
+    OnePole(f32),
+}
+
+/// A value that smoothly ramps towards its target, one sample at a time.
+///
+/// # Example
+///
+/// ```
+/// use clack_plugin::smoothing::{Smoother, SmoothingStyle};
+///
+/// let mut smoother = Smoother::new(SmoothingStyle::Linear(1.0), 4000.0);
+/// smoother.set_target(1.0);
+///
+/// let mut buffer = [0.0; 6];
+/// smoother.fill(&mut buffer);
+/// assert_eq!(buffer, [0.25, 0.5, 0.75, 1.0, 1.0, 1.0]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Smoother {
+    style: SmoothingStyle,
+    sample_rate: f32,
+    current: f32,
+    target: f32,
+    ramp: Ramp,
+    steps_left: u32,
+}
+
+impl Smoother {
+    /// Creates a new smoother with the given style, running at the given sample rate.
+    ///
+    /// The smoother starts at `0.0`. Use [`reset`](Smoother::reset) to set its initial value.
+    #[inline]
+    pub fn new(style: SmoothingStyle, sample_rate: f64) -> Self {
+        Self {
+            style,
+            sample_rate: sample_rate as f32,
+            current: 0.0,
+            target: 0.0,
+            ramp: Ramp::Add(0.0),
+            steps_left: 0,
+        }
+    }
+
+    /// Returns the smoothing style of this smoother.
+    #[inline]
+    pub fn style(&self) -> SmoothingStyle {
+        self.style
+    }
+
+    /// Changes the smoothing style of this smoother.
+    ///
+    /// This only affects the next calls to [`set_target`](Smoother::set_target): any ongoing ramp
+    /// is left untouched.
+    #[inline]
+    pub fn set_style(&mut self, style: SmoothingStyle) {
+        self.style = style;
+    }
+
+    /// Changes the sample rate this smoother is running at.
+    ///
+    /// This only affects the next calls to [`set_target`](Smoother::set_target): any ongoing ramp
+    /// is left untouched.
+    #[inline]
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate as f32;
+    }
+
+    /// Returns the current value of this smoother, i.e. the last value that was produced.
+    #[inline]
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    /// Returns the value this smoother is ramping towards.
+    #[inline]
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// Returns `true` if this smoother has not reached its target yet.
+    #[inline]
+    pub fn is_smoothing(&self) -> bool {
+        self.steps_left > 0
+    }
+
+    /// Immediately sets both the current value and the target of this smoother, without smoothing.
+    #[inline]
+    pub fn reset(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.steps_left = 0;
+    }
+
+    /// Sets a new target for this smoother, starting a new ramp from the current value.
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+
+        let (duration, ramp) = match self.style {
+            SmoothingStyle::None => (0.0, Ramp::Add(0.0)),
+            SmoothingStyle::Linear(duration) => (duration, Ramp::Add(0.0)),
+            SmoothingStyle::OnePole(duration) => (duration, Ramp::OnePole(0.0)),
+            SmoothingStyle::Multiplicative(duration) => (duration, Ramp::Multiply(0.0)),
+        };
+
+        let steps = (duration * self.sample_rate / 1000.0).round() as u32;
+        if steps == 0 || self.current == target {
+            self.reset(target);
+            return;
+        }
+
+        let delta = target - self.current;
+        self.ramp = match ramp {
+            Ramp::OnePole(_) => Ramp::OnePole(ONE_POLE_SETTLE_RATIO.powf(1.0 / steps as f32)),
+            Ramp::Multiply(_) if self.current * target > 0.0 => {
+                Ramp::Multiply((target / self.current).powf(1.0 / steps as f32))
+            }
+            _ => Ramp::Add(delta / steps as f32),
+        };
+        self.steps_left = steps;
+    }
+
+    /// Advances this smoother by one sample, and returns the new value.
+    #[inline]
+    #[allow(clippy::should_implement_trait)] // This is an infinite generator, not an iterator.
+    pub fn next(&mut self) -> f32 {
+        match self.steps_left {
+            0 => return self.target,
+            1 => self.current = self.target,
+            _ => match self.ramp {
+                Ramp::Add(step) => self.current += step,
+                Ramp::Multiply(factor) => self.current *= factor,
+                Ramp::OnePole(coefficient) => {
+                    self.current = self.target + (self.current - self.target) * coefficient
+                }
+            },
+        }
+
+        self.steps_left -= 1;
+        self.current
+    }
+
+    /// Fills the given buffer with the next values of this smoother, one per sample.
+    #[inline]
+    pub fn fill(&mut self, buffer: &mut [f32]) {
+        if !self.is_smoothing() {
+            buffer.fill(self.target);
+            return;
+        }
+
+        for sample in buffer {
+            *sample = self.next();
+        }
+    }
+
+    /// Returns an endless iterator over the next values of this smoother, one per sample.
+    ///
+    /// This is meant to be zipped with a buffer, or limited with [`Iterator::take`].
+    #[inline]
+    pub fn values(&mut self) -> impl Iterator<Item = f32> + '_ {
+        core::iter::from_fn(|| Some(self.next()))
+    }
+}
+
+/// A parameter whose value and modulation amount are driven by events, and whose sum is smoothed
+/// by a [`Smoother`].
+///
+/// A smoothed parameter can either be global, in which case it only reacts to events that target
+/// all notes, or it can be bound to a single voice (see [`set_voice`](SmoothedParam::set_voice)),
+/// in which case it also reacts to polyphonic events targeting that voice's [`Pckn`].
+///
+/// Monophonic modulation (targeting all notes) and polyphonic modulation (targeting a voice) are
+/// tracked separately, and the polyphonic amount is added on top of the monophonic one.
+#[derive(Clone, Debug)]
+pub struct SmoothedParam {
+    param_id: ClapId,
+    voice: Option<Pckn>,
+    value: f64,
+    mono_modulation: f64,
+    poly_modulation: f64,
+    smoother: Smoother,
+}
+
+impl SmoothedParam {
+    /// Creates a new global smoothed parameter with the given ID and initial value.
+    ///
+    /// The given smoother is immediately reset to the initial value.
+    pub fn new(param_id: ClapId, value: f64, mut smoother: Smoother) -> Self {
+        smoother.reset(value as f32);
+
+        Self {
+            param_id,
+            voice: None,
+            value,
+            mono_modulation: 0.0,
+            poly_modulation: 0.0,
+            smoother,
+        }
+    }
+
+    /// Returns the ID of the parameter.
+    #[inline]
+    pub fn param_id(&self) -> ClapId {
+        self.param_id
+    }
+
+    /// Returns the [`Pckn`] of the voice this parameter is bound to, or `None` if it is global.
+    #[inline]
+    pub fn voice(&self) -> Option<Pckn> {
+        self.voice
+    }
+
+    /// Binds this parameter to the voice matching the given [`Pckn`], or makes it global if `None`.
+    #[inline]
+    pub fn set_voice(&mut self, voice: Option<Pckn>) {
+        self.voice = voice;
+    }
+
+    /// Returns the base value of the parameter, excluding modulation.
+    #[inline]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns the current total modulation amount of the parameter, i.e. the sum of its
+    /// monophonic and polyphonic modulation amounts.
+    #[inline]
+    pub fn modulation(&self) -> f64 {
+        self.mono_modulation + self.poly_modulation
+    }
+
+    /// Returns the current monophonic modulation amount of the parameter.
+    #[inline]
+    pub fn mono_modulation(&self) -> f64 {
+        self.mono_modulation
+    }
+
+    /// Returns the current polyphonic modulation amount of the parameter.
+    ///
+    /// This is always `0.0` if the parameter is not bound to a voice.
+    #[inline]
+    pub fn poly_modulation(&self) -> f64 {
+        self.poly_modulation
+    }
+
+    /// Returns the underlying smoother.
+    #[inline]
+    pub fn smoother(&self) -> &Smoother {
+        &self.smoother
+    }
+
+    /// Returns the underlying smoother mutably, e.g. to change its sample rate.
+    #[inline]
+    pub fn smoother_mut(&mut self) -> &mut Smoother {
+        &mut self.smoother
+    }
+
+    /// Sets the base value of the parameter, and ramps towards the new modulated value.
+    #[inline]
+    pub fn set_value(&mut self, value: f64) {
+        self.value = value;
+        self.retarget();
+    }
+
+    /// Sets the monophonic modulation amount of the parameter, and ramps towards the new
+    /// modulated value.
+    #[inline]
+    pub fn set_mono_modulation(&mut self, modulation: f64) {
+        self.mono_modulation = modulation;
+        self.retarget();
+    }
+
+    /// Sets the polyphonic modulation amount of the parameter, and ramps towards the new
+    /// modulated value.
+    #[inline]
+    pub fn set_poly_modulation(&mut self, modulation: f64) {
+        self.poly_modulation = modulation;
+        self.retarget();
+    }
+
+    /// Immediately sets both the base value and the monophonic modulation amount, without
+    /// smoothing. The polyphonic modulation amount is reset to `0.0`.
+    ///
+    /// This is useful when a voice starts, to pick up the current state of the global parameter.
+    #[inline]
+    pub fn reset(&mut self, value: f64, mono_modulation: f64) {
+        self.value = value;
+        self.mono_modulation = mono_modulation;
+        self.poly_modulation = 0.0;
+        self.smoother.reset((value + mono_modulation) as f32);
+    }
+
+    /// Handles the given event, if it is a [`ParamValueEvent`] or a [`ParamModEvent`] targeting
+    /// this parameter.
+    ///
+    /// Returns `true` if the event was applied, `false` otherwise.
+    ///
+    /// [`ParamValueEvent`]: crate::events::event_types::ParamValueEvent
+    /// [`ParamModEvent`]: crate::events::event_types::ParamModEvent
+    pub fn handle_event(&mut self, event: &UnknownEvent) -> bool {
+        match event.as_core_event() {
+            Some(CoreEventSpace::ParamValue(event))
+                if self.accepts(event.param_id(), event.pckn()) =>
+            {
+                self.set_value(event.value());
+                true
+            }
+            Some(CoreEventSpace::ParamMod(event))
+                if self.accepts(event.param_id(), event.pckn()) =>
+            {
+                if event.pckn().matches_all() {
+                    self.set_mono_modulation(event.amount());
+                } else {
+                    self.set_poly_modulation(event.amount());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Handles all the events of the given batch.
+    ///
+    /// See [`handle_event`](SmoothedParam::handle_event).
+    #[inline]
+    pub fn handle_batch(&mut self, batch: &EventBatch) {
+        for event in batch.events() {
+            self.handle_event(event);
+        }
+    }
+
+    /// Advances the parameter by one sample, and returns the new modulated value.
+    #[inline]
+    #[allow(clippy::should_implement_trait)] // This is an infinite generator, not an iterator.
+    pub fn next(&mut self) -> f32 {
+        self.smoother.next()
+    }
+
+    /// Fills the given buffer with the next modulated values of this parameter, one per sample.
+    #[inline]
+    pub fn fill(&mut self, buffer: &mut [f32]) {
+        self.smoother.fill(buffer)
+    }
+
+    /// Returns an endless iterator over the next modulated values of this parameter, one per
+    /// sample.
+    ///
+    /// See [`Smoother::values`].
+    #[inline]
+    pub fn values(&mut self) -> impl Iterator<Item = f32> + '_ {
+        self.smoother.values()
+    }
+
+    fn accepts(&self, param_id: Option<ClapId>, pckn: Pckn) -> bool {
+        if param_id != Some(self.param_id) {
+            return false;
+        }
+
+        match &self.voice {
+            None => pckn.matches_all(),
+            Some(voice) => pckn.matches(voice),
+        }
+    }
+
+    #[inline]
+    fn retarget(&mut self) {
+        self.smoother
+            .set_target((self.value + self.modulation()) as f32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::event_types::{ParamModEvent, ParamValueEvent};
+    use crate::events::io::EventBuffer;
+    use crate::utils::Cookie;
+
+    const PARAM: ClapId = ClapId::new(1);
+
+    #[test]
+    fn linear_reaches_target_exactly() {
+        let mut smoother = Smoother::new(SmoothingStyle::Linear(2.0), 2000.0);
+        smoother.reset(1.0);
+        smoother.set_target(-1.0);
+
+        let mut buffer = [0.0; 5];
+        smoother.fill(&mut buffer);
+        assert_eq!(buffer, [0.5, 0.0, -0.5, -1.0, -1.0]);
+        assert!(!smoother.is_smoothing());
+    }
+
+    #[test]
+    fn one_pole_converges_monotonically() {
+        let mut smoother = Smoother::new(SmoothingStyle::OnePole(10.0), 1000.0);
+        smoother.set_target(1.0);
+
+        let values: Vec<f32> = smoother.values().take(10).collect();
+        assert!(values.windows(2).all(|w| w[0] < w[1]));
+        assert!(values[0] > 0.5);
+        assert_eq!(values[9], 1.0);
+        assert!(!smoother.is_smoothing());
+    }
+
+    #[test]
+    fn multiplicative_is_geometric_and_falls_back_to_linear() {
+        let mut smoother = Smoother::new(SmoothingStyle::Multiplicative(2.0), 1000.0);
+        smoother.reset(100.0);
+        smoother.set_target(10_000.0);
+        assert_eq!(smoother.next(), 1000.0);
+        assert_eq!(smoother.next(), 10_000.0);
+
+        smoother.reset(0.0);
+        smoother.set_target(4.0);
+        assert_eq!(smoother.next(), 2.0);
+        assert_eq!(smoother.next(), 4.0);
+    }
+
+    #[test]
+    fn handles_global_and_voice_events_in_batches() {
+        let voice = Pckn::new(0u16, 0u16, 60u16, 7u32);
+        let smoother = Smoother::new(SmoothingStyle::None, 48_000.0);
+        let mut global = SmoothedParam::new(PARAM, 0.5, smoother.clone());
+        let mut voiced = SmoothedParam::new(PARAM, 0.5, smoother);
+        voiced.set_voice(Some(voice));
+
+        let mut events = EventBuffer::new();
+        events.push(&ParamValueEvent::new(
+            2,
+            PARAM,
+            Pckn::match_all(),
+            1.0,
+            Cookie::empty(),
+        ));
+        events.push(&ParamModEvent::new(3, PARAM, voice, 0.25, Cookie::empty()));
+        events.push(&ParamModEvent::new(
+            3,
+            ClapId::new(2),
+            Pckn::match_all(),
+            0.25,
+            Cookie::empty(),
+        ));
+
+        let mut global_out = [0.0; 5];
+        let mut voiced_out = [0.0; 5];
+        for batch in events.as_input().batch() {
+            global.handle_batch(&batch);
+            voiced.handle_batch(&batch);
+            global.fill(&mut global_out[batch.sample_bounds()]);
+            voiced.fill(&mut voiced_out[batch.sample_bounds()]);
+        }
+
+        assert_eq!(global_out, [0.5, 0.5, 1.0, 1.0, 1.0]);
+        assert_eq!(voiced_out, [0.5, 0.5, 1.0, 1.25, 1.25]);
+        assert_eq!(global.modulation(), 0.0);
+        assert_eq!(voiced.modulation(), 0.25);
+    }
+
+    #[test]
+    fn adds_poly_modulation_on_top_of_mono_modulation() {
+        let voice = Pckn::new(0u16, 0u16, 60u16, 7u32);
+        let smoother = Smoother::new(SmoothingStyle::None, 48_000.0);
+        let mut voiced = SmoothedParam::new(PARAM, 0.5, smoother);
+        voiced.set_voice(Some(voice));
+
+        let mut events = EventBuffer::new();
+        events.push(&ParamModEvent::new(
+            0,
+            PARAM,
+            Pckn::match_all(),
+            0.25,
+            Cookie::empty(),
+        ));
+        events.push(&ParamModEvent::new(1, PARAM, voice, 0.125, Cookie::empty()));
+        events.push(&ParamModEvent::new(
+            2,
+            PARAM,
+            Pckn::match_all(),
+            -0.25,
+            Cookie::empty(),
+        ));
+
+        let mut output = [0.0; 3];
+        for batch in events.as_input().batch() {
+            voiced.handle_batch(&batch);
+            voiced.fill(&mut output[batch.sample_bounds()]);
+        }
+
+        assert_eq!(output, [0.75, 0.875, 0.375]);
+        assert_eq!(voiced.mono_modulation(), -0.25);
+        assert_eq!(voiced.poly_modulation(), 0.125);
+        assert_eq!(voiced.modulation(), -0.125);
+    }
+}
@@ -0,0 +1,549 @@
+//! Polyphonic voice management.
+//!
+//! This module provides [`VoiceManager`], a fixed-capacity voice allocator that takes care of the
+//! note-handling bookkeeping of polyphonic instruments:
+//!
+//! * allocating voices on [`NoteOnEvent`]s, and stealing them according to a [`StealingPolicy`]
+//!   when all voices are busy;
+//! * releasing voices on [`NoteOffEvent`]s, and stopping them on [`NoteChokeEvent`]s;
+//! * routing polyphonic note expressions and parameter value and modulation events to the voices
+//!   they target, using [`Pckn::matches`];
+//! * telling the host when voices end, by sending [`NoteEndEvent`]s.
+//!
+//! The actual sound generation is left to the [`Voice`] implementation.
+//!
+//! No allocation is performed after the manager has been created, so it can be freely used on the
+//! audio thread.
+
+#![deny(missing_docs)]
+
+use crate::events::event_types::{
+    NoteEndEvent, NoteExpressionEvent, NoteOffEvent, NoteOnEvent, ParamModEvent, ParamValueEvent,
+};
+use crate::events::io::{EventBatch, OutputEvents};
+use crate::events::spaces::CoreEventSpace;
+use crate::events::{Event, Pckn, UnknownEvent};
+
+#[cfg(doc)]
+use crate::events::event_types::NoteChokeEvent;
+
+/// A single voice of a polyphonic instrument, managed by a [`VoiceManager`].
+///
+/// Voices are allocated once, when the [`VoiceManager`] is created, and are then reused for every
+/// note they play.
+pub trait Voice {
+    /// Starts playing the note of the given [`NoteOnEvent`].
+    ///
+    /// This is also called when this voice is stolen to play another note, in which case it is
+    /// up to the implementation to reset or to smoothly transition its internal state.
+    fn start(&mut self, event: &NoteOnEvent);
+
+    /// Releases the note this voice is playing, e.g. by starting the release stage of its
+    /// envelope.
+    ///
+    /// The voice keeps playing until [`is_finished`](Voice::is_finished) returns `true`.
+    fn release(&mut self, event: &NoteOffEvent);
+
+    /// Returns `true` if this voice has finished playing its note, and can be reused.
+    fn is_finished(&self) -> bool;
+
+    /// Returns the current loudness of this voice, in an arbitrary linear unit.
+    ///
+    /// This is used by the [`StealingPolicy::Quietest`] policy. The default implementation always
+    /// returns `0.0`.
+    #[inline]
+    fn loudness(&self) -> f32 {
+        0.0
+    }
+
+    /// Handles a [`NoteExpressionEvent`] targeting this voice.
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn handle_note_expression(&mut self, _event: &NoteExpressionEvent) {}
+
+    /// Handles a polyphonic [`ParamValueEvent`] targeting this voice.
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn handle_param_value(&mut self, _event: &ParamValueEvent) {}
+
+    /// Handles a polyphonic [`ParamModEvent`] targeting this voice.
+    ///
+    /// The default implementation does nothing.
+    #[inline]
+    fn handle_param_mod(&mut self, _event: &ParamModEvent) {}
+}
+
+/// How a [`VoiceManager`] picks the voice to steal when a new note starts while all voices are
+/// busy.
+///
+/// Regardless of the policy, voices that have already been released are always stolen before
+/// voices that are still held.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum StealingPolicy {
+    /// New notes are dropped when all voices are busy.
+    None,
+    /// The voice that started playing first is stolen.
+    #[default]
+    Oldest,
+    /// The voice with the lowest [`loudness`](Voice::loudness) is stolen.
+    Quietest,
+    /// A voice already playing the same key on the same channel is always reused, even if other
+    /// voices are available. Otherwise, this behaves like [`Oldest`](StealingPolicy::Oldest).
+    ///
+    /// With this policy, a key can never be played by more than one voice at a time.
+    SameKey,
+}
+
+/// The note a voice is currently playing.
+#[derive(Copy, Clone, Debug)]
+struct PlayingNote {
+    pckn: Pckn,
+    age: u64,
+    released: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Slot<V> {
+    voice: V,
+    note: Option<PlayingNote>,
+}
+
+/// A fixed-capacity polyphonic voice allocator.
+///
+/// See the [module documentation](self) for more information.
+///
+/// [`NoteEndEvent`]s are pushed to the given [`OutputEvents`] on a best-effort basis: if the host's
+/// output event queue is full, they are silently dropped.
+#[derive(Clone, Debug)]
+pub struct VoiceManager<V> {
+    slots: Box<[Slot<V>]>,
+    voice_count: usize,
+    policy: StealingPolicy,
+    next_age: u64,
+}
+
+impl<V: Voice> VoiceManager<V> {
+    /// Creates a new voice manager with the given capacity, using the given function to create
+    /// each of the voices.
+    ///
+    /// All voices are available by default, see [`set_voice_count`](Self::set_voice_count).
+    pub fn new(capacity: usize, mut new_voice: impl FnMut() -> V) -> Self {
+        Self {
+            slots: (0..capacity)
+                .map(|_| Slot {
+                    voice: new_voice(),
+                    note: None,
+                })
+                .collect(),
+            voice_count: capacity,
+            policy: StealingPolicy::default(),
+            next_age: 0,
+        }
+    }
+
+    /// Sets the voice stealing policy of this manager.
+    #[inline]
+    pub fn with_stealing_policy(mut self, policy: StealingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the voice stealing policy of this manager.
+    #[inline]
+    pub fn stealing_policy(&self) -> StealingPolicy {
+        self.policy
+    }
+
+    /// Changes the voice stealing policy of this manager.
+    #[inline]
+    pub fn set_stealing_policy(&mut self, policy: StealingPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns the total number of voices this manager holds.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the maximum number of voices that can currently play at the same time.
+    #[inline]
+    pub fn voice_count(&self) -> usize {
+        self.voice_count
+    }
+
+    /// Sets the maximum number of voices that can play at the same time, e.g. to switch the
+    /// instrument to mono by setting it to `1`.
+    ///
+    /// The count is clamped between `1` and the [`capacity`](Self::capacity). Voices that exceed
+    /// the new count are not stopped, but no new voices are started until the count is respected.
+    #[inline]
+    pub fn set_voice_count(&mut self, voice_count: usize) {
+        self.voice_count = voice_count.clamp(1, self.capacity().max(1));
+    }
+
+    /// Returns the number of voices that are currently playing.
+    #[inline]
+    pub fn active_count(&self) -> usize {
+        self.slots.iter().filter(|s| s.note.is_some()).count()
+    }
+
+    /// Returns `true` if any voice is currently playing.
+    #[inline]
+    pub fn has_active_voices(&self) -> bool {
+        self.slots.iter().any(|s| s.note.is_some())
+    }
+
+    /// Returns an iterator over all the voices that are currently playing.
+    #[inline]
+    pub fn active_voices(&self) -> impl Iterator<Item = &V> {
+        self.slots
+            .iter()
+            .filter(|s| s.note.is_some())
+            .map(|s| &s.voice)
+    }
+
+    /// Returns a mutable iterator over all the voices that are currently playing.
+    #[inline]
+    pub fn active_voices_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.slots
+            .iter_mut()
+            .filter(|s| s.note.is_some())
+            .map(|s| &mut s.voice)
+    }
+
+    /// Handles the given event.
+    ///
+    /// Note On, Note Off and Note Choke events start, release and stop voices, respectively. Note
+    /// Expression events, as well as Parameter Value and Parameter Modulation events that target
+    /// specific notes, are forwarded to all the voices they match.
+    ///
+    /// Any [`NoteEndEvent`] caused by a voice being stolen or choked is pushed to `output`.
+    ///
+    /// Returns `true` if the event was handled, or `false` if it is not a note-related event, in
+    /// which case it should be processed by the plugin itself. Notably, this includes Parameter
+    /// Value and Parameter Modulation events that target all notes.
+    pub fn handle_event(&mut self, event: &UnknownEvent, output: &mut OutputEvents) -> bool {
+        match event.as_core_event() {
+            Some(CoreEventSpace::NoteOn(event)) => self.note_on(event, output),
+            Some(CoreEventSpace::NoteOff(event)) => {
+                for slot in self.matching_slots(event.pckn()) {
+                    if let Some(note) = slot.note.as_mut().filter(|n| !n.released) {
+                        note.released = true;
+                        slot.voice.release(event);
+                    }
+                }
+            }
+            Some(CoreEventSpace::NoteChoke(event)) => {
+                let time = event.header().time();
+                for slot in self.matching_slots(event.pckn()) {
+                    if let Some(note) = slot.note.take() {
+                        end_note(time, note, output);
+                    }
+                }
+            }
+            Some(CoreEventSpace::NoteExpression(event)) => {
+                for slot in self.matching_slots(event.pckn()) {
+                    slot.voice.handle_note_expression(event);
+                }
+            }
+            Some(CoreEventSpace::ParamValue(event)) if !event.pckn().matches_all() => {
+                for slot in self.matching_slots(event.pckn()) {
+                    slot.voice.handle_param_value(event);
+                }
+            }
+            Some(CoreEventSpace::ParamMod(event)) if !event.pckn().matches_all() => {
+                for slot in self.matching_slots(event.pckn()) {
+                    slot.voice.handle_param_mod(event);
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Handles all the events of the given batch.
+    ///
+    /// See [`handle_event`](Self::handle_event).
+    #[inline]
+    pub fn handle_batch(&mut self, batch: &EventBatch, output: &mut OutputEvents) {
+        for event in batch.events() {
+            self.handle_event(event, output);
+        }
+    }
+
+    /// Frees all the voices that have [finished](Voice::is_finished) playing, and pushes a
+    /// [`NoteEndEvent`] for each of them at the given sample time.
+    ///
+    /// This should be called after the voices have been rendered, e.g. at the end of each
+    /// process block.
+    pub fn end_finished_voices(&mut self, time: u32, output: &mut OutputEvents) {
+        for slot in self.slots.iter_mut() {
+            if slot.note.is_some() && slot.voice.is_finished() {
+                if let Some(note) = slot.note.take() {
+                    end_note(time, note, output);
+                }
+            }
+        }
+    }
+
+    /// Immediately stops all voices, and pushes a [`NoteEndEvent`] for each of them at the given
+    /// sample time.
+    pub fn stop_all(&mut self, time: u32, output: &mut OutputEvents) {
+        for slot in self.slots.iter_mut() {
+            if let Some(note) = slot.note.take() {
+                end_note(time, note, output);
+            }
+        }
+    }
+
+    /// Immediately stops all voices, without notifying the host.
+    ///
+    /// This is meant to be used when the plugin is reset or deactivated, at which point the host
+    /// considers all notes to be terminated.
+    #[inline]
+    pub fn reset(&mut self) {
+        for slot in self.slots.iter_mut() {
+            slot.note = None;
+        }
+    }
+
+    fn note_on(&mut self, event: &NoteOnEvent, output: &mut OutputEvents) {
+        // Note On events must always target a specific key.
+        let pckn = event.pckn();
+        if pckn.key.is_all() {
+            return;
+        }
+
+        let Some(index) = self.allocate(pckn) else {
+            return;
+        };
+
+        let slot = &mut self.slots[index];
+        if let Some(stolen) = slot.note.take() {
+            end_note(event.header().time(), stolen, output);
+        }
+
+        slot.note = Some(PlayingNote {
+            pckn,
+            age: self.next_age,
+            released: false,
+        });
+        self.next_age += 1;
+
+        slot.voice.start(event);
+    }
+
+    /// Returns the index of the slot that should play the given new note.
+    fn allocate(&self, pckn: Pckn) -> Option<usize> {
+        if self.policy == StealingPolicy::SameKey {
+            let same_key = self.slots.iter().position(|s| {
+                s.note.is_some_and(|n| {
+                    n.pckn.port_index == pckn.port_index
+                        && n.pckn.channel == pckn.channel
+                        && n.pckn.key == pckn.key
+                })
+            });
+
+            if same_key.is_some() {
+                return same_key;
+            }
+        }
+
+        if self.active_count() < self.voice_count {
+            if let Some(free) = self.slots.iter().position(|s| s.note.is_none()) {
+                return Some(free);
+            }
+        }
+
+        let candidates = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| Some((i, s.note?, &s.voice)));
+
+        match self.policy {
+            StealingPolicy::None => None,
+            StealingPolicy::Oldest | StealingPolicy::SameKey => candidates
+                .min_by_key(|(_, note, _)| (!note.released, note.age))
+                .map(|(i, _, _)| i),
+            StealingPolicy::Quietest => candidates
+                .min_by(|(_, a, a_voice), (_, b, b_voice)| {
+                    (!a.released)
+                        .cmp(&!b.released)
+                        .then(a_voice.loudness().total_cmp(&b_voice.loudness()))
+                        .then(a.age.cmp(&b.age))
+                })
+                .map(|(i, _, _)| i),
+        }
+    }
+
+    fn matching_slots(&mut self, pckn: Pckn) -> impl Iterator<Item = &mut Slot<V>> {
+        self.slots
+            .iter_mut()
+            .filter(move |s| s.note.is_some_and(|n| pckn.matches(&n.pckn)))
+    }
+}
+
+/// Notifies the host that the given note has ended.
+///
+/// The Note End event carries the same PCKN as the Note On event that started the note.
+#[inline]
+fn end_note(time: u32, note: PlayingNote, output: &mut OutputEvents) {
+    let _ = output.try_push(NoteEndEvent::new(time, note.pckn));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Match;
+    use crate::events::event_types::NoteChokeEvent;
+    use crate::events::io::EventBuffer;
+    use crate::utils::{ClapId, Cookie};
+
+    #[derive(Clone, Default)]
+    struct TestVoice {
+        key: u16,
+        loudness: f32,
+        released: bool,
+        modulation: f64,
+    }
+
+    impl Voice for TestVoice {
+        fn start(&mut self, event: &NoteOnEvent) {
+            self.key = event.key().into_specific().unwrap();
+            self.loudness = event.velocity() as f32;
+            self.released = false;
+            self.modulation = 0.0;
+        }
+
+        fn release(&mut self, _event: &NoteOffEvent) {
+            self.released = true;
+        }
+
+        fn is_finished(&self) -> bool {
+            self.released
+        }
+
+        fn loudness(&self) -> f32 {
+            self.loudness
+        }
+
+        fn handle_param_mod(&mut self, event: &ParamModEvent) {
+            self.modulation = event.amount();
+        }
+    }
+
+    fn note_on(key: u16, note_id: u32, velocity: f64) -> NoteOnEvent {
+        NoteOnEvent::new(0, Pckn::new(0u16, 0u16, key, note_id), velocity)
+    }
+
+    fn keys(manager: &VoiceManager<TestVoice>) -> Vec<u16> {
+        let mut keys: Vec<_> = manager.active_voices().map(|v| v.key).collect();
+        keys.sort();
+        keys
+    }
+
+    fn ended(output: &EventBuffer) -> Vec<Pckn> {
+        output
+            .iter()
+            .filter_map(|e| e.as_event::<NoteEndEvent>())
+            .map(|e| e.pckn())
+            .collect()
+    }
+
+    #[test]
+    fn steals_oldest_and_quietest_voices() {
+        let mut output = EventBuffer::new();
+        let mut manager = VoiceManager::new(2, TestVoice::default);
+
+        manager.handle_event(note_on(60, 1, 0.2).as_ref(), &mut output.as_output());
+        manager.handle_event(note_on(62, 2, 0.8).as_ref(), &mut output.as_output());
+        manager.handle_event(note_on(64, 3, 1.0).as_ref(), &mut output.as_output());
+        assert_eq!(keys(&manager), [62, 64]);
+        assert_eq!(ended(&output), [Pckn::new(0u16, 0u16, 60u16, 1u32)]);
+
+        manager.set_stealing_policy(StealingPolicy::Quietest);
+        manager.handle_event(note_on(65, 4, 1.0).as_ref(), &mut output.as_output());
+        assert_eq!(keys(&manager), [64, 65]);
+
+        manager.set_stealing_policy(StealingPolicy::None);
+        manager.handle_event(note_on(67, 5, 1.0).as_ref(), &mut output.as_output());
+        assert_eq!(keys(&manager), [64, 65]);
+        assert_eq!(ended(&output).len(), 2);
+    }
+
+    #[test]
+    fn same_key_policy_reuses_voices() {
+        let mut output = EventBuffer::new();
+        let mut manager =
+            VoiceManager::new(4, TestVoice::default).with_stealing_policy(StealingPolicy::SameKey);
+
+        manager.handle_event(note_on(60, 1, 1.0).as_ref(), &mut output.as_output());
+        manager.handle_event(note_on(60, 2, 1.0).as_ref(), &mut output.as_output());
+        assert_eq!(manager.active_count(), 1);
+        assert_eq!(ended(&output), [Pckn::new(0u16, 0u16, 60u16, 1u32)]);
+    }
+
+    #[test]
+    fn releases_chokes_and_ends_voices() {
+        let mut output = EventBuffer::new();
+        let mut manager = VoiceManager::new(4, TestVoice::default);
+
+        for (key, id) in [(60, 1), (62, 2), (64, 3)] {
+            manager.handle_event(note_on(key, id, 1.0).as_ref(), &mut output.as_output());
+        }
+
+        let off = NoteOffEvent::new(0, Pckn::new(0u16, 0u16, 60u16, Match::All), 0.0);
+        assert!(manager.handle_event(off.as_ref(), &mut output.as_output()));
+        assert_eq!(manager.active_count(), 3);
+        assert!(output.is_empty());
+
+        manager.end_finished_voices(32, &mut output.as_output());
+        assert_eq!(keys(&manager), [62, 64]);
+        assert_eq!(ended(&output), [Pckn::new(0u16, 0u16, 60u16, 1u32)]);
+        assert_eq!(output.get(0).unwrap().header().time(), 32);
+
+        let choke = NoteChokeEvent::new(8, Pckn::new(0u16, 0u16, Match::All, 3u32));
+        assert!(manager.handle_event(choke.as_ref(), &mut output.as_output()));
+        assert_eq!(keys(&manager), [62]);
+        assert_eq!(ended(&output)[1], Pckn::new(0u16, 0u16, 64u16, 3u32));
+
+        manager.reset();
+        assert!(!manager.has_active_voices());
+        assert_eq!(output.len(), 2);
+    }
+
+    #[test]
+    fn routes_polyphonic_events_through_pckn() {
+        let mut output = EventBuffer::new();
+        let mut manager = VoiceManager::new(4, TestVoice::default);
+
+        for (key, id) in [(60, 1), (62, 2)] {
+            manager.handle_event(note_on(key, id, 1.0).as_ref(), &mut output.as_output());
+        }
+
+        let param = ClapId::new(0);
+        let poly = ParamModEvent::new(
+            0,
+            param,
+            Pckn::new(0u16, 0u16, 62u16, 2u32),
+            0.5,
+            Cookie::empty(),
+        );
+        assert!(manager.handle_event(poly.as_ref(), &mut output.as_output()));
+
+        let global = ParamModEvent::new(0, param, Pckn::match_all(), 1.0, Cookie::empty());
+        assert!(!manager.handle_event(global.as_ref(), &mut output.as_output()));
+
+        let modulations: Vec<_> = manager
+            .active_voices()
+            .map(|v| (v.key, v.modulation))
+            .collect();
+        assert!(modulations.contains(&(60, 0.0)));
+        assert!(modulations.contains(&(62, 0.5)));
+    }
+}